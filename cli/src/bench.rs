@@ -0,0 +1,122 @@
+//! The benchmark runner of the `bench` subcommand. Benchmarks are defined
+//! using `(defbench name body...)` forms in files ending in `_bench.rusht`.
+//! As with tests, all other forms of a file are interpreted before its
+//! benchmarks. Each benchmark is warmed up and then run repeatedly in samples
+//! for the target duration, reporting the mean number of operations per
+//! second and its variation across the samples.
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+use rusht::{Expr, Interpreter};
+
+use crate::forms;
+
+/// The suffix of the names of benchmark files.
+const BENCH_FILE_SUFFIX: &str = "_bench.rusht";
+
+/// The name of the form defining a benchmark.
+const DEFBENCH: &str = "defbench";
+
+/// The number of samples taken during the target duration.
+const SAMPLES: u32 = 50;
+
+/// Runs the benchmarks in the given files and directories, which are searched
+/// recursively for benchmark files. Returns an error, if any benchmark failed.
+///
+/// # Arguments
+///
+/// * `interpreter` - The interpreter, of which each benchmark file gets a
+///     copy.
+/// * `paths` - The paths of the files and directories.
+/// * `duration` - The target duration of each benchmark. Additionally, each
+///     benchmark is warmed up for a tenth of this duration.
+pub fn run<'a, I>(interpreter: &mut Interpreter, paths: I, duration: Duration) -> Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut files = vec![];
+    for path in paths {
+        forms::collect_files(Path::new(path), BENCH_FILE_SUFFIX, &mut files)
+            .with_context(|| format!("failed to find benchmark files in `{}`", path))?;
+    }
+    files.sort();
+
+    let mut failed = 0;
+    for file in files {
+        let source = std::fs::read_to_string(&file)
+            .with_context(|| format!("failed to read benchmarks from `{}`", file.display()))?;
+        let mut base = interpreter.fork();
+        let benches = match forms::split(&mut base, &source, DEFBENCH) {
+            Ok(benches) => benches,
+            Err(error) => {
+                println!("{} ... FAILED to load: {}", file.display(), error);
+                failed += 1;
+                continue;
+            }
+        };
+
+        for (name, body) in benches {
+            match bench(&mut base.fork(), &body, duration) {
+                Ok((mean, deviation)) => println!(
+                    "bench {}::{} ... {:.0} ops/sec (\u{b1} {:.1}%)",
+                    file.display(),
+                    name,
+                    mean,
+                    deviation / mean * 100.0
+                ),
+                Err(error) => {
+                    println!("bench {}::{} ... FAILED: {}", file.display(), name, error);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("{} benchmark(s) failed", failed);
+    }
+    Ok(())
+}
+
+/// Runs a single benchmark and returns the mean and standard deviation of
+/// the operations per second measured in each sample.
+fn bench(
+    interpreter: &mut Interpreter,
+    body: &[Expr],
+    duration: Duration,
+) -> rusht::Result<(f64, f64)> {
+    let mut run = || {
+        body.iter()
+            .try_for_each(|expr| interpreter.eval(expr.clone()).map(|_| ()))
+    };
+
+    // The warm-up is used to estimate how many operations fit into a sample.
+    let start = Instant::now();
+    let mut warm_up_ops = 0_u32;
+    while start.elapsed() < duration / 10 || warm_up_ops == 0 {
+        run()?;
+        warm_up_ops += 1;
+    }
+    let ops_per_sec = f64::from(warm_up_ops) / start.elapsed().as_secs_f64();
+    let sample_duration = duration / SAMPLES;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let batch = ((ops_per_sec * sample_duration.as_secs_f64()) as u32).max(1);
+
+    let mut samples = vec![];
+    let start = Instant::now();
+    while start.elapsed() < duration || samples.is_empty() {
+        let sample_start = Instant::now();
+        for _ in 0..batch {
+            run()?;
+        }
+        samples.push(f64::from(batch) / sample_start.elapsed().as_secs_f64());
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+    Ok((mean, variance.sqrt()))
+}
@@ -0,0 +1,40 @@
+//! Checking the syntax of programs without interpreting them.
+use anyhow::{bail, Context, Result};
+
+use crate::diagnostic::Renderer;
+
+/// Checks the syntax of the given files and prints all errors found. Returns
+/// an error if any of the files could not be read or contains syntax errors.
+pub fn check_files<'a, I>(file_paths: I, renderer: Renderer) -> Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut error_count = 0;
+    for file_path in file_paths {
+        let source = std::fs::read_to_string(file_path)
+            .with_context(|| format!("failed to read program from file `{}`", file_path))?;
+
+        for (error, span) in rusht::syntax_errors(&source) {
+            eprint!(
+                "{}",
+                renderer.render(&error, file_path, &source, Some(span))
+            );
+            error_count += 1;
+        }
+    }
+
+    if error_count > 0 {
+        bail!("found {} syntax error(s)", error_count);
+    }
+    Ok(())
+}
+
+/// Returns the one-based line and column of the given byte offset.
+pub fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
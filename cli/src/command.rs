@@ -0,0 +1,164 @@
+//! REPL commands, which start with a colon and are handled by the REPL
+//! itself instead of being interpreted, e.g. `:save session.rusht`.
+use std::ffi::OsStr;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use rusht::Interpreter;
+
+use crate::debug::ReplDebugger;
+
+/// The character starting a command.
+pub const PREFIX: char = ':';
+
+const HELP: &str = "\
+:save FILE     saves the current definitions to a file
+:restore FILE  restores the definitions saved to a file
+:load FILE     interprets a file, so it can be reloaded later on
+:reload [NAME] interprets all loaded files and required modules again, or only those named NAME
+:break NAME    pauses the program before every call of a function
+:step          pauses the program before the next call
+:continue      continues a paused program
+:locals        prints the arguments of the current call of a paused program
+:set NAME on   enables a setting (show-meta: prints the type and evaluation time)
+:set NAME off  disables a setting
+:help          prints this help";
+
+/// The settings of the REPL, which can be changed using `:set`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Settings {
+    /// Whether the type and evaluation time are printed after each result.
+    pub show_meta: bool,
+}
+
+/// Runs the given command line, e.g. `:save session.rusht`.
+///
+/// # Arguments
+///
+/// * `interpreter` - The interpreter of the REPL.
+/// * `debugger` - The debugger attached to the interpreter.
+/// * `settings` - The settings of the REPL.
+/// * `line` - The command line, including the leading colon.
+pub fn run(
+    interpreter: &mut Interpreter,
+    debugger: &ReplDebugger,
+    settings: &mut Settings,
+    line: &str,
+) -> Result<()> {
+    let mut words = line.trim_start_matches(PREFIX).split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some("save"), Some(path), None) => save(interpreter, path),
+        (Some("restore"), Some(path), None) => restore(interpreter, path),
+        (Some("load"), Some(path), None) => load(interpreter, path),
+        (Some(name @ ("save" | "restore" | "load")), _, _) => bail!("usage: :{} FILE", name),
+        (Some("reload" | "r"), name, None) => reload(interpreter, name),
+        (Some("break" | "b"), Some(name), None) => {
+            debugger.add_breakpoint(name);
+            Ok(())
+        }
+        (Some("break" | "b"), _, _) => bail!("usage: :break NAME"),
+        (Some("step" | "s"), None, _) => {
+            debugger.step();
+            println!("pausing before the next call");
+            Ok(())
+        }
+        (Some("continue" | "c" | "locals" | "l"), None, _) => bail!("no program is paused"),
+        (Some("set"), Some(name), Some(value)) if words.next().is_none() => {
+            let value = match value {
+                "on" => true,
+                "off" => false,
+                _ => bail!("expected `on` or `off`, found `{}`", value),
+            };
+            match name {
+                "show-meta" => settings.show_meta = value,
+                _ => bail!("unknown setting `{}`", name),
+            }
+            Ok(())
+        }
+        (Some("set"), _, _) => bail!("usage: :set NAME on|off"),
+        (Some("help" | "h"), None, _) => {
+            println!("{}", HELP);
+            Ok(())
+        }
+        (Some(name), _, _) => bail!("unknown command `:{}`", name),
+        (None, _, _) => bail!("missing command after `:`"),
+    }
+}
+
+/// Saves the current definitions as source code to the given file, except
+/// for the variables defined by the REPL itself, and prints the names of the
+/// definitions, that can't be written as source code.
+fn save(interpreter: &Interpreter, path: &str) -> Result<()> {
+    let (source, skipped) = interpreter.to_source_filtered(|name| !is_internal(name));
+    std::fs::write(path, source)
+        .with_context(|| format!("failed to save session to `{}`", path))?;
+    if !skipped.is_empty() {
+        let names = skipped
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>();
+        println!("skipped {}, which can't be saved", names.join(", "));
+    }
+    Ok(())
+}
+
+/// Returns whether the variable of the given name is defined by the REPL,
+/// i.e. the arguments or one of the previous results.
+fn is_internal(name: &str) -> bool {
+    name == "argv"
+        || name == crate::REPL_LAST_RESULT_VARIABLE
+        || name
+            .strip_prefix(crate::REPL_RESULT_VARIABLE_PREFIX)
+            .is_some_and(|count| count.parse::<usize>().is_ok())
+}
+
+/// Interprets the given file and prints the value of its last expression.
+fn load(interpreter: &mut Interpreter, path: &str) -> Result<()> {
+    let value = interpreter
+        .load(path)
+        .with_context(|| format!("failed to load `{}`", path))?;
+    println!("{}", value);
+    Ok(())
+}
+
+/// Reloads the loaded files and required modules of the given name, or all
+/// of them if no name is given. A file matches a name if its path or the
+/// name of the file without extension is equal to it.
+fn reload(interpreter: &mut Interpreter, name: Option<&str>) -> Result<()> {
+    let files = interpreter
+        .loaded_files()
+        .into_iter()
+        .filter(|path| {
+            name.is_none_or(|name| {
+                path == Path::new(name) || path.file_stem() == Some(OsStr::new(name))
+            })
+        })
+        .collect::<Vec<_>>();
+    match (name, files.is_empty()) {
+        (Some(name), true) => bail!("no file named `{}` has been loaded", name),
+        (None, true) => bail!("no files have been loaded"),
+        _ => {}
+    }
+
+    for path in files {
+        interpreter
+            .reload(&path)
+            .with_context(|| format!("failed to reload `{}`", path.display()))?;
+        println!("reloaded `{}`", path.display());
+    }
+    Ok(())
+}
+
+/// Restores the definitions saved to the given file.
+fn restore(interpreter: &mut Interpreter, path: &str) -> Result<()> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read session from `{}`", path))?;
+    if source.trim().is_empty() {
+        return Ok(());
+    }
+    interpreter
+        .interpret_all(source)
+        .with_context(|| format!("failed to restore session from `{}`", path))?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! Tab completion of special forms, prelude functions, and user definitions
+//! in the REPL.
+use std::sync::RwLock;
+
+use linefeed::{Completer, Completion, Prompter, Terminal};
+
+/// The characters separating the words to be completed.
+pub const WORD_BREAK_CHARS: &str = " \t\n()\"";
+
+/// A completer offering the names defined in the interpreter. As the
+/// interpreter itself can't be shared with the terminal interface, the names
+/// are stored separately and have to be updated after each evaluation.
+#[derive(Debug, Default)]
+pub struct NameCompleter {
+    names: RwLock<Vec<String>>,
+}
+
+impl NameCompleter {
+    /// Replaces the names offered for completion.
+    pub fn set_names(&self, names: Vec<String>) {
+        if let Ok(mut current) = self.names.write() {
+            *current = names;
+        }
+    }
+}
+
+impl<Term: Terminal> Completer<Term> for NameCompleter {
+    fn complete(
+        &self,
+        word: &str,
+        _: &Prompter<Term>,
+        _: usize,
+        _: usize,
+    ) -> Option<Vec<Completion>> {
+        let names = self.names.read().ok()?;
+        Some(
+            names
+                .iter()
+                .filter(|name| name.starts_with(word))
+                .cloned()
+                .map(Completion::simple)
+                .collect(),
+        )
+    }
+}
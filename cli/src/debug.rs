@@ -0,0 +1,127 @@
+//! The debugger of the REPL. It pauses programs at breakpoints, after
+//! stepping, and when an error occurs, and then reads debugger commands,
+//! e.g. `:locals`, until the program is continued.
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use linefeed::{DefaultTerminal, Interface, ReadResult};
+
+use rusht::{Debugger, Error, Expr, Frame};
+
+const DEBUG_PROMPT: &str = "debug> ";
+const DEBUG_HELP: &str =
+    "the program is paused, use :step, :continue, :locals, or :break NAME to continue";
+
+/// The debugger of the REPL. Clones share their breakpoints.
+#[derive(Clone)]
+pub struct ReplDebugger {
+    reader: Arc<Interface<DefaultTerminal>>,
+    state: Rc<RefCell<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The names of the functions, before whose calls the program is paused.
+    breakpoints: BTreeSet<String>,
+    /// Whether the program is paused before the next call.
+    stepping: bool,
+}
+
+impl ReplDebugger {
+    /// Creates a new debugger reading commands using the given reader.
+    pub fn new(reader: Arc<Interface<DefaultTerminal>>) -> ReplDebugger {
+        ReplDebugger {
+            reader,
+            state: Rc::default(),
+        }
+    }
+
+    /// Pauses the program before every call of the function of the given
+    /// name.
+    pub fn add_breakpoint(&self, name: &str) {
+        self.state.borrow_mut().breakpoints.insert(name.to_string());
+    }
+
+    /// Pauses the program before the next call of any function.
+    pub fn step(&self) {
+        self.state.borrow_mut().stepping = true;
+    }
+
+    /// Reads and runs debugger commands, until the program is continued.
+    fn pause(&self, frames: &[Frame]) -> Result<()> {
+        self.reader
+            .set_prompt(DEBUG_PROMPT)
+            .context("failed to set prompt")?;
+
+        while let ReadResult::Input(line) =
+            self.reader.read_line().context("failed to read line")?
+        {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (Some(":step" | ":s"), None, _) => {
+                    self.step();
+                    break;
+                }
+                (Some(":continue" | ":c"), None, _) => break,
+                (Some(":locals" | ":l"), None, _) => print_locals(frames),
+                (Some(":break" | ":b"), Some(name), None) => self.add_breakpoint(name),
+                (None, _, _) => {}
+                _ => println!("{}", DEBUG_HELP),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debugger for ReplDebugger {
+    fn on_call(&self, frames: &[Frame]) {
+        let Some(frame) = frames.last() else {
+            return;
+        };
+        let pause = {
+            let mut state = self.state.borrow_mut();
+            let pause = state.stepping || state.breakpoints.contains(&frame.name);
+            state.stepping = false;
+            pause
+        };
+
+        if pause {
+            println!("paused before calling `{}`", frame.name);
+            print_locals(frames);
+            if let Err(error) = self.pause(frames) {
+                println!("{:#}", error);
+            }
+        }
+    }
+
+    fn on_error(&self, expr: &Expr, error: &Error, frames: &[Frame]) {
+        self.state.borrow_mut().stepping = false;
+        // At the top level, there is nothing to inspect besides the error
+        // itself, which is printed by the REPL anyway.
+        if frames.is_empty() {
+            return;
+        }
+
+        println!("error in `{}`: {}", expr, error);
+        print_locals(frames);
+        if let Err(error) = self.pause(frames) {
+            println!("{:#}", error);
+        }
+    }
+}
+
+/// Prints the name and local variables of the innermost call.
+fn print_locals(frames: &[Frame]) {
+    match frames.last() {
+        Some(frame) => {
+            println!("in `{}` (call depth {}):", frame.name, frames.len());
+            for (name, value) in &frame.locals {
+                println!("  {} = {}", name, value);
+            }
+        }
+        None => println!("at the top level, there are no local variables"),
+    }
+}
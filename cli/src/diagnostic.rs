@@ -0,0 +1,199 @@
+//! Rendering of errors as diagnostics, showing the error message along with
+//! the offending line of the program and a caret marking the location.
+use std::fmt::{Display, Formatter, Write};
+use std::io::IsTerminal;
+use std::ops::Range;
+
+use crate::check;
+
+/// The possible values of the `--color` flag.
+pub const COLOR_CHOICES: &[&str] = &["auto", "always", "never"];
+
+/// The environment variable disabling colors if set to a non-empty value.
+const NO_COLOR_VARIABLE: &str = "NO_COLOR";
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders errors as diagnostics.
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    /// Creates a new renderer for the given value of the `--color` flag.
+    /// With `auto`, colors are used if stderr is a terminal and `NO_COLOR`
+    /// is not set.
+    pub fn new(color_choice: &str) -> Renderer {
+        let color = match color_choice {
+            "always" => true,
+            "never" => false,
+            _ => {
+                std::env::var_os(NO_COLOR_VARIABLE).is_none_or(|v| v.is_empty())
+                    && std::io::stderr().is_terminal()
+            }
+        };
+        Renderer { color }
+    }
+
+    /// Returns whether colors are enabled.
+    pub fn color(self) -> bool {
+        self.color
+    }
+
+    /// Renders an error of the given program. Syntax errors are located in
+    /// the source, while other errors only refer to the program as a whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error to render.
+    /// * `name` - The name of the program, e.g. its file path.
+    /// * `source` - The source code of the program.
+    pub fn render_error(self, error: &rusht::Error, name: &str, source: &str) -> String {
+        let span = rusht::syntax_errors(source)
+            .into_iter()
+            .find(|(syntax_error, _)| syntax_error == error)
+            .map(|(_, span)| span);
+        self.render(error, name, source, span)
+    }
+
+    /// Renders the given message. If a span is given, the location is
+    /// printed along with the line it starts in and a caret marking it.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message describing the error.
+    /// * `name` - The name of the program, e.g. its file path.
+    /// * `source` - The source code of the program.
+    /// * `span` - The byte range of the error in the source, if known.
+    pub fn render(
+        self,
+        message: &dyn Display,
+        name: &str,
+        source: &str,
+        span: Option<Range<usize>>,
+    ) -> String {
+        let mut out = format!(
+            "{}: {}\n",
+            self.paint(RED, "error"),
+            self.paint(BOLD, message)
+        );
+        let Some(span) = span else {
+            let _ = writeln!(out, "{} {}", self.paint(BLUE, "-->"), name);
+            return out;
+        };
+
+        let (line, column) = check::position(source, span.start);
+        let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let text = source[line_start..].lines().next().unwrap_or_default();
+        let end = span.end.min(line_start + text.len()).max(span.start);
+        let width = source[span.start..end].chars().count().max(1);
+        let gutter = " ".repeat(line.to_string().len());
+
+        let _ = writeln!(
+            out,
+            "{}{} {}:{}:{}",
+            gutter,
+            self.paint(BLUE, "-->"),
+            name,
+            line,
+            column
+        );
+        let _ = writeln!(out, "{} {}", gutter, self.paint(BLUE, "|"));
+        let _ = writeln!(
+            out,
+            "{} {} {}",
+            self.paint(BLUE, line),
+            self.paint(BLUE, "|"),
+            text
+        );
+        let _ = writeln!(
+            out,
+            "{} {} {}{}",
+            gutter,
+            self.paint(BLUE, "|"),
+            " ".repeat(column - 1),
+            self.paint(RED, "^".repeat(width))
+        );
+        out
+    }
+
+    /// Returns the given text in the given style, if colors are enabled.
+    fn paint<T>(self, style: &'static str, text: T) -> Painted<T>
+    where
+        T: Display,
+    {
+        Painted {
+            style: if self.color { Some(style) } else { None },
+            text,
+        }
+    }
+}
+
+/// A text, that is displayed in a style.
+struct Painted<T> {
+    style: Option<&'static str>,
+    text: T,
+}
+
+impl<T> Display for Painted<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.style {
+            Some(style) => write!(f, "{}{}{}", style, self.text, RESET),
+            None => write!(f, "{}", self.text),
+        }
+    }
+}
+
+/// The exit code of failures, that are neither syntax nor IO errors.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// The exit code of programs, that could not be parsed.
+pub const EXIT_SYNTAX_ERROR: i32 = 2;
+
+/// The exit code of failures to read files.
+pub const EXIT_IO_ERROR: i32 = 3;
+
+/// Returns the exit code for the given error of a program. Programs exiting
+/// using `(exit code)` exit with the given code.
+pub fn exit_code(error: &rusht::Error) -> i32 {
+    use rusht::Error;
+
+    match error {
+        Error::Exit(code) => *code,
+        Error::Interrupted(signal) => 128 + signal.number(),
+        Error::UnexpectedEndOfTokenStream
+        | Error::UnexpectedClosingParenthesis
+        | Error::MissingClosingParenthesis
+        | Error::MismatchedParenthesis(..)
+        | Error::MissingMapValue
+        | Error::UnterminatedString
+        | Error::InvalidNumber(_)
+        | Error::InvalidCharacter(_)
+        | Error::UnexpectedCharacter(_) => EXIT_SYNTAX_ERROR,
+        Error::FileNotReadable(_)
+        | Error::ModuleNotFound(_)
+        | Error::ProcessFailed(_)
+        | Error::NetworkFailed(_) => EXIT_IO_ERROR,
+        _ => EXIT_FAILURE,
+    }
+}
+
+/// An error, that has already been reported to the user, so it is not
+/// printed again when exiting. It contains the exit code of the process.
+#[derive(Debug)]
+pub struct Reported(pub i32);
+
+impl Display for Reported {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error has already been reported")
+    }
+}
+
+impl std::error::Error for Reported {}
@@ -0,0 +1,107 @@
+//! Generation of Markdown documentation, as done by the `doc` subcommand.
+//! Every top-level definition of a script is documented with its signature
+//! and docstring, e.g. `(def add "Adds two numbers." (func (a b) (+ a b)))`.
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use rusht::Expr;
+
+use crate::forms;
+
+/// The extension of script files, that are documented when searching
+/// directories.
+const SCRIPT_FILE_SUFFIX: &str = ".rusht";
+
+/// Returns the documentation of the definitions in the given files and the
+/// script files found recursively in the given directories, followed by the
+/// documentation of the builtins, as Markdown.
+///
+/// # Arguments
+///
+/// * `paths` - The paths of the files and directories.
+pub fn generate<'a, I>(paths: I) -> Result<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut files = vec![];
+    for path in paths {
+        forms::collect_files(Path::new(path), SCRIPT_FILE_SUFFIX, &mut files)
+            .with_context(|| format!("failed to find script files in `{}`", path))?;
+    }
+    files.sort();
+
+    let mut out = String::from("# Reference\n");
+    for file in files {
+        let source = std::fs::read_to_string(&file)
+            .with_context(|| format!("failed to read `{}`", file.display()))?;
+        let exprs = rusht::parse(&source)
+            .with_context(|| format!("failed to parse `{}`", file.display()))?;
+
+        let _ = write!(out, "\n## {}\n", file.display());
+        for (signature, docstring) in exprs.iter().filter_map(definition) {
+            let _ = write!(out, "\n### `{}`\n", signature);
+            if let Some(docstring) = docstring {
+                let _ = write!(out, "\n{}\n", docstring);
+            }
+        }
+    }
+
+    out.push_str("\n## Builtins\n");
+    for doc in rusht::builtin_docs() {
+        let _ = write!(out, "\n### `{}`\n\n{}\n", doc.signature, doc.description);
+    }
+    Ok(out)
+}
+
+/// Returns the signature and docstring of the given expression, if it is a
+/// definition using `def` or `defn`. The signature of a function lists its
+/// arguments, e.g. `(add a b)`.
+fn definition(expr: &Expr) -> Option<(String, Option<&str>)> {
+    let (name, docstring, value) = match expr {
+        Expr::List(list) => match list.as_slice() {
+            [Expr::Ident(def), Expr::Ident(name), value] if def == "def" => (name, None, value),
+            [Expr::Ident(def), Expr::Ident(name), Expr::Str(doc), value] if def == "def" => {
+                (name, Some(doc.as_str()), value)
+            }
+            [Expr::Ident(defn), Expr::Ident(name), Expr::List(args), _, ..] if defn == "defn" => {
+                return Some((function_signature(name, args), None));
+            }
+            [Expr::Ident(defn), Expr::Ident(name), Expr::Str(doc), Expr::List(args), _, ..]
+                if defn == "defn" =>
+            {
+                return Some((function_signature(name, args), Some(doc.as_str())));
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let signature = match value {
+        Expr::List(list) => match list.as_slice() {
+            [Expr::Ident(func), Expr::List(args), _, ..] if func == "func" => {
+                function_signature(name, args)
+            }
+            _ => name.clone(),
+        },
+        _ => name.clone(),
+    };
+    Some((signature, docstring))
+}
+
+/// Returns the signature of a function of the given name and arguments, e.g.
+/// `(add a b)`, or only the name, if the function takes no arguments.
+fn function_signature(name: &str, args: &[Expr]) -> String {
+    if args.is_empty() {
+        return name.to_string();
+    }
+    let args = args.iter().map(ToString::to_string);
+    format!(
+        "({})",
+        std::iter::once(name.to_string())
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
@@ -0,0 +1,116 @@
+//! Custom line editing commands of the REPL, supporting bracketed paste and
+//! inputs spanning multiple lines.
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use linefeed::{Command, DefaultTerminal, Interface, Prompter};
+
+use crate::vi;
+
+/// The possible values of the `--keybindings` flag.
+pub const KEYBINDINGS: &[&str] = &["emacs", "vi"];
+
+/// Enables bracketed paste, making the terminal surround pasted text with
+/// the `PASTE_START` and `PASTE_END` sequences.
+const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+/// The sequence sent by Alt+Enter, which inserts a newline without
+/// accepting the input.
+const INSERT_NEWLINE: &str = "\x1b\r";
+
+/// The keybindings used to edit lines.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Keybindings {
+    /// The emacs-style keybindings of linefeed.
+    Emacs,
+    /// A vi mode on top of them, see the `vi` module.
+    Vi,
+}
+
+impl Keybindings {
+    /// Returns the keybindings of the given name, defaulting to emacs.
+    pub fn from_name(name: &str) -> Keybindings {
+        match name {
+            "vi" => Keybindings::Vi,
+            _ => Keybindings::Emacs,
+        }
+    }
+}
+
+/// Binds the custom commands of the REPL. Pasted text is inserted as a
+/// whole, instead of accepting the input at every newline, and Alt+Enter
+/// inserts a newline, so inputs spanning multiple lines can be edited at
+/// once. With vi keybindings, the vi mode is bound as well. The width of the terminal is stored in `columns` whenever a line
+/// is accepted, see `highlight::Highlighter`.
+pub fn bind(
+    reader: &Interface<DefaultTerminal>,
+    columns: Arc<AtomicUsize>,
+    keybindings: Keybindings,
+) {
+    let vi = match keybindings {
+        Keybindings::Vi => Some(vi::bind(reader)),
+        Keybindings::Emacs => None,
+    };
+    let pasting = Arc::new(AtomicBool::new(false));
+
+    let start = pasting.clone();
+    let paste_vi = vi.clone();
+    define(reader, "rusht-paste-start", PASTE_START, move |_| {
+        start.store(true, Ordering::Relaxed);
+        // Pasted text is inserted, instead of being executed as commands.
+        if let Some(vi) = &paste_vi {
+            vi.reset();
+        }
+        Ok(())
+    });
+    let end = pasting.clone();
+    define(reader, "rusht-paste-end", PASTE_END, move |_| {
+        end.store(false, Ordering::Relaxed);
+        Ok(())
+    });
+    define(reader, "rusht-insert-newline", INSERT_NEWLINE, |prompter| {
+        prompter.insert(1, '\n')
+    });
+    define(reader, "rusht-accept-line", "\r", move |prompter| {
+        if pasting.load(Ordering::Relaxed) {
+            prompter.insert(1, '\n')
+        } else {
+            columns.store(prompter.screen_size().columns, Ordering::Relaxed);
+            if let Some(vi) = &vi {
+                vi.reset();
+            }
+            prompter.accept_input()
+        }
+    });
+    reader.bind_sequence("\n", Command::from_str("rusht-accept-line"));
+}
+
+/// Defines a command of the given name and binds it to the given sequence.
+fn define<F>(reader: &Interface<DefaultTerminal>, name: &'static str, sequence: &'static str, f: F)
+where
+    F: Fn(&mut Prompter<DefaultTerminal>) -> io::Result<()> + Send + Sync + 'static,
+{
+    reader.define_function(
+        name,
+        Arc::new(move |prompter: &mut Prompter<DefaultTerminal>, _, _| f(prompter)),
+    );
+    reader.bind_sequence(sequence, Command::from_str(name));
+}
+
+/// Enables or disables bracketed paste in the terminal.
+pub fn set_bracketed_paste(enabled: bool) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(
+        if enabled {
+            ENABLE_BRACKETED_PASTE
+        } else {
+            DISABLE_BRACKETED_PASTE
+        }
+        .as_bytes(),
+    )?;
+    stdout.flush()
+}
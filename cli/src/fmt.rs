@@ -0,0 +1,54 @@
+//! Formatting of script files, as done by the `fmt` subcommand.
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::forms;
+
+/// The extension of script files, that are formatted when searching
+/// directories.
+const SCRIPT_FILE_SUFFIX: &str = ".rusht";
+
+/// Formats the given files and the script files found recursively in the
+/// given directories in place. If `check` is set, the files are not changed,
+/// but an error is returned if any of them is not formatted.
+///
+/// # Arguments
+///
+/// * `paths` - The paths of the files and directories.
+/// * `check` - Whether to only check if the files are formatted.
+pub fn format_files<'a, I>(paths: I, check: bool) -> Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut files = vec![];
+    for path in paths {
+        forms::collect_files(Path::new(path), SCRIPT_FILE_SUFFIX, &mut files)
+            .with_context(|| format!("failed to find script files in `{}`", path))?;
+    }
+    files.sort();
+
+    let mut unformatted = 0;
+    for file in files {
+        let source = std::fs::read_to_string(&file)
+            .with_context(|| format!("failed to read `{}`", file.display()))?;
+        let formatted = rusht::format(&source)
+            .with_context(|| format!("failed to format `{}`", file.display()))?;
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("{} is not formatted", file.display());
+            unformatted += 1;
+        } else {
+            std::fs::write(&file, formatted)
+                .with_context(|| format!("failed to write `{}`", file.display()))?;
+        }
+    }
+
+    if unformatted > 0 {
+        bail!("{} file(s) are not formatted", unformatted);
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Collecting named forms, such as tests defined using `deftest`, from script
+//! files.
+use std::path::{Path, PathBuf};
+
+use rusht::{Expr, Interpreter};
+
+/// A named form, e.g. `(deftest name body...)`, with its name and body.
+pub type NamedForm = (String, Vec<Expr>);
+
+/// Interprets all forms of the given source except for those starting with
+/// the given identifier, which are returned by name along with their bodies.
+///
+/// # Arguments
+///
+/// * `interpreter` - The interpreter, in which the other forms are
+///     interpreted.
+/// * `source` - The source code.
+/// * `ident` - The identifier starting the forms to return, e.g. `deftest`.
+pub fn split(
+    interpreter: &mut Interpreter,
+    source: &str,
+    ident: &str,
+) -> rusht::Result<Vec<NamedForm>> {
+    let mut forms = vec![];
+    for expr in rusht::parse(source)? {
+        match expr {
+            Expr::List(list) if matches!(list.first(), Some(Expr::Ident(i)) if i == ident) => {
+                match list.get(1) {
+                    Some(Expr::Ident(name)) => forms.push((name.clone(), list[2..].to_vec())),
+                    _ => return Err(rusht::Error::UnexpectedType),
+                }
+            }
+            expr => {
+                interpreter.eval(expr)?;
+            }
+        }
+    }
+    Ok(forms)
+}
+
+/// Adds the given file or the files with names ending in the given suffix
+/// found recursively in the given directory to `files`.
+pub fn collect_files(path: &Path, suffix: &str, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, suffix, files)?;
+        } else if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(suffix))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
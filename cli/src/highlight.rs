@@ -0,0 +1,191 @@
+//! Syntax highlighting of REPL input using `Interpreter::classify`. As
+//! linefeed can't style the line being edited, each line is redrawn
+//! highlighted once it has been entered, along with the previous lines of an
+//! input spanning multiple lines, so unbalanced parentheses are only marked
+//! as long as the input is incomplete.
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use rusht::{Interpreter, TokenClass};
+
+/// The colors of parentheses, chosen by their depth.
+const RAINBOW: &[&str] = &["\x1b[33m", "\x1b[35m", "\x1b[36m", "\x1b[32m", "\x1b[34m"];
+const CYAN: &str = "\x1b[36m";
+const GREEN: &str = "\x1b[32m";
+const MAGENTA: &str = "\x1b[35m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[1;34m";
+/// The warning color of invalid tokens, e.g. unbalanced parentheses.
+const RED: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+/// The markers of linefeed around invisible parts of the prompt, e.g. escape
+/// sequences.
+const START_INVISIBLE: char = '\x01';
+const END_INVISIBLE: char = '\x02';
+
+/// Redraws the lines of the current input highlighted.
+#[derive(Debug)]
+pub struct Highlighter {
+    enabled: bool,
+    /// The width of the terminal when the last line was entered, which is
+    /// updated by the command accepting a line.
+    columns: Arc<AtomicUsize>,
+    /// The prompts and lines of the current input.
+    lines: Vec<(String, String)>,
+}
+
+impl Highlighter {
+    /// Creates a new highlighter, which does nothing unless enabled.
+    pub fn new(enabled: bool) -> Highlighter {
+        Highlighter {
+            enabled,
+            columns: Arc::new(AtomicUsize::new(0)),
+            lines: vec![],
+        }
+    }
+
+    /// Returns the width of the terminal to be updated whenever a line is
+    /// entered, see `editing::bind`.
+    pub fn columns(&self) -> Arc<AtomicUsize> {
+        self.columns.clone()
+    }
+
+    /// Redraws the lines of the current input highlighted after the given line
+    /// has been entered.
+    ///
+    /// # Arguments
+    ///
+    /// * `interpreter` - The interpreter, whose definitions are highlighted
+    ///     as functions.
+    /// * `prompt` - The prompt, after which the line was entered.
+    /// * `line` - The line.
+    pub fn line_entered(
+        &mut self,
+        interpreter: &Interpreter,
+        prompt: &str,
+        line: &str,
+    ) -> io::Result<()> {
+        // Only the last line of the prompt is redrawn.
+        let prompt = prompt.rsplit('\n').next().unwrap_or_default();
+        self.lines.push((prompt.to_string(), line.to_string()));
+
+        let columns = self.columns.load(Ordering::Relaxed);
+        // The width of other characters, e.g. tabs, wide characters, or
+        // escape sequences, is not computed, so lines containing them are
+        // kept as written.
+        let plain = |s: &str| {
+            s.chars()
+                .all(|c| c == '\n' || c == ' ' || c.is_ascii_graphic())
+        };
+        if !self.enabled
+            || columns == 0
+            || !self
+                .lines
+                .iter()
+                .all(|(prompt, line)| plain(&visible(prompt)) && plain(line))
+        {
+            return Ok(());
+        }
+
+        // The cursor is on the row after each line, as if the line had
+        // been drawn by linefeed, which ends full rows with a space and a
+        // carriage return.
+        let rows = self
+            .lines
+            .iter()
+            .map(|(prompt, line)| width(&visible(prompt), line, columns) / columns + 1)
+            .sum::<usize>();
+        let input = self
+            .lines
+            .iter()
+            .map(|(_, line)| line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let classes = interpreter.classify(&input);
+
+        let mut out = format!("\x1b[{}A\r\x1b[J", rows);
+        let mut offset = 0;
+        for (prompt, line) in &self.lines {
+            out.push_str(&prompt.replace([START_INVISIBLE, END_INVISIBLE], ""));
+            let mut column = visible(prompt).len();
+            let end = offset + line.len();
+            let mut classes = classes
+                .iter()
+                .filter(|(span, class)| {
+                    span.start >= offset && span.end <= end && !color(*class).is_empty()
+                })
+                .peekable();
+            for (i, c) in line.char_indices() {
+                let i = offset + i;
+                if let Some((span, class)) = classes.peek() {
+                    if span.start == i {
+                        out.push_str(color(*class));
+                    }
+                }
+                out.push(c);
+                if let Some((span, _)) = classes.peek() {
+                    if span.end == i + 1 {
+                        out.push_str(RESET);
+                        classes.next();
+                    }
+                }
+                column = if c == '\n' { 0 } else { column + 1 };
+                if column == columns {
+                    out.push_str(" \r");
+                    column = 0;
+                }
+            }
+            out.push('\n');
+            offset = end + 1;
+        }
+
+        let mut stdout = io::stdout();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+
+    /// Forgets the lines of the current input, which is complete.
+    pub fn input_complete(&mut self) {
+        self.lines.clear();
+    }
+}
+
+/// Returns the given prompt without its invisible parts.
+fn visible(prompt: &str) -> String {
+    let mut visible = String::new();
+    let mut hidden = false;
+    for c in prompt.chars() {
+        match c {
+            START_INVISIBLE => hidden = true,
+            END_INVISIBLE => hidden = false,
+            _ if !hidden => visible.push(c),
+            _ => {}
+        }
+    }
+    visible
+}
+
+/// Returns the number of columns taken up by the given prompt and line, where
+/// newlines take up the rest of their row.
+fn width(prompt: &str, line: &str, columns: usize) -> usize {
+    line.chars().fold(prompt.len(), |column, c| match c {
+        '\n' => column + columns - column % columns,
+        _ => column + 1,
+    })
+}
+
+/// Returns the escape sequence coloring tokens of the given class.
+fn color(class: TokenClass) -> &'static str {
+    match class {
+        TokenClass::Paren(depth) => RAINBOW[depth % RAINBOW.len()],
+        TokenClass::Num => CYAN,
+        TokenClass::Str | TokenClass::Char => GREEN,
+        TokenClass::Bool | TokenClass::Nil | TokenClass::Quote => MAGENTA,
+        TokenClass::Keyword => YELLOW,
+        TokenClass::Function => BLUE,
+        TokenClass::Invalid => RED,
+        TokenClass::Ident => "",
+    }
+}
@@ -1,85 +1,741 @@
 #![forbid(unsafe_code)]
 #![deny(clippy::pedantic)]
 
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
-use clap::{App, Arg};
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
 use linefeed::{DefaultTerminal, Interface, ReadResult};
 
-use rusht::Interpreter;
+use rusht::{Error, Expr, Interpreter, Timings};
+
+use crate::command::Settings;
+use crate::complete::NameCompleter;
+use crate::debug::ReplDebugger;
+use crate::diagnostic::{Renderer, Reported};
+use crate::output::Format;
+
+mod bench;
+mod check;
+mod command;
+mod complete;
+mod debug;
+mod diagnostic;
+mod doc;
+mod editing;
+mod fmt;
+mod forms;
+mod highlight;
+mod output;
+mod server;
+mod test_runner;
+mod vi;
 
 const PROGRAM_NAME: &str = "rusht";
 const REPL_PROMPT: &str = "rusht> ";
+const REPL_CONTINUATION_PROMPT: &str = "....> ";
+const REPL_INIT_FILE_NAME: &str = ".rushtrc";
+const REPL_PROMPT_VARIABLE: &str = "*prompt*";
+const REPL_KEYBINDINGS_VARIABLE: &str = "*keybindings*";
+const REPL_LAST_RESULT_VARIABLE: &str = "_";
+const REPL_RESULT_VARIABLE_PREFIX: char = '$';
 const REPL_HISTORY_FILE_NAME: &str = ".rusht_history";
-const REPL_HISTORY_SIZE: usize = 100;
+const REPL_HISTORY_FILE_VARIABLE: &str = "RUSHT_HISTORY";
+const REPL_HISTORY_SIZE: &str = "100";
+const MODULE_PATH_VARIABLE: &str = "RUSHT_PATH";
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+fn main() {
+    if let Err(error) = run() {
+        // Errors, that have already been reported, are not printed again.
+        let code = if let Some(Reported(code)) = error.downcast_ref() {
+            *code
+        } else {
+            eprintln!("Error: {:?}", error);
+            failure_exit_code(&error)
+        };
+        std::process::exit(code);
+    }
+}
 
-fn main() -> Result<()> {
-    let matches = App::new(PROGRAM_NAME)
+/// Returns the exit code for an error, that has not been reported, based on
+/// its innermost known cause.
+fn failure_exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .chain()
+        .find_map(|cause| match cause.downcast_ref::<Error>() {
+            Some(error) => Some(diagnostic::exit_code(error)),
+            None => cause
+                .is::<std::io::Error>()
+                .then_some(diagnostic::EXIT_IO_ERROR),
+        })
+        .unwrap_or(diagnostic::EXIT_FAILURE)
+}
+
+/// Returns the directories searched for modules: the directory of the entry
+/// script (or the current directory, if there is none), followed by the
+/// directories passed using `--path` and those listed in `RUSHT_PATH`.
+///
+/// # Arguments
+///
+/// * `file` - The path of the entry script, if any.
+/// * `dirs` - The directories passed using `--path`.
+fn search_path<'a, I>(file: Option<&str>, dirs: I) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let entry_dir = file
+        .and_then(|file| Path::new(file).parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    let env_dirs = std::env::var_os(MODULE_PATH_VARIABLE)
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    std::iter::once(entry_dir)
+        .chain(dirs.into_iter().map(PathBuf::from))
+        .chain(env_dirs)
+        .collect()
+}
+
+/// Returns the definition of the command line arguments and subcommands.
+#[allow(clippy::too_many_lines)]
+fn app() -> App<'static> {
+    App::new(PROGRAM_NAME)
         .version("0.1.0")
         .author("Isabella Sch\u{f6}n, Marc Tr\u{f6}litzsch")
         .arg(Arg::new("FILE").about("program read from script file"))
-        .get_matches();
+        .arg(
+            Arg::new("eval")
+                .short('e')
+                .long("eval")
+                .value_name("EXPR")
+                .about("evaluates the given expressions, prints the result, and exits")
+                .takes_value(true)
+                .conflicts_with("FILE"),
+        )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .value_name("FILE")
+                .about("checks the syntax of the given files without interpreting them")
+                .takes_value(true)
+                .multiple(true)
+                .conflicts_with_all(&["FILE", "eval"]),
+        )
+        .arg(
+            Arg::new("dump-tokens")
+                .long("dump-tokens")
+                .about("prints the tokens of the program instead of interpreting it"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .about("does not print the value of the program"),
+        )
+        .arg(
+            Arg::new("time")
+                .long("time")
+                .about("prints the time spent tokenizing, parsing, and evaluating the program")
+                .requires("FILE"),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .about("re-runs the program whenever the file or a module it requires changes")
+                .requires("FILE")
+                .conflicts_with("time"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("FORMAT")
+                .about("the format in which the result is printed")
+                .takes_value(true)
+                .possible_values(output::FORMATS)
+                .default_value("text"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .about("whether errors and REPL input are printed in color (disabled in auto mode if NO_COLOR is set)")
+                .takes_value(true)
+                .possible_values(diagnostic::COLOR_CHOICES)
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::new("no-init")
+                .long("no-init")
+                .about("skips interpreting the init file (~/.rushtrc) when starting the REPL"),
+        )
+        .arg(
+            Arg::new("keybindings")
+                .long("keybindings")
+                .value_name("KEYMAP")
+                .about("the keybindings of the REPL (default: *keybindings* from the init file or emacs)")
+                .takes_value(true)
+                .possible_values(editing::KEYBINDINGS),
+        )
+        .arg(
+            Arg::new("history-file")
+                .long("history-file")
+                .value_name("PATH")
+                .about("the file, in which the REPL history is stored (default: ~/.rusht_history)")
+                .takes_value(true)
+                .env(REPL_HISTORY_FILE_VARIABLE),
+        )
+        .arg(
+            Arg::new("history-size")
+                .long("history-size")
+                .value_name("SIZE")
+                .about("the maximum number of entries kept in the REPL history")
+                .takes_value(true)
+                .default_value(REPL_HISTORY_SIZE),
+        )
+        .arg(
+            Arg::new("history-ignore-space")
+                .long("history-ignore-space")
+                .about("does not add inputs starting with a space to the REPL history"),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("DIR")
+                .about("adds a directory to search for modules loaded using require")
+                .takes_value(true)
+                .multiple_occurrences(true),
+        )
+        .arg(
+            Arg::new("ARGS")
+                .about("arguments passed to the program as `argv`")
+                .multiple(true)
+                .last(true),
+        )
+        .arg(
+            Arg::new("plugin")
+                .long("plugin")
+                .value_name("PATH")
+                .about("loads builtins from a shared library")
+                .takes_value(true)
+                .multiple_occurrences(true),
+        )
+        .arg(
+            Arg::new("listen")
+                .long("listen")
+                .value_name("ADDR")
+                .about("serves programs sent over TCP, one per line, answering with JSON")
+                .takes_value(true)
+                .conflicts_with_all(&["FILE", "eval", "check"]),
+        )
+        .arg(
+            Arg::new("per-connection")
+                .long("per-connection")
+                .about("gives each connection its own environment instead of a shared one")
+                .requires("listen"),
+        )
+        .arg(
+            Arg::new("allow-io")
+                .long("allow-io")
+                .about("allows served programs to spawn processes and open sockets")
+                .requires("listen"),
+        )
+        .subcommand(
+            App::new("test")
+                .about("runs the tests defined using deftest in files ending in _test.rusht")
+                .arg(
+                    Arg::new("PATH")
+                        .about("the test files or directories to search for test files")
+                        .multiple(true)
+                        .default_value("."),
+                ),
+        )
+        .subcommand(
+            App::new("bench")
+                .about("runs the benchmarks defined using defbench in files ending in _bench.rusht")
+                .arg(
+                    Arg::new("PATH")
+                        .about("the benchmark files or directories to search for benchmark files")
+                        .multiple(true)
+                        .default_value("."),
+                )
+                .arg(
+                    Arg::new("duration")
+                        .long("duration")
+                        .value_name("SECONDS")
+                        .about("the target duration of each benchmark")
+                        .takes_value(true)
+                        .validator(|s| match s.parse().map(Duration::try_from_secs_f64) {
+                            Ok(Ok(duration)) if !duration.is_zero() => Ok(()),
+                            _ => Err("expected a positive number of seconds, that isn't too large"),
+                        })
+                        .default_value("1"),
+                ),
+        )
+        .subcommand(
+            App::new("fmt")
+                .about("formats script files in place")
+                .arg(
+                    Arg::new("PATH")
+                        .about("the files or directories to search for files ending in .rusht")
+                        .multiple(true)
+                        .default_value("."),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .about("checks whether the files are formatted without changing them"),
+                ),
+        )
+        .subcommand(
+            App::new("transpile")
+                .about("prints the program as a Rust function evaluating it (experimental)")
+                .arg(
+                    Arg::new("FILE")
+                        .about("the script file to transpile")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .about("the name of the generated function")
+                        .takes_value(true)
+                        .validator(|s| {
+                            let mut chars = s.chars();
+                            match chars.next() {
+                                Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+                                _ => return Err("expected a Rust identifier"),
+                            }
+                            if chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                                Ok(())
+                            } else {
+                                Err("expected a Rust identifier")
+                            }
+                        })
+                        .default_value("run"),
+                ),
+        )
+        .subcommand(
+            App::new("doc")
+                .about("prints Markdown documentation of the definitions and builtins")
+                .arg(
+                    Arg::new("PATH")
+                        .about("the files or directories to search for files ending in .rusht")
+                        .multiple(true)
+                        .default_value("."),
+                ),
+        )
+}
+
+/// Parses the command line arguments and runs the requested mode.
+fn run() -> Result<()> {
+    let matches = app().get_matches();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_args(matches.values_of("ARGS").into_iter().flatten());
+    interpreter.set_search_path(search_path(
+        matches.value_of("FILE"),
+        matches.values_of("path").into_iter().flatten(),
+    ));
+    for plugin in matches.values_of("plugin").into_iter().flatten() {
+        interpreter
+            .load_plugin(plugin)
+            .with_context(|| format!("failed to load plugin `{}`", plugin))?;
+    }
+
+    if run_subcommand(&mut interpreter, &matches)? {
+        return Ok(());
+    }
+
+    let renderer = Renderer::new(matches.value_of("color").unwrap_or_default());
+    if let Some(files) = matches.values_of("check") {
+        return check::check_files(files, renderer);
+    }
+
+    if matches.is_present("dump-tokens") {
+        let source = match (matches.value_of("eval"), matches.value_of("FILE")) {
+            (Some(input), _) => input.to_string(),
+            (None, Some(file)) => {
+                std::fs::read_to_string(file).context("failed to read program from file")?
+            }
+            (None, None) => bail!("no program to tokenize given"),
+        };
+        return dump_tokens(&source);
+    }
+
+    if let Some(address) = matches.value_of("listen") {
+        return server::listen(
+            interpreter,
+            address,
+            matches.is_present("per-connection"),
+            matches.is_present("allow-io"),
+        );
+    }
+
+    let format = Format::from_name(matches.value_of("output").unwrap_or_default());
+    let quiet = matches.is_present("quiet");
+    if let Some(input) = matches.value_of("eval") {
+        return eval(interpreter, input, format, quiet, renderer);
+    }
 
     match matches.value_of("FILE") {
-        None => start_repl(),
-        Some(file) => interpret_file(file),
+        None => {
+            let history = History {
+                path: matches
+                    .value_of_os("history-file")
+                    .map(PathBuf::from)
+                    .or_else(default_history_file_path),
+                size: matches.value_of_t_or_exit("history-size"),
+                ignore_space: matches.is_present("history-ignore-space"),
+            };
+            start_repl(
+                interpreter,
+                !matches.is_present("no-init"),
+                &history,
+                matches.value_of("keybindings"),
+                renderer,
+            )
+        }
+        Some(file) if matches.is_present("watch") => {
+            watch_file(interpreter, file, format, quiet, renderer)
+        }
+        Some(file) => interpret_file(
+            interpreter,
+            file,
+            matches.is_present("time"),
+            format,
+            quiet,
+            renderer,
+        ),
     }
 }
 
-/// Interprets the code at the given file path.
-fn interpret_file(file_path: &str) -> Result<()> {
-    let mut interpreter = Interpreter::new();
-    let result = std::fs::read_to_string(file_path)
-        .context("failed to read program from file")
-        .and_then(|file| {
-            interpreter
-                .interpret(file)
-                .context("failed to interpret file")
-        })?;
-
-    println!("{}", result);
+/// Runs the requested subcommand, if any, returning whether one was run.
+fn run_subcommand(interpreter: &mut Interpreter, args: &ArgMatches) -> Result<bool> {
+    if let Some(matches) = args.subcommand_matches("test") {
+        test_runner::run(interpreter, matches.values_of("PATH").into_iter().flatten())?;
+        return Ok(true);
+    }
+
+    if let Some(matches) = args.subcommand_matches("fmt") {
+        fmt::format_files(
+            matches.values_of("PATH").into_iter().flatten(),
+            matches.is_present("check"),
+        )?;
+        return Ok(true);
+    }
+
+    if let Some(matches) = args.subcommand_matches("transpile") {
+        let file = matches.value_of("FILE").unwrap_or_default();
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("failed to read program from `{}`", file))?;
+        let name = matches.value_of("name").unwrap_or_default();
+        print!(
+            "{}",
+            rusht::transpile(&source, name)
+                .with_context(|| format!("failed to transpile `{}`", file))?
+        );
+        return Ok(true);
+    }
+
+    if let Some(matches) = args.subcommand_matches("doc") {
+        print!(
+            "{}",
+            doc::generate(matches.values_of("PATH").into_iter().flatten())?
+        );
+        return Ok(true);
+    }
+
+    if let Some(matches) = args.subcommand_matches("bench") {
+        let duration = Duration::try_from_secs_f64(matches.value_of_t_or_exit("duration"))
+            .context("invalid benchmark duration")?;
+        bench::run(
+            interpreter,
+            matches.values_of("PATH").into_iter().flatten(),
+            duration,
+        )?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Prints the tokens of the given program, one per line, prefixed with their
+/// location.
+fn dump_tokens(source: &str) -> Result<()> {
+    for (token, span) in rusht::tokenize(source).context("failed to tokenize program")? {
+        let (line, column) = check::position(source, span.start);
+        println!(
+            "{}:{}\t{}..{}\t{:?}",
+            line, column, span.start, span.end, token
+        );
+    }
     Ok(())
 }
 
-/// Starts a new REPL.
-fn start_repl() -> Result<()> {
-    let reader = init_reader().context("failed to initialize reader")?;
+/// Interprets the given expressions and prints the value of the last one,
+/// unless `quiet` is set.
+fn eval(
+    mut interpreter: Interpreter,
+    input: &str,
+    format: Format,
+    quiet: bool,
+    renderer: Renderer,
+) -> Result<()> {
+    let result = interpreter.interpret_all(input);
+    output::print_result(format, quiet, renderer, "<eval>", input, result)
+}
 
-    let mut interpreter = Interpreter::new();
-    while let ReadResult::Input(input) = reader.read_line().context("failed to read line")? {
-        reader.add_history(input.clone());
-        match interpreter.interpret(input.as_str()) {
-            Ok(result) => println!("{}", result),
-            // The implementation of the `Display` trait generated by the
-            // thiserror crate may not be recognized properly by your editor,
-            // so the following line may show up as an error.
-            Err(error) => println!("{:?}: {}", error, error),
+/// Interprets the code at the given file path. If requested, the time spent
+/// in each step is printed to stderr afterwards.
+fn interpret_file(
+    mut interpreter: Interpreter,
+    file_path: &str,
+    time: bool,
+    format: Format,
+    quiet: bool,
+    renderer: Renderer,
+) -> Result<()> {
+    let file = std::fs::read_to_string(file_path).context("failed to read program from file")?;
+    let (result, timings) = match interpreter.interpret_timed(&file) {
+        Ok((result, timings)) => (Ok(result), timings),
+        Err(err) => (Err(err), Timings::default()),
+    };
+
+    output::print_result(format, quiet, renderer, file_path, &file, result)?;
+    if time {
+        eprintln!("tokenize: {:?}", timings.tokenize);
+        eprintln!("parse:    {:?}", timings.parse);
+        eprintln!("eval:     {:?}", timings.eval);
+    }
+    Ok(())
+}
+
+/// Interprets the code at the given file path and re-runs it whenever the
+/// file or a module it requires changes, clearing the screen before printing the new result. This
+/// function only returns once the program exits using `exit`.
+fn watch_file(
+    mut interpreter: Interpreter,
+    file_path: &str,
+    format: Format,
+    quiet: bool,
+    renderer: Renderer,
+) -> Result<()> {
+    let mut exit_code = None;
+    let mut print = |result| {
+        if let Err(Error::Exit(code)) = result {
+            exit_code = Some(code);
+            return false;
+        }
+        if std::io::stdout().is_terminal() {
+            print!("{}", CLEAR_SCREEN);
+        }
+        let source = std::fs::read_to_string(file_path).unwrap_or_default();
+        // Errors are printed as well, so there is nothing left to do.
+        let _ = output::print_result(format, quiet, renderer, file_path, &source, result);
+        true
+    };
+
+    if print(interpreter.load(file_path)) {
+        interpreter.watch(file_path, WATCH_INTERVAL, &mut print);
+    }
+    exit_code.map_or(Ok(()), |code| Err(Reported(code).into()))
+}
+
+/// The location and size of the REPL history.
+struct History {
+    /// The file, in which the history is stored, if any.
+    path: Option<PathBuf>,
+    /// The maximum number of entries kept.
+    size: usize,
+    /// Whether inputs starting with a space are not added.
+    ignore_space: bool,
+}
+
+/// Starts a new REPL. If requested, the init file is interpreted before the
+/// first prompt, e.g. to make definitions, change the prompt by defining
+/// `*prompt*`, or select keybindings, that are not given, by defining
+/// `*keybindings*`.
+fn start_repl(
+    mut interpreter: Interpreter,
+    init: bool,
+    history: &History,
+    keybindings: Option<&str>,
+    renderer: Renderer,
+) -> Result<()> {
+    println!(
+        "Rusht {} ({} {})\nType :help for a list of commands.",
+        rusht::VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    if let Some(p) = init_file_path().filter(|p| init && p.exists()) {
+        match interpreter.load(&p) {
+            Err(Error::Exit(code)) => return Err(Reported(code).into()),
+            Err(error) => {
+                let source = std::fs::read_to_string(&p).unwrap_or_default();
+                eprint!(
+                    "{}",
+                    renderer.render_error(&error, &p.display().to_string(), &source)
+                );
+            }
+            Ok(_) => {}
+        }
+    }
+
+    let reader = Arc::new(init_reader(history).context("failed to initialize reader")?);
+    let debugger = ReplDebugger::new(reader.clone());
+    interpreter.set_debugger(debugger.clone());
+    let mut current_prompt = prompt(&interpreter);
+    reader
+        .set_prompt(&current_prompt)
+        .context("failed to set prompt")?;
+    let completer = Arc::new(NameCompleter::default());
+    completer.set_names(interpreter.completions(""));
+    reader.set_completer(completer.clone());
+    let mut highlighter =
+        highlight::Highlighter::new(renderer.color() && std::io::stdout().is_terminal());
+    editing::bind(
+        &reader,
+        highlighter.columns(),
+        editing::Keybindings::from_name(keybindings.unwrap_or_else(|| {
+            match interpreter.get(REPL_KEYBINDINGS_VARIABLE) {
+                Some(Expr::Str(name)) => name,
+                _ => "emacs",
+            }
+        })),
+    );
+    if std::io::stdout().is_terminal() {
+        editing::set_bracketed_paste(true).context("failed to enable bracketed paste")?;
+    }
+
+    let mut settings = Settings::default();
+    let mut exit_code = None;
+    let mut buffer = String::new();
+    let mut result_count = 0;
+    while let ReadResult::Input(line) = reader.read_line().context("failed to read line")? {
+        buffer.push_str(&line);
+        buffer.push('\n');
+        highlighter
+            .line_entered(&interpreter, &current_prompt, &line)
+            .context("failed to highlight input")?;
+
+        // Keep reading lines until the input is complete, meaning that it can
+        // be parsed or fails to parse for reasons other than missing input.
+        if is_incomplete(&buffer) {
+            current_prompt = REPL_CONTINUATION_PROMPT.to_string();
+            reader
+                .set_prompt(&current_prompt)
+                .context("failed to set prompt")?;
+            continue;
+        }
+        highlighter.input_complete();
+
+        let input = std::mem::take(&mut buffer);
+        if input.trim().is_empty() {
+            continue;
+        }
+        // Like in shells, repeated inputs are only added once and inputs
+        // starting with a space can be kept out of the history.
+        if !(history.ignore_space && input.starts_with(' ')) {
+            reader.add_history_unique(input.trim_end().to_string());
+        }
+        if input.trim_start().starts_with(command::PREFIX) {
+            if let Err(error) =
+                command::run(&mut interpreter, &debugger, &mut settings, input.trim())
+            {
+                println!("{:#}", error);
+            }
+        } else {
+            let start = Instant::now();
+            match interpreter.interpret_all(input.as_str()) {
+                Ok(result) => {
+                    if settings.show_meta {
+                        let millis = start.elapsed().as_secs_f64() * 1000.0;
+                        println!("{} : {} ({:.1}ms)", result, result.type_name(), millis);
+                    } else {
+                        println!("{}", result);
+                    }
+                    // Results are bound to `_` and `$1`, `$2`, ..., so they
+                    // can be used in later inputs.
+                    result_count += 1;
+                    interpreter.define(REPL_LAST_RESULT_VARIABLE, result.clone());
+                    interpreter.define(
+                        format!("{}{}", REPL_RESULT_VARIABLE_PREFIX, result_count),
+                        result,
+                    );
+                }
+                Err(Error::Exit(code)) => {
+                    exit_code = Some(code);
+                    break;
+                }
+                Err(error) => eprint!("{}", renderer.render_error(&error, "<repl>", &input)),
+            }
         }
+        completer.set_names(interpreter.completions(""));
+        current_prompt = prompt(&interpreter);
+        reader
+            .set_prompt(&current_prompt)
+            .context("failed to set prompt")?;
+    }
+
+    if std::io::stdout().is_terminal() {
+        editing::set_bracketed_paste(false).context("failed to disable bracketed paste")?;
     }
 
-    if let Some(p) = history_file_path() {
+    if let Some(p) = &history.path {
+        // The history file, and possibly its directory, are created on the
+        // first save.
+        if let Some(dir) = p.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).context("failed to create history directory")?;
+        }
         reader.save_history(p).context("failed to write history")?;
     }
 
-    Ok(())
+    exit_code.map_or(Ok(()), |code| Err(Reported(code).into()))
+}
+
+/// Returns the prompt of the REPL, which can be changed by defining
+/// `*prompt*` as a string.
+fn prompt(interpreter: &Interpreter) -> String {
+    match interpreter.get(REPL_PROMPT_VARIABLE) {
+        Some(Expr::Str(prompt)) => prompt.clone(),
+        _ => REPL_PROMPT.to_string(),
+    }
+}
+
+/// Returns whether the given input is incomplete, i.e. whether it ends within
+/// a list or string.
+fn is_incomplete(input: &str) -> bool {
+    matches!(
+        rusht::parse(input),
+        Err(Error::MissingClosingParenthesis | Error::UnterminatedString)
+    )
 }
 
 /// Returns an initialized terminal interface.
 ///
 /// The returned value is either an `Ok`, containing an initialized interface,
 /// or an `Err`.
-fn init_reader() -> Result<Interface<DefaultTerminal>> {
+fn init_reader(history: &History) -> Result<Interface<DefaultTerminal>> {
     let reader = Interface::new(PROGRAM_NAME).context("failed to get terminal interface")?;
 
     reader
         .set_prompt(REPL_PROMPT)
         .context("failed to set prompt")?;
-    reader.set_history_size(REPL_HISTORY_SIZE);
+    reader.set_history_size(history.size);
     reader.lock_reader().set_string_chars("\"");
     reader.lock_reader().set_blink_matching_paren(true);
+    reader
+        .lock_reader()
+        .set_word_break_chars(complete::WORD_BREAK_CHARS);
 
-    if let Some(p) = history_file_path() {
+    if let Some(p) = &history.path {
         if p.exists() {
             reader.load_history(p).context("failed to load history")?
         }
@@ -88,10 +744,16 @@ fn init_reader() -> Result<Interface<DefaultTerminal>> {
     Ok(reader)
 }
 
-/// Returns the path to the REPL history.
+/// Returns the path to the init file interpreted when starting the REPL.
+fn init_file_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|d| d.join(REPL_INIT_FILE_NAME))
+}
+
+/// Returns the default path to the REPL history.
 ///
 /// The returned value depends on the operating system and is either a `Some`,
-/// containing the path of an existing history file, or a `None`.
-fn history_file_path() -> Option<PathBuf> {
+/// containing the path of the history file in the home directory, or a
+/// `None`.
+fn default_history_file_path() -> Option<PathBuf> {
     dirs::home_dir().map(|d| d.join(REPL_HISTORY_FILE_NAME))
 }
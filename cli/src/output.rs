@@ -0,0 +1,73 @@
+//! Printing of evaluation results in human- or machine-readable formats.
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use rusht::Expr;
+
+use crate::diagnostic::{self, Renderer, Reported};
+
+/// The possible values of the `--output` flag.
+pub const FORMATS: &[&str] = &["text", "json"];
+
+/// The format in which results are printed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    /// The display representation of the value.
+    Text,
+    /// A JSON object containing either the value and its type or the error.
+    Json,
+}
+
+impl Format {
+    /// Returns the format of the given name, falling back to `Text` for
+    /// unknown names.
+    pub fn from_name(name: &str) -> Format {
+        match name {
+            "json" => Format::Json,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// Returns the JSON object describing the given result, containing either
+/// the value and its type or the error.
+pub fn to_json(result: &rusht::Result<Expr>) -> Value {
+    match result {
+        Ok(value) => json!({ "ok": true, "value": value, "type": value.type_name() }),
+        Err(err) => json!({ "ok": false, "error": err, "message": err.to_string() }),
+    }
+}
+
+/// Prints the given result of a program. In the text format, values are
+/// printed to stdout and errors are rendered as diagnostics to stderr. If the
+/// program failed or exited using `exit`, `Reported` is returned, so the
+/// failure is reflected in the exit code.
+///
+/// # Arguments
+///
+/// * `format` - The format, in which the result is printed.
+/// * `quiet` - Whether printing the value of successful programs is skipped.
+/// * `renderer` - The renderer of errors in the text format.
+/// * `name` - The name of the program, e.g. its file path.
+/// * `source` - The source code of the program.
+/// * `result` - The result of interpreting the program.
+pub fn print_result(
+    format: Format,
+    quiet: bool,
+    renderer: Renderer,
+    name: &str,
+    source: &str,
+    result: rusht::Result<Expr>,
+) -> Result<()> {
+    match (format, &result) {
+        // Exiting is not a failure, so there is nothing to print.
+        (_, Err(rusht::Error::Exit(_))) => {}
+        (_, Ok(_)) if quiet => {}
+        (Format::Text, Ok(value)) => println!("{}", value),
+        (Format::Text, Err(err)) => eprint!("{}", renderer.render_error(err, name, source)),
+        (Format::Json, _) => println!("{}", to_json(&result)),
+    }
+    result
+        .map(|_| ())
+        .map_err(|err| Reported(diagnostic::exit_code(&err)).into())
+}
@@ -0,0 +1,144 @@
+//! A server exposing a long-lived interpreter over TCP, e.g. to editors.
+//!
+//! Clients send one program per line and receive one JSON object per line in
+//! response, using the same format as `--output json`. A program exiting
+//! using `exit` closes the connection, but not the server. As clients are
+//! not necessarily trusted, spawning processes and opening sockets is denied
+//! unless explicitly allowed. Reading the standard input is always denied, as
+//! it is not connected to any client and would block all of them.
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use anyhow::{Context, Result};
+
+use rusht::{Error, Expr, Interpreter, Operation};
+
+use crate::output;
+
+/// A message sent from a connection to the thread owning the interpreter.
+enum Message {
+    /// A program received from the given connection.
+    Request {
+        connection: usize,
+        input: String,
+        reply: Sender<Response>,
+    },
+    /// The given connection has been closed.
+    Closed(usize),
+}
+
+/// The response to a single request.
+struct Response {
+    /// The result encoded as a single line of JSON.
+    json: String,
+    /// Whether the program exited using `exit`.
+    exit: bool,
+}
+
+/// Listens on the given address and interprets the programs sent by the
+/// clients until the process is terminated. Connections are served
+/// concurrently, but their programs are interpreted one at a time.
+///
+/// # Arguments
+///
+/// * `interpreter` - The interpreter used to interpret the programs.
+/// * `address` - The address to listen on, e.g. `127.0.0.1:7878`.
+/// * `per_connection` - Whether each connection gets its own environment,
+///     forked from `interpreter`, instead of sharing one.
+/// * `allow_io` - Whether programs may spawn processes and open sockets.
+pub fn listen(
+    mut interpreter: Interpreter,
+    address: &str,
+    per_connection: bool,
+    allow_io: bool,
+) -> Result<()> {
+    interpreter.set_policy(move |operation: Operation, _: &[Expr]| match operation {
+        Operation::ReadInput => false,
+        Operation::Spawn | Operation::Connect | Operation::Listen => allow_io,
+        _ => true,
+    });
+    let listener =
+        TcpListener::bind(address).with_context(|| format!("failed to listen on `{}`", address))?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || accept(&listener, &sender));
+
+    let mut sessions = HashMap::new();
+    for message in receiver {
+        match message {
+            Message::Request {
+                connection,
+                input,
+                reply,
+            } => {
+                let interpreter = if per_connection {
+                    sessions
+                        .entry(connection)
+                        .or_insert_with(|| interpreter.fork())
+                } else {
+                    &mut interpreter
+                };
+                let result = interpreter.interpret_all(&input);
+                // The connection may already be closed, in which case there
+                // is no one left to respond to.
+                let _ = reply.send(Response {
+                    json: output::to_json(&result).to_string(),
+                    exit: matches!(result, Err(Error::Exit(_))),
+                });
+            }
+            Message::Closed(connection) => {
+                sessions.remove(&connection);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Accepts connections and serves each one on a thread of its own.
+fn accept(listener: &TcpListener, requests: &Sender<Message>) {
+    for (connection, stream) in listener.incoming().enumerate() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let requests = requests.clone();
+        thread::spawn(move || {
+            if let Err(err) = serve(connection, stream, &requests) {
+                eprintln!("Connection {} failed: {}", connection, err);
+            }
+            let _ = requests.send(Message::Closed(connection));
+        });
+    }
+}
+
+/// Forwards the programs received from a client to the interpreter and writes
+/// back the responses until the client disconnects or a program exits.
+fn serve(connection: usize, stream: TcpStream, requests: &Sender<Message>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let (reply, responses) = mpsc::channel();
+    for line in BufReader::new(stream).lines() {
+        let input = line?;
+        if input.trim().is_empty() {
+            continue;
+        }
+        let request = Message::Request {
+            connection,
+            input,
+            reply: reply.clone(),
+        };
+        if requests.send(request).is_err() {
+            break;
+        }
+        let Ok(response) = responses.recv() else {
+            break;
+        };
+        writeln!(writer, "{}", response.json)?;
+        if response.exit {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! The test runner of the `test` subcommand. Tests are defined using
+//! `(deftest name body...)` forms in files ending in `_test.rusht`. All other
+//! forms of a file are interpreted before its tests, e.g. to define the
+//! functions under test. Each test is run in an isolated copy of the
+//! resulting interpreter and passes, if its body is interpreted without
+//! errors, e.g. failing assertions.
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+
+use rusht::Interpreter;
+
+use crate::forms;
+
+/// The suffix of the names of test files.
+const TEST_FILE_SUFFIX: &str = "_test.rusht";
+
+/// The name of the form defining a test.
+const DEFTEST: &str = "deftest";
+
+/// Runs the tests in the given files and directories, which are searched
+/// recursively for test files. Returns an error, if any test failed.
+///
+/// # Arguments
+///
+/// * `interpreter` - The interpreter, of which each test file gets a copy.
+/// * `paths` - The paths of the files and directories.
+pub fn run<'a, I>(interpreter: &mut Interpreter, paths: I) -> Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut files = vec![];
+    for path in paths {
+        forms::collect_files(Path::new(path), TEST_FILE_SUFFIX, &mut files)
+            .with_context(|| format!("failed to find test files in `{}`", path))?;
+    }
+    files.sort();
+
+    let (mut passed, mut failed) = (0, 0);
+    for file in files {
+        let source = std::fs::read_to_string(&file)
+            .with_context(|| format!("failed to read tests from `{}`", file.display()))?;
+        let mut base = interpreter.fork();
+        let tests = match forms::split(&mut base, &source, DEFTEST) {
+            Ok(tests) => tests,
+            Err(error) => {
+                println!("{} ... FAILED to load: {}", file.display(), error);
+                failed += 1;
+                continue;
+            }
+        };
+
+        for (name, body) in tests {
+            let mut test = base.fork();
+            let start = Instant::now();
+            let result = body
+                .into_iter()
+                .try_for_each(|expr| test.eval(expr).map(|_| ()));
+            let elapsed = start.elapsed();
+
+            match result {
+                Ok(()) => {
+                    println!("test {}::{} ... ok ({:?})", file.display(), name, elapsed);
+                    passed += 1;
+                }
+                Err(error) => {
+                    println!(
+                        "test {}::{} ... FAILED ({:?}): {}",
+                        file.display(),
+                        name,
+                        elapsed,
+                        error
+                    );
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!("test result: {} passed; {} failed", passed, failed);
+    if failed > 0 {
+        bail!("{} test(s) failed", failed);
+    }
+    Ok(())
+}
@@ -0,0 +1,416 @@
+//! A vi mode for linefeed, which only provides emacs-style keybindings. Every
+//! printable ASCII key is bound to a command, that inserts the key in insert
+//! mode and executes it as a vi command in normal mode, entered by Escape.
+//!
+//! Supported are the motions `h`, `l`, `w`, `b`, `e`, `0`, `^`, `$`, and
+//! space, the operators `d` and `c` combined with a motion or doubled, `x`,
+//! `X`, `D`, `C`, `S`, `r`, `p`, `P`, `i`, `a`, `I`, `A`, `j` and `k` to move
+//! through the history, and counts.
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use linefeed::{Command, DefaultTerminal, Interface, Prompter};
+
+const ESCAPE: &str = "\x1b";
+
+/// The time to wait for the rest of an escape sequence after Escape, after
+/// which it switches to normal mode. The default of linefeed makes switching
+/// modes noticeably slow.
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// The quotes, within which parentheses are not matched.
+const STRING_CHARS: &str = "\"";
+
+/// The state of the vi mode.
+#[derive(Debug, Default)]
+pub struct Vi {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Whether keys are executed as commands instead of inserted.
+    normal: bool,
+    /// The operator or `r`, waiting for a motion or a character.
+    pending: Option<char>,
+    /// The count typed before a command.
+    count: Option<usize>,
+}
+
+impl Vi {
+    /// Switches back to insert mode, e.g. when a line has been accepted, so
+    /// each line starts in insert mode.
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = State::default();
+    }
+}
+
+/// Binds the vi mode and returns its state.
+pub fn bind(reader: &Interface<DefaultTerminal>) -> Arc<Vi> {
+    let vi = Arc::new(Vi::default());
+
+    let escape = vi.clone();
+    reader.define_function(
+        "rusht-vi-normal-mode",
+        Arc::new(move |prompter: &mut Prompter<DefaultTerminal>, _, _| {
+            let mut state = escape.state.lock().unwrap();
+            if state.normal {
+                state.pending = None;
+                state.count = None;
+                return Ok(());
+            }
+            state.normal = true;
+            let cursor = previous(prompter.buffer(), prompter.cursor());
+            prompter.set_cursor(cursor)
+        }),
+    );
+    reader.bind_sequence(ESCAPE, Command::from_str("rusht-vi-normal-mode"));
+    reader
+        .lock_reader()
+        .set_keyseq_timeout(Some(ESCAPE_TIMEOUT));
+
+    let key = vi.clone();
+    reader.define_function(
+        "rusht-vi-key",
+        Arc::new(move |prompter: &mut Prompter<DefaultTerminal>, _, ch| {
+            let mut state = key.state.lock().unwrap();
+            if state.normal {
+                execute(&mut state, prompter, ch)
+            } else {
+                insert(prompter, ch)
+            }
+        }),
+    );
+    for ch in ' '..='~' {
+        reader.bind_sequence(ch.to_string(), Command::from_str("rusht-vi-key"));
+    }
+
+    vi
+}
+
+/// Inserts the given character, blinking the matching parenthesis like
+/// linefeed does when inserting a closing one.
+fn insert(prompter: &mut Prompter<DefaultTerminal>, ch: char) -> io::Result<()> {
+    prompter.insert(1, ch)?;
+    let open = match ch {
+        ')' => '(',
+        ']' => '[',
+        '}' => '{',
+        _ => return Ok(()),
+    };
+    match matching_paren(&prompter.buffer()[..prompter.cursor()], open, ch) {
+        Some(pos) => prompter.blink(pos),
+        None => Ok(()),
+    }
+}
+
+/// Executes the given key in normal mode.
+fn execute(
+    state: &mut State,
+    prompter: &mut Prompter<DefaultTerminal>,
+    ch: char,
+) -> io::Result<()> {
+    let buffer = prompter.buffer().to_string();
+    let cursor = prompter.cursor();
+
+    if state.pending == Some('r') {
+        state.pending = None;
+        state.count = None;
+        if cursor < buffer.len() {
+            prompter.delete_range(cursor..next(&buffer, cursor))?;
+            prompter.insert(1, ch)?;
+            prompter.set_cursor(cursor)?;
+        }
+        return Ok(());
+    }
+    if let Some(digit) = ch.to_digit(10).filter(|d| *d != 0 || state.count.is_some()) {
+        state.count = Some(state.count.unwrap_or(0) * 10 + digit as usize);
+        return Ok(());
+    }
+
+    let count = state.count.take().unwrap_or(1);
+    if let Some(operator) = state.pending.take() {
+        return apply(state, prompter, operator, ch, count);
+    }
+
+    match ch {
+        'd' | 'c' | 'r' => {
+            state.pending = Some(ch);
+            state.count = Some(count).filter(|c| *c > 1);
+        }
+        'i' => state.normal = false,
+        'a' => {
+            state.normal = false;
+            prompter.set_cursor(next(&buffer, cursor))?;
+        }
+        'I' => {
+            state.normal = false;
+            prompter.set_cursor(0)?;
+        }
+        'A' => {
+            state.normal = false;
+            prompter.set_cursor(buffer.len())?;
+        }
+        'x' => {
+            let end = (0..count).fold(cursor, |pos, _| next(&buffer, pos));
+            prompter.kill_range(cursor..end)?;
+            normalize(prompter)?;
+        }
+        'X' => {
+            let start = (0..count).fold(cursor, |pos, _| previous(&buffer, pos));
+            prompter.kill_range(start..cursor)?;
+        }
+        'D' | 'C' => {
+            prompter.kill_range(cursor..)?;
+            state.normal = ch == 'D';
+            if state.normal {
+                normalize(prompter)?;
+            }
+        }
+        'S' => {
+            prompter.kill_range(..)?;
+            state.normal = false;
+        }
+        'p' | 'P' => {
+            if ch == 'p' {
+                prompter.set_cursor(next(&buffer, cursor))?;
+            }
+            for _ in 0..count {
+                prompter.yank()?;
+            }
+            let end = prompter.cursor();
+            prompter.set_cursor(previous(prompter.buffer(), end))?;
+        }
+        'k' | 'j' => {
+            for _ in 0..count {
+                let len = prompter.history_len();
+                let entry = match (ch, prompter.history_index()) {
+                    ('k', None) => len.checked_sub(1),
+                    ('k', Some(i)) => Some(i.saturating_sub(1)),
+                    (_, Some(i)) if i + 1 < len => Some(i + 1),
+                    (_, Some(_)) => None,
+                    (_, None) => break,
+                };
+                if entry.is_none() && ch == 'k' {
+                    break;
+                }
+                prompter.select_history_entry(entry)?;
+            }
+            prompter.set_cursor(0)?;
+        }
+        _ => {
+            if let Some(pos) = motion(&buffer, cursor, ch, count) {
+                prompter.set_cursor(pos)?;
+                normalize(prompter)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies the given operator to the text, over which the given key moves,
+/// or to the whole buffer, if the key repeats the operator.
+fn apply(
+    state: &mut State,
+    prompter: &mut Prompter<DefaultTerminal>,
+    operator: char,
+    ch: char,
+    count: usize,
+) -> io::Result<()> {
+    let buffer = prompter.buffer().to_string();
+    let cursor = prompter.cursor();
+    let range = if ch == operator {
+        0..buffer.len()
+    } else {
+        // Like in vi, `cw` on a word changes it without the following
+        // whitespace, and `e` includes the character it moves to.
+        let on_word = buffer[cursor..]
+            .chars()
+            .next()
+            .is_some_and(|c| kind(c) != Kind::Whitespace);
+        let change_word = (operator, ch) == ('c', 'w') && on_word;
+        let end = if change_word {
+            motion(&buffer, current_word_end(&buffer, cursor), 'e', count - 1)
+        } else {
+            motion(&buffer, cursor, ch, count)
+        };
+        match end {
+            Some(end) if ch == 'e' || change_word => cursor..next(&buffer, end),
+            Some(end) => cursor.min(end)..cursor.max(end),
+            None => return Ok(()),
+        }
+    };
+    prompter.kill_range(range)?;
+    if operator == 'c' {
+        state.normal = false;
+        return Ok(());
+    }
+    normalize(prompter)
+}
+
+/// Returns the position, to which the given motion moves the cursor, or
+/// `None` if the key is not a motion.
+fn motion(buffer: &str, cursor: usize, ch: char, count: usize) -> Option<usize> {
+    let step: fn(&str, usize) -> usize = match ch {
+        'h' => previous,
+        'l' | ' ' => next,
+        'w' => next_word,
+        'b' => previous_word,
+        'e' => word_end,
+        '0' => return Some(0),
+        '^' => return Some(buffer.len() - buffer.trim_start().len()),
+        '$' => return Some(buffer.len()),
+        _ => return None,
+    };
+    Some((0..count).fold(cursor, |pos, _| step(buffer, pos)))
+}
+
+/// Moves the cursor onto the last character, if it is behind it, as the
+/// cursor is always on a character in normal mode.
+fn normalize(prompter: &mut Prompter<DefaultTerminal>) -> io::Result<()> {
+    let buffer = prompter.buffer();
+    if prompter.cursor() >= buffer.len() {
+        let cursor = previous(buffer, buffer.len());
+        prompter.set_cursor(cursor)?;
+    }
+    Ok(())
+}
+
+/// Returns the position of the character before the given position.
+fn previous(buffer: &str, pos: usize) -> usize {
+    buffer[..pos]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(i, _)| i)
+}
+
+/// Returns the position of the character after the given position.
+fn next(buffer: &str, pos: usize) -> usize {
+    buffer[pos..]
+        .chars()
+        .next()
+        .map_or(pos, |c| pos + c.len_utf8())
+}
+
+/// The kinds of characters, of which words consist. Words are runs of
+/// characters of the same kind other than whitespace, so `(foo-bar 1)`
+/// consists of the words `(`, `foo-bar`, `1`, and `)`.
+#[derive(PartialEq)]
+enum Kind {
+    Whitespace,
+    Punctuation,
+    Symbol,
+}
+
+fn kind(ch: char) -> Kind {
+    if ch.is_whitespace() {
+        Kind::Whitespace
+    } else if "()[]{}'`,;\"".contains(ch) {
+        Kind::Punctuation
+    } else {
+        Kind::Symbol
+    }
+}
+
+/// Returns the characters of the given buffer with their positions, followed
+/// by the index of the character at the given position.
+fn chars(buffer: &str, pos: usize) -> (Vec<(usize, char)>, usize) {
+    let chars = buffer.char_indices().collect::<Vec<_>>();
+    let i = chars
+        .iter()
+        .position(|(p, _)| *p >= pos)
+        .unwrap_or(chars.len());
+    (chars, i)
+}
+
+/// Returns the position, at which the given characters end or the character
+/// of the given index is.
+fn position(buffer: &str, chars: &[(usize, char)], i: usize) -> usize {
+    chars.get(i).map_or(buffer.len(), |(p, _)| *p)
+}
+
+/// Returns the start of the next word, like `w` in vi.
+fn next_word(buffer: &str, pos: usize) -> usize {
+    let (chars, mut i) = chars(buffer, pos);
+    let kind_at = |i: usize| kind(chars[i].1);
+    if i < chars.len() {
+        let start = kind_at(i);
+        while i < chars.len() && start != Kind::Whitespace && kind_at(i) == start {
+            i += 1;
+        }
+    }
+    while i < chars.len() && kind_at(i) == Kind::Whitespace {
+        i += 1;
+    }
+    position(buffer, &chars, i)
+}
+
+/// Returns the start of the current or previous word, like `b` in vi.
+fn previous_word(buffer: &str, pos: usize) -> usize {
+    let (chars, mut i) = chars(buffer, pos);
+    let kind_at = |i: usize| kind(chars[i].1);
+    if i == 0 {
+        return 0;
+    }
+    i -= 1;
+    while i > 0 && kind_at(i) == Kind::Whitespace {
+        i -= 1;
+    }
+    let start = kind_at(i);
+    while i > 0 && kind_at(i - 1) == start {
+        i -= 1;
+    }
+    position(buffer, &chars, i)
+}
+
+/// Returns the end of the word at the given position.
+fn current_word_end(buffer: &str, pos: usize) -> usize {
+    let (chars, mut i) = chars(buffer, pos);
+    while i + 1 < chars.len() && kind(chars[i + 1].1) == kind(chars[i].1) {
+        i += 1;
+    }
+    position(buffer, &chars, i)
+}
+
+/// Returns the end of the current or next word, like `e` in vi.
+fn word_end(buffer: &str, pos: usize) -> usize {
+    let (chars, mut i) = chars(buffer, pos);
+    let kind_at = |i: usize| kind(chars[i].1);
+    if i + 1 >= chars.len() {
+        return pos;
+    }
+    i += 1;
+    while i + 1 < chars.len() && kind_at(i) == Kind::Whitespace {
+        i += 1;
+    }
+    let start = kind_at(i);
+    while i + 1 < chars.len() && kind_at(i + 1) == start {
+        i += 1;
+    }
+    position(buffer, &chars, i)
+}
+
+/// Returns the position of the parenthesis matching the closing one at the
+/// end of the given text, skipping parentheses within strings.
+fn matching_paren(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, ch) in text.char_indices().rev() {
+        if quote.is_some() {
+            if quote == Some(ch) {
+                quote = None;
+            }
+        } else if STRING_CHARS.contains(ch) {
+            quote = Some(ch);
+        } else if ch == close {
+            depth += 1;
+        } else if ch == open {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
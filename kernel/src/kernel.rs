@@ -0,0 +1,246 @@
+//! Handling of the requests sent by the frontend.
+use std::convert::TryFrom;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use rusht::{Error, Interpreter};
+
+use crate::message::{self, Message, PROTOCOL_VERSION};
+use crate::zmtp::{Publisher, Writer};
+
+/// The characters separating the words to be completed or inspected.
+const WORD_BREAK_CHARS: &str = " \t\n()\"";
+
+/// The state of a running kernel.
+pub struct Kernel {
+    interpreter: Interpreter,
+    /// The key used to sign messages.
+    key: Vec<u8>,
+    /// The socket, on which outputs and status updates are published.
+    iopub: Publisher,
+    /// The id of the session, which is part of every message sent.
+    session: String,
+    /// The number of messages sent, used to derive unique message ids.
+    messages_sent: u64,
+    /// The number of executed cells.
+    execution_count: u64,
+}
+
+impl Kernel {
+    /// Returns a new kernel interpreting the executed cells using the given
+    /// interpreter.
+    pub fn new(interpreter: Interpreter, key: Vec<u8>, iopub: Publisher) -> Kernel {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Kernel {
+            interpreter,
+            key,
+            iopub,
+            session: format!("{:x}-{:x}", process::id(), started.as_nanos()),
+            messages_sent: 0,
+            execution_count: 0,
+        }
+    }
+
+    /// Handles a request received on the shell or control socket and sends
+    /// the reply to the given writer. Returns whether the kernel has been
+    /// asked to shut down.
+    pub fn handle(&mut self, request: &Message, reply_to: &mut Writer) -> std::io::Result<bool> {
+        let Some(name) = request.msg_type().strip_suffix("_request") else {
+            eprintln!("Ignoring message of type `{}`", request.msg_type());
+            return Ok(false);
+        };
+
+        self.publish(request, "status", json!({ "execution_state": "busy" }));
+        let content = match name {
+            "kernel_info" => Some(kernel_info()),
+            "execute" => Some(self.execute(request)),
+            "complete" => Some(self.complete(&request.content)),
+            "inspect" => Some(self.inspect(&request.content)),
+            "is_complete" => Some(is_complete(&request.content)),
+            "shutdown" => Some(json!({ "status": "ok", "restart": request.content["restart"] })),
+            _ => {
+                eprintln!("Ignoring unsupported request `{}`", request.msg_type());
+                None
+            }
+        };
+        if let Some(content) = content {
+            let reply = self.message(request, &format!("{}_reply", name), content);
+            reply_to.write_message(&reply.encode(&self.key))?;
+        }
+        self.publish(request, "status", json!({ "execution_state": "idle" }));
+        Ok(name == "shutdown")
+    }
+
+    /// Interprets the code of a cell, publishing its input and either its
+    /// value or its error.
+    fn execute(&mut self, request: &Message) -> Value {
+        let code = request.content["code"].as_str().unwrap_or_default();
+        let silent = request.content["silent"].as_bool().unwrap_or(false);
+        if !silent {
+            self.execution_count += 1;
+        }
+        let execution_count = self.execution_count;
+        self.publish(
+            request,
+            "execute_input",
+            json!({ "code": code, "execution_count": execution_count }),
+        );
+        if code.trim().is_empty() {
+            return json!({ "status": "ok", "execution_count": execution_count });
+        }
+
+        match self.interpreter.interpret_all(code) {
+            Ok(value) => {
+                if !silent {
+                    let data = json!({
+                        "execution_count": execution_count,
+                        "data": { "text/plain": value.to_string() },
+                        "metadata": {},
+                    });
+                    self.publish(request, "execute_result", data);
+                }
+                json!({
+                    "status": "ok",
+                    "execution_count": execution_count,
+                    "user_expressions": {},
+                    "payload": [],
+                })
+            }
+            Err(err) => {
+                let error = json!({
+                    "ename": error_name(&err),
+                    "evalue": err.to_string(),
+                    "traceback": [err.to_string()],
+                });
+                self.publish(request, "error", error.clone());
+                let mut reply = json!({ "status": "error", "execution_count": execution_count });
+                if let (Some(reply), Value::Object(error)) = (reply.as_object_mut(), error) {
+                    reply.extend(error);
+                }
+                reply
+            }
+        }
+    }
+
+    /// Returns the names completing the word before the cursor.
+    fn complete(&self, content: &Value) -> Value {
+        let (code, cursor) = code_and_cursor(content);
+        let start = word_start(code, cursor);
+        json!({
+            "status": "ok",
+            "matches": self.interpreter.completions(&code[start..cursor]),
+            "cursor_start": code[..start].chars().count(),
+            "cursor_end": code[..cursor].chars().count(),
+            "metadata": {},
+        })
+    }
+
+    /// Returns the documentation of the builtin or the value of the variable
+    /// under the cursor.
+    fn inspect(&self, content: &Value) -> Value {
+        let (code, cursor) = code_and_cursor(content);
+        let end = code[cursor..]
+            .find(|c| WORD_BREAK_CHARS.contains(c))
+            .map_or(code.len(), |end| cursor + end);
+        let name = &code[word_start(code, cursor)..end];
+
+        let text = rusht::builtin_docs()
+            .into_iter()
+            .find(|doc| doc.name() == name)
+            .map(|doc| format!("{}\n\n{}", doc.signature, doc.description))
+            .or_else(|| {
+                self.interpreter
+                    .get(name)
+                    .map(|value| format!("{} = {}", name, value))
+            });
+        json!({
+            "status": "ok",
+            "found": text.is_some(),
+            "data": text.map_or_else(|| json!({}), |text| json!({ "text/plain": text })),
+            "metadata": {},
+        })
+    }
+
+    /// Publishes a message of the given type on the `IOPub` socket.
+    fn publish(&mut self, parent: &Message, msg_type: &str, content: Value) {
+        let mut message = self.message(parent, msg_type, content);
+        message.identities = vec![msg_type.as_bytes().to_vec()];
+        self.iopub.publish(&message.encode(&self.key));
+    }
+
+    /// Returns a new message in response to the given one.
+    fn message(&mut self, parent: &Message, msg_type: &str, content: Value) -> Message {
+        self.messages_sent += 1;
+        let msg_id = format!("{}-{}", self.session, self.messages_sent);
+        Message {
+            identities: parent.identities.clone(),
+            header: message::header(&msg_id, &self.session, msg_type),
+            parent_header: parent.header.clone(),
+            metadata: json!({}),
+            content,
+        }
+    }
+}
+
+/// Returns the information about the kernel and the language.
+fn kernel_info() -> Value {
+    json!({
+        "status": "ok",
+        "protocol_version": PROTOCOL_VERSION,
+        "implementation": "rusht",
+        "implementation_version": rusht::VERSION,
+        "language_info": {
+            "name": "rusht",
+            "version": rusht::VERSION,
+            "mimetype": "text/x-rusht",
+            "file_extension": ".rusht",
+        },
+        "banner": format!("Rusht {}", rusht::VERSION),
+        "help_links": [],
+    })
+}
+
+/// Returns whether the code of a cell is complete, i.e. whether all lists and
+/// strings are closed.
+fn is_complete(content: &Value) -> Value {
+    let code = content["code"].as_str().unwrap_or_default();
+    let status = match rusht::parse(code) {
+        Ok(_) => "complete",
+        Err(Error::MissingClosingParenthesis | Error::UnterminatedString) => "incomplete",
+        Err(_) => "invalid",
+    };
+    json!({ "status": status, "indent": "" })
+}
+
+/// Returns the code of a request and the byte offset of the cursor, which
+/// the frontend passes in characters.
+fn code_and_cursor(content: &Value) -> (&str, usize) {
+    let code = content["code"].as_str().unwrap_or_default();
+    let cursor = content["cursor_pos"]
+        .as_u64()
+        .and_then(|cursor| usize::try_from(cursor).ok())
+        .and_then(|cursor| code.char_indices().nth(cursor))
+        .map_or(code.len(), |(offset, _)| offset);
+    (code, cursor)
+}
+
+/// Returns the byte offset of the start of the word ending at the cursor.
+fn word_start(code: &str, cursor: usize) -> usize {
+    code[..cursor]
+        .rfind(|c| WORD_BREAK_CHARS.contains(c))
+        .map_or(0, |start| start + 1)
+}
+
+/// Returns the name of the variant of the given error, e.g.
+/// `FunctionNotDefined`.
+fn error_name(error: &Error) -> String {
+    let name = format!("{:?}", error);
+    name.split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,102 @@
+//! A Jupyter kernel for rusht, started by the frontend with the path of a
+//! connection file. Output written by `print` goes to the stdout of the
+//! kernel instead of the notebook, and interrupting a cell is not supported.
+#![forbid(unsafe_code)]
+#![deny(clippy::pedantic)]
+
+use std::convert::TryFrom;
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use rusht::Interpreter;
+
+use crate::kernel::Kernel;
+use crate::message::Message;
+use crate::zmtp::{Connection, Frame, Publisher, Writer};
+
+mod kernel;
+mod message;
+mod zmtp;
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("Error: {:?}", error);
+        std::process::exit(1);
+    }
+}
+
+/// Reads the connection file, binds the sockets, and handles requests until
+/// the frontend asks the kernel to shut down.
+fn run() -> Result<()> {
+    let path = std::env::args_os()
+        .nth(1)
+        .context("usage: rusht-kernel CONNECTION_FILE")?;
+    let info: Value = serde_json::from_str(
+        &std::fs::read_to_string(&path).context("failed to read connection file")?,
+    )
+    .context("failed to parse connection file")?;
+
+    let key = info["key"].as_str().unwrap_or_default().as_bytes().to_vec();
+    if info["transport"] != "tcp" {
+        bail!("unsupported transport {}", info["transport"]);
+    }
+    if !key.is_empty() && info["signature_scheme"] != "hmac-sha256" {
+        bail!("unsupported signature scheme {}", info["signature_scheme"]);
+    }
+    let bind = |port: &str| -> Result<TcpListener> {
+        let ip = info["ip"].as_str().unwrap_or("127.0.0.1");
+        let number = info[port]
+            .as_u64()
+            .and_then(|number| u16::try_from(number).ok())
+            .with_context(|| format!("connection file is missing `{}`", port))?;
+        TcpListener::bind((ip, number)).with_context(|| format!("failed to bind `{}`", port))
+    };
+
+    zmtp::echo(bind("hb_port")?);
+    let iopub = Publisher::bind(bind("iopub_port")?);
+    let (sender, receiver) = mpsc::channel();
+    for port in ["shell_port", "control_port", "stdin_port"] {
+        route(bind(port)?, &sender);
+    }
+
+    let mut kernel = Kernel::new(Interpreter::new(), key.clone(), iopub);
+    for (frames, mut writer) in receiver {
+        let request = match Message::decode(frames, &key) {
+            Ok(request) => request,
+            Err(err) => {
+                eprintln!("Ignoring invalid message: {:#}", err);
+                continue;
+            }
+        };
+        match kernel.handle(&request, &mut writer) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(err) => eprintln!("Failed to send reply: {}", err),
+        }
+    }
+    Ok(())
+}
+
+/// Accepts connections on the given router socket in the background and
+/// forwards every message received, together with a writer to reply to it.
+fn route(listener: TcpListener, requests: &Sender<(Vec<Frame>, Writer)>) {
+    let requests = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let requests = requests.clone();
+            thread::spawn(move || -> std::io::Result<()> {
+                let mut connection = Connection::accept(stream, "ROUTER")?;
+                loop {
+                    let frames = connection.read_message()?;
+                    if requests.send((frames, connection.writer()?)).is_err() {
+                        return Ok(());
+                    }
+                }
+            });
+        }
+    });
+}
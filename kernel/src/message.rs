@@ -0,0 +1,221 @@
+//! Messages of the Jupyter messaging protocol and their encoding as ZMQ
+//! frames.
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::zmtp::Frame;
+
+/// The version of the messaging protocol implemented.
+pub const PROTOCOL_VERSION: &str = "5.3";
+
+/// The frame separating the routing identities from the message.
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+/// A message exchanged with the frontend.
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// The routing identities preceding the message, which are sent back
+    /// unchanged with the reply.
+    pub identities: Vec<Frame>,
+    pub header: Value,
+    pub parent_header: Value,
+    pub metadata: Value,
+    pub content: Value,
+}
+
+impl Message {
+    /// Returns the type of the message, e.g. `execute_request`.
+    pub fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+
+    /// Decodes the message from the given frames, checking its signature.
+    pub fn decode(mut frames: Vec<Frame>, key: &[u8]) -> Result<Message> {
+        let delimiter = frames
+            .iter()
+            .position(|frame| frame == DELIMITER)
+            .context("message is missing the delimiter")?;
+        let parts = frames.split_off(delimiter);
+        let [_, signature, header, parent_header, metadata, content, ..] = parts.as_slice() else {
+            bail!("message is incomplete");
+        };
+        if !key.is_empty() {
+            // The comparison takes the same time regardless of how much of
+            // the signature is correct.
+            let valid = decode_hex(signature).is_some_and(|signature| {
+                mac(key, &[header, parent_header, metadata, content])
+                    .verify_slice(&signature)
+                    .is_ok()
+            });
+            if !valid {
+                bail!("message has an invalid signature");
+            }
+        }
+
+        Ok(Message {
+            identities: frames,
+            header: serde_json::from_slice(header)?,
+            parent_header: serde_json::from_slice(parent_header)?,
+            metadata: serde_json::from_slice(metadata)?,
+            content: serde_json::from_slice(content)?,
+        })
+    }
+
+    /// Encodes the message as frames, signing it with the given key.
+    pub fn encode(&self, key: &[u8]) -> Vec<Frame> {
+        let parts = [
+            &self.header,
+            &self.parent_header,
+            &self.metadata,
+            &self.content,
+        ]
+        .map(|part| part.to_string().into_bytes());
+        let signature = if key.is_empty() {
+            String::new()
+        } else {
+            sign(key, &parts.each_ref().map(Vec::as_slice))
+        };
+
+        let mut frames = self.identities.clone();
+        frames.push(DELIMITER.to_vec());
+        frames.push(signature.into_bytes());
+        frames.extend(parts);
+        frames
+    }
+}
+
+/// Returns a new header for a message of the given type.
+///
+/// # Arguments
+///
+/// * `msg_id` - The unique id of the message.
+/// * `session` - The id of the session of the kernel.
+/// * `msg_type` - The type of the message, e.g. `execute_reply`.
+pub fn header(msg_id: &str, session: &str, msg_type: &str) -> Value {
+    json!({
+        "msg_id": msg_id,
+        "session": session,
+        "username": "kernel",
+        "date": timestamp(),
+        "msg_type": msg_type,
+        "version": PROTOCOL_VERSION,
+    })
+}
+
+/// Returns the hex encoded HMAC-SHA256 of the given parts.
+fn sign(key: &[u8], parts: &[&[u8]]) -> String {
+    mac(key, parts)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::new(), |mut out, byte| {
+            let _ = write!(out, "{:02x}", byte);
+            out
+        })
+}
+
+/// Returns the HMAC-SHA256 of the concatenation of the given parts.
+fn mac(key: &[u8], parts: &[&[u8]]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+/// Decodes the given hex string, returning `None` if it is invalid.
+fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    let pairs = hex.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).ok()?;
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect()
+}
+
+/// Returns the current time in ISO 8601 format, e.g.
+/// `2021-06-01T12:00:00.000000Z`.
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let (days, seconds) = (now.as_secs() / 86_400, now.as_secs() % 86_400);
+
+    // Converts the days since the epoch to a date in the proleptic Gregorian
+    // calendar, see http://howardhinnant.github.io/date_algorithms.html.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        now.subsec_micros()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // Test cases 2 and 6 of RFC 4231.
+        assert_eq!(
+            sign(b"Jefe", &[b"what do ya want ", b"for nothing?"]),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            sign(
+                &[0xaa; 131],
+                &[b"Test Using Larger Than Block-Size Key - Hash Key First"]
+            ),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_decode_checks_signature() {
+        let message = Message {
+            identities: vec![b"client".to_vec()],
+            header: header("1", "session", "kernel_info_request"),
+            parent_header: json!({}),
+            metadata: json!({}),
+            content: json!({}),
+        };
+        let frames = message.encode(b"key");
+        let decoded = Message::decode(frames.clone(), b"key").expect("valid signature");
+        assert_eq!(decoded.identities, message.identities);
+        assert_eq!(decoded.msg_type(), "kernel_info_request");
+
+        assert!(Message::decode(frames.clone(), b"other key").is_err());
+        let mut tampered = frames;
+        tampered[2][0] ^= 1;
+        assert!(Message::decode(tampered, b"key").is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! A minimal implementation of ZMTP 3.0, the wire protocol of ZMQ, which
+//! is used by Jupyter frontends to talk to kernels. Only the NULL security
+//! mechanism is supported, and the kernel always takes the server role, so
+//! only accepting connections is implemented.
+use std::convert::TryFrom;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The flag of frames followed by further frames of the same message.
+const MORE: u8 = 0x01;
+/// The flag of frames, whose size is encoded using eight instead of one byte.
+const LONG: u8 = 0x02;
+/// The flag of frames containing a command instead of a message.
+const COMMAND: u8 = 0x04;
+
+/// A frame of a message.
+pub type Frame = Vec<u8>;
+
+/// An accepted connection, that has completed the handshake.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: Writer,
+}
+
+impl Connection {
+    /// Performs the handshake on the given stream, announcing the given
+    /// socket type, e.g. `ROUTER`.
+    pub fn accept(stream: TcpStream, socket_type: &str) -> io::Result<Connection> {
+        let mut greeting = [0; 64];
+        greeting[0] = 0xff;
+        greeting[9] = 0x7f;
+        greeting[10] = 3;
+        greeting[12..16].copy_from_slice(b"NULL");
+        greeting[32] = 1;
+
+        let mut writer = Writer {
+            stream: stream.try_clone()?,
+        };
+        writer.stream.write_all(&greeting)?;
+        let mut reader = BufReader::new(stream);
+        let mut peer = [0; 64];
+        reader.read_exact(&mut peer)?;
+        if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 {
+            return Err(invalid_data("peer does not speak ZMTP 3"));
+        }
+        if peer[12..32]
+            .iter()
+            .copied()
+            .ne(greeting[12..32].iter().copied())
+        {
+            return Err(invalid_data("peer requires a security mechanism"));
+        }
+
+        let mut ready = b"\x05READY\x0bSocket-Type".to_vec();
+        let size =
+            u32::try_from(socket_type.len()).map_err(|_| invalid_data("invalid socket type"))?;
+        ready.extend_from_slice(&size.to_be_bytes());
+        ready.extend_from_slice(socket_type.as_bytes());
+        writer.write_frame(COMMAND, &ready)?;
+        writer.stream.flush()?;
+
+        let mut connection = Connection { reader, writer };
+        match connection.read_frame()? {
+            (flags, body) if flags & COMMAND != 0 && body.starts_with(b"\x05READY") => {
+                Ok(connection)
+            }
+            _ => Err(invalid_data("peer did not send READY")),
+        }
+    }
+
+    /// Returns a writer sending messages over this connection, e.g. from
+    /// another thread.
+    pub fn writer(&self) -> io::Result<Writer> {
+        Ok(Writer {
+            stream: self.writer.stream.try_clone()?,
+        })
+    }
+
+    /// Reads the next message, skipping commands like subscriptions.
+    pub fn read_message(&mut self) -> io::Result<Vec<Frame>> {
+        let mut frames = vec![];
+        loop {
+            let (flags, body) = self.read_frame()?;
+            if flags & COMMAND != 0 {
+                continue;
+            }
+            frames.push(body);
+            if flags & MORE == 0 {
+                return Ok(frames);
+            }
+        }
+    }
+
+    /// Sends a message consisting of the given frames.
+    pub fn write_message(&mut self, frames: &[Frame]) -> io::Result<()> {
+        self.writer.write_message(frames)
+    }
+
+    /// Reads the next frame, returning its flags and body.
+    fn read_frame(&mut self) -> io::Result<(u8, Frame)> {
+        let mut flags = [0];
+        self.reader.read_exact(&mut flags)?;
+        let size = if flags[0] & LONG == 0 {
+            let mut size = [0];
+            self.reader.read_exact(&mut size)?;
+            u64::from(size[0])
+        } else {
+            let mut size = [0; 8];
+            self.reader.read_exact(&mut size)?;
+            u64::from_be_bytes(size)
+        };
+
+        let mut body = vec![];
+        (&mut self.reader).take(size).read_to_end(&mut body)?;
+        if body.len() as u64 != size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok((flags[0], body))
+    }
+}
+
+/// The sending half of a connection.
+pub struct Writer {
+    stream: TcpStream,
+}
+
+impl Writer {
+    /// Sends a message consisting of the given frames.
+    pub fn write_message(&mut self, frames: &[Frame]) -> io::Result<()> {
+        for (i, frame) in frames.iter().enumerate() {
+            let more = if i + 1 < frames.len() { MORE } else { 0 };
+            self.write_frame(more, frame)?;
+        }
+        self.stream.flush()
+    }
+
+    /// Sends a single frame with the given flags, setting `LONG` if needed.
+    fn write_frame(&mut self, flags: u8, body: &[u8]) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(body.len() + 9);
+        if let Ok(size) = u8::try_from(body.len()) {
+            buffer.extend_from_slice(&[flags, size]);
+        } else {
+            buffer.push(flags | LONG);
+            buffer.extend_from_slice(&(body.len() as u64).to_be_bytes());
+        }
+        buffer.extend_from_slice(body);
+        self.stream.write_all(&buffer)
+    }
+}
+
+/// A publisher sending messages to all subscribers. Subscriptions are not
+/// filtered, which is sufficient for Jupyter frontends subscribing to every
+/// message.
+#[derive(Clone, Default)]
+pub struct Publisher {
+    subscribers: Arc<Mutex<Vec<Writer>>>,
+}
+
+impl Publisher {
+    /// Accepts subscribers on the given listener in the background.
+    pub fn bind(listener: TcpListener) -> Publisher {
+        let publisher = Publisher::default();
+        let subscribers = Arc::clone(&publisher.subscribers);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let Ok(mut connection) = Connection::accept(stream, "PUB") else {
+                    continue;
+                };
+                let Ok(writer) = connection.writer() else {
+                    continue;
+                };
+                if let Ok(mut subscribers) = subscribers.lock() {
+                    subscribers.push(writer);
+                }
+                // Subscriptions are read and discarded until the subscriber
+                // disconnects.
+                thread::spawn(move || while connection.read_message().is_ok() {});
+            }
+        });
+        publisher
+    }
+
+    /// Sends the given message to all subscribers, dropping those, that have
+    /// disconnected.
+    pub fn publish(&self, frames: &[Frame]) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain_mut(|subscriber| subscriber.write_message(frames).is_ok());
+        }
+    }
+}
+
+/// Accepts connections on the given listener in the background and sends
+/// every message received back to its sender, as needed for heartbeats.
+pub fn echo(listener: TcpListener) {
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            thread::spawn(move || -> io::Result<()> {
+                let mut connection = Connection::accept(stream, "REP")?;
+                loop {
+                    let message = connection.read_message()?;
+                    connection.write_message(&message)?;
+                }
+            });
+        }
+    });
+}
+
+/// Returns an error for a peer violating the protocol.
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,189 @@
+//! Procedural macros evaluating rusht programs at compile time, e.g. to embed
+//! generated tables or configuration in Rust code.
+#![forbid(unsafe_code)]
+#![deny(clippy::pedantic)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::{quote, ToTokens};
+use syn::{parse_macro_input, LitStr};
+
+use rusht::{Expr, Interpreter, Quotas};
+
+/// The limits on evaluating programs, so programs that don't terminate or
+/// recurse endlessly fail to compile instead of hanging or crashing the
+/// compiler.
+const QUOTAS: Quotas = Quotas {
+    max_string_length: None,
+    max_list_length: None,
+    max_depth: None,
+    max_steps: Some(1_000_000),
+    max_recursion_depth: Some(2_000),
+};
+
+/// The size of the stack of the thread evaluating programs, which fits the
+/// maximum recursion depth even in debug builds, where the compiler's own
+/// stack would overflow first.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Evaluates the given program during macro expansion and expands to the
+/// value of its last expression as a Rust literal. Integers and floats become
+/// unsuffixed literals, which take the integer or float type expected by the
+/// surrounding code, e.g. `u8` or `f32`, strings `&'static str` literals,
+/// booleans `bool` literals, and lists of values of the same type arrays.
+/// Programs failing to evaluate or resulting in other values, e.g. ratios or
+/// lambdas, are reported as compile errors.
+///
+/// As integer literals can't initialize floats, programs computing float
+/// constants must return floats, e.g. `(+ 40.0 2)` instead of `(+ 40 2)`,
+/// which returned a float as well before integers were introduced.
+///
+/// Programs are evaluated with the prelude, but every privileged operation,
+/// like reading input, is denied by the policy. Evaluation is limited to a
+/// million steps and a recursion depth of 2000, so e.g. endless recursion
+/// is reported as a compile error.
+///
+/// ```
+/// const LIMIT: i64 = rusht_macros::rusht_eval!("(+ 40 2)");
+/// const SMALL: u8 = rusht_macros::rusht_eval!("(+ 40 2)");
+/// const OFFSET: i8 = rusht_macros::rusht_eval!("(- 2 44)");
+/// const RATE: f64 = rusht_macros::rusht_eval!("(+ 40.0 2)");
+/// const PRIMES: [i64; 3] = rusht_macros::rusht_eval!("(quote (2 3 5))");
+///
+/// assert_eq!(LIMIT, 42);
+/// assert_eq!(SMALL, 42);
+/// assert_eq!(OFFSET, -42);
+/// assert_eq!(RATE, 42.0);
+/// assert_eq!(PRIMES, [2, 3, 5]);
+/// ```
+///
+/// ```compile_fail
+/// const RATE: f64 = rusht_macros::rusht_eval!("(+ 40 2)");
+/// ```
+#[proc_macro]
+pub fn rusht_eval(input: TokenStream) -> TokenStream {
+    let program = parse_macro_input!(input as LitStr);
+    match evaluate(&program.value()) {
+        Ok(tokens) => tokens.into(),
+        Err(message) => syn::Error::new(program.span(), message)
+            .to_compile_error()
+            .into(),
+    }
+}
+
+/// A value, that can be embedded as a Rust literal. Unlike values of the
+/// interpreter, constants can be sent back from the thread evaluating the
+/// program.
+#[derive(Debug, Clone, PartialEq)]
+enum Constant {
+    Int(i64),
+    Num(f64),
+    Str(String),
+    Bool(bool),
+    Array(Vec<Constant>),
+}
+
+/// Evaluates the given program and returns its value as a Rust literal or a
+/// message describing why it can't be embedded.
+fn evaluate(program: &str) -> Result<TokenStream2, String> {
+    let program = program.to_string();
+    let constant = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_policy(|_, _: &[Expr]| false);
+            interpreter.set_quotas(QUOTAS);
+            let value = interpreter
+                .interpret_all(program)
+                .map_err(|err| format!("failed to evaluate program: {}", err))?;
+            to_constant(&value)
+        })
+        .map_err(|err| format!("failed to start evaluating program: {}", err))?
+        .join()
+        .map_err(|_| "evaluating program panicked".to_string())??;
+    Ok(to_literal(&constant))
+}
+
+/// Returns the given value as a constant.
+fn to_constant(value: &Expr) -> Result<Constant, String> {
+    match value {
+        Expr::Int(x) => Ok(Constant::Int(*x)),
+        Expr::Num(x) => Ok(Constant::Num(*x)),
+        Expr::Str(x) => Ok(Constant::Str(x.clone())),
+        Expr::Bool(x) => Ok(Constant::Bool(*x)),
+        Expr::List(list) => {
+            if let Some(item) = list
+                .iter()
+                .find(|item| item.type_name() != list[0].type_name())
+            {
+                return Err(format!(
+                    "list of `{}` values contains a value of type `{}`",
+                    list[0].type_name(),
+                    item.type_name()
+                ));
+            }
+            let items = list.iter().map(to_constant).collect::<Result<_, _>>()?;
+            Ok(Constant::Array(items))
+        }
+        _ => Err(format!(
+            "value `{}` of type `{}` can't be embedded",
+            value,
+            value.type_name()
+        )),
+    }
+}
+
+/// Returns the given constant as a Rust literal.
+fn to_literal(constant: &Constant) -> TokenStream2 {
+    match constant {
+        Constant::Int(x) => Literal::i64_unsuffixed(*x).into_token_stream(),
+        Constant::Num(x) if x.is_nan() => quote!(::core::f64::NAN),
+        Constant::Num(x) if x.is_infinite() && *x > 0.0 => quote!(::core::f64::INFINITY),
+        Constant::Num(x) if x.is_infinite() => quote!(::core::f64::NEG_INFINITY),
+        Constant::Num(x) => Literal::f64_unsuffixed(*x).into_token_stream(),
+        Constant::Str(x) => quote!(#x),
+        Constant::Bool(x) => quote!(#x),
+        Constant::Array(items) => {
+            let items = items.iter().map(to_literal);
+            quote!([#(#items),*])
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_literals() {
+        let expand = |program| evaluate(program).map(|tokens| tokens.to_string());
+        assert_eq!(expand("(+ 40 2)"), Ok("42".to_string()));
+        assert_eq!(expand("(- 0 42)"), Ok("- 42".to_string()));
+        assert_eq!(expand("(/ 1.0 2)"), Ok("0.5".to_string()));
+        assert_eq!(expand("(+ 40.0 2)"), Ok("42.0".to_string()));
+        assert_eq!(expand("(concat \"a\" \"b\")"), Ok("\"ab\"".to_string()));
+        assert_eq!(expand("(def x 1) (= x 1)"), Ok("true".to_string()));
+        assert_eq!(expand("(quote ((1) (2)))"), Ok("[[1] , [2]]".to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(evaluate("(foo)").is_err());
+        assert!(evaluate("(read)").is_err());
+        assert!(evaluate("(func (x) x)").is_err());
+        assert!(evaluate("(quote (1 \"a\"))").is_err());
+        assert!(evaluate("(/ 1 3)").is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(
+            evaluate("(def f (func () (f))) (f)").map(|tokens| tokens.to_string()),
+            Err("failed to evaluate program: quota for recursion depth exceeded".to_string())
+        );
+        assert_eq!(
+            evaluate("(loop ((i 0)) (recur (+ i 1)))").map(|tokens| tokens.to_string()),
+            Err("failed to evaluate program: quota for evaluation steps exceeded".to_string())
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! The audit log records definitions and side-effecting builtin calls made
+//! during evaluation. Recording is disabled by default and can be enabled
+//! using `Interpreter::record_audit_log`.
+use std::time::SystemTime;
+
+use crate::expr::Expr;
+use crate::tokenize::Span;
+
+/// The kind of an audited action.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum RecordKind {
+    /// A variable was defined or redefined using `def`.
+    Define,
+    /// A side-effecting builtin was called.
+    Call,
+}
+
+/// A single entry of the audit log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The kind of the recorded action.
+    pub kind: RecordKind,
+
+    /// The name of the defined variable or called builtin.
+    pub name: String,
+
+    /// The value assigned by a definition or the arguments passed to a
+    /// builtin.
+    pub args: Vec<Expr>,
+
+    /// The point in time at which the action was performed.
+    pub timestamp: SystemTime,
+
+    /// The span of the top-level expression, during whose interpretation
+    /// the action was performed, within the input passed to the interpreter.
+    /// Expressions passed to `Interpreter::eval` have no span.
+    pub span: Option<Span>,
+}
+
+impl Record {
+    /// Creates a new record with the current time as its timestamp.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the recorded action.
+    /// * `name` - The name of the defined variable or called builtin.
+    /// * `args` - The assigned value or the passed arguments.
+    /// * `span` - The span of the top-level expression being interpreted.
+    pub(crate) fn now(kind: RecordKind, name: &str, args: Vec<Expr>, span: Option<Span>) -> Record {
+        Record {
+            kind,
+            name: name.to_string(),
+            args,
+            timestamp: SystemTime::now(),
+            span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Interpreter;
+
+    #[test]
+    fn records_spans() {
+        let mut interpreter = Interpreter::new();
+        interpreter.record_audit_log(true);
+        interpreter.interpret("(def a 1)\n(def b 2)").unwrap();
+        interpreter
+            .eval(crate::parse("(def c 3)").unwrap().remove(0))
+            .unwrap();
+
+        let records = interpreter.take_audit_log();
+        let spans = records
+            .iter()
+            .map(|record| (record.name.as_str(), record.span.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            vec![("a", Some(0..9)), ("b", Some(10..19)), ("c", None)]
+        );
+        assert!(records
+            .iter()
+            .all(|record| record.kind == RecordKind::Define));
+    }
+}
@@ -0,0 +1,107 @@
+//! Conditions and restarts. Errors returned by builtins are signaled to the
+//! handlers established using `with-handler` before the stack is unwound,
+//! innermost handler first. A handler returning normally declines to handle
+//! the error, so the next handler is tried and finally the error propagates.
+//! To recover instead, a handler invokes a restart established using
+//! `with-restart` further down the stack, near the failing call. The restart
+//! is called and the stack is unwound up to the `with-restart` form, which
+//! returns the value of the restart. This way, the recovery policy can be
+//! chosen at the top level, while the fix is executed near the error site.
+use crate::expr::{Expr, Lambda};
+use crate::Error;
+
+/// The handlers and restarts currently established.
+#[derive(Debug, Default)]
+pub struct Conditions {
+    /// The enabled handlers, innermost last.
+    pub handlers: Vec<Lambda>,
+
+    /// The names and lambdas of the restarts, innermost last.
+    pub restarts: Vec<(String, Lambda)>,
+
+    /// The index of the restart being unwound to and its value.
+    pub unwinding: Option<(usize, Expr)>,
+}
+
+impl Conditions {
+    /// Returns the index of the innermost restart of the given name.
+    pub fn find_restart(&self, name: &str) -> Option<usize> {
+        self.restarts
+            .iter()
+            .rposition(|(restart, _)| restart == name)
+    }
+
+    /// Takes the value of the invoked restart, if the stack is unwound to
+    /// the restart of the given index.
+    pub fn take_unwinding(&mut self, index: usize) -> Option<Expr> {
+        match self.unwinding.take() {
+            Some((target, value)) if target == index => Some(value),
+            other => {
+                self.unwinding = other;
+                None
+            }
+        }
+    }
+}
+
+/// Returns whether the given error is signaled to the handlers. Exiting,
+/// interruptions, and the unwinding to a restart or loop stop the evaluation
+/// and are not signaled.
+pub fn is_signaled(error: &Error) -> bool {
+    match error {
+        Error::Exit(_) | Error::RestartInvoked(_) | Error::Recur => false,
+        #[cfg(feature = "signals")]
+        Error::Interrupted(_) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lambda() -> Lambda {
+        Lambda {
+            args: vec![],
+            optional: vec![],
+            rest: None,
+            body: vec![Expr::Num(0.0)],
+            captured: vec![],
+            bound: vec![],
+        }
+    }
+
+    #[test]
+    fn test_find_restart() {
+        let conditions = Conditions {
+            restarts: vec![
+                ("retry".to_string(), lambda()),
+                ("skip".to_string(), lambda()),
+                ("retry".to_string(), lambda()),
+            ],
+            ..Conditions::default()
+        };
+        assert_eq!(conditions.find_restart("retry"), Some(2));
+        assert_eq!(conditions.find_restart("skip"), Some(1));
+        assert_eq!(conditions.find_restart("abort"), None);
+    }
+
+    #[test]
+    fn test_take_unwinding() {
+        let mut conditions = Conditions {
+            unwinding: Some((1, Expr::Num(5.0))),
+            ..Conditions::default()
+        };
+        assert_eq!(conditions.take_unwinding(2), None);
+        assert_eq!(conditions.take_unwinding(1), Some(Expr::Num(5.0)));
+        assert_eq!(conditions.take_unwinding(1), None);
+    }
+
+    #[test]
+    fn test_is_signaled() {
+        assert!(is_signaled(&Error::UnexpectedType));
+        assert!(is_signaled(&Error::Raised("failed".to_string())));
+        assert!(!is_signaled(&Error::Exit(1)));
+        assert!(!is_signaled(&Error::RestartInvoked("retry".to_string())));
+    }
+}
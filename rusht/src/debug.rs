@@ -0,0 +1,164 @@
+//! Debuggers are notified while a program is interpreted, e.g. to pause it at
+//! breakpoints or to inspect the state of a failed program.
+use std::rc::Rc;
+
+use crate::condition;
+use crate::expr::Expr;
+use crate::Error;
+
+/// A call of a lambda, that is being interpreted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The name, under which the lambda was called.
+    pub name: String,
+    /// The arguments of the lambda and their values.
+    pub locals: Vec<(String, Expr)>,
+}
+
+/// A debugger is notified about calls and errors during interpretation. As
+/// the notifications are synchronous, a debugger can pause the program by
+/// not returning, e.g. while it waits for user input.
+pub trait Debugger {
+    /// Called before the body of a lambda is interpreted.
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - The call stack. The last frame is the call about to be
+    ///     interpreted.
+    fn on_call(&self, frames: &[Frame]);
+
+    /// Called after interpreting an expression failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - The innermost expression, that failed.
+    /// * `error` - The error.
+    /// * `frames` - The call stack at the time the error occurred.
+    fn on_error(&self, expr: &Expr, error: &Error, frames: &[Frame]);
+}
+
+/// The state of the attached debugger.
+pub(crate) struct Debugging {
+    pub(crate) debugger: Rc<dyn Debugger>,
+    /// The current call stack.
+    pub(crate) frames: Vec<Frame>,
+    /// The innermost expression, that failed, and the call stack at the time
+    /// of the failure. The debugger is notified once the error has reached
+    /// the top level.
+    pub(crate) failure: Option<(Expr, Vec<Frame>)>,
+}
+
+impl Debugging {
+    pub(crate) fn new(debugger: Rc<dyn Debugger>) -> Debugging {
+        Debugging {
+            debugger,
+            frames: vec![],
+            failure: None,
+        }
+    }
+
+    /// Remembers the given expression and the current call stack, if it is
+    /// the first failure since the last error was handled or reached the top
+    /// level. The unwinding done by `exit`, `recur`, and restarts is ignored,
+    /// see `condition::is_signaled`.
+    pub(crate) fn fail(&mut self, expr: Expr, error: &Error) {
+        if self.failure.is_none() && condition::is_signaled(error) {
+            self.failure = Some((expr, self.frames.clone()));
+        }
+    }
+
+    /// Forgets the remembered failure, as an expression has been interpreted
+    /// successfully since, so the error has been handled.
+    pub(crate) fn recover(&mut self) {
+        self.failure = None;
+    }
+
+    /// Notifies the debugger about the given error, which has reached the
+    /// top level, if it has been remembered.
+    pub(crate) fn report(&mut self, error: &Error) {
+        if let Some((expr, frames)) = self.failure.take() {
+            self.debugger.on_error(&expr, error, &frames);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::Interpreter;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: RefCell<Vec<String>>,
+    }
+
+    impl Debugger for Rc<Recorder> {
+        fn on_call(&self, frames: &[Frame]) {
+            let frame = frames.last().unwrap();
+            self.events.borrow_mut().push(format!(
+                "call {} {:?} {}",
+                frame.name,
+                frame.locals,
+                frames.len()
+            ));
+        }
+
+        fn on_error(&self, expr: &Expr, error: &Error, frames: &[Frame]) {
+            self.events
+                .borrow_mut()
+                .push(format!("error {} {} {}", expr, error, frames.len()));
+        }
+    }
+
+    #[test]
+    fn notifies_debugger() {
+        let recorder = Rc::new(Recorder::default());
+        let mut interpreter = Interpreter::new();
+        interpreter.set_debugger(recorder.clone());
+
+        interpreter
+            .interpret_all("(def f (func (x) (+ x y))) (f 1)")
+            .unwrap_err();
+        interpreter.interpret("(f 2)").unwrap_err();
+
+        assert_eq!(
+            *recorder.events.borrow(),
+            vec![
+                "call f [(\"x\", Int(1))] 1",
+                "error (+ x y) variable `y` is not defined 1",
+                "call f [(\"x\", Int(2))] 1",
+                "error (+ x y) variable `y` is not defined 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_handled_errors() {
+        let recorder = Rc::new(Recorder::default());
+        let mut interpreter = Interpreter::new();
+        interpreter.set_debugger(recorder.clone());
+
+        interpreter
+            .interpret(
+                "(with-restart (skip (func () 0))
+                   (with-handler (func (m) (invoke-restart skip)) (error \"bad\")))",
+            )
+            .unwrap();
+        interpreter
+            .interpret("(loop ((i 0)) (if (< i 2) (recur (+ i 1)) i))")
+            .unwrap();
+        interpreter.interpret("(exit 1)").unwrap_err();
+        interpreter.interpret("(foo)").unwrap_err();
+
+        assert_eq!(
+            *recorder.events.borrow(),
+            vec![
+                "call handler [(\"m\", Str(\"bad\"))] 1",
+                "call skip [] 2",
+                "error (foo) function `foo` is not defined 0",
+            ]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! The execution environment, mapping variable names to their values.
+//!
+//! An environment consists of its own, mutable variables and an optional
+//! frozen environment shared with other environments. Forking an environment
+//! freezes its variables, so the original and the fork can share them, while
+//! changes made afterwards are only visible to the environment making them.
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::expr::Expr;
+
+/// An execution environment containing variable definitions.
+#[derive(Debug, Default, Clone)]
+pub struct Env {
+    /// The variables defined in this environment. A value of `None` marks a
+    /// variable that was removed, but is still defined in the shared
+    /// environment.
+    vars: HashMap<String, Option<Expr>>,
+
+    /// The frozen environment shared with forks.
+    shared: Option<Rc<Env>>,
+}
+
+impl Env {
+    /// Returns the value of the variable with the given name.
+    pub fn get(&self, name: &str) -> Option<&Expr> {
+        match self.vars.get(name) {
+            Some(value) => value.as_ref(),
+            None => self.shared.as_ref().and_then(|shared| shared.get(name)),
+        }
+    }
+
+    /// Defines or updates a variable.
+    pub fn insert(&mut self, name: String, value: Expr) {
+        self.vars.insert(name, Some(value));
+    }
+
+    /// Removes a variable.
+    pub fn remove(&mut self, name: &str) {
+        if self.shared.as_ref().and_then(|s| s.get(name)).is_some() {
+            self.vars.insert(name.to_string(), None);
+        } else {
+            self.vars.remove(name);
+        }
+    }
+
+    /// Returns all defined variables in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Expr)> {
+        let mut vars = HashMap::new();
+        let mut env = Some(self);
+        while let Some(current) = env {
+            for (name, value) in &current.vars {
+                vars.entry(name).or_insert(value);
+            }
+            env = current.shared.as_deref();
+        }
+        vars.into_iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| (name, value)))
+    }
+
+    /// Returns a new environment, containing the same variables as this one.
+    /// Instead of copying, the variables of this environment are frozen and
+    /// shared between both environments, while subsequent changes to either
+    /// environment are not visible to the other one.
+    pub fn fork(&mut self) -> Env {
+        if !self.vars.is_empty() {
+            let frozen = std::mem::take(self);
+            self.shared = Some(Rc::new(frozen));
+        }
+        Env {
+            vars: HashMap::new(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert_and_get() {
+        let mut env = Env::default();
+        env.insert("a".to_string(), Expr::Num(1.0));
+        assert_eq!(env.get("a"), Some(&Expr::Num(1.0)));
+        assert_eq!(env.get("b"), None);
+    }
+
+    #[test]
+    fn fork_is_isolated() {
+        let mut parent = Env::default();
+        parent.insert("a".to_string(), Expr::Num(1.0));
+
+        let mut child = parent.fork();
+        child.insert("a".to_string(), Expr::Num(2.0));
+        child.insert("b".to_string(), Expr::Num(3.0));
+        parent.insert("c".to_string(), Expr::Num(4.0));
+
+        assert_eq!(parent.get("a"), Some(&Expr::Num(1.0)));
+        assert_eq!(parent.get("b"), None);
+        assert_eq!(child.get("a"), Some(&Expr::Num(2.0)));
+        assert_eq!(child.get("c"), None);
+    }
+
+    #[test]
+    fn remove_shared() {
+        let mut parent = Env::default();
+        parent.insert("a".to_string(), Expr::Num(1.0));
+
+        let mut child = parent.fork();
+        child.remove("a");
+
+        assert_eq!(child.get("a"), None);
+        assert_eq!(child.iter().count(), 0);
+        assert_eq!(parent.get("a"), Some(&Expr::Num(1.0)));
+    }
+
+    #[test]
+    fn iter_prefers_own_variables() {
+        let mut parent = Env::default();
+        parent.insert("a".to_string(), Expr::Num(1.0));
+
+        let mut child = parent.fork();
+        child.insert("a".to_string(), Expr::Num(2.0));
+
+        assert_eq!(
+            child.iter().collect::<Vec<_>>(),
+            vec![(&"a".to_string(), &Expr::Num(2.0))]
+        );
+    }
+}
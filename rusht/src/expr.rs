@@ -12,11 +12,16 @@
 //! `TryFrom` trait. Conversely, conversion in the other direction is
 //! guaranteed to succeed and is thus implemented using the `Into` trait.
 
-use std::convert::TryFrom;
-use std::fmt::{Display, Formatter};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
 
+use crate::map::Key;
+use crate::number;
 use crate::tokenize::Token;
-use crate::{Error, Result};
+use crate::{Context, Env, Error, Result};
 
 /// Lambda is a struct representing a single lambda expression.
 #[derive(Debug, PartialEq, Clone)]
@@ -26,21 +31,316 @@ pub struct Lambda {
     /// passed values.
     pub args: Vec<String>,
 
-    /// The body of the lambda. This body will be interpreted upon invocation
-    /// of the lambda expression.
-    pub body: Box<Expr>,
+    /// The names and default values of the optional arguments, following
+    /// `args`. The default of an omitted argument is interpreted on
+    /// invocation, after defining the arguments before it.
+    pub optional: Vec<(String, Expr)>,
+
+    /// The name of the rest argument, written after `&`, if any. On
+    /// invocation, it is defined as a list of the values passed in addition
+    /// to those for `args`.
+    pub rest: Option<String>,
+
+    /// The body of the lambda. These expressions will be interpreted in order
+    /// upon invocation of the lambda expression, returning the value of the
+    /// last one.
+    pub body: Vec<Expr>,
+
+    /// The local variables visible where the lambda was created, as their
+    /// names and values at that time. On invocation, these are defined
+    /// before the arguments, so the body sees the variables of the scope
+    /// it was written in, e.g. those of an enclosing lambda, rather than
+    /// those of the caller. Global variables are not captured, but looked
+    /// up on invocation.
+    pub captured: Vec<(String, Expr)>,
+
+    /// The arguments already passed by a partial application, as the names
+    /// and values of the variables to define before those in `args`. This is
+    /// empty, unless currying is enabled.
+    pub bound: Vec<(String, Expr)>,
+}
+
+impl Lambda {
+    /// Returns the parameter list as written, e.g. `(a (b 10) & rest)`.
+    #[must_use]
+    pub fn params(&self) -> Vec<Expr> {
+        let mut params = self
+            .args
+            .iter()
+            .cloned()
+            .map(Expr::Ident)
+            .collect::<Vec<_>>();
+        params.extend(
+            self.optional.iter().map(|(name, default)| {
+                Expr::List(vec![Expr::Ident(name.clone()), default.clone()])
+            }),
+        );
+        if let Some(rest) = &self.rest {
+            params.extend([Expr::Ident("&".to_string()), Expr::Ident(rest.clone())]);
+        }
+        params
+    }
+}
+
+/// External is an opaque handle to a Rust value owned by the host. Scripts
+/// can only pass it around, while native functions can downcast it back to
+/// the original value. Two handles are equal, if they refer to the same value.
+#[derive(Clone)]
+pub struct External {
+    type_name: &'static str,
+    value: Rc<dyn Any>,
+}
+
+impl External {
+    /// Wraps the given value into a new handle.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to be wrapped.
+    pub fn new<T>(value: T) -> External
+    where
+        T: Any,
+    {
+        External {
+            type_name: std::any::type_name::<T>(),
+            value: Rc::new(value),
+        }
+    }
+
+    /// Returns the name of the type of the wrapped value.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns a reference to the wrapped value, if it is of type `T`.
+    #[must_use]
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: Any,
+    {
+        self.value.downcast_ref()
+    }
+}
+
+impl PartialEq for External {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl Debug for External {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("External")
+            .field("type_name", &self.type_name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A builtin function, that is passed the execution environment and the
+/// interpreter context in addition to its interpreted arguments, so it can
+/// call the functions passed to it, e.g. `map`, or use the settings of the
+/// interpreter, e.g. `=`. Unlike special forms, these
+/// are values like prelude functions, so they can be passed as arguments
+/// and shadowed by definitions of the same name.
+#[derive(Clone, Copy)]
+pub struct Intrinsic(pub(crate) fn(Vec<Expr>, &mut Env, &mut Context) -> Result<Expr>);
+
+impl PartialEq for Intrinsic {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::fn_addr_eq(self.0, other.0)
+    }
+}
+
+impl Debug for Intrinsic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Intrinsic").finish_non_exhaustive()
+    }
 }
 
 /// An expression in the "Rusht" language.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expr {
+    /// The absence of a value, e.g. returned by forms, that are only
+    /// interpreted for their side effects.
+    Nil,
+    /// An integer, e.g. `42`. See the `number` module for how integers,
+    /// ratios, and floats are combined.
+    Int(i64),
+    /// A ratio in lowest terms, whose denominator is greater than one, e.g.
+    /// `1/3`.
+    Ratio(i64, i64),
+    /// A float, e.g. `4.2`.
     Num(f64),
     Str(String),
+    /// A character, i.e. a Unicode scalar value, e.g. `#\a` or `#\space`.
+    Char(char),
     Ident(String),
+    /// A keyword, e.g. `:name`, which evaluates to itself. The name is
+    /// stored without the leading colon.
+    Keyword(String),
+    /// A symbol, i.e. a quoted identifier, which is not resolved as a
+    /// variable.
+    Symbol(String),
     Bool(bool),
     List(Vec<Expr>),
+    Vector(Vec<Expr>),
+    Map(BTreeMap<Key, Expr>),
     Func(fn(Vec<Expr>) -> Result<Expr>),
-    Lambda(Lambda),
+    Lambda(Box<Lambda>),
+    Intrinsic(Intrinsic),
+    External(External),
+}
+
+impl Expr {
+    /// Returns the name of the type of this expression, e.g. `num` for
+    /// numbers.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Expr::Nil => "nil",
+            Expr::Int(_) => "int",
+            Expr::Ratio(..) => "ratio",
+            Expr::Num(_) => "num",
+            Expr::Str(_) => "str",
+            Expr::Char(_) => "char",
+            Expr::Ident(_) => "ident",
+            Expr::Keyword(_) => "keyword",
+            Expr::Symbol(_) => "symbol",
+            Expr::Bool(_) => "bool",
+            Expr::List(_) => "list",
+            Expr::Vector(_) => "vector",
+            Expr::Map(_) => "map",
+            Expr::Func(_) | Expr::Intrinsic(_) => "func",
+            Expr::Lambda(_) => "lambda",
+            Expr::External(_) => "external",
+        }
+    }
+
+    /// Returns whether this expression is identical to the given one, as
+    /// done by `eq?`. Handles and prelude functions are identical, if they
+    /// refer to the same value or function. Atoms, i.e. `nil`, numbers,
+    /// strings, characters, identifiers, keywords, symbols, and booleans, are immutable and thus identical, if they
+    /// have the same type and value. Lambdas capture no environment apart
+    /// from local variables and partially applied arguments, so they are
+    /// identical, if their arguments, bodies, captured variables, and bound
+    /// arguments are. Lists, vectors, and maps
+    /// are copied when passed around, so they have no identity, and only
+    /// empty ones are identical.
+    #[must_use]
+    pub fn is_eq(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (Expr::Nil, Expr::Nil) => true,
+            (Expr::Int(a), Expr::Int(b)) => a == b,
+            (Expr::Ratio(a, b), Expr::Ratio(c, d)) => a == c && b == d,
+            (Expr::Num(a), Expr::Num(b)) => a == b,
+            (Expr::Char(a), Expr::Char(b)) => a == b,
+            (Expr::Str(a), Expr::Str(b))
+            | (Expr::Ident(a), Expr::Ident(b))
+            | (Expr::Keyword(a), Expr::Keyword(b))
+            | (Expr::Symbol(a), Expr::Symbol(b)) => a == b,
+            (Expr::Bool(a), Expr::Bool(b)) => a == b,
+            (Expr::List(a), Expr::List(b)) | (Expr::Vector(a), Expr::Vector(b)) => {
+                a.is_empty() && b.is_empty()
+            }
+            (Expr::Map(a), Expr::Map(b)) => a.is_empty() && b.is_empty(),
+            (Expr::Func(a), Expr::Func(b)) => std::ptr::fn_addr_eq(*a, *b),
+            (Expr::Intrinsic(a), Expr::Intrinsic(b)) => a == b,
+            (Expr::Lambda(a), Expr::Lambda(b)) => a == b,
+            (Expr::External(a), Expr::External(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Returns whether this expression is structurally equal to the given
+    /// one, as done by `equal?`. Lists and vectors are equal, if their
+    /// elements are pairwise equal, and maps, if they have the same keys and the values
+    /// of the keys are equal. All other expressions are equal, if they are
+    /// identical (see `is_eq`). No type coercion is performed.
+    #[must_use]
+    pub fn is_equal(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (Expr::List(a), Expr::List(b)) | (Expr::Vector(a), Expr::Vector(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.is_equal(b))
+            }
+            (Expr::Map(a), Expr::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|((a, x), (b, y))| a == b && x.is_equal(y))
+            }
+            _ => self.is_eq(other),
+        }
+    }
+
+    /// Returns this expression as quoted data, i.e. with all identifiers,
+    /// also those nested in lists, vectors, and maps, replaced by symbols of
+    /// the same name, as done by `quote`.
+    #[must_use]
+    pub fn quoted(self) -> Expr {
+        match self {
+            Expr::Ident(name) => Expr::Symbol(name),
+            Expr::List(list) => Expr::List(list.into_iter().map(Expr::quoted).collect()),
+            Expr::Vector(vector) => Expr::Vector(vector.into_iter().map(Expr::quoted).collect()),
+            Expr::Map(map) => Expr::Map(
+                map.into_iter()
+                    .map(|(key, value)| (key.quoted(), value.quoted()))
+                    .collect(),
+            ),
+            expr => expr,
+        }
+    }
+
+    /// Returns source code, that evaluates to this expression, e.g. for
+    /// saving a value to a file. Lists are quoted, vectors and maps are
+    /// written as literals, symbols are written using `string->symbol`, and
+    /// lambdas are written using `func`. Returns `None` for expressions, that can not be written as
+    /// source code, such as prelude functions, external handles, partially
+    /// applied lambdas, and strings containing quotation marks.
+    #[must_use]
+    pub fn to_source(&self) -> Option<String> {
+        match self {
+            Expr::List(list) => {
+                let items = list.iter().map(Expr::to_code).collect::<Option<Vec<_>>>()?;
+                Some(format!("(quote {})", stringify(&items)))
+            }
+            Expr::Symbol(name) => Some(format!(
+                "(string->symbol {})",
+                Expr::Str(name.clone()).to_code()?
+            )),
+            Expr::Vector(vector) => write_vector(vector, Expr::to_source),
+            Expr::Map(map) => write_map(map, Expr::to_source),
+            Expr::Lambda(lambda) if lambda.bound.is_empty() && lambda.captured.is_empty() => {
+                let mut func = vec![Expr::Ident("func".to_string()), Expr::List(lambda.params())];
+                func.extend(lambda.body.iter().cloned());
+                Expr::List(func).to_code()
+            }
+            _ => self.to_code(),
+        }
+    }
+
+    /// Returns source code, that parses to this expression without being
+    /// evaluated, e.g. the body of a lambda or the items of a quoted list.
+    fn to_code(&self) -> Option<String> {
+        match self {
+            Expr::Str(x) if !x.contains('"') => Some(self.to_string()),
+            Expr::Nil
+            | Expr::Int(_)
+            | Expr::Ratio(..)
+            | Expr::Num(_)
+            | Expr::Char(_)
+            | Expr::Ident(_)
+            | Expr::Keyword(_)
+            | Expr::Symbol(_)
+            | Expr::Bool(_) => Some(self.to_string()),
+            Expr::List(list) => Some(stringify(
+                &list.iter().map(Expr::to_code).collect::<Option<Vec<_>>>()?,
+            )),
+            Expr::Vector(vector) => write_vector(vector, Expr::to_code),
+            Expr::Map(map) => write_map(map, Expr::to_code),
+            _ => None,
+        }
+    }
 }
 
 /// This implementation of the `Display` trait determines how the different
@@ -48,19 +348,106 @@ pub enum Expr {
 impl Display for Expr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Expr::Num(x) => write!(f, "{}", x),
+            Expr::Nil => write!(f, "nil"),
+            Expr::Int(x) => write!(f, "{}", x),
+            Expr::Ratio(n, d) => write!(f, "{}/{}", n, d),
+            Expr::Num(x) => write!(f, "{}", number::format_float(*x)),
             Expr::Str(x) => write!(f, "\"{}\"", x),
-            Expr::Ident(x) => write!(f, "{}", x),
+            Expr::Char(' ') => write!(f, "#\\space"),
+            Expr::Char('\n') => write!(f, "#\\newline"),
+            Expr::Char(x) => write!(f, "#\\{}", x),
+            Expr::Ident(x) | Expr::Symbol(x) => write!(f, "{}", x),
+            Expr::Keyword(x) => write!(f, ":{}", x),
             Expr::Bool(x) => write!(f, "{}", x),
-            Expr::Lambda(Lambda { args, body }) => {
-                write!(f, "\u{3bb} {} -> {}", stringify(args), body.to_string())
-            }
+            Expr::Lambda(lambda) => write!(
+                f,
+                "\u{3bb} {} -> {}",
+                stringify(&lambda.params()),
+                lambda
+                    .body
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
             Expr::List(list) => write!(f, "{}", stringify(list)),
-            Expr::Func(_) => write!(f, "prelude function"),
+            Expr::Vector(vector) => write!(
+                f,
+                "[{}]",
+                vector
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            Expr::Map(map) => write!(
+                f,
+                "{{{}}}",
+                map.iter()
+                    .map(|(key, value)| format!("{} {}", key.as_expr(), value))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            Expr::Func(_) | Expr::Intrinsic(_) => write!(f, "prelude function"),
+            Expr::External(external) => write!(f, "#<{}>", external.type_name()),
         }
     }
 }
 
+/// Expressions are serialized to the corresponding data types, e.g. lists to
+/// sequences, maps to maps, keywords to their names, and ratios to floats. Functions, lambdas, and external handles have no such
+/// counterpart and are serialized using their string representation.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Expr {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Expr::Nil => serializer.serialize_unit(),
+            Expr::Int(x) => serializer.serialize_i64(*x),
+            #[allow(clippy::cast_precision_loss)]
+            Expr::Ratio(n, d) => serializer.serialize_f64(*n as f64 / *d as f64),
+            Expr::Num(x) => serializer.serialize_f64(*x),
+            Expr::Char(x) => serializer.serialize_char(*x),
+            Expr::Str(x) | Expr::Ident(x) | Expr::Keyword(x) | Expr::Symbol(x) => {
+                serializer.serialize_str(x)
+            }
+            Expr::Bool(x) => serializer.serialize_bool(*x),
+            Expr::List(list) | Expr::Vector(list) => serializer.collect_seq(list),
+            Expr::Map(map) => serializer.collect_map(map.iter().map(|(k, v)| (k.as_expr(), v))),
+            Expr::Func(_) | Expr::Intrinsic(_) | Expr::Lambda(_) | Expr::External(_) => {
+                serializer.collect_str(self)
+            }
+        }
+    }
+}
+
+/// Returns the source code of a vector literal of the given vector, whose
+/// elements are written using the given function.
+fn write_vector<F>(vector: &[Expr], to_code: F) -> Option<String>
+where
+    F: Fn(&Expr) -> Option<String>,
+{
+    let items = vector.iter().map(to_code).collect::<Option<Vec<_>>>()?;
+    Some(format!("[{}]", items.join(" ")))
+}
+
+/// Returns the source code of a map literal of the given map. The keys of map
+/// literals are not evaluated, while the values are written using the given
+/// function.
+fn write_map<F>(map: &BTreeMap<Key, Expr>, value_to_code: F) -> Option<String>
+where
+    F: Fn(&Expr) -> Option<String>,
+{
+    let mut items = vec![];
+    for (key, value) in map {
+        items.push(key.as_expr().to_code()?);
+        items.push(value_to_code(value)?);
+    }
+    Some(format!("{{{}}}", items.join(" ")))
+}
+
 /// Returns the string representation of a given slice.
 ///
 /// # Arguments
@@ -76,6 +463,20 @@ where
     )
 }
 
+impl From<i64> for Expr {
+    fn from(n: i64) -> Self {
+        Expr::Int(n)
+    }
+}
+
+/// Lengths and indices become integers, which are large enough for any of
+/// them in practice.
+impl From<usize> for Expr {
+    fn from(n: usize) -> Self {
+        Expr::Int(i64::try_from(n).unwrap_or(i64::MAX))
+    }
+}
+
 impl From<f64> for Expr {
     fn from(n: f64) -> Self {
         Expr::Num(n)
@@ -88,6 +489,12 @@ impl From<String> for Expr {
     }
 }
 
+impl From<char> for Expr {
+    fn from(c: char) -> Self {
+        Expr::Char(c)
+    }
+}
+
 impl From<bool> for Expr {
     fn from(b: bool) -> Self {
         Expr::Bool(b)
@@ -99,11 +506,16 @@ impl TryFrom<Token> for Expr {
 
     fn try_from(value: Token) -> Result<Self> {
         match value {
+            Token::Int(x) => Ok(Expr::Int(x)),
+            Token::Ratio(n, d) => Ok(Expr::Ratio(n, d)),
             Token::Num(x) => Ok(Expr::Num(x)),
             Token::Str(x) => Ok(Expr::Str(x)),
+            Token::Char(x) => Ok(Expr::Char(x)),
             Token::Ident(x) => Ok(Expr::Ident(x)),
+            Token::Keyword(x) => Ok(Expr::Keyword(x)),
             Token::Bool(x) => Ok(Expr::Bool(x)),
-            Token::Paren(_) => Err(Error::UnexpectedType),
+            Token::Nil => Ok(Expr::Nil),
+            Token::Paren(_) | Token::Quote(_) => Err(Error::UnexpectedType),
         }
     }
 }
@@ -113,6 +525,10 @@ impl TryFrom<Expr> for f64 {
 
     fn try_from(expr: Expr) -> Result<Self> {
         match expr {
+            #[allow(clippy::cast_precision_loss)]
+            Expr::Int(n) => Ok(n as f64),
+            #[allow(clippy::cast_precision_loss)]
+            Expr::Ratio(n, d) => Ok(n as f64 / d as f64),
             Expr::Num(n) => Ok(n),
             Expr::Bool(true) => Ok(1.0),
             Expr::Bool(false) => Ok(0.0),
@@ -122,14 +538,38 @@ impl TryFrom<Expr> for f64 {
     }
 }
 
+/// Floats are coerced to integers, if they are integral and in the range of
+/// integers.
+impl TryFrom<Expr> for i64 {
+    type Error = Error;
+
+    fn try_from(expr: Expr) -> Result<Self> {
+        if let Expr::Int(n) = expr {
+            return Ok(n);
+        }
+        let n: f64 = expr.try_into()?;
+        // The bounds are powers of two, so they are exact as floats.
+        #[allow(clippy::cast_precision_loss)]
+        let in_range = (i64::MIN as f64..-(i64::MIN as f64)).contains(&n);
+        if n.fract() != 0.0 || !in_range {
+            return Err(Error::UnexpectedType);
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(n as i64)
+    }
+}
+
 impl TryFrom<Expr> for String {
     type Error = Error;
 
     fn try_from(expr: Expr) -> Result<Self> {
         match expr {
             Expr::Str(s) => Ok(s),
+            Expr::Char(c) => Ok(c.to_string()),
             Expr::Bool(b) => Ok(b.to_string()),
-            Expr::Num(n) => Ok(n.to_string()),
+            Expr::Int(n) => Ok(n.to_string()),
+            Expr::Ratio(..) => Ok(expr.to_string()),
+            Expr::Num(n) => Ok(number::format_float(n)),
             _ => Err(Error::UnexpectedType),
         }
     }
@@ -140,9 +580,12 @@ impl TryFrom<Expr> for bool {
 
     fn try_from(expr: Expr) -> Result<Self> {
         match expr {
+            Expr::Nil => Ok(false),
             Expr::Bool(b) => Ok(b),
+            Expr::Int(x) => Ok(x != 0),
             Expr::Num(x) if x == 0.0 => Ok(false),
-            Expr::Num(_) => Ok(true),
+            // Ratios are never zero.
+            Expr::Ratio(..) | Expr::Num(_) => Ok(true),
             Expr::Str(s) if ["true", "1"].contains(&s.trim()) => Ok(true),
             Expr::Str(s) if ["false", "0", ""].contains(&s.trim()) => Ok(false),
             _ => Err(Error::UnexpectedType),
@@ -166,16 +609,111 @@ mod test {
                 Expr::Num(5.0),
                 Expr::Str("foo".to_string()),
                 Expr::List(vec![Expr::Ident("bar".to_string()), Expr::Bool(true),]),
-                Expr::Lambda(Lambda {
+                Expr::Lambda(Box::new(Lambda {
                     args: vec!["a".to_string()],
-                    body: Box::from(Expr::List(vec![
+                    optional: vec![],
+                    rest: None,
+                    body: vec![Expr::List(vec![
                         Expr::Ident("+".to_string()),
                         Expr::Ident("a".to_string()),
                         Expr::Num(1.0)
-                    ])),
-                })
+                    ])],
+                    captured: vec![],
+                    bound: vec![],
+                }))
             ]),
-            "(5 \"foo\" (bar true) \u{3bb} (a) -> (+ a 1))"
+            "(5.0 \"foo\" (bar true) \u{3bb} (a) -> (+ a 1.0))"
         )
     }
+
+    #[test]
+    fn test_type_name() {
+        assert_eq!(Expr::Num(1.0).type_name(), "num");
+        assert_eq!(Expr::List(vec![]).type_name(), "list");
+    }
+
+    #[test]
+    fn test_to_source() {
+        let lambda = Expr::Lambda(Box::new(Lambda {
+            args: vec!["a".to_string()],
+            optional: vec![],
+            rest: None,
+            body: vec![Expr::List(vec![
+                Expr::Ident("+".to_string()),
+                Expr::Ident("a".to_string()),
+                Expr::Str("b".to_string()),
+            ])],
+            captured: vec![],
+            bound: vec![],
+        }));
+        assert_eq!(
+            lambda.to_source(),
+            Some("(func (a) (+ a \"b\"))".to_string())
+        );
+        for (x, source) in [
+            (Expr::Num(-1.5), "-1.5"),
+            (Expr::Num(-0.0), "-0.0"),
+            (Expr::Num(2.0), "2.0"),
+            (Expr::Int(i64::MIN), "-9223372036854775808"),
+            (Expr::Ratio(-2, 3), "-2/3"),
+        ] {
+            assert_eq!(x.to_source(), Some(source.to_string()));
+            let tokens = crate::tokenize::tokenize(source).unwrap();
+            assert_eq!(crate::parse::parse_all(tokens), Ok(vec![x]));
+        }
+        assert_eq!(
+            Expr::List(vec![Expr::Bool(true), Expr::List(vec![])]).to_source(),
+            Some("(quote (true ()))".to_string())
+        );
+        assert_eq!(
+            Expr::Vector(vec![Expr::Num(-1.0), Expr::List(vec![])]).to_source(),
+            Some("[-1.0 (quote ())]".to_string())
+        );
+        assert_eq!(Expr::Str("\"".to_string()).to_source(), None);
+        assert_eq!(Expr::List(vec![lambda]).to_source(), None);
+
+        let partial = Expr::Lambda(Box::new(Lambda {
+            args: vec![],
+            optional: vec![],
+            rest: None,
+            body: vec![Expr::Ident("b".to_string())],
+            captured: vec![],
+            bound: vec![("b".to_string(), Expr::Num(1.0))],
+        }));
+        assert_eq!(partial.to_source(), None);
+    }
+
+    #[test]
+    fn test_external() {
+        let external = External::new(42_u8);
+        assert_eq!(external.downcast_ref::<u8>(), Some(&42));
+        assert_eq!(external.downcast_ref::<u16>(), None);
+        assert_eq!(Expr::External(external.clone()).to_string(), "#<u8>");
+        assert_eq!(external, external.clone());
+        assert_ne!(external, External::new(42_u8));
+    }
+
+    #[test]
+    fn test_identity() {
+        let external = Expr::External(External::new(42_u8));
+        assert!(external.is_eq(&external.clone()));
+        assert!(!external.is_equal(&Expr::External(External::new(42_u8))));
+
+        let lambda = |body: &str| {
+            Expr::Lambda(Box::new(Lambda {
+                args: vec!["x".to_string()],
+                optional: vec![],
+                rest: None,
+                body: vec![Expr::Ident(body.to_string())],
+                captured: vec![],
+                bound: vec![],
+            }))
+        };
+        assert!(lambda("x").is_eq(&lambda("x")));
+        assert!(!lambda("x").is_eq(&lambda("y")));
+
+        let list = Expr::List(vec![lambda("x"), external.clone()]);
+        assert!(!list.is_eq(&list.clone()));
+        assert!(list.is_equal(&list.clone()));
+    }
 }
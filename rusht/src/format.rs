@@ -0,0 +1,290 @@
+//! Formatting of source code in a canonical layout. Lists are written on a
+//! single line, if they fit into the maximum width. Otherwise, their
+//! elements are written on separate lines, indented relative to the opening
+//! parenthesis, except for the first argument of a function, which stays on
+//! the line of the function. Vector and map literals, that don't fit, are
+//! written with one element, or one key and value, per line. Literals and identifiers are kept exactly as
+//! written, so formatting formatted code does not change it.
+use crate::tokenize::{closing, Lexer, Token};
+use crate::{Error, Result};
+
+/// The maximum width of a line, that lists are fit into.
+const MAX_WIDTH: usize = 80;
+
+/// The indentation of the elements of a list, that does not fit on a line.
+const INDENT: usize = 2;
+
+/// The tree of tokens of a formatted expression.
+enum Node<'a> {
+    /// A literal or identifier as written in the source code.
+    Atom(&'a str),
+    List(Vec<Node<'a>>),
+    Vector(Vec<Node<'a>>),
+    /// A map literal of keys, each followed by its value.
+    Map(Vec<Node<'a>>),
+    /// A node written with a leading quote prefix, e.g. an apostrophe.
+    Quoted(&'static str, Box<Node<'a>>),
+}
+
+/// Formats the given source code. Top-level expressions are separated by a
+/// single newline, or by an empty line, if there was at least one before.
+///
+/// # Arguments
+///
+/// * `input` - The source code to format.
+///
+/// # Errors
+///
+/// * `MissingClosingParenthesis` - If a list is not closed.
+/// * `UnexpectedClosingParenthesis` - If a parenthesis is closed, that was
+///     never opened, or directly after a quote.
+/// * `MismatchedParenthesis` - If a list is closed by a different kind of
+///     brace than it was opened with.
+/// * `UnexpectedEndOfTokenStream` - If the input ends with a quote.
+///
+/// Additionally, all the errors returned by the tokenizer can be returned.
+pub fn format(input: &str) -> Result<String> {
+    let mut out = String::new();
+    // The lists, vectors, and maps being parsed, from the outermost to the
+    // innermost one, each with the quotes preceding it and its opening
+    // parenthesis, bracket, or brace.
+    let mut stack: Vec<(Vec<&str>, char, Vec<Node>)> = vec![];
+    // The quotes preceding the next node.
+    let mut quotes = vec![];
+    let mut start = 0;
+    let mut last_end = 0;
+
+    for (token, span) in Lexer::new(input) {
+        if stack.is_empty() && quotes.is_empty() {
+            start = span.start;
+        }
+        let node = match token? {
+            Token::Paren(open) if closing(open).is_some() => {
+                stack.push((std::mem::take(&mut quotes), open, vec![]));
+                continue;
+            }
+            Token::Quote(prefix) => {
+                quotes.push(prefix);
+                continue;
+            }
+            Token::Paren(close) => match stack.pop() {
+                Some((list_quotes, open, nodes)) if quotes.is_empty() => {
+                    let expected = closing(open).unwrap_or(close);
+                    if close != expected {
+                        return Err(Error::MismatchedParenthesis(expected, close));
+                    }
+                    let node = match open {
+                        '{' => Node::Map(nodes),
+                        '[' => Node::Vector(nodes),
+                        _ => Node::List(nodes),
+                    };
+                    quote(list_quotes, node)
+                }
+                _ => return Err(Error::UnexpectedClosingParenthesis),
+            },
+            _ => quote(
+                std::mem::take(&mut quotes),
+                Node::Atom(&input[span.clone()]),
+            ),
+        };
+
+        if let Some((_, _, list)) = stack.last_mut() {
+            list.push(node);
+        } else {
+            // Top-level expressions are written as soon as they are complete.
+            if !out.is_empty() {
+                let gap = &input[last_end..start];
+                out.push_str(if gap.matches('\n').count() > 1 {
+                    "\n\n"
+                } else {
+                    "\n"
+                });
+            }
+            render(&node, 0, &mut out);
+            last_end = span.end;
+        }
+    }
+
+    if !stack.is_empty() {
+        return Err(Error::MissingClosingParenthesis);
+    }
+    if !quotes.is_empty() {
+        return Err(Error::UnexpectedEndOfTokenStream);
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Wraps the given node in the given quotes, the outermost first.
+fn quote<'a>(quotes: Vec<&'static str>, node: Node<'a>) -> Node<'a> {
+    quotes
+        .into_iter()
+        .rev()
+        .fold(node, |node, prefix| Node::Quoted(prefix, Box::new(node)))
+}
+
+/// Writes the given node at the given column.
+fn render(node: &Node, column: usize, out: &mut String) {
+    if let Node::Quoted(prefix, node) = node {
+        out.push_str(prefix);
+        render(node, column + prefix.len(), out);
+        return;
+    }
+    let flat = flat(node);
+    let list = match node {
+        Node::List(list) if column + flat.chars().count() > MAX_WIDTH && list.len() > 1 => list,
+        Node::Vector(vector) if column + flat.chars().count() > MAX_WIDTH => {
+            render_rows(('[', ']'), vector, 1, column, out);
+            return;
+        }
+        Node::Map(map) if column + flat.chars().count() > MAX_WIDTH => {
+            render_rows(('{', '}'), map, 2, column, out);
+            return;
+        }
+        _ => {
+            out.push_str(&flat);
+            return;
+        }
+    };
+
+    out.push('(');
+    render(&list[0], column + 1, out);
+    let mut rest = &list[1..];
+    // The first argument is kept on the line of the function it is passed
+    // to, e.g. the name in `(def name`.
+    if let Node::Atom(head) = &list[0] {
+        out.push(' ');
+        render(&rest[0], column + head.chars().count() + 2, out);
+        rest = &rest[1..];
+    }
+    for node in rest {
+        out.push('\n');
+        out.push_str(&" ".repeat(column + INDENT));
+        render(node, column + INDENT, out);
+    }
+    out.push(')');
+}
+
+/// Writes the given elements of a vector or map literal enclosed in the given
+/// brackets at the given column, with the given number of elements per line,
+/// e.g. two for a key and its value.
+fn render_rows(
+    brackets: (char, char),
+    nodes: &[Node],
+    per_row: usize,
+    column: usize,
+    out: &mut String,
+) {
+    out.push(brackets.0);
+    for (i, row) in nodes.chunks(per_row).enumerate() {
+        if i > 0 {
+            out.push('\n');
+            out.push_str(&" ".repeat(column + 1));
+        }
+        render(&row[0], column + 1, out);
+        if let Some(value) = row.get(1) {
+            out.push(' ');
+            render(value, column + flat(&row[0]).chars().count() + 2, out);
+        }
+    }
+    out.push(brackets.1);
+}
+
+/// Returns the given node written on a single line.
+fn flat(node: &Node) -> String {
+    match node {
+        Node::Atom(atom) => (*atom).to_string(),
+        Node::List(list) => format!("({})", list.iter().map(flat).collect::<Vec<_>>().join(" ")),
+        Node::Vector(vector) => format!(
+            "[{}]",
+            vector.iter().map(flat).collect::<Vec<_>>().join(" ")
+        ),
+        Node::Map(map) => format!("{{{}}}", map.iter().map(flat).collect::<Vec<_>>().join(" ")),
+        Node::Quoted(prefix, node) => format!("{}{}", prefix, flat(node)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_flat() {
+        assert_eq!(
+            format("  ( def  x\n 1.50 )(+ x\t\"a  b\")"),
+            Ok("(def x 1.50)\n(+ x \"a  b\")\n".to_string())
+        );
+        assert_eq!(format(""), Ok(String::new()));
+    }
+
+    #[test]
+    fn format_keeps_empty_lines() {
+        assert_eq!(
+            format("(def a 1)\n\n\n(def b 2)\n(def c 3)"),
+            Ok("(def a 1)\n\n(def b 2)\n(def c 3)\n".to_string())
+        );
+    }
+
+    #[test]
+    fn format_long_list() {
+        let input = format!("(def f (func (x) (concat \"{}\" x)))", "a".repeat(70));
+        let expected = format!(
+            "(def f\n  (func (x)\n    (concat \"{}\"\n      x)))\n",
+            "a".repeat(70)
+        );
+        assert_eq!(format(&input), Ok(expected.clone()));
+        assert_eq!(format(&expected), Ok(expected));
+    }
+
+    #[test]
+    fn format_unbalanced() {
+        assert_eq!(format("(+ 1"), Err(Error::MissingClosingParenthesis));
+        assert_eq!(format("1)"), Err(Error::UnexpectedClosingParenthesis));
+        assert_eq!(format("(1 ')"), Err(Error::UnexpectedClosingParenthesis));
+        assert_eq!(format("1 '"), Err(Error::UnexpectedEndOfTokenStream));
+        assert_eq!(format("{1 2)"), Err(Error::MismatchedParenthesis('}', ')')));
+        assert_eq!(format("[1 2)"), Err(Error::MismatchedParenthesis(']', ')')));
+    }
+
+    #[test]
+    fn format_quote() {
+        assert_eq!(
+            format("' ( 1  'x )\n'` ()\n`(1 , x ,@ (y))"),
+            Ok("'(1 'x)\n'`()\n`(1 ,x ,@(y))\n".to_string())
+        );
+    }
+
+    #[test]
+    fn format_map() {
+        assert_eq!(
+            format("{ :a  1 :b (f  x) }"),
+            Ok("{:a 1 :b (f x)}\n".to_string())
+        );
+        let input = format!("(def m {{:name \"{}\" :age 3}})", "a".repeat(70));
+        let expected = format!("(def m\n  {{:name \"{}\"\n   :age 3}})\n", "a".repeat(70));
+        assert_eq!(format(&input), Ok(expected.clone()));
+        assert_eq!(format(&expected), Ok(expected));
+        let input = format!("{{:f (func (x) (concat \"{}\" x))}}", "a".repeat(70));
+        let expected = format!(
+            "{{:f (func (x)\n      (concat \"{}\"\n        x))}}\n",
+            "a".repeat(70)
+        );
+        assert_eq!(format(&input), Ok(expected.clone()));
+        assert_eq!(format(&expected), Ok(expected));
+    }
+
+    #[test]
+    fn format_vector() {
+        assert_eq!(format("[ 1  [] ]"), Ok("[1 []]\n".to_string()));
+        let input = format!("(def v [\"{}\" 1 2])", "a".repeat(75));
+        let expected = format!("(def v\n  [\"{}\"\n   1\n   2])\n", "a".repeat(75));
+        assert_eq!(format(&input), Ok(expected.clone()));
+        assert_eq!(format(&expected), Ok(expected));
+        let input = format!("[(concat \"{}\" x)]", "a".repeat(75));
+        let expected = format!("[(concat \"{}\"\n   x)]\n", "a".repeat(75));
+        assert_eq!(format(&input), Ok(expected.clone()));
+        assert_eq!(format(&expected), Ok(expected));
+    }
+}
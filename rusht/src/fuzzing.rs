@@ -0,0 +1,249 @@
+//! Generators of tokens, expressions and well-formed source code for fuzz
+//! targets, available with the `fuzzing` feature. A generator consumes the
+//! raw bytes provided by the fuzzer, so the generated values are fully
+//! determined by, and shrink with, the input:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| {
+//!     let (source, exprs) = rusht::Generator::new(data).source();
+//!     assert_eq!(rusht::parse(&source), Ok(exprs));
+//! });
+//! ```
+//!
+//! `Token` and `Expr` implement `arbitrary::Arbitrary` using the generator,
+//! so they can be taken as the input of fuzz targets directly:
+//!
+//! ```ignore
+//! fuzz_target!(|expr: rusht::Expr| {
+//!     if let Some(source) = expr.quoted().to_source() {
+//!         let _ = rusht::Interpreter::new().interpret(&source);
+//!     }
+//! });
+//! ```
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::expr::Expr;
+use crate::tokenize::Token;
+
+/// The maximum nesting depth of generated lists.
+const MAX_DEPTH: usize = 4;
+
+/// The maximum number of elements of generated lists, strings and
+/// identifiers, and of expressions in generated source code.
+const MAX_LEN: u8 = 5;
+
+/// The characters identifiers may start with.
+const IDENT_START: &[char] = &[
+    'a', 'f', 'x', 'z', '_', '+', '*', '<', '=', '$', '\u{3bb}', '\u{e9}',
+];
+
+/// The characters identifiers may continue with.
+const IDENT_CONTINUE: &[char] = &['a', 'q', '1', '9', '-', '?', '!', '*', '\u{301}', '\u{663}'];
+
+/// The characters of generated strings.
+const STR_CHARS: &[char] = &[
+    'a',
+    'Z',
+    '0',
+    ' ',
+    '(',
+    ')',
+    '\n',
+    '\u{201c}',
+    '\u{1f600}',
+    '\\',
+];
+
+/// A generator of values derived from raw bytes. Once the bytes are
+/// exhausted, zeros are used, so generation always terminates.
+#[derive(Debug, Clone)]
+pub struct Generator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Generator<'a> {
+    /// Creates a generator consuming the given bytes.
+    #[must_use]
+    pub fn new(data: &'a [u8]) -> Generator<'a> {
+        Generator { data }
+    }
+
+    /// Returns an arbitrary token, which is not necessarily part of a
+    /// well-formed program.
+    pub fn token(&mut self) -> Token {
+        match self.byte() % 6 {
+            0 => Token::Paren(self.choose(&['(', ')', '{', '}', '[', ']'])),
+            1 => Token::Num(self.num()),
+            2 => Token::Str(self.string()),
+            3 => Token::Ident(self.ident()),
+            4 => Token::Bool(self.choose(&[true, false])),
+            _ => Token::Quote(self.choose(&["'", "`", ",", ",@"])),
+        }
+    }
+
+    /// Returns an arbitrary expression, that can be written as source code,
+    /// i.e. a literal, an identifier, or a list of such expressions.
+    pub fn expr(&mut self) -> Expr {
+        self.expr_with_depth(MAX_DEPTH)
+    }
+
+    /// Returns well-formed source code together with the expressions it
+    /// parses to.
+    pub fn source(&mut self) -> (String, Vec<Expr>) {
+        let len = 1 + self.byte() % MAX_LEN;
+        let exprs = (0..len).map(|_| self.expr()).collect::<Vec<_>>();
+        let separator = self.choose(&[" ", "\n", "  \t"]);
+        let source = exprs
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(separator);
+        (source, exprs)
+    }
+
+    /// Returns an expression with lists nested at most `depth` levels deep.
+    fn expr_with_depth(&mut self, depth: usize) -> Expr {
+        match self.byte() % 5 {
+            0 if depth > 0 => {
+                let len = self.byte() % MAX_LEN;
+                Expr::List((0..len).map(|_| self.expr_with_depth(depth - 1)).collect())
+            }
+            // Integral floats are displayed like integers, so they would be
+            // read as integers.
+            #[allow(clippy::cast_possible_truncation)]
+            1 => match self.num() {
+                x if x.fract() == 0.0 => Expr::Int(x as i64),
+                x => Expr::Num(x),
+            },
+            2 => Expr::Str(self.string()),
+            3 => Expr::Bool(self.choose(&[true, false])),
+            _ => Expr::Ident(self.ident()),
+        }
+    }
+
+    /// Returns a non-negative number, that is displayed without exponent.
+    fn num(&mut self) -> f64 {
+        f64::from(u16::from_le_bytes([self.byte(), self.byte()])) / 4.0
+    }
+
+    /// Returns a string without quotation marks.
+    fn string(&mut self) -> String {
+        let len = self.byte() % MAX_LEN;
+        (0..len).map(|_| self.choose(STR_CHARS)).collect()
+    }
+
+    /// Returns a valid identifier, which is not a boolean.
+    fn ident(&mut self) -> String {
+        let len = self.byte() % MAX_LEN;
+        let first = self.choose(IDENT_START);
+        std::iter::once(first)
+            .chain((0..len).map(|_| self.choose(IDENT_CONTINUE)))
+            .collect()
+    }
+
+    /// Returns one of the given items.
+    fn choose<T: Copy>(&mut self, items: &[T]) -> T {
+        items[usize::from(self.byte()) % items.len()]
+    }
+
+    /// Returns the next byte, or zero if all bytes have been consumed.
+    fn byte(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((&byte, rest)) => {
+                self.data = rest;
+                byte
+            }
+            None => 0,
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for Token {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Token> {
+        generate(u, Generator::token)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Expr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Expr> {
+        generate(u, Generator::expr)
+    }
+}
+
+/// Generates a value from the bytes of the given `Unstructured`, consuming
+/// the bytes used by the generator, so values generated after it use the
+/// remaining ones.
+fn generate<'a, T, F>(u: &mut Unstructured<'a>, f: F) -> arbitrary::Result<T>
+where
+    F: FnOnce(&mut Generator<'a>) -> T,
+{
+    let data = u.peek_bytes(u.len()).unwrap_or_default();
+    let mut generator = Generator::new(data);
+    let value = f(&mut generator);
+    u.bytes(data.len() - generator.data.len())?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parse, Interpreter};
+
+    /// Returns pseudo-random inputs of different lengths.
+    fn inputs() -> impl Iterator<Item = Vec<u8>> {
+        let mut state = 0x2545_f491_u32;
+        (0..500).map(move |i| {
+            (0..i % 64)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state.to_le_bytes()[0]
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn source_round_trip() {
+        for input in inputs() {
+            let (source, exprs) = Generator::new(&input).source();
+            assert_eq!(parse(&source), Ok(exprs), "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn to_source_round_trip() {
+        for input in inputs() {
+            let expr = Generator::new(&input).expr().quoted();
+            let source = expr.to_source().expect("expression can not be written");
+            assert_eq!(
+                Interpreter::new().interpret(&source),
+                Ok(expr),
+                "source: {}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn arbitrary_uses_generator() {
+        for input in inputs() {
+            let mut u = Unstructured::new(&input);
+            let mut generator = Generator::new(&input);
+            assert_eq!(Expr::arbitrary(&mut u), Ok(generator.expr()));
+            assert_eq!(Token::arbitrary(&mut u), Ok(generator.token()));
+            assert_eq!(u.len(), generator.data.len());
+        }
+    }
+
+    #[test]
+    fn token_is_total() {
+        for input in inputs() {
+            let mut generator = Generator::new(&input);
+            for _ in 0..10 {
+                generator.token();
+            }
+        }
+    }
+}
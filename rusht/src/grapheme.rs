@@ -0,0 +1,193 @@
+//! Grapheme-aware string builtins, available with the `unicode` feature.
+//! Scalar values (`char`s) do not match what users perceive as characters:
+//! `é` may be written as `e` followed by a combining accent, and emoji like
+//! 👩‍👩‍👧 consist of several scalar values joined by zero width joiners.
+//! These builtins operate on grapheme clusters instead, so slicing never
+//! separates such characters.
+//!
+//! The segmentation into extended grapheme clusters (UAX #29) is done by
+//! `unicode-segmentation`.
+use std::convert::TryInto;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::expr::Expr;
+use crate::{BuiltinDoc, Env, Error, Result};
+
+/// Defines the grapheme builtins in the given environment.
+pub fn register(env: &mut Env) {
+    env.insert(
+        "graphemes".to_string(),
+        Expr::Func(|args| rusht_graphemes(&args)),
+    );
+    env.insert(
+        "str-len-graphemes".to_string(),
+        Expr::Func(|args| rusht_str_len_graphemes(&args)),
+    );
+    env.insert(
+        "substr-graphemes".to_string(),
+        Expr::Func(|args| rusht_substr_graphemes(&args)),
+    );
+}
+
+/// The documentation of the grapheme builtins.
+pub const DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(graphemes str)",
+        description: "Returns a list of the grapheme clusters of the string.",
+    },
+    BuiltinDoc {
+        signature: "(str-len-graphemes str)",
+        description: "Returns the number of grapheme clusters of the string.",
+    },
+    BuiltinDoc {
+        signature: "(substr-graphemes str start [end])",
+        description: "Returns the grapheme clusters from `start` up to, but excluding, `end`.",
+    },
+];
+
+/// Splits the given string into its extended grapheme clusters.
+pub fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+/// Returns the grapheme clusters of the given string as a list of strings.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one.
+/// * `UnexpectedType` - If the argument can't be coerced to a string.
+fn rusht_graphemes(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [s] => {
+            let s: String = s.clone().try_into()?;
+            Ok(Expr::List(
+                graphemes(&s)
+                    .into_iter()
+                    .map(|g| Expr::Str(g.to_string()))
+                    .collect(),
+            ))
+        }
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the number of grapheme clusters of the given string.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one.
+/// * `UnexpectedType` - If the argument can't be coerced to a string.
+fn rusht_str_len_graphemes(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [s] => {
+            let s: String = s.clone().try_into()?;
+            Ok(Expr::from(graphemes(&s).len()))
+        }
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the grapheme clusters of a string in the given range.
+///
+/// # Arguments
+///
+/// * `args[0]` - The string.
+/// * `args[1]` - The index of the first grapheme cluster.
+/// * `args[2]` - The index after the last grapheme cluster, by default the
+///     end of the string.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two or
+///     three.
+/// * `UnexpectedType` - If the arguments can't be coerced to a string and
+///     numbers.
+/// * `IndexOutOfBounds` - If the range is not within the string.
+fn rusht_substr_graphemes(args: &[Expr]) -> Result<Expr> {
+    let (s, start, end) = match args {
+        [s, start] => (s, start, None),
+        [s, start, end] => (s, start, Some(end)),
+        &_ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let s: String = s.clone().try_into()?;
+    let graphemes = graphemes(&s);
+
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    let index = |expr: &Expr| -> Result<usize> {
+        let index: f64 = expr.clone().try_into()?;
+        Ok(index as usize)
+    };
+    let start = index(start)?;
+    let end = end.map_or(Ok(graphemes.len()), index)?;
+    if end > graphemes.len() {
+        return Err(Error::IndexOutOfBounds(end));
+    }
+    if start > end {
+        return Err(Error::IndexOutOfBounds(start));
+    }
+    Ok(Expr::Str(graphemes[start..end].concat()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_graphemes {
+        ($($name:ident: $input:expr => $expected:expr),*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(graphemes($input), $expected as Vec<&str>);
+                }
+            )*
+        };
+    }
+
+    test_graphemes!(
+        graphemes_ascii: "abc" => vec!["a", "b", "c"],
+        graphemes_empty: "" => vec![],
+        graphemes_combining_mark: "e\u{301}x" => vec!["e\u{301}", "x"],
+        graphemes_crlf: "a\r\nb\n" => vec!["a", "\r\n", "b", "\n"],
+        graphemes_zwj_sequence: "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}!" => vec![
+            "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}",
+            "!"
+        ],
+        graphemes_emoji_modifier: "\u{1f44d}\u{1f3fd}\u{1f44d}" => vec!["\u{1f44d}\u{1f3fd}", "\u{1f44d}"],
+        graphemes_flags: "\u{1f1e9}\u{1f1ea}\u{1f1eb}\u{1f1f7}\u{1f1e9}" => vec![
+            "\u{1f1e9}\u{1f1ea}",
+            "\u{1f1eb}\u{1f1f7}",
+            "\u{1f1e9}"
+        ],
+        graphemes_digits: "\u{663}1_" => vec!["\u{663}", "1", "_"],
+        graphemes_keycap: "1\u{fe0f}\u{20e3}" => vec!["1\u{fe0f}\u{20e3}"],
+        graphemes_hangul: "\u{1100}\u{1161}\u{11a8}\u{ac00}" => vec![
+            "\u{1100}\u{1161}\u{11a8}",
+            "\u{ac00}"
+        ],
+        graphemes_spacing_mark: "\u{915}\u{93f}" => vec!["\u{915}\u{93f}"],
+        graphemes_prepend: "\u{600}1" => vec!["\u{600}1"]
+    );
+
+    #[test]
+    fn substr() {
+        let s = Expr::Str("cafe\u{301}s".to_string());
+        assert_eq!(
+            rusht_substr_graphemes(&[s.clone(), Expr::Num(3.0), Expr::Num(4.0)]),
+            Ok(Expr::Str("e\u{301}".to_string()))
+        );
+        assert_eq!(
+            rusht_substr_graphemes(&[s.clone(), Expr::Num(4.0)]),
+            Ok(Expr::Str("s".to_string()))
+        );
+        assert_eq!(
+            rusht_substr_graphemes(&[s.clone(), Expr::Num(0.0), Expr::Num(6.0)]),
+            Err(Error::IndexOutOfBounds(6))
+        );
+        assert_eq!(rusht_str_len_graphemes(&[s]), Ok(Expr::Int(5)));
+        assert_eq!(
+            rusht_str_len_graphemes(&[Expr::Str("\u{1100}\u{1161}\u{11a8}".to_string())]),
+            Ok(Expr::Int(1))
+        );
+    }
+}
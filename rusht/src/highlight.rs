@@ -0,0 +1,150 @@
+//! Classification of the tokens of a (possibly incomplete or invalid) input,
+//! e.g. for syntax highlighting in an editor or REPL.
+use crate::tokenize::{closing, Lexer, Span, Token};
+
+/// The class of a token, determining how it should be highlighted.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TokenClass {
+    /// A balanced parenthesis, brace, or bracket, nested at the given depth.
+    /// The outermost parentheses have a depth of zero.
+    Paren(usize),
+    /// A prefix quoting or unquoting the following expression, e.g. the
+    /// apostrophe in `'(1 2)`.
+    Quote,
+    Num,
+    Str,
+    Char,
+    Bool,
+    Nil,
+    Keyword,
+    /// An identifier, that is not known to be a function.
+    Ident,
+    /// An identifier referring to a special form or function.
+    Function,
+    /// An unbalanced or mismatched parenthesis, brace, or bracket, or an
+    /// invalid or unterminated literal.
+    Invalid,
+}
+
+/// Classifies the tokens of the given input. Unlike tokenizing, classifying
+/// never fails, but marks invalid tokens as such.
+///
+/// # Arguments
+///
+/// * `input` - The input to classify.
+/// * `is_function` - Returns whether an identifier refers to a function.
+pub fn classify<F>(input: &str, is_function: F) -> Vec<(Span, TokenClass)>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut classes = vec![];
+    // The indices of the opening parentheses, braces, and brackets, that have
+    // not been closed yet, and the matching closing ones.
+    let mut open_parens = vec![];
+
+    for (token, span) in Lexer::new(input) {
+        let class = match token {
+            Ok(Token::Paren(paren)) => match closing(paren) {
+                Some(close) => {
+                    open_parens.push((classes.len(), close));
+                    TokenClass::Paren(open_parens.len() - 1)
+                }
+                None => match open_parens.pop() {
+                    Some((_, close)) if close == paren => TokenClass::Paren(open_parens.len()),
+                    _ => TokenClass::Invalid,
+                },
+            },
+            Ok(Token::Quote(_)) => TokenClass::Quote,
+            Ok(Token::Int(_) | Token::Ratio(..) | Token::Num(_)) => TokenClass::Num,
+            Ok(Token::Str(_)) => TokenClass::Str,
+            Ok(Token::Char(_)) => TokenClass::Char,
+            Ok(Token::Bool(_)) => TokenClass::Bool,
+            Ok(Token::Nil) => TokenClass::Nil,
+            Ok(Token::Keyword(_)) => TokenClass::Keyword,
+            Ok(Token::Ident(ident)) if is_function(&ident) => TokenClass::Function,
+            Ok(Token::Ident(_)) => TokenClass::Ident,
+            Err(_) => TokenClass::Invalid,
+        };
+        classes.push((span, class));
+    }
+
+    // Parentheses, that have not been closed, are unbalanced.
+    for (i, _) in open_parens {
+        classes[i].1 = TokenClass::Invalid;
+    }
+
+    classes
+}
+
+#[cfg(test)]
+mod test {
+    use super::TokenClass::*;
+    use super::*;
+
+    #[test]
+    fn classify_expression() {
+        assert_eq!(
+            classify("(f (g 1) \"a\" true x)", |ident| ident != "x"),
+            vec![
+                (0..1, Paren(0)),
+                (1..2, Function),
+                (3..4, Paren(1)),
+                (4..5, Function),
+                (6..7, Num),
+                (7..8, Paren(1)),
+                (9..12, Str),
+                (13..17, Bool),
+                (18..19, Ident),
+                (19..20, Paren(0))
+            ]
+        );
+    }
+
+    #[test]
+    fn classify_unbalanced() {
+        assert_eq!(
+            classify("((1) \"a", |_| false),
+            vec![
+                (0..1, Invalid),
+                (1..2, Paren(1)),
+                (2..3, Num),
+                (3..4, Paren(1)),
+                (5..7, Invalid)
+            ]
+        );
+        assert_eq!(classify(")", |_| false), vec![(0..1, Invalid)]);
+        assert_eq!(
+            classify("{()}(}", |_| false),
+            vec![
+                (0..1, Paren(0)),
+                (1..2, Paren(1)),
+                (2..3, Paren(1)),
+                (3..4, Paren(0)),
+                (4..5, Paren(0)),
+                (5..6, Invalid)
+            ]
+        );
+        assert_eq!(
+            classify("[(])", |_| false),
+            vec![
+                (0..1, Paren(0)),
+                (1..2, Paren(1)),
+                (2..3, Invalid),
+                (3..4, Invalid)
+            ]
+        );
+    }
+
+    #[test]
+    fn classify_quote() {
+        assert_eq!(
+            classify("'(x)", |_| true),
+            vec![
+                (0..1, Quote),
+                (1..2, Paren(0)),
+                (2..3, Function),
+                (3..4, Paren(0))
+            ]
+        );
+    }
+}
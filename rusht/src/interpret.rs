@@ -2,184 +2,2277 @@
 //! Here we pass our built syntax tree.
 //! If the tree is built up in the correct way, we can easily parse
 //! through it and call the needed function with the passed arguments.
-use crate::expr::{Expr, Lambda};
-use crate::{Env, Error, Result};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+use crate::condition;
+use crate::debug::Frame;
+use crate::expr::{Expr, Intrinsic, Lambda};
+use crate::logging::{self, Level};
+use crate::map::Key;
+use crate::number::Number;
+use crate::pattern;
+#[cfg(feature = "signals")]
+use crate::signal::{self, Signal};
+use crate::syntax::Macro;
+use crate::vector;
+use crate::{prelude, BuiltinDoc, Context, Env, Error, Operation, RecordKind, Result};
+
+/// The names of the special forms, which are handled by the interpreter
+/// itself instead of being defined in the environment.
+pub const SPECIAL_FORMS: &[&str] = &[
+    "def",
+    "set!",
+    "vec-set!",
+    "defn",
+    "defsyntax",
+    "do",
+    "func",
+    "let",
+    "let*",
+    "letrec",
+    "loop",
+    "recur",
+    "for",
+    "dotimes",
+    "quote",
+    "quasiquote",
+    "unquote",
+    "unquote-splicing",
+    "require",
+    "log-info",
+    "log-warn",
+    "log-error",
+    "if",
+    "when",
+    "unless",
+    "case",
+    "match",
+    "with-handler",
+    "with-restart",
+    "invoke-restart",
+    #[cfg(feature = "signals")]
+    "on-sigint",
+    #[cfg(feature = "signals")]
+    "on-sigterm",
+];
+
+/// The documentation of the special forms.
+pub const SPECIAL_FORM_DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(def name [docstring] value)",
+        description: "Defines a variable. The optional docstring documents it.",
+    },
+    BuiltinDoc {
+        signature: "(set! name value)",
+        description: "Updates a defined variable in the innermost scope defining it.",
+    },
+    BuiltinDoc {
+        signature: "(vec-set! name index value)",
+        description: "Sets the element at the index of the vector stored in the variable to the value.",
+    },
+    BuiltinDoc {
+        signature: "(defn name [docstring] (args...) body...)",
+        description: "Defines a lambda taking the given arguments, same as `(def name (func (args...) body...))`.",
+    },
+    BuiltinDoc {
+        signature: "(defsyntax name (syntax-rules (literals...) (pattern template)...))",
+        description: "Defines a hygienic macro, whose uses are replaced by the template of the first rule, whose pattern matches, before being interpreted.",
+    },
+    BuiltinDoc {
+        signature: "(do exprs...)",
+        description: "Interprets the expressions in order and returns the value of the last one.",
+    },
+    BuiltinDoc {
+        signature: "(func (args... [(name default)...] [& rest]) body...)",
+        description: "Returns a lambda taking the given arguments, optional ones defaulting to the given value, and collecting additional ones into the list `rest`. The body is interpreted in order.",
+    },
+    BuiltinDoc {
+        signature: "(let ((name value)...) body...)",
+        description: "Interprets the body with the given variables defined in a local scope.",
+    },
+    BuiltinDoc {
+        signature: "(let* ((name value)...) body...)",
+        description: "Like `let`, but each value can refer to the variables defined before it.",
+    },
+    BuiltinDoc {
+        signature: "(letrec ((name value)...) body...)",
+        description: "Like `let*`, but all variables are defined before interpreting the values, e.g. for mutually recursive lambdas.",
+    },
+    BuiltinDoc {
+        signature: "(loop ((name value)...) body...)",
+        description: "Like `let*`, but `recur` within the body rebinds the variables and interprets the body again.",
+    },
+    BuiltinDoc {
+        signature: "(recur values...)",
+        description: "Continues the innermost `loop` with its variables bound to the given values. Must be in tail position of the loop's body.",
+    },
+    BuiltinDoc {
+        signature: "(for (name list) body...)",
+        description: "Interprets the body for each element of the list and returns a list of the values.",
+    },
+    BuiltinDoc {
+        signature: "(dotimes (name count) body...)",
+        description: "Interprets the body for each number from 0 up to the count, exclusive.",
+    },
+    BuiltinDoc {
+        signature: "(quote expr)",
+        description: "Returns the given expression without interpreting it, with identifiers as symbols.",
+    },
+    BuiltinDoc {
+        signature: "(quasiquote expr)",
+        description: "Like `quote`, but interprets the expressions marked by `unquote` and splices the lists of those marked by `unquote-splicing`, also within nested lists.",
+    },
+    BuiltinDoc {
+        signature: "(unquote expr)",
+        description: "Marks an expression to be interpreted within `quasiquote`.",
+    },
+    BuiltinDoc {
+        signature: "(unquote-splicing expr)",
+        description: "Marks an expression, whose list is spliced into the surrounding list, within `quasiquote`.",
+    },
+    BuiltinDoc {
+        signature: "(require name)",
+        description: "Interprets the module `name.rusht` from the search path once.",
+    },
+    BuiltinDoc {
+        signature: "(log-info message fields...)",
+        description: "Logs an informational message with the given fields, passed as a map, e.g. `{:user \"bob\"}`, or as alternating string keys and values.",
+    },
+    BuiltinDoc {
+        signature: "(log-warn message fields...)",
+        description: "Like `log-info`, but logs a warning.",
+    },
+    BuiltinDoc {
+        signature: "(log-error message fields...)",
+        description: "Like `log-info`, but logs an error.",
+    },
+    BuiltinDoc {
+        signature: "(if cond on-true [on-false])",
+        description: "Interprets and returns `on-true` if the condition is truthy and `on-false` (`nil` by default) otherwise. Only the chosen branch is interpreted.",
+    },
+    BuiltinDoc {
+        signature: "(when cond body...)",
+        description: "Interprets the body if the condition is truthy and returns `nil` otherwise.",
+    },
+    BuiltinDoc {
+        signature: "(unless cond body...)",
+        description: "Interprets the body if the condition is falsy and returns `nil` otherwise.",
+    },
+    BuiltinDoc {
+        signature: "(case expr (value body...)... [(else body...)])",
+        description: "Interprets the body of the first clause whose literal value is equal to the expression, or of the `else` clause.",
+    },
+    BuiltinDoc {
+        signature: "(match value (pattern body...)...)",
+        description: "Interprets the body of the first clause whose pattern matches the value, with the variables bound by the pattern defined.",
+    },
+    BuiltinDoc {
+        signature: "(with-handler handler body...)",
+        description: "Interprets the body, calling the lambda with the message of each error raised by a builtin before unwinding.",
+    },
+    BuiltinDoc {
+        signature: "(with-restart (name restart) body...)",
+        description: "Interprets the body, in which handlers can invoke the restart to return its value from this form.",
+    },
+    BuiltinDoc {
+        signature: "(invoke-restart name args...)",
+        description: "Calls the innermost restart of the given name and unwinds to the form establishing it.",
+    },
+    #[cfg(feature = "signals")]
+    BuiltinDoc {
+        signature: "(on-sigint handler)",
+        description:
+            "Calls the lambda on SIGINT instead of terminating, then aborts the evaluation.",
+    },
+    #[cfg(feature = "signals")]
+    BuiltinDoc {
+        signature: "(on-sigterm handler)",
+        description:
+            "Calls the lambda on SIGTERM instead of terminating, then aborts the evaluation.",
+    },
+];
+
+/// The documentation of the intrinsics, i.e. the builtins calling the
+/// functions passed to them.
+pub const INTRINSIC_DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(= x y...)",
+        description: "Returns whether the given values are numerically equal, coercing them to numbers. Values differing by at most the epsilon of the interpreter, `f64::EPSILON` by default, are equal.",
+    },
+    BuiltinDoc {
+        signature: "(map function list)",
+        description: "Returns a list of the values of the function called with each element of the list.",
+    },
+    BuiltinDoc {
+        signature: "(filter function list)",
+        description: "Returns a list of the elements of the list, for which the function returns a truthy value.",
+    },
+    BuiltinDoc {
+        signature: "(flat-map function list)",
+        description: "Returns a list of the elements of the values of the function called with each element of the list. Values other than lists are kept as they are.",
+    },
+    BuiltinDoc {
+        signature: "(reduce function [init] list)",
+        description: "Combines the elements of the list from left to right by calling the function with the accumulated value and each element, starting with the initial value or the first element.",
+    },
+    BuiltinDoc {
+        signature: "(any? predicate list)",
+        description: "Returns whether the predicate returns a truthy value for any element of the list. Stops at the first such element.",
+    },
+    BuiltinDoc {
+        signature: "(every? predicate list)",
+        description: "Returns whether the predicate returns a truthy value for every element of the list. Stops at the first other element.",
+    },
+    BuiltinDoc {
+        signature: "(count predicate list)",
+        description: "Returns the number of elements of the list, for which the predicate returns a truthy value.",
+    },
+    BuiltinDoc {
+        signature: "(group-by function list)",
+        description: "Returns a map of the values of the function called with the elements of the list to the lists of these elements.",
+    },
+    BuiltinDoc {
+        signature: "(sort list [less])",
+        description: "Returns a copy of the list of numbers or strings sorted in ascending order, or the list sorted using the function, which returns whether its first argument is ordered before the second. The sort is stable.",
+    },
+];
+
+/// Defines the intrinsics, i.e. the builtins calling the functions passed to
+/// them, in the given environment.
+pub(crate) fn register(env: &mut Env) {
+    let intrinsics: [(&str, Intrinsic); 10] = [
+        ("=", Intrinsic(rusht_numeric_equal)),
+        (
+            "map",
+            Intrinsic(|args, env, ctx| rusht_map(&args, Mapping::Map, env, ctx)),
+        ),
+        (
+            "filter",
+            Intrinsic(|args, env, ctx| rusht_map(&args, Mapping::Filter, env, ctx)),
+        ),
+        (
+            "flat-map",
+            Intrinsic(|args, env, ctx| rusht_map(&args, Mapping::FlatMap, env, ctx)),
+        ),
+        ("reduce", Intrinsic(rusht_reduce)),
+        ("sort", Intrinsic(rusht_sort)),
+        (
+            "any?",
+            Intrinsic(|args, env, ctx| rusht_quantify(&args, Quantifier::Any, env, ctx)),
+        ),
+        (
+            "every?",
+            Intrinsic(|args, env, ctx| rusht_quantify(&args, Quantifier::Every, env, ctx)),
+        ),
+        (
+            "count",
+            Intrinsic(|args, env, ctx| rusht_quantify(&args, Quantifier::Count, env, ctx)),
+        ),
+        (
+            "group-by",
+            Intrinsic(|args, env, ctx| rusht_group_by(&args, env, ctx)),
+        ),
+    ];
+    for (name, intrinsic) in intrinsics {
+        env.insert(name.to_string(), Expr::Intrinsic(intrinsic));
+    }
+}
 
 /// Interprets the given abstract syntax tree, returning  either the resulting
 /// token or an error.
 ///
-/// * `ast` - An abstract syntax tree.
-/// * `env` - The global execution environment containing variable definitions.
+/// * `ast` - An abstract syntax tree.
+/// * `env` - The global execution environment containing variable definitions.
+/// * `ctx` - The interpreter context, containing e.g. the policy.
+///
+/// # Errors
+///
+/// * `VariableNotDefined` - When the arguments contain an identifier, for
+///     which no corresponding value is found in the execution environment.
+/// * `FunctionNotDefined` - When attempting to call an undefined function.
+/// * `UnexpectedType` - If an unexpected type was encountered.
+/// * `OperationNotPermitted` - If a privileged function is called, but the
+///     policy does not allow it.
+/// * `QuotaExceeded` - If a function returns a value exceeding the quotas,
+///     or the evaluation takes too many steps or nests too deeply.
+pub fn interpret(ast: Expr, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    ctx.depth += 1;
+    let out = match ctx.quotas.check_recursion_depth(ctx.depth) {
+        Ok(()) if ctx.debugging.is_none() && ctx.observing.is_none() => {
+            interpret_expr(ast, env, ctx)
+        }
+        Ok(()) => interpret_reported(ast, env, ctx),
+        Err(error) => Err(error),
+    };
+    ctx.depth -= 1;
+    out
+}
+
+/// Interprets the given abstract syntax tree, remembering the innermost
+/// expression that failed. See `interpret` for details. Never inlined, as
+/// every nested evaluation adds a frame of `interpret` to the stack.
+#[inline(never)]
+fn interpret_reported(ast: Expr, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    // Remember the innermost expression that failed, so the debugger and
+    // the observer can be notified about it once the error reaches the top
+    // level. Errors handled before, e.g. using a restart, are forgotten.
+    let expr = ast.clone();
+    let out = interpret_expr(ast, env, ctx);
+    match &out {
+        Ok(_) => {
+            if let Some(observing) = &mut ctx.observing {
+                observing.recover();
+            }
+            if let Some(debugging) = &mut ctx.debugging {
+                debugging.recover();
+            }
+        }
+        Err(error) => {
+            if let Some(observing) = &mut ctx.observing {
+                observing.fail(expr.clone(), error, ctx.span.clone());
+            }
+            if let Some(debugging) = &mut ctx.debugging {
+                debugging.fail(expr, error);
+            }
+        }
+    }
+    out
+}
+
+/// Interprets the given abstract syntax tree without notifying the debugger
+/// about errors. See `interpret` for details.
+#[allow(clippy::too_many_lines)]
+fn interpret_expr(ast: Expr, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    // Recorded signals are handled before calls, which are safe points, as
+    // no builtin is running.
+    #[cfg(feature = "signals")]
+    if matches!(ast, Expr::List(_)) && !ctx.signal_handlers.is_empty() {
+        if let Some(signal) = signal::take_pending(&mut ctx.signals_handled) {
+            return handle_signal(signal, env, ctx);
+        }
+    }
+
+    if matches!(ast, Expr::List(_)) {
+        ctx.steps += 1;
+        ctx.quotas.check_steps(ctx.steps)?;
+    }
+
+    match ast {
+        expr @ (Expr::Nil
+        | Expr::Bool(_)
+        | Expr::Ident(_)
+        | Expr::Keyword(_)
+        | Expr::Symbol(_)
+        | Expr::Str(_)
+        | Expr::Char(_)
+        | Expr::Int(_)
+        | Expr::Ratio(..)
+        | Expr::Num(_)) => Ok(expr),
+        Expr::Map(map) => interpret_map(map, env, ctx),
+        Expr::Vector(vector) => {
+            let out = Expr::Vector(interpret_args(&vector, env, ctx)?);
+            ctx.quotas.check(&out)?;
+            Ok(out)
+        }
+        Expr::List(exprs) => match exprs.first() {
+            Some(Expr::Ident(ident)) => match ident.as_str() {
+                "def" => rusht_def(&exprs[1..], env, ctx),
+                "set!" => rusht_set(&exprs[1..], env, ctx),
+                "vec-set!" => rusht_vec_set(&exprs[1..], env, ctx),
+                "defn" => rusht_defn(&exprs[1..], env, ctx),
+                "defsyntax" => rusht_defsyntax(&exprs[1..], ctx),
+                "do" => interpret_body(&exprs[1..], env, ctx),
+                "func" => rusht_lambda(&exprs[1..], env, ctx),
+                "let" => rusht_let(&exprs[1..], Binding::Parallel, env, ctx),
+                "let*" => rusht_let(&exprs[1..], Binding::Sequential, env, ctx),
+                "letrec" => rusht_let(&exprs[1..], Binding::Recursive, env, ctx),
+                "loop" => rusht_loop(&exprs[1..], env, ctx),
+                "recur" => rusht_recur(&exprs[1..], env, ctx),
+                "for" => rusht_for(&exprs[1..], env, ctx),
+                "dotimes" => rusht_dotimes(&exprs[1..], env, ctx),
+                "quote" => match &exprs[1..] {
+                    [expr] => Ok(expr.clone().quoted()),
+                    _ => Err(Error::InvalidNumberOfArguments),
+                },
+                "quasiquote" => rusht_quasiquote(&exprs[1..], env, ctx),
+                "unquote" | "unquote-splicing" => Err(Error::Unquote(ident.clone())),
+                "require" => rusht_require(&exprs[1..], env, ctx),
+                "log-info" => rusht_log(Level::Info, &exprs[1..], env, ctx),
+                "log-warn" => rusht_log(Level::Warn, &exprs[1..], env, ctx),
+                "log-error" => rusht_log(Level::Error, &exprs[1..], env, ctx),
+                "if" => rusht_if(&exprs[1..], env, ctx),
+                "when" => rusht_when(&exprs[1..], true, env, ctx),
+                "unless" => rusht_when(&exprs[1..], false, env, ctx),
+                "case" => rusht_case(&exprs[1..], env, ctx),
+                "match" => rusht_match(&exprs[1..], env, ctx),
+                "with-handler" => rusht_with_handler(&exprs[1..], env, ctx),
+                "with-restart" => rusht_with_restart(&exprs[1..], env, ctx),
+                "invoke-restart" => rusht_invoke_restart(&exprs[1..], env, ctx),
+                #[cfg(feature = "signals")]
+                "on-sigint" => rusht_on_signal(Signal::Interrupt, &exprs[1..], env, ctx),
+                #[cfg(feature = "signals")]
+                "on-sigterm" => rusht_on_signal(Signal::Terminate, &exprs[1..], env, ctx),
+                _ if ctx.macros.contains_key(ident) => rusht_expand(ident, &exprs[1..], env, ctx),
+                _ => match env.get(ident).cloned() {
+                    Some(callable @ (Expr::Func(_) | Expr::Intrinsic(_) | Expr::Lambda(_))) => {
+                        let args = interpret_args(&exprs[1..], env, ctx)?;
+                        call(ident, callable, args, env, ctx)
+                    }
+                    Some(_) => Err(Error::UnexpectedType),
+                    None => Err(Error::FunctionNotDefined(ident.to_string())),
+                },
+            },
+            Some(head @ Expr::List(_)) => {
+                let (name, callable) = interpret_to_callable(head, env, ctx)?;
+                let args = interpret_args(&exprs[1..], env, ctx)?;
+                call(&name, callable, args, env, ctx)
+            }
+            Some(expr) => Err(Error::NotAnIdentifier(expr.to_string())),
+            None => Err(Error::EmptyListExpression),
+        },
+        _ => Err(Error::UnexpectedType),
+    }
+}
+
+/// Interprets the values of a map literal. Like the other forms, that are
+/// not on the path of a call, it is never inlined, so the frames of
+/// `interpret_expr` stay small.
+#[inline(never)]
+fn interpret_map(map: BTreeMap<Key, Expr>, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let mut out = BTreeMap::new();
+    for (key, value) in map {
+        out.insert(key, interpret_args(&[value], env, ctx)?.remove(0));
+    }
+    let out = Expr::Map(out);
+    ctx.quotas.check(&out)?;
+    Ok(out)
+}
+
+/// Expands a call of the macro of the given name and interprets the
+/// expansion.
+#[inline(never)]
+fn rusht_expand(name: &str, args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let expansion = ctx.macros[name].expand(name, args)?;
+    ctx.quotas.check(&expansion)?;
+    interpret(expansion, env, ctx)
+}
+
+/// Calls a builtin (prelude) function, intrinsic, or lambda with the given,
+/// already interpreted arguments and notifies the observer about the call.
+/// Errors returned by prelude functions are signaled to the established
+/// handlers, those returned by intrinsics and lambdas already have been by
+/// the builtins they call.
+///
+/// # Arguments
+///
+/// * `name` - The name under which the function is called.
+/// * `callable` - The builtin function, intrinsic, or lambda.
+/// * `args` - The interpreted arguments.
+/// * `env` - The current execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the callable is not a function.
+///
+/// Additionally, all errors returned by the function are returned.
+fn call(
+    name: &str,
+    callable: Expr,
+    args: Vec<Expr>,
+    env: &mut Env,
+    ctx: &mut Context,
+) -> Result<Expr> {
+    if let Some(observing) = &mut ctx.observing {
+        observing.enter_call(name, &args, ctx.span.clone());
+    }
+    let out = match callable {
+        Expr::Func(func) => {
+            let out = call_builtin(name, func, args, ctx);
+            signal_error(out, env, ctx)
+        }
+        Expr::Intrinsic(Intrinsic(func)) => func(args, env, ctx),
+        Expr::Lambda(lambda) => interpret_lambda(name, *lambda, &args, env, ctx),
+        _ => Err(Error::UnexpectedType),
+    };
+    if let Some(observing) = &mut ctx.observing {
+        observing.leave_call(name, &out, ctx.span.clone());
+    }
+    out
+}
+
+/// Calls a builtin (prelude) function with the given, already interpreted
+/// arguments. Privileged builtins are authorized and recorded before they are
+/// called, and the returned value is checked against the quotas. Builtins
+/// called under another name, e.g. after being passed as an argument, are
+/// authorized and recorded under their prelude name.
+///
+/// # Arguments
+///
+/// * `name` - The name under which the builtin is called.
+/// * `func` - The builtin function.
+/// * `args` - The interpreted arguments.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `OperationNotPermitted` - If the builtin is privileged and the policy
+///     does not allow it.
+/// * `QuotaExceeded` - If the returned value exceeds the quotas.
+///
+/// Additionally, all errors returned by the builtin itself are returned.
+#[inline(never)]
+pub fn call_builtin(
+    name: &str,
+    func: fn(Vec<Expr>) -> Result<Expr>,
+    args: Vec<Expr>,
+    ctx: &mut Context,
+) -> Result<Expr> {
+    let privileged = match prelude::operation(name) {
+        Some(operation) => Some((name, operation)),
+        None => prelude::operation_of(func),
+    };
+    if let Some((name, operation)) = privileged {
+        ctx.authorize(operation, &args)?;
+        ctx.record(RecordKind::Call, name, &args);
+    }
+    let out = func(args)?;
+    ctx.quotas.check(&out)?;
+    Ok(out)
+}
+
+/// Interprets a lambda expression and returns the resulting expression. A
+/// lambda creates a copy of the execution environment it is called in, so
+/// names are bound late and a lambda defined using `def` can call itself
+/// recursively by its name. If currying is enabled and fewer arguments than expected are passed, a
+/// lambda awaiting the remaining arguments is returned instead.
+///
+/// # Arguments
+///
+/// * `name` - The name under which the lambda is called.
+/// * `lambda` - A lambda expression to be evaluated.
+/// * `given_args` - The interpreted arguments passed at the invocation.
+/// * `env` - The current execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If too many arguments without a rest
+///     argument, or too few without currying, are passed.
+/// * `RecurNotInTailPosition` - If the body calls `recur` outside of a loop
+///     of its own.
+///
+/// Additionally, all errors returned by the default values of optional
+/// arguments or the body are returned.
+pub fn interpret_lambda(
+    name: &str,
+    mut lambda: Lambda,
+    given_args: &[Expr],
+    env: &mut Env,
+    ctx: &mut Context,
+) -> Result<Expr> {
+    if ctx.currying && given_args.len() < lambda.args.len() {
+        let remaining = lambda.args.split_off(given_args.len());
+        lambda
+            .bound
+            .extend(lambda.args.into_iter().zip(given_args.iter().cloned()));
+        lambda.args = remaining;
+        return Ok(Expr::Lambda(Box::new(lambda)));
+    }
+    let max_args = lambda.args.len() + lambda.optional.len();
+    if given_args.len() < lambda.args.len()
+        || (lambda.rest.is_none() && given_args.len() > max_args)
+    {
+        return Err(Error::InvalidNumberOfArguments);
+    }
+
+    // create a local copy of the execution environment and add the
+    // captured variables and the passed arguments, preceded by those bound
+    // by partial applications and followed by the rest argument, as
+    // variables to this new local environment. Omitted optional arguments
+    // take their default value, which is interpreted after defining the
+    // arguments before it.
+    let names = lambda
+        .captured
+        .iter()
+        .chain(&lambda.bound)
+        .map(|(name, _)| name)
+        .chain(&lambda.args)
+        .chain(lambda.optional.iter().map(|(name, _)| name))
+        .chain(&lambda.rest)
+        .cloned()
+        .collect::<Vec<_>>();
+    let caller_locals = std::mem::take(&mut ctx.locals);
+    let out = in_local_scope(&names, env, ctx, |local_env, ctx| {
+        // The body sees the variables of the scope the lambda was created
+        // in rather than those of the caller, so local variables of the
+        // caller shadowing global ones are reset. Other local variables of
+        // the caller, that were not yet defined when the lambda was created,
+        // e.g. the lambda itself within `letrec` or a local `defn`, remain
+        // visible, so such lambdas can call themselves.
+        if let Some(globals) = &ctx.globals {
+            for name in &caller_locals {
+                if ctx.locals.contains(name) {
+                    continue;
+                }
+                match globals.get(name) {
+                    Some(value) => local_env.insert(name.clone(), value.clone()),
+                    None => ctx.locals.push(name.clone()),
+                }
+            }
+        }
+        for (key, val) in lambda.captured {
+            local_env.insert(key, val);
+        }
+        let mut given_args = given_args.iter().cloned();
+        let mut locals = lambda.bound;
+        locals.extend(lambda.args.into_iter().zip(given_args.by_ref()));
+        for (key, val) in &locals {
+            local_env.insert(key.clone(), val.clone());
+        }
+        for (key, default) in lambda.optional {
+            let val = match given_args.next() {
+                Some(val) => val,
+                None => interpret_args(&[default], local_env, ctx)?.remove(0),
+            };
+            local_env.insert(key.clone(), val.clone());
+            locals.push((key, val));
+        }
+        if let Some(rest) = lambda.rest {
+            let val = Expr::List(given_args.collect());
+            local_env.insert(rest.clone(), val.clone());
+            locals.push((rest, val));
+        }
+
+        if let Some(debugging) = &mut ctx.debugging {
+            debugging.frames.push(Frame {
+                name: name.to_string(),
+                locals,
+            });
+            debugging.debugger.on_call(&debugging.frames);
+        }
+        let mut out = interpret_body(&lambda.body, local_env, ctx);
+        // `recur` does not unwind past the lambda to a loop it is called
+        // from, as its call is not in tail position of the loop.
+        if out == Err(Error::Recur) {
+            ctx.recur = None;
+            out = Err(Error::RecurNotInTailPosition);
+        }
+        if let Some(debugging) = &mut ctx.debugging {
+            debugging.frames.pop();
+        }
+        out
+    });
+    ctx.locals = caller_locals;
+    out
+}
+
+/// Recursively interprets the arguments of the given slice of expressions.
+///
+/// # Arguments
+///
+/// * `args` - A slice of expressions to be interpreted.
+/// * `env` - The global execution environment containing variable definitions.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `VariableNotDefined` - When the arguments contain an identifier, for
+///     which no corresponding value is found in the execution environment.
+fn interpret_args(exprs: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Vec<Expr>> {
+    exprs
+        .iter()
+        .cloned()
+        .map(|t| interpret(t, env, ctx))
+        .collect::<Result<Vec<_>>>()
+        .and_then(|args| resolve_variables(&args, env))
+}
+
+/// Replaces identifiers in the given slice of tokens with their corresponding
+/// values from the environment.
+///
+/// # Arguments
+///
+/// * `args` - A slice of tokens, in which variables should be resolved.
+/// * `env` - The global execution environment containing variable definitions.
+///
+/// # Errors
+///
+/// * `VariableNotDefined` - When the arguments contain an identifier, for
+///     which no corresponding value is found in the execution environment.
+fn resolve_variables(args: &[Expr], env: &mut Env) -> Result<Vec<Expr>> {
+    args.iter()
+        .map(|token| match token {
+            Expr::Ident(var_name) => match env.get(var_name) {
+                Some(x) => Ok(x.clone()),
+                None => Err(Error::VariableNotDefined(var_name.clone())),
+            },
+            x => Ok(x.clone()),
+        })
+        .collect::<Result<Vec<_>>>()
+}
+
+/// Defines or updates a variable in the environment.
+///
+/// # Arguments
+///
+/// * `args` - The arguments passed at the `def` function invocation. Should
+///     have a length of exactly two elements, the variable name and value,
+///     or three elements, if a docstring is passed between them. Docstrings
+///     are only used to generate documentation and ignored otherwise.
+/// * `env` - The global execution environment containing the existing function
+///     and variable definitions.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 2 or 3.
+/// * `UnexpectedType` - If the first argument could not be coerced to a
+///     string or the docstring is not a string.
+fn rusht_def(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    match args {
+        [Expr::Ident(key), val] | [Expr::Ident(key), Expr::Str(_), val] => {
+            let val = interpret_args(std::slice::from_ref(val), env, ctx)?.remove(0);
+            ctx.record(RecordKind::Define, key, std::slice::from_ref(&val));
+            if let Some(observing) = &ctx.observing {
+                observing.define(key, &val, ctx.span.clone());
+            }
+            env.insert(key.clone(), val.clone());
+            if ctx.globals.is_some() {
+                ctx.locals.push(key.clone());
+            }
+            Ok(val)
+        }
+        [_, _] | [_, _, _] => Err(Error::UnexpectedType),
+        _ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Defines a macro using `syntax-rules`. Macros are global and expanded
+/// wherever they are used afterwards, see the `syntax` module for details.
+///
+/// # Arguments
+///
+/// * `args[0]` - The name of the macro.
+/// * `args[1]` - The `syntax-rules` form, a list of `syntax-rules`, the
+///     literals, and the rules.
+/// * `ctx` - The interpreter context, in which the macro is defined.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 2.
+/// * `UnexpectedType` - If the name is not an identifier or the second
+///     argument is not a valid `syntax-rules` form.
+fn rusht_defsyntax(args: &[Expr], ctx: &mut Context) -> Result<Expr> {
+    let (name, rules) = match args {
+        [Expr::Ident(name), Expr::List(rules)] => (name, rules),
+        [_, _] => return Err(Error::UnexpectedType),
+        _ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let rules = match rules.split_first() {
+        Some((Expr::Ident(form), rules)) if form == "syntax-rules" => rules,
+        _ => return Err(Error::UnexpectedType),
+    };
+    ctx.macros.insert(name.clone(), Macro::new(rules)?);
+    Ok(Expr::Nil)
+}
+
+/// Updates an existing variable. Unlike `def`, which always defines the
+/// variable in the current scope, the variable is updated in the innermost
+/// scope defining it, once the current scope is left.
+///
+/// # Arguments
+///
+/// * `args[0]` - The name of the variable.
+/// * `args[1]` - The new value.
+/// * `env` - The current execution environment.
+/// * `ctx` - The interpreter context, in which the assignment is recorded.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 2.
+/// * `UnexpectedType` - If the name is not an identifier.
+/// * `VariableNotDefined` - If the variable is not defined.
+fn rusht_set(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let (name, value) = match args {
+        [Expr::Ident(name), value] => (name, value),
+        [_, _] => return Err(Error::UnexpectedType),
+        _ => return Err(Error::InvalidNumberOfArguments),
+    };
+    if env.get(name).is_none() {
+        return Err(Error::VariableNotDefined(name.clone()));
+    }
+    let value = interpret_args(std::slice::from_ref(value), env, ctx)?.remove(0);
+    assign(name, value.clone(), env, ctx);
+    Ok(value)
+}
+
+/// Sets an element of the vector stored in an existing variable. As
+/// vectors are copied when passed around, the variable is updated like
+/// using `set!`.
+///
+/// # Arguments
+///
+/// * `args[0]` - The name of the variable.
+/// * `args[1]` - The index of the element.
+/// * `args[2]` - The new value of the element.
+/// * `env` - The current execution environment.
+/// * `ctx` - The interpreter context, in which the assignment is recorded.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 3.
+/// * `UnexpectedType` - If the name is not an identifier, the variable
+///     does not store a vector, or the index can't be coerced to a number.
+/// * `VariableNotDefined` - If the variable is not defined.
+/// * `IndexOutOfBounds` - If the index is out of bounds of the vector.
+/// * `QuotaExceeded` - If the updated vector exceeds the quotas.
+fn rusht_vec_set(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let name = match args {
+        [Expr::Ident(name), _, _] => name,
+        [_, _, _] => return Err(Error::UnexpectedType),
+        _ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let [index, value]: [Expr; 2] = interpret_args(&args[1..], env, ctx)?
+        .try_into()
+        .map_err(|_| Error::InvalidNumberOfArguments)?;
+    let mut vector = match env.get(name) {
+        Some(Expr::Vector(vector)) => vector.clone(),
+        Some(_) => return Err(Error::UnexpectedType),
+        None => return Err(Error::VariableNotDefined(name.clone())),
+    };
+    let position = vector::index(&index, vector.len())?;
+    vector[position] = value.clone();
+    let vector = Expr::Vector(vector);
+    ctx.quotas.check(&vector)?;
+    assign(name, vector, env, ctx);
+    Ok(value)
+}
+
+/// Updates the given variable in the environment and records the
+/// assignment, so it is propagated to the innermost scope defining the
+/// variable.
+fn assign(name: &str, value: Expr, env: &mut Env, ctx: &mut Context) {
+    ctx.record(RecordKind::Define, name, std::slice::from_ref(&value));
+    if let Some(observing) = &ctx.observing {
+        observing.define(name, &value, ctx.span.clone());
+    }
+    env.insert(name.to_string(), value);
+    ctx.assignments.push(name.to_string());
+}
+
+/// Defines a lambda by expanding to `def` and `func`, so
+/// `(defn name (args...) body...)` is the same as
+/// `(def name (func (args...) body...))`.
+///
+/// # Arguments
+///
+/// * `args` - The arguments passed at the `defn` invocation: the name, an
+///     optional docstring, the arguments of the lambda, and its body.
+/// * `env` - The execution environment, in which the lambda is defined.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no arguments of the lambda or no body
+///     are passed.
+///
+/// Additionally, all errors returned by `def` and `func` are returned.
+fn rusht_defn(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let (def, lambda) = match args {
+        [name, docstring @ Expr::Str(_), Expr::List(_), _, ..] => {
+            (vec![name.clone(), docstring.clone()], &args[2..])
+        }
+        [name, _, _, ..] => (vec![name.clone()], &args[1..]),
+        _ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let func = [vec![Expr::Ident("func".to_string())], lambda.to_vec()].concat();
+    rusht_def(&[def, vec![Expr::List(func)]].concat(), env, ctx)
+}
+
+/// Constructs a lambda expression from the given arguments.
+///
+/// # Arguments
+///
+/// * `exprs[0]` - A list of identifiers representing the arguments of the
+///     lambda expression. They can be followed by optional arguments, each a
+///     list of an identifier and its default value, and finally `&` and the
+///     name of the rest argument, which collects any additional values into
+///     a list.
+/// * `exprs[1..]` - The body of the lambda expression, one or more
+///     expressions interpreted in order.
+/// * `env` - The execution environment, whose local variables are
+///     captured by the lambda.
+/// * `ctx` - The interpreter context containing the names of the local
+///     variables.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the first argument is not a list of parameters
+///     in the above order.
+/// * `InvalidNumberOfArguments` - If no body is passed.
+fn rusht_lambda(exprs: &[Expr], env: &Env, ctx: &Context) -> Result<Expr> {
+    let (params, body) = match exprs {
+        [Expr::List(params), body @ ..] if !body.is_empty() => (params.as_slice(), body),
+        [_, _, ..] => return Err(Error::UnexpectedType),
+        &_ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let (params, rest) = match params {
+        [params @ .., Expr::Ident(ampersand), Expr::Ident(rest)] if ampersand == "&" => {
+            (params, Some(rest.clone()))
+        }
+        params => (params, None),
+    };
+
+    let mut args = vec![];
+    let mut optional = vec![];
+    for param in params {
+        match param {
+            Expr::Ident(name) if name != "&" && optional.is_empty() => args.push(name.clone()),
+            Expr::List(param) => match param.as_slice() {
+                [Expr::Ident(name), default] if name != "&" => {
+                    optional.push((name.clone(), default.clone()));
+                }
+                _ => return Err(Error::UnexpectedType),
+            },
+            _ => return Err(Error::UnexpectedType),
+        }
+    }
+
+    let mut captured: Vec<(String, Expr)> = vec![];
+    for name in &ctx.locals {
+        if captured.iter().any(|(captured, _)| captured == name) {
+            continue;
+        }
+        if let Some(value) = env.get(name) {
+            captured.push((name.clone(), value.clone()));
+        }
+    }
+
+    Ok(Expr::Lambda(Box::new(Lambda {
+        args,
+        optional,
+        rest,
+        body: body.to_vec(),
+        captured,
+        bound: vec![],
+    })))
+}
+
+/// Splits the arguments of a form like `let` into the names and values of
+/// the bindings and the body.
+///
+/// # Arguments
+///
+/// * `args[0]` - A list of bindings, each a list of the name of a variable
+///     and its value.
+/// * `args[1..]` - The body.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no bindings are passed.
+/// * `UnexpectedType` - If the bindings are not a list of lists, each of an
+///     identifier and a value.
+fn split_bindings(args: &[Expr]) -> Result<(Vec<String>, Vec<Expr>, &[Expr])> {
+    let Some((Expr::List(bindings), body)) = args.split_first() else {
+        return Err(match args {
+            [] => Error::InvalidNumberOfArguments,
+            _ => Error::UnexpectedType,
+        });
+    };
+    let mut names = Vec::with_capacity(bindings.len());
+    let mut values = Vec::with_capacity(bindings.len());
+    for binding in bindings {
+        let Expr::List(binding) = binding else {
+            return Err(Error::UnexpectedType);
+        };
+        let [Expr::Ident(name), value] = binding.as_slice() else {
+            return Err(Error::UnexpectedType);
+        };
+        names.push(name.clone());
+        values.push(value.clone());
+    }
+    Ok((names, values, body))
+}
+
+/// The ways the values of the bindings of a local scope are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Binding {
+    /// All values are interpreted before any variable is defined, as done by
+    /// `let`.
+    Parallel,
+
+    /// Each value is interpreted in the local scope after defining the
+    /// variables before it, as done by `let*`.
+    Sequential,
+
+    /// All variables are defined as `nil` before the values are
+    /// interpreted in order, as done by `letrec`.
+    Recursive,
+}
+
+/// Returns the given expression without interpreting it and with identifiers
+/// as symbols, like `quote`, except for the expressions marked by `unquote`,
+/// which are interpreted, and those marked by `unquote-splicing`, which are
+/// interpreted to lists, whose elements are inserted in place of them.
+/// Nested lists are processed the same way.
+///
+/// # Arguments
+///
+/// * `args` - The expression to be quoted.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If not exactly one expression is given or
+///     an expression is not marked by exactly one `unquote` or
+///     `unquote-splicing`.
+/// * `UnexpectedType` - If an expression marked by `unquote-splicing` is not
+///     interpreted to a list or is not within a list.
+///
+/// Additionally, all errors returned by the unquoted expressions are returned.
+fn rusht_quasiquote(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let [expr] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let out = quasiquote(expr, env, ctx)?;
+    ctx.quotas.check(&out)?;
+    Ok(out)
+}
+
+/// Quotes a single expression within `quasiquote`, see `rusht_quasiquote`.
+fn quasiquote(expr: &Expr, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let Expr::List(list) = expr else {
+        return Ok(expr.clone().quoted());
+    };
+    if let [Expr::Ident(ident), exprs @ ..] = list.as_slice() {
+        if ident == "unquote" || ident == "unquote-splicing" {
+            let [expr] = exprs else {
+                return Err(Error::InvalidNumberOfArguments);
+            };
+            if ident == "unquote-splicing" {
+                return Err(Error::UnexpectedType);
+            }
+            return Ok(interpret_args(std::slice::from_ref(expr), env, ctx)?.remove(0));
+        }
+    }
+    let mut out = Vec::with_capacity(list.len());
+    for item in list {
+        match item {
+            Expr::List(spliced) if is_splice(spliced) => {
+                let Expr::List(values) = interpret_args(&spliced[1..], env, ctx)?.remove(0) else {
+                    return Err(Error::UnexpectedType);
+                };
+                out.extend(values);
+            }
+            _ => out.push(quasiquote(item, env, ctx)?),
+        }
+    }
+    Ok(Expr::List(out))
+}
+
+/// Returns whether the list is an `unquote-splicing` form with exactly one
+/// expression.
+fn is_splice(list: &[Expr]) -> bool {
+    matches!(list, [Expr::Ident(ident), _] if ident == "unquote-splicing")
+}
+
+/// Interprets a body with local variables defined in a copy of the
+/// environment, which is discarded afterwards, so neither the variables nor
+/// definitions made by the body are visible outside of it.
+///
+/// # Arguments
+///
+/// * `args[0]` - A list of bindings, each a list of the name of a variable
+///     and its value.
+/// * `args[1..]` - The body.
+/// * `binding` - How the values are interpreted.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no bindings are passed.
+/// * `UnexpectedType` - If the bindings are not a list of lists, each of an
+///     identifier and a value.
+///
+/// Additionally, all errors returned by the values or the body are returned.
+fn rusht_let(args: &[Expr], binding: Binding, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let (names, values, body) = split_bindings(args)?;
+    let values = match binding {
+        Binding::Parallel => interpret_args(&values, env, ctx)?,
+        Binding::Sequential | Binding::Recursive => values,
+    };
+    in_local_scope(&names, env, ctx, |local_env, ctx| {
+        if binding == Binding::Recursive {
+            for name in &names {
+                local_env.insert(name.clone(), Expr::Nil);
+            }
+        }
+        for (name, value) in names.iter().zip(values) {
+            let value = match binding {
+                Binding::Parallel => value,
+                Binding::Sequential => interpret_args(&[value], local_env, ctx)?.remove(0),
+                Binding::Recursive => {
+                    let mut value = interpret_args(&[value], local_env, ctx)?.remove(0);
+                    // The variables are still `nil` while the lambdas are
+                    // created, so they are looked up where they are called,
+                    // see `interpret_lambda`.
+                    if let Expr::Lambda(lambda) = &mut value {
+                        lambda.captured.retain(|(name, _)| !names.contains(name));
+                    }
+                    value
+                }
+            };
+            local_env.insert(name.clone(), value);
+        }
+        interpret_body(body, local_env, ctx)
+    })
+}
+
+/// Interprets a body repeatedly with local variables, like `let*`. Whenever
+/// `recur` is called in tail position of the body, the stack is unwound to the loop, the
+/// variables are bound to the values passed to `recur`, and the body is
+/// interpreted again. Thus, iterating does not grow the stack. Otherwise,
+/// the value of the body is returned.
+///
+/// # Arguments
+///
+/// * `args[0]` - A list of bindings, each a list of the name of a variable
+///     and its initial value.
+/// * `args[1..]` - The body.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no bindings are passed or `recur` is
+///     called with a different number of values than there are variables.
+/// * `UnexpectedType` - If the bindings are not a list of lists, each of an
+///     identifier and a value.
+/// * `RecurNotInTailPosition` - If `recur` is used in the body, but not in
+///     tail position, see `check_tail_position`.
+///
+/// Additionally, all errors returned by the values or the body are returned.
+fn rusht_loop(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let (names, values, body) = split_bindings(args)?;
+    check_body_tail_position(body, true)?;
+    in_local_scope(&names, env, ctx, |local_env, ctx| {
+        for (name, value) in names.iter().zip(values) {
+            let value = interpret_args(&[value], local_env, ctx)?.remove(0);
+            local_env.insert(name.clone(), value);
+        }
+
+        loop {
+            match interpret_body(body, local_env, ctx) {
+                Err(Error::Recur) => {
+                    let values = ctx.recur.take().unwrap_or_default();
+                    if values.len() != names.len() {
+                        return Err(Error::InvalidNumberOfArguments);
+                    }
+                    for (name, value) in names.iter().zip(values) {
+                        local_env.insert(name.clone(), value);
+                    }
+                }
+                out => return out,
+            }
+        }
+    })
+}
+
+/// Unwinds the stack to the innermost `loop`, which continues with the
+/// given, interpreted values.
+///
+/// # Arguments
+///
+/// * `args` - The new values of the variables of the loop.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context, in which the values are stored.
+///
+/// # Errors
+///
+/// * `Recur` - Always, to unwind the stack, or because `recur` is called
+///     outside of a loop.
+///
+/// Additionally, all errors returned by the values are returned.
+fn rusht_recur(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    ctx.recur = Some(interpret_args(args, env, ctx)?);
+    Err(Error::Recur)
+}
+
+/// Checks that `recur` is only used in tail position within the given
+/// expression, i.e. as the last expression of a body or as a branch of `if`,
+/// `when`, `unless`, `case`, or `match` in tail position, so no evaluation
+/// is pending when it unwinds the stack to its loop. Quoted expressions,
+/// macro definitions, and the bodies of nested loops, which are checked when
+/// they are interpreted, are skipped.
+///
+/// # Arguments
+///
+/// * `expr` - The expression to check.
+/// * `tail` - Whether the expression is in tail position.
+///
+/// # Errors
+///
+/// * `RecurNotInTailPosition` - If `recur` is used, but not in tail position.
+fn check_tail_position(expr: &Expr, tail: bool) -> Result<()> {
+    let exprs = match expr {
+        Expr::List(exprs) => exprs,
+        Expr::Vector(exprs) => return check_body_tail_position(exprs, false),
+        Expr::Map(map) => {
+            return map
+                .values()
+                .try_for_each(|value| check_tail_position(value, false))
+        }
+        _ => return Ok(()),
+    };
+    let Some((Expr::Ident(ident), args)) = exprs.split_first() else {
+        return check_body_tail_position(exprs, false);
+    };
+    match ident.as_str() {
+        "recur" if !tail => Err(Error::RecurNotInTailPosition),
+        "quote" | "quasiquote" | "defsyntax" => Ok(()),
+        "loop" => match args.first() {
+            Some(Expr::List(bindings)) => bindings.iter().try_for_each(|binding| match binding {
+                Expr::List(binding) => {
+                    check_body_tail_position(binding.get(1..).unwrap_or_default(), false)
+                }
+                _ => Ok(()),
+            }),
+            _ => Ok(()),
+        },
+        "if" => match args.split_first() {
+            Some((condition, branches)) => {
+                check_tail_position(condition, false)?;
+                branches
+                    .iter()
+                    .try_for_each(|branch| check_tail_position(branch, tail))
+            }
+            None => Ok(()),
+        },
+        "do" => check_body_tail_position(args, tail),
+        "when" | "unless" | "let" | "let*" | "letrec" => match args.split_first() {
+            Some((head, body)) => {
+                check_tail_position(head, false)?;
+                check_body_tail_position(body, tail)
+            }
+            None => Ok(()),
+        },
+        "case" | "match" => match args.split_first() {
+            Some((scrutinee, clauses)) => {
+                check_tail_position(scrutinee, false)?;
+                clauses.iter().try_for_each(|clause| match clause {
+                    Expr::List(clause) => {
+                        check_body_tail_position(clause.get(1..).unwrap_or_default(), tail)
+                    }
+                    _ => Ok(()),
+                })
+            }
+            None => Ok(()),
+        },
+        _ => check_body_tail_position(args, false),
+    }
+}
+
+/// Checks the given body like `check_tail_position`, where only the last
+/// expression is in tail position, if the body is.
+fn check_body_tail_position(body: &[Expr], tail: bool) -> Result<()> {
+    let last = body.len().saturating_sub(1);
+    body.iter()
+        .enumerate()
+        .try_for_each(|(i, expr)| check_tail_position(expr, tail && i == last))
+}
+
+/// Splits the arguments of a form like `for` into the name of the variable,
+/// the interpreted value it iterates over, and the body.
+///
+/// # Arguments
+///
+/// * `args[0]` - A list of the name of the variable and the value.
+/// * `args[1..]` - The body.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no variable is passed.
+/// * `UnexpectedType` - If the variable is not a list of an identifier and a
+///     value.
+///
+/// Additionally, all errors returned by the value are returned.
+fn split_iteration<'a>(
+    args: &'a [Expr],
+    env: &mut Env,
+    ctx: &mut Context,
+) -> Result<(&'a String, Expr, &'a [Expr])> {
+    let Some((head, body)) = args.split_first() else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let Expr::List(head) = head else {
+        return Err(Error::UnexpectedType);
+    };
+    let [Expr::Ident(name), value] = head.as_slice() else {
+        return Err(Error::UnexpectedType);
+    };
+    let value = interpret_args(std::slice::from_ref(value), env, ctx)?.remove(0);
+    Ok((name, value, body))
+}
+
+/// Interprets a body for each element of a list, which is defined as a
+/// variable in a local scope, and returns a list of the values of the body.
+///
+/// # Arguments
+///
+/// * `args[0]` - A list of the name of the variable and the list.
+/// * `args[1..]` - The body.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no variable is passed.
+/// * `UnexpectedType` - If the variable is not a list of an identifier and a
+///     list.
+/// * `QuotaExceeded` - If the returned list exceeds the quotas.
+///
+/// Additionally, all errors returned by the list or the body are returned.
+fn rusht_for(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let (name, list, body) = split_iteration(args, env, ctx)?;
+    let Expr::List(list) = list else {
+        return Err(Error::UnexpectedType);
+    };
+
+    let out = in_local_scope(std::slice::from_ref(name), env, ctx, |local_env, ctx| {
+        let mut values = Vec::with_capacity(list.len());
+        for item in list {
+            local_env.insert(name.clone(), item);
+            values.push(interpret_body(body, local_env, ctx)?);
+        }
+        Ok(Expr::List(values))
+    })?;
+    ctx.quotas.check(&out)?;
+    Ok(out)
+}
+
+/// Interprets a body for each number from 0 up to a count, exclusive, which
+/// is defined as a variable in a local scope. Returns `nil`.
+///
+/// # Arguments
+///
+/// * `args[0]` - A list of the name of the variable and the count.
+/// * `args[1..]` - The body.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no variable is passed.
+/// * `UnexpectedType` - If the variable is not a list of an identifier and a
+///     count, that can be coerced to a number.
+///
+/// Additionally, all errors returned by the count or the body are returned.
+fn rusht_dotimes(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let (name, count, body) = split_iteration(args, env, ctx)?;
+    let count: f64 = count.try_into()?;
+
+    in_local_scope(std::slice::from_ref(name), env, ctx, |local_env, ctx| {
+        #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+        for i in 0..count.max(0.0) as usize {
+            local_env.insert(name.clone(), Expr::from(i));
+            interpret_body(body, local_env, ctx)?;
+        }
+        Ok(Expr::Nil)
+    })
+}
+
+/// Interprets the module of the given name in the global environment and
+/// returns the value of its last expression. Each module is interpreted only
+/// once; subsequent requires return the cached value. Requiring a module,
+/// that is currently being interpreted, returns `nil` instead of
+/// recursing. When required within a local scope, the definitions made by
+/// the module are visible in the scope as well, unless shadowed by local
+/// variables.
+///
+/// # Arguments
+///
+/// * `args[0]` - The name of the module, e.g. `"utils"` for `utils.rusht`.
+/// * `env` - The current execution environment.
+/// * `ctx` - The interpreter context, holding the global environment while
+///     interpreting within a local scope.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 1.
+/// * `UnexpectedType` - If the name is not a string.
+/// * `OperationNotPermitted` - If the policy does not allow requiring the
+///     module.
+/// * `InvalidModuleName` - If the name is a path rather than a name.
+/// * `ModuleNotFound` - If the module is not found in the search path.
+/// * `FileNotReadable` - If the module file could not be read.
+///
+/// Additionally, all errors returned while interpreting the module are
+/// returned.
+fn rusht_require(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let args = interpret_args(args, env, ctx)?;
+    let name = match args.as_slice() {
+        [Expr::Str(name)] => name,
+        [_] => return Err(Error::UnexpectedType),
+        _ => return Err(Error::InvalidNumberOfArguments),
+    };
+
+    ctx.authorize(Operation::Require, &args)?;
+    let path = ctx.modules.resolve(name)?;
+    if let Some(value) = ctx.modules.get(&path) {
+        return Ok(value.clone());
+    }
+    ctx.record(RecordKind::Call, "require", &args);
+
+    let source = std::fs::read_to_string(&path)
+        .map_err(|err| Error::FileNotReadable(format!("`{}`: {}", path.display(), err)))?;
+    ctx.modules.insert(path.clone(), Expr::Nil);
+    let out = match ctx.globals.take() {
+        Some(mut globals) => {
+            let before = globals.clone();
+            let locals = std::mem::take(&mut ctx.locals);
+            let out = interpret_module(&source, &mut globals, ctx);
+            ctx.locals = locals;
+            // Local variables shadowing the global ones are kept.
+            for (name, value) in globals.iter() {
+                if before.get(name) != Some(value) && env.get(name) == before.get(name) {
+                    env.insert(name.clone(), value.clone());
+                }
+            }
+            ctx.globals = Some(globals);
+            out
+        }
+        None => interpret_module(&source, env, ctx),
+    };
+    match &out {
+        Ok(value) => ctx.modules.insert(path, value.clone()),
+        Err(_) => ctx.modules.remove(&path),
+    }
+    out
+}
+
+/// Interprets all expressions of the given module source in the given
+/// environment and returns the value of the last one.
+fn interpret_module(source: &str, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    crate::parse(source).and_then(|exprs| {
+        exprs
+            .into_iter()
+            .try_fold(Expr::Nil, |_, expr| interpret_value(expr, env, ctx))
+    })
+}
+
+/// Returns whether the given values are numerically equal within the epsilon
+/// of the interpreter, see `prelude::approx_equal`. Unlike the other
+/// comparisons, `=` is an intrinsic, as the epsilon is part of the context.
+///
+/// # Arguments
+///
+/// * `args` - The values to compare.
+/// * `_env` - The execution environment.
+/// * `ctx` - The interpreter context containing the epsilon.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If one or more of the values can't be coerced to a
+///     number.
+fn rusht_numeric_equal(args: Vec<Expr>, _env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    prelude::approx_equal(args, ctx.epsilon)
+}
+
+/// Passes a message and key-value fields to the logger and returns the
+/// message.
+///
+/// # Arguments
+///
+/// * `level` - The level of the message.
+/// * `args[0]` - The message.
+/// * `args[1..]` - Either a map, whose keys are keywords or strings, or
+///     alternating keys, which are strings, and values.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context containing the logger.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no message or a key without a value is
+///     passed.
+/// * `UnexpectedType` - If the message or a key is not a string, or a key of
+///     the map is neither a keyword nor a string.
+fn rusht_log(level: Level, args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let args = interpret_args(args, env, ctx)?;
+    let record = logging::record(level, args, ctx.span.clone())?;
+    ctx.logger.log(&record);
+    Ok(Expr::Str(record.message))
+}
+
+/// Calls the given function with a local scope, i.e. a copy of the
+/// execution environment, in which the variables of the given names are
+/// local. Afterwards, the assignments made using `set!` within the scope to
+/// other variables, which are defined in the enclosing environment, are
+/// applied to it, so they are visible once the scope is left. While
+/// interpreting within the outermost scope, the global environment is moved
+/// into the context, so modules can be required into it, and lambdas
+/// capture the local variables only.
+///
+/// # Arguments
+///
+/// * `locals` - The names of the variables local to the scope.
+/// * `env` - The enclosing execution environment.
+/// * `ctx` - The interpreter context.
+/// * `f` - The function interpreting e.g. a body in the local scope.
+#[inline(never)]
+fn in_local_scope<F>(locals: &[String], env: &mut Env, ctx: &mut Context, f: F) -> Result<Expr>
+where
+    F: FnOnce(&mut Env, &mut Context) -> Result<Expr>,
+{
+    let mark = ctx.assignments.len();
+    let locals_mark = ctx.locals.len();
+    ctx.locals.extend_from_slice(locals);
+    let mut local_env = env.clone();
+    let outermost = ctx.globals.is_none();
+    if outermost {
+        ctx.globals = Some(std::mem::take(env));
+    }
+    let out = f(&mut local_env, ctx);
+    if outermost {
+        *env = ctx.globals.take().unwrap_or_default();
+    }
+    ctx.locals.truncate(locals_mark);
+    for name in ctx.assignments.split_off(mark) {
+        if locals.contains(&name) || env.get(&name).is_none() {
+            continue;
+        }
+        if let Some(value) = local_env.get(&name) {
+            env.insert(name.clone(), value.clone());
+            ctx.assignments.push(name);
+        }
+    }
+    out
+}
+
+/// Interprets the given expressions in order and returns the value of the
+/// last one, or `nil`, if there are none. Identifiers bound in the
+/// environment are resolved to their values, so `(let ((x 1)) x)` returns
+/// `1` rather than the identifier `x`.
+fn interpret_body(exprs: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    exprs
+        .iter()
+        .cloned()
+        .try_fold(Expr::Nil, |_, expr| interpret_value(expr, env, ctx))
+}
+
+/// Interprets the given expression like `interpret`, but resolves the
+/// result to the value of a variable, if it is an identifier.
+///
+/// # Errors
+///
+/// * `VariableNotDefined` - If the result is an identifier, that is not
+///     bound in the environment.
+///
+/// Additionally, all errors returned by `interpret` are returned.
+pub fn interpret_value(expr: Expr, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    match interpret(expr, env, ctx)? {
+        Expr::Ident(name) => env
+            .get(&name)
+            .cloned()
+            .ok_or(Error::VariableNotDefined(name)),
+        out => Ok(out),
+    }
+}
+
+/// Interprets the condition and then only the branch it selects, so
+/// recursive definitions terminate and side effects of the other branch,
+/// such as `exit`, are not performed.
+///
+/// # Arguments
+///
+/// * `args[0]` - The condition.
+/// * `args[1]` - The branch interpreted, if the condition is truthy.
+/// * `args[2]` - The optional branch interpreted, if the condition is falsy.
+///     Without it, `nil` is returned.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If not two or three arguments are passed.
+/// * `UnexpectedType` - If the condition can not be coerced to a boolean.
+///
+/// Additionally, all errors returned by the condition or the chosen branch
+/// are returned.
+fn rusht_if(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let (cond, on_true, on_false) = match args {
+        [cond, on_true] => (cond, on_true, &Expr::Nil),
+        [cond, on_true, on_false] => (cond, on_true, on_false),
+        _ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let cond: bool = interpret_args(std::slice::from_ref(cond), env, ctx)?
+        .remove(0)
+        .try_into()?;
+    let branch = if cond { on_true } else { on_false };
+    interpret_value(branch.clone(), env, ctx)
+}
+
+/// Interprets the given body, if the condition is truthy or falsy, as
+/// expected. Otherwise, the body is skipped and `nil` is returned.
+///
+/// # Arguments
+///
+/// * `args[0]` - The condition.
+/// * `args[1..]` - The body.
+/// * `expected` - Whether the condition must be truthy, as done by `when`,
+///     or falsy, as done by `unless`.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no condition is passed.
+/// * `UnexpectedType` - If the condition can not be coerced to a boolean.
+///
+/// Additionally, all errors returned by the condition or the body are
+/// returned.
+fn rusht_when(args: &[Expr], expected: bool, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let Some((cond, body)) = args.split_first() else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let cond: bool = interpret_args(std::slice::from_ref(cond), env, ctx)?
+        .remove(0)
+        .try_into()?;
+    if cond == expected {
+        interpret_body(body, env, ctx)
+    } else {
+        Ok(Expr::Nil)
+    }
+}
+
+/// Interprets the body of the first clause, whose value is equal to the
+/// interpreted scrutinee, as done by `equal?`. The values of the clauses are
+/// quoted literals, so identifiers match symbols of the same name. A clause
+/// with the value `else` matches any scrutinee. If no clause matches, `nil` is returned.
+///
+/// # Arguments
+///
+/// * `args[0]` - The scrutinee.
+/// * `args[1..]` - The clauses, each a list of a value and a body.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no scrutinee is passed.
+/// * `UnexpectedType` - If a clause is not a non-empty list.
+///
+/// Additionally, all errors returned by the scrutinee or the matching body
+/// are returned.
+fn rusht_case(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let Some((scrutinee, clauses)) = args.split_first() else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let scrutinee = interpret_args(std::slice::from_ref(scrutinee), env, ctx)?.remove(0);
+    for clause in clauses {
+        let Expr::List(clause) = clause else {
+            return Err(Error::UnexpectedType);
+        };
+        let Some((value, body)) = clause.split_first() else {
+            return Err(Error::UnexpectedType);
+        };
+        if matches!(value, Expr::Ident(ident) if ident == "else")
+            || value.clone().quoted().is_equal(&scrutinee)
+        {
+            return interpret_body(body, env, ctx);
+        }
+    }
+    Ok(Expr::Nil)
+}
+
+/// Interprets the body of the first clause, whose pattern matches the
+/// interpreted scrutinee, in a local scope defining the variables bound by
+/// the pattern. The patterns are described in the `pattern` module. If no
+/// clause matches, `nil` is returned.
+///
+/// # Arguments
+///
+/// * `args[0]` - The scrutinee.
+/// * `args[1..]` - The clauses, each a list of a pattern and a body.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
 ///
 /// # Errors
 ///
-/// * `AttemptedToUseFunctionAsVariable` - When the arguments contain an
-///     identifier that would resolve to a function definition.
-/// * `VariableNotDefined` - When the arguments contain an identifier, for
-///     which no corresponding value is found in the execution environment.
-/// * `FunctionNotDefined` - When attempting to call an undefined function.
-/// * `UnexpectedType` - If an unexpected type was encountered.
-pub fn interpret(ast: Expr, env: &mut Env) -> Result<Expr> {
-    match ast {
-        expr @ (Expr::Bool(_) | Expr::Ident(_) | Expr::Str(_) | Expr::Num(_)) => Ok(expr),
-        Expr::List(exprs) => match exprs.first() {
-            Some(Expr::Ident(ident)) => match ident.as_str() {
-                "def" => rusht_def(&exprs[1..], env),
-                "func" => rusht_lambda(&exprs[1..]),
-                "quote" => Ok(Expr::List(exprs[1..].to_vec())),
-                _ => match env.get(ident).cloned() {
-                    Some(Expr::Func(func)) => interpret_args(&exprs[1..], env).and_then(func),
-                    Some(Expr::Lambda(lambda)) => interpret_lambda(lambda, &exprs[1..], env),
-                    Some(_) => Err(Error::UnexpectedType),
-                    None => Err(Error::FunctionNotDefined(ident.to_string())),
-                },
-            },
-            Some(expr) => Err(Error::NotAnIdentifier(expr.to_string())),
-            None => Err(Error::EmptyListExpression),
-        },
+/// * `InvalidNumberOfArguments` - If no scrutinee is passed.
+/// * `UnexpectedType` - If a clause is not a non-empty list or its pattern
+///     is invalid.
+///
+/// Additionally, all errors returned by the scrutinee or the matching body
+/// are returned.
+fn rusht_match(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let Some((scrutinee, clauses)) = args.split_first() else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let scrutinee = interpret_args(std::slice::from_ref(scrutinee), env, ctx)?.remove(0);
+    for clause in clauses {
+        let Expr::List(clause) = clause else {
+            return Err(Error::UnexpectedType);
+        };
+        let Some((pattern, body)) = clause.split_first() else {
+            return Err(Error::UnexpectedType);
+        };
+        if let Some(bindings) = pattern::match_pattern(pattern, &scrutinee)? {
+            let names = pattern::pattern_names(pattern);
+            return in_local_scope(&names, env, ctx, |local_env, ctx| {
+                for (name, value) in bindings {
+                    local_env.insert(name, value);
+                }
+                interpret_body(body, local_env, ctx)
+            });
+        }
+    }
+    Ok(Expr::Nil)
+}
+
+/// Interprets the given expression to a builtin or lambda and returns it
+/// together with the name it is called under, which is the identifier or
+/// `lambda` for other expressions.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the expression is not a function.
+fn interpret_to_callable(expr: &Expr, env: &mut Env, ctx: &mut Context) -> Result<(String, Expr)> {
+    let name = match expr {
+        Expr::Ident(name) => name.clone(),
+        _ => "lambda".to_string(),
+    };
+    match interpret_args(std::slice::from_ref(expr), env, ctx)?.remove(0) {
+        callable @ (Expr::Func(_) | Expr::Intrinsic(_) | Expr::Lambda(_)) => Ok((name, callable)),
         _ => Err(Error::UnexpectedType),
     }
 }
 
-/// Interprets a lambda expression and returns the resulting expression. A
-/// lambda creates a copy of its surrounding execution environment.
+/// Returns the name a function passed as a value is called under, which is
+/// the name of its type, as it has no name of its own.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the value is not a function.
+fn callable_name(callable: &Expr) -> Result<&'static str> {
+    match callable {
+        Expr::Func(_) | Expr::Intrinsic(_) | Expr::Lambda(_) => Ok(callable.type_name()),
+        _ => Err(Error::UnexpectedType),
+    }
+}
+
+/// The ways the results of a function called with each element of a list
+/// are collected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mapping {
+    /// The results are collected, as done by `map`.
+    Map,
+
+    /// The elements, for which a truthy value is returned, are collected, as
+    /// done by `filter`.
+    Filter,
+
+    /// The elements of the results are collected, as done by `flat-map`.
+    FlatMap,
+}
+
+/// Calls a function with each element of a list and collects the results in
+/// a list.
 ///
 /// # Arguments
 ///
-/// * `lambda` - A lambda expression to be evaluated.
-/// * `given_args` - The arguments passed at the invocation.
-/// * `env` - The current execution environment.
-fn interpret_lambda(lambda: Lambda, given_args: &[Expr], env: &Env) -> Result<Expr> {
-    if lambda.args.len() != given_args.len() {
+/// * `args[0]` - The function.
+/// * `args[1]` - The list.
+/// * `mapping` - How the results are collected.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 2.
+/// * `UnexpectedType` - If the function is not a function, the list is not
+///     a list, or, when filtering, a result can not be coerced to a boolean.
+///
+/// Additionally, all errors returned by the function are returned.
+fn rusht_map(args: &[Expr], mapping: Mapping, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let [callable, list] = args else {
         return Err(Error::InvalidNumberOfArguments);
-    }
+    };
+    let name = callable_name(callable)?;
+    let Expr::List(list) = list else {
+        return Err(Error::UnexpectedType);
+    };
 
-    // create a local copy of the execution environment and add the passed
-    // arguments as variables to this new local environment.
-    let mut local_env = env.clone();
-    for (key, val) in lambda.args.iter().zip(&mut given_args.iter()) {
-        local_env.insert(key.clone(), val.clone());
+    let mut out = Vec::with_capacity(list.len());
+    for item in list {
+        let value = call(name, callable.clone(), vec![item.clone()], env, ctx)?;
+        match mapping {
+            Mapping::Map => out.push(value),
+            Mapping::Filter => {
+                if value.try_into()? {
+                    out.push(item.clone());
+                }
+            }
+            Mapping::FlatMap => prelude::flatten_into(vec![value], false, &mut out),
+        }
     }
+    let out = Expr::List(out);
+    ctx.quotas.check(&out)?;
+    Ok(out)
+}
+
+/// The ways the results of a predicate called with each element of a list
+/// are combined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quantifier {
+    /// Whether the predicate holds for any element, as done by `any?`.
+    Any,
+
+    /// Whether the predicate holds for every element, as done by `every?`.
+    Every,
 
-    interpret(*lambda.body, &mut local_env)
+    /// The number of elements, for which the predicate holds, as done by
+    /// `count`.
+    Count,
 }
 
-/// Recursively interprets the arguments of the given slice of expressions.
+/// Calls a predicate with each element of a list and combines the results.
+/// Stops calling the predicate as soon as the result is known.
 ///
 /// # Arguments
 ///
-/// * `args` - A slice of expressions to be interpreted.
-/// * `env` - The global execution environment containing variable definitions.
+/// * `args[0]` - The predicate.
+/// * `args[1]` - The list.
+/// * `quantifier` - How the results are combined.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
 ///
 /// # Errors
 ///
-/// * `AttemptedToUseFunctionAsVariable` - When the arguments contain an
-///     identifier that would resolve to a function definition.
-/// * `VariableNotDefined` - When the arguments contain an identifier, for
-///     which no corresponding value is found in the execution environment.
-fn interpret_args(exprs: &[Expr], env: &mut Env) -> Result<Vec<Expr>> {
-    exprs
-        .iter()
-        .cloned()
-        .map(|t| interpret(t, env))
-        .collect::<Result<Vec<_>>>()
-        .and_then(|args| resolve_variables(&args, env))
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 2.
+/// * `UnexpectedType` - If the predicate is not a function, the list is not
+///     a list, or a result can not be coerced to a boolean.
+///
+/// Additionally, all errors returned by the predicate are returned.
+fn rusht_quantify(
+    args: &[Expr],
+    quantifier: Quantifier,
+    env: &mut Env,
+    ctx: &mut Context,
+) -> Result<Expr> {
+    let [predicate, list] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let name = callable_name(predicate)?;
+    let Expr::List(list) = list else {
+        return Err(Error::UnexpectedType);
+    };
+
+    let mut count = 0usize;
+    for item in list {
+        let holds: bool =
+            call(name, predicate.clone(), vec![item.clone()], env, ctx)?.try_into()?;
+        match quantifier {
+            Quantifier::Any if holds => return Ok(Expr::Bool(true)),
+            Quantifier::Every if !holds => return Ok(Expr::Bool(false)),
+            Quantifier::Count if holds => count += 1,
+            _ => {}
+        }
+    }
+    Ok(match quantifier {
+        Quantifier::Any => Expr::Bool(false),
+        Quantifier::Every => Expr::Bool(true),
+        Quantifier::Count => Expr::from(count),
+    })
 }
 
-/// Replaces identifiers in the given slice of tokens with their corresponding
-/// values from the environment.
+/// Groups the elements of a list by the value of a function called with
+/// each element. Returns a map of the values to the lists of elements.
 ///
 /// # Arguments
 ///
-/// * `args` - A slice of tokens, in which variables should be resolved.
-/// * `env` - The global execution environment containing variable definitions.
+/// * `args[0]` - The function, see `interpret_to_callable`.
+/// * `args[1]` - The list.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
 ///
 /// # Errors
 ///
-/// * `AttemptedToUseFunctionAsVariable` - When the arguments contain an
-///     identifier that would resolve to a function definition.
-/// * `VariableNotDefined` - When the arguments contain an identifier, for
-///     which no corresponding value is found in the execution environment.
-fn resolve_variables(args: &[Expr], env: &mut Env) -> Result<Vec<Expr>> {
-    args.iter()
-        .map(|token| match token {
-            Expr::Ident(var_name) => match env.get(var_name) {
-                Some(Expr::Func(_)) => {
-                    Err(Error::AttemptedToUseFunctionAsVariable(var_name.clone()))
-                }
-                Some(x) => Ok(x.clone()),
-                None => Err(Error::VariableNotDefined(var_name.clone())),
-            },
-            x => Ok(x.clone()),
-        })
-        .collect::<Result<Vec<_>>>()
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 2.
+/// * `UnexpectedType` - If the function is not a function, the list is not
+///     a list, or a value is not a valid key.
+///
+/// Additionally, all errors returned by the function are returned.
+fn rusht_group_by(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let [callable, list] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let name = callable_name(callable)?;
+    let Expr::List(list) = list else {
+        return Err(Error::UnexpectedType);
+    };
+
+    let mut groups: BTreeMap<Key, Vec<Expr>> = BTreeMap::new();
+    for item in list {
+        let key = call(name, callable.clone(), vec![item.clone()], env, ctx)?;
+        groups.entry(Key::new(key)?).or_default().push(item.clone());
+    }
+    let out = Expr::Map(
+        groups
+            .into_iter()
+            .map(|(key, items)| (key, Expr::List(items)))
+            .collect(),
+    );
+    ctx.quotas.check(&out)?;
+    Ok(out)
 }
 
-/// Defines or updates a variable in the environment.
+/// Combines the elements of a list from left to right by calling a function
+/// with the accumulated value and each element.
 ///
 /// # Arguments
 ///
-/// * `args` - The arguments passed at the `def` function invocation. Should
-///     have a length of exactly two elements, the variable name and value.
-/// * `env` - The global execution environment containing the existing function
-///     and variable definitions.
+/// * `args[0]` - The function, see `interpret_to_callable`.
+/// * `args[1]` - The optional initial value. If it is omitted, the first
+///     element of the list is used.
+/// * `args[2]` - The list.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
 ///
 /// # Errors
 ///
-/// * `InvalidNumberOfArguments` - If the length of `args` is not 2.
-/// * `UnexpectedType` - If the first argument could not be coerced to a
-///     string.
-fn rusht_def(args: &[Expr], env: &mut Env) -> Result<Expr> {
-    match args {
-        [Expr::Ident(key), val] => {
-            let val = interpret(val.clone(), env)?;
-            env.insert(key.clone(), val.clone());
-            Ok(val)
-        }
-        [_, _] => Err(Error::UnexpectedType),
-        _ => Err(Error::InvalidNumberOfArguments),
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 2 or 3.
+/// * `IndexOutOfBounds` - If no initial value is passed and the list is
+///     empty.
+/// * `UnexpectedType` - If the function is not a function or the list is
+///     not a list.
+///
+/// Additionally, all errors returned by the function are returned.
+fn rusht_reduce(mut args: Vec<Expr>, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(Error::InvalidNumberOfArguments);
+    }
+    let callable = args.remove(0);
+    let name = callable_name(&callable)?;
+    let Some(Expr::List(list)) = args.pop() else {
+        return Err(Error::UnexpectedType);
+    };
+
+    let mut list = list.into_iter();
+    let Some(mut acc) = args.pop().or_else(|| list.next()) else {
+        return Err(Error::IndexOutOfBounds(0));
+    };
+    for item in list {
+        acc = call(name, callable.clone(), vec![acc, item], env, ctx)?;
     }
+    Ok(acc)
 }
 
-/// Constructs a lambda expression from the given arguments.
+/// Sorts a list in ascending order. Without a function, the list must
+/// consist of either only numbers or only strings.
 ///
 /// # Arguments
 ///
-/// * `exprs[0]` - A list of identifiers representing the arguments of the
-///     lambda expression.
-/// * `exprs[1]` - The body of the lambda expression
-///
-/// # Errors
-///
-/// * `UnexpectedType` - If the first argument is not of type `Expr::List`.
-/// * `InvalidNumberOfArguments` - If the number of arguments is not equal to
-///     two.
-fn rusht_lambda(exprs: &[Expr]) -> Result<Expr> {
-    match exprs {
-        [Expr::List(args), body] if args.iter().all(|x| matches!(x, Expr::Ident(_))) => {
-            let args = args
-                .iter()
-                .cloned()
-                .map(|x| match x {
-                    Expr::Ident(x) => Ok(x),
-                    _ => unreachable!("previously checked using the match guard"),
-                })
-                .collect::<Result<Vec<_>>>()?;
+/// * `args[0]` - The list.
+/// * `args[1]` - The optional function returning whether its first argument
+///     is ordered before the second.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 1 or 2.
+/// * `UnexpectedType` - If the list is not a list, the function is not a
+///     function or a result can not be coerced to a boolean, or, without a
+///     function, the elements are neither all numbers nor all strings.
+///
+/// Additionally, all errors returned by the function are returned.
+fn rusht_sort(mut args: Vec<Expr>, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let less = match args.len() {
+        1 => None,
+        2 => args.pop(),
+        _ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let Some(Expr::List(list)) = args.pop() else {
+        return Err(Error::UnexpectedType);
+    };
+
+    let Some(less) = less else {
+        let nums: Option<Vec<Number>> = list.iter().map(Number::of).collect();
+        if let Some(mut nums) = nums {
+            nums.sort_by(Number::total_cmp);
+            return Ok(Expr::List(nums.into_iter().map(Expr::from).collect()));
+        }
+        let strs: Option<Vec<String>> = list
+            .into_iter()
+            .map(|item| match item {
+                Expr::Str(str) => Some(str),
+                _ => None,
+            })
+            .collect();
+        let mut strs = strs.ok_or(Error::UnexpectedType)?;
+        strs.sort();
+        return Ok(Expr::List(strs.into_iter().map(Expr::Str).collect()));
+    };
+
+    let name = callable_name(&less)?;
+    let mut less = |a: &Expr, b: &Expr| -> Result<bool> {
+        call(name, less.clone(), vec![a.clone(), b.clone()], env, ctx)?.try_into()
+    };
+    merge_sort(list, &mut less).map(Expr::List)
+}
+
+/// Sorts the given list stably using a fallible comparison, which returns
+/// whether its first argument is ordered before the second. Unlike the
+/// sorting functions of the standard library, an inconsistent comparison
+/// does not panic, but results in an unspecified order.
+///
+/// # Errors
+///
+/// All errors returned by the comparison are returned.
+fn merge_sort(
+    mut list: Vec<Expr>,
+    less: &mut impl FnMut(&Expr, &Expr) -> Result<bool>,
+) -> Result<Vec<Expr>> {
+    if list.len() <= 1 {
+        return Ok(list);
+    }
+    let right = merge_sort(list.split_off(list.len() / 2), less)?;
+    let left = merge_sort(list, less)?;
 
-            Ok(Expr::Lambda(Lambda {
-                args,
-                body: Box::from(body.clone()),
-            }))
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if less(b, a)? {
+            out.extend(right.next());
+        } else {
+            out.extend(left.next());
         }
-        [_, _] => Err(Error::UnexpectedType),
-        &_ => Err(Error::InvalidNumberOfArguments),
     }
+    out.extend(left);
+    out.extend(right);
+    Ok(out)
+}
+
+/// Interprets the given expression to a lambda, which is either given
+/// directly or by its name.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the expression is not a lambda.
+fn interpret_to_lambda(expr: &Expr, env: &mut Env, ctx: &mut Context) -> Result<Lambda> {
+    let lambda = match interpret_args(std::slice::from_ref(expr), env, ctx)?.pop() {
+        Some(Expr::Ident(name)) => env.get(&name).cloned(),
+        lambda => lambda,
+    };
+    match lambda {
+        Some(Expr::Lambda(lambda)) => Ok(*lambda),
+        _ => Err(Error::UnexpectedType),
+    }
+}
+
+/// Signals the error of the given result, if any, to the established
+/// handlers, innermost first. While a handler runs, it and the handlers
+/// established after it are disabled, so errors raised by the handler are
+/// only signaled to the outer handlers. Returns the result unchanged, if all
+/// handlers decline by returning.
+///
+/// # Errors
+///
+/// The error of the given result, or any error returned by a handler, e.g.
+/// `RestartInvoked`, if it invokes a restart.
+#[inline(never)]
+fn signal_error(out: Result<Expr>, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let Err(error) = &out else {
+        return out;
+    };
+    if !condition::is_signaled(error) {
+        return out;
+    }
+    let message = Expr::Str(error.to_string());
+    for depth in (0..ctx.conditions.handlers.len()).rev() {
+        let mut disabled = ctx.conditions.handlers.split_off(depth);
+        let handler = disabled[0].clone();
+        let result = interpret_lambda("handler", handler, std::slice::from_ref(&message), env, ctx);
+        ctx.conditions.handlers.append(&mut disabled);
+        result?;
+    }
+    out
+}
+
+/// Establishes a handler while interpreting the given body. The handler is
+/// called with the message of every error signaled within the body.
+///
+/// # Arguments
+///
+/// * `args[0]` - The handler, a lambda or the name of one, taking a single
+///     argument.
+/// * `args[1..]` - The body.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context, in which the handler is established.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no handler is passed.
+/// * `UnexpectedType` - If the handler is not a lambda.
+///
+/// Additionally, all errors returned by the body are returned.
+fn rusht_with_handler(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let Some((handler, body)) = args.split_first() else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let handler = interpret_to_lambda(handler, env, ctx)?;
+
+    let depth = ctx.conditions.handlers.len();
+    ctx.conditions.handlers.push(handler);
+    let out = interpret_body(body, env, ctx);
+    ctx.conditions.handlers.truncate(depth);
+    out
+}
+
+/// Establishes a named restart while interpreting the given body. If the
+/// restart is invoked within the body, the value of the restart is returned
+/// instead of the value of the body.
+///
+/// # Arguments
+///
+/// * `args[0]` - A list of the name of the restart and the restart, a lambda
+///     or the name of one.
+/// * `args[1..]` - The body.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context, in which the restart is established.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no restart is passed.
+/// * `UnexpectedType` - If the restart is not a list of an identifier and a
+///     lambda.
+///
+/// Additionally, all errors returned by the body are returned.
+fn rusht_with_restart(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let Some((restart, body)) = args.split_first() else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let Expr::List(restart) = restart else {
+        return Err(Error::UnexpectedType);
+    };
+    let [Expr::Ident(name), restart] = restart.as_slice() else {
+        return Err(Error::UnexpectedType);
+    };
+    let restart = interpret_to_lambda(restart, env, ctx)?;
+
+    let index = ctx.conditions.restarts.len();
+    ctx.conditions.restarts.push((name.clone(), restart));
+    let out = interpret_body(body, env, ctx);
+    ctx.conditions.restarts.truncate(index);
+    match out {
+        Err(Error::RestartInvoked(name)) => ctx
+            .conditions
+            .take_unwinding(index)
+            .ok_or(Error::RestartInvoked(name)),
+        out => out,
+    }
+}
+
+/// Calls the innermost restart of the given name with the given, interpreted
+/// arguments and unwinds the stack to the `with-restart` form establishing
+/// it.
+///
+/// # Arguments
+///
+/// * `args[0]` - The name of the restart.
+/// * `args[1..]` - The arguments passed to the restart.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context containing the restarts.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no name is passed.
+/// * `UnexpectedType` - If the name is not an identifier.
+/// * `RestartNotFound` - If no restart of the given name is established.
+/// * `RestartInvoked` - If the restart returned, to unwind the stack.
+///
+/// Additionally, all errors returned by the restart are returned.
+fn rusht_invoke_restart(args: &[Expr], env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    let Some((name, args)) = args.split_first() else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let Expr::Ident(name) = name else {
+        return Err(Error::UnexpectedType);
+    };
+    let Some(index) = ctx.conditions.find_restart(name) else {
+        return Err(Error::RestartNotFound(name.clone()));
+    };
+
+    let args = interpret_args(args, env, ctx)?;
+    let restart = ctx.conditions.restarts[index].1.clone();
+    let value = interpret_lambda(name, restart, &args, env, ctx)?;
+    ctx.conditions.unwinding = Some((index, value));
+    Err(Error::RestartInvoked(name.clone()))
+}
+
+/// Registers the handler of the given signal, replacing the previous one.
+/// The handler is called without arguments.
+///
+/// # Arguments
+///
+/// * `signal` - The signal to handle.
+/// * `args[0]` - The handler, a lambda or the name of one.
+/// * `env` - The execution environment.
+/// * `ctx` - The interpreter context, in which the handler is registered.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the length of `args` is not 1.
+/// * `UnexpectedType` - If the handler is not a lambda.
+#[cfg(feature = "signals")]
+fn rusht_on_signal(
+    signal: Signal,
+    args: &[Expr],
+    env: &mut Env,
+    ctx: &mut Context,
+) -> Result<Expr> {
+    let [handler] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let handler = interpret_to_lambda(handler, env, ctx)?;
+    if ctx
+        .signal_handlers
+        .insert(signal, handler.clone())
+        .is_none()
+    {
+        // Signals received before the handler was registered are not
+        // handled.
+        ctx.signals_handled.insert(signal, signal.received());
+        signal::install(signal);
+    }
+    Ok(Expr::Lambda(Box::new(handler)))
+}
+
+/// Calls the handler registered for the given signal, if any, and aborts the
+/// evaluation.
+///
+/// # Errors
+///
+/// * `Interrupted` - If the handler returned.
+///
+/// Additionally, all errors returned by the handler are returned.
+#[cfg(feature = "signals")]
+fn handle_signal(signal: Signal, env: &mut Env, ctx: &mut Context) -> Result<Expr> {
+    if let Some(handler) = ctx.signal_handlers.get(&signal).cloned() {
+        interpret_lambda(&signal.to_string(), handler, &[], env, ctx)?;
+    }
+    Err(Error::Interrupted(signal))
 }
 
 #[cfg(test)]
 mod test {
-    use std::collections::HashMap;
-
     use crate::prelude;
     use crate::prelude::create;
 
@@ -195,6 +2288,7 @@ mod test {
                 Expr::Num(15.0),
             ]),
             &mut prelude::create(),
+            &mut Context::default(),
         );
         assert_eq!(out, Ok(Expr::Num(24.0)))
     }
@@ -213,13 +2307,14 @@ mod test {
                 ]),
             ]),
             &mut prelude::create(),
+            &mut Context::default(),
         );
         assert_eq!(out, Ok(Expr::Num(24.0)))
     }
 
     #[test]
     fn test_def() {
-        let mut env = HashMap::new();
+        let mut env = Env::default();
 
         interpret(
             Expr::List(vec![
@@ -228,12 +2323,32 @@ mod test {
                 Expr::Num(5.0),
             ]),
             &mut env,
+            &mut Context::default(),
         )
         .expect("error");
 
         assert_eq!(env.get("a").expect("key missing"), &Expr::Num(5.0))
     }
 
+    #[test]
+    fn test_def_with_docstring() {
+        let mut env = Env::default();
+
+        interpret(
+            Expr::List(vec![
+                Expr::Ident("def".to_string()),
+                Expr::Ident("a".to_string()),
+                Expr::Str("The answer.".to_string()),
+                Expr::Num(42.0),
+            ]),
+            &mut env,
+            &mut Context::default(),
+        )
+        .expect("error");
+
+        assert_eq!(env.get("a").expect("key missing"), &Expr::Num(42.0))
+    }
+
     #[test]
     fn test_def_and_use() {
         let mut env = create();
@@ -245,6 +2360,7 @@ mod test {
                 Expr::Num(5.0),
             ]),
             &mut env,
+            &mut Context::default(),
         )
         .expect("error");
 
@@ -255,6 +2371,7 @@ mod test {
                 Expr::Num(10.0),
             ]),
             &mut env,
+            &mut Context::default(),
         )
         .expect("error");
 
@@ -281,6 +2398,7 @@ mod test {
                 ]),
             ]),
             &mut env,
+            &mut Context::default(),
         )
         .expect("error");
 
@@ -290,6 +2408,7 @@ mod test {
                 Expr::Str("Tester".to_string()),
             ]),
             &mut env,
+            &mut Context::default(),
         )
         .expect("error");
 
@@ -318,6 +2437,7 @@ mod test {
                 ]),
             ]),
             &mut env,
+            &mut Context::default(),
         )
         .expect("error");
 
@@ -328,9 +2448,107 @@ mod test {
                 Expr::Num(4.0),
             ]),
             &mut env,
+            &mut Context::default(),
         )
         .expect("error");
 
         assert_eq!(out, Expr::Num(7.0))
     }
+
+    #[test]
+    fn test_policy_denies_privileged_function() {
+        let mut ctx = Context {
+            policy: std::rc::Rc::new(|op, _: &[Expr]| op != crate::Operation::ReadInput),
+            ..Context::default()
+        };
+
+        let out = interpret(
+            Expr::List(vec![Expr::Ident("read".to_string())]),
+            &mut create(),
+            &mut ctx,
+        );
+
+        assert_eq!(
+            out,
+            Err(Error::OperationNotPermitted(crate::Operation::ReadInput))
+        )
+    }
+
+    #[test]
+    fn test_policy_denies_aliased_privileged_function() {
+        let mut env = create();
+        let mut ctx = Context {
+            policy: std::rc::Rc::new(|op, _: &[Expr]| op != crate::Operation::ReadInput),
+            ..Context::default()
+        };
+
+        interpret(
+            Expr::List(vec![
+                Expr::Ident("def".to_string()),
+                Expr::Ident("input".to_string()),
+                Expr::Ident("read".to_string()),
+            ]),
+            &mut env,
+            &mut ctx,
+        )
+        .expect("error");
+        let out = interpret(
+            Expr::List(vec![Expr::Ident("input".to_string())]),
+            &mut env,
+            &mut ctx,
+        );
+
+        assert_eq!(
+            out,
+            Err(Error::OperationNotPermitted(crate::Operation::ReadInput))
+        )
+    }
+
+    #[test]
+    fn test_audit_log_records_def() {
+        let mut ctx = Context {
+            audit_log: Some(vec![]),
+            ..Context::default()
+        };
+
+        interpret(
+            Expr::List(vec![
+                Expr::Ident("def".to_string()),
+                Expr::Ident("a".to_string()),
+                Expr::Num(5.0),
+            ]),
+            &mut create(),
+            &mut ctx,
+        )
+        .expect("error");
+
+        let log = ctx.audit_log.expect("audit log disabled");
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].kind, RecordKind::Define);
+        assert_eq!(log[0].name, "a");
+        assert_eq!(log[0].args, vec![Expr::Num(5.0)]);
+    }
+
+    #[test]
+    fn test_quota_exceeded() {
+        let mut ctx = Context {
+            quotas: crate::Quotas {
+                max_string_length: Some(5),
+                ..crate::Quotas::default()
+            },
+            ..Context::default()
+        };
+
+        let out = interpret(
+            Expr::List(vec![
+                Expr::Ident("concat".to_string()),
+                Expr::Str("foo".to_string()),
+                Expr::Str("bar".to_string()),
+            ]),
+            &mut create(),
+            &mut ctx,
+        );
+
+        assert_eq!(out, Err(Error::QuotaExceeded(crate::Quota::StringLength)))
+    }
 }
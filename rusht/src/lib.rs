@@ -5,25 +5,85 @@
 //! able to handle the written identifiers which
 //! are our operaters with the allocated execution.
 
-#![forbid(unsafe_code)]
+#![cfg_attr(
+    not(any(feature = "plugins", feature = "signals")),
+    forbid(unsafe_code)
+)]
+#![cfg_attr(any(feature = "plugins", feature = "signals"), deny(unsafe_code))]
 #![deny(clippy::pedantic)]
 
 use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use thiserror::Error;
 
-pub use crate::expr::Expr;
-pub use crate::tokenize::Token;
+use crate::condition::Conditions;
+use crate::debug::Debugging;
+use crate::env::Env;
+#[cfg(feature = "signals")]
+use crate::expr::Lambda;
+use crate::module::Modules;
+use crate::observe::Observing;
+use crate::syntax::Macro;
 
+pub use crate::audit::{Record, RecordKind};
+pub use crate::debug::{Debugger, Frame};
+pub use crate::expr::{Expr, External, Intrinsic};
+#[cfg(feature = "fuzzing")]
+pub use crate::fuzzing::Generator;
+pub use crate::highlight::TokenClass;
+pub use crate::logging::{Level, LogRecord, Logger, StderrLogger};
+pub use crate::map::Key;
+pub use crate::observe::{Event, Observer};
+pub use crate::plugin::{Registry, REGISTER_SYMBOL};
+pub use crate::policy::{AllowAll, Operation, Policy};
+pub use crate::quota::{Quota, Quotas, DEFAULT_MAX_RECURSION_DEPTH};
+#[cfg(feature = "signals")]
+pub use crate::signal::Signal;
+pub use crate::tokenize::{Span, Token};
+
+mod audit;
+mod condition;
+mod debug;
+mod env;
 mod expr;
+mod format;
+#[cfg(feature = "fuzzing")]
+mod fuzzing;
+#[cfg(feature = "unicode")]
+mod grapheme;
+mod highlight;
 mod interpret;
+mod logging;
+mod map;
+mod math;
+mod module;
+#[cfg(feature = "net")]
+mod net;
+mod number;
+mod observe;
 mod parse;
+mod pattern;
+mod plugin;
+mod policy;
 mod prelude;
+mod pretty;
+mod process;
+mod quota;
+#[cfg(feature = "signals")]
+mod signal;
+mod syntax;
 mod tokenize;
+mod transpile;
+mod vector;
 
 /// Using an enum for Error Handling to call the right message
 /// when an error occurs.
-#[derive(Error, Debug, Eq, PartialEq)]
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Error {
     #[error("token stream ended unexpectedly")]
     UnexpectedEndOfTokenStream,
@@ -31,14 +91,16 @@ pub enum Error {
     UnexpectedClosingParenthesis,
     #[error("missing expected closing parenthesis")]
     MissingClosingParenthesis,
+    #[error("expected closing `{0}`, but found `{1}`")]
+    MismatchedParenthesis(char, char),
+    #[error("missing value of the last key in a map literal")]
+    MissingMapValue,
     #[error("encountered an unexpected type")]
     UnexpectedType,
     #[error("invalid number of arguments passed")]
     InvalidNumberOfArguments,
     #[error("function `{0}` is not defined")]
     FunctionNotDefined(String),
-    #[error("attempted to use function `{0}` as a variable")]
-    AttemptedToUseFunctionAsVariable(String),
     #[error("variable `{0}` is not defined")]
     VariableNotDefined(String),
     #[error("expression `{0}` is not an identifier")]
@@ -47,17 +109,347 @@ pub enum Error {
     EmptyListExpression,
     #[error("index `{0}` is out of bounds")]
     IndexOutOfBounds(usize),
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("operation `{0}` is not permitted by the policy")]
+    OperationNotPermitted(Operation),
+    #[error("quota for {0} exceeded")]
+    QuotaExceeded(Quota),
+    #[error("missing closing quotation mark of string literal")]
+    UnterminatedString,
+    #[error("`{0}` is not a valid number")]
+    InvalidNumber(String),
+    #[error("`{0}` is not a valid character")]
+    InvalidCharacter(String),
+    #[error("unexpected character `{0}`")]
+    UnexpectedCharacter(char),
+    #[error("failed to read file {0}")]
+    FileNotReadable(String),
+    #[error("assertion failed: {0}")]
+    AssertionFailed(String),
+    #[error("module `{0}` not found in the search path")]
+    ModuleNotFound(String),
+    #[error("`{0}` is not a valid module name")]
+    InvalidModuleName(String),
+    #[error("program exited with status {0}")]
+    Exit(i32),
+    #[error("process operation failed: {0}")]
+    ProcessFailed(String),
+    #[error("{0}")]
+    Raised(String),
+    #[error("restart `{0}` is not established")]
+    RestartNotFound(String),
+    #[error("restart `{0}` invoked")]
+    RestartInvoked(String),
+    #[error("`recur` called outside of `loop`")]
+    Recur,
+    #[error("`recur` used outside of the tail position of `loop`")]
+    RecurNotInTailPosition,
+    #[error("`{0}` used outside of `quasiquote`")]
+    Unquote(String),
+    #[error("no rule of macro `{0}` matches")]
+    NoMatchingRule(String),
+    #[cfg(feature = "signals")]
+    #[error("interrupted by {0}")]
+    Interrupted(Signal),
+    #[cfg(feature = "plugins")]
+    #[error("failed to load plugin: {0}")]
+    PluginNotLoaded(String),
+    #[cfg(feature = "net")]
+    #[error("network operation failed: {0}")]
+    NetworkFailed(String),
+}
+
+/// The version of the interpreter.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The documentation of a builtin function or special form.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BuiltinDoc {
+    /// An example invocation, e.g. `(nth index list)`.
+    pub signature: &'static str,
+    /// A short description of what the builtin does.
+    pub description: &'static str,
+}
+
+impl BuiltinDoc {
+    /// Returns the name of the builtin, e.g. `nth`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.signature
+            .trim_start_matches('(')
+            .split(|c: char| c.is_whitespace() || c == ')')
+            .next()
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the documentation of all special forms and prelude functions.
+#[must_use]
+pub fn builtin_docs() -> Vec<BuiltinDoc> {
+    let docs = [
+        interpret::SPECIAL_FORM_DOCS,
+        interpret::INTRINSIC_DOCS,
+        prelude::DOCS,
+        map::DOCS,
+        math::DOCS,
+        vector::DOCS,
+        pretty::DOCS,
+        process::DOCS,
+    ]
+    .concat();
+    #[cfg(feature = "unicode")]
+    let docs = [&docs, grapheme::DOCS].concat();
+    #[cfg(feature = "net")]
+    let docs = [&docs, net::DOCS].concat();
+    docs
 }
 
 /// Type resulting either a success (`Ok`) or failure (`Err`)
 pub type Result<T> = std::result::Result<T, Error>;
 
-type Env = HashMap<String, Expr>;
+/// The values of the variables shadowed by the definitions of a loaded file.
+/// A value of `None` means, that the variable was not defined before.
+type Shadowed = HashMap<String, Option<Expr>>;
+
+/// The state of an interpreter, that is not part of the execution
+/// environment and thus not visible to scripts.
+pub(crate) struct Context {
+    policy: Rc<dyn Policy>,
+    audit_log: Option<Vec<Record>>,
+    quotas: Quotas,
+    debugging: Option<Debugging>,
+    observing: Option<Observing>,
+    logger: Rc<dyn Logger>,
+    modules: Modules,
+    conditions: Conditions,
+    recur: Option<Vec<Expr>>,
+    assignments: Vec<String>,
+    /// The names of the local variables visible in the current scope, which
+    /// lambdas created in it capture.
+    locals: Vec<String>,
+    globals: Option<Env>,
+    /// The span of the top-level expression being interpreted within the
+    /// input passed to the interpreter, if it was parsed from one.
+    span: Option<Span>,
+    /// The number of lists evaluated for the current top-level expression.
+    steps: usize,
+    /// The depth of the evaluations currently in progress.
+    depth: usize,
+    macros: HashMap<String, Macro>,
+    currying: bool,
+    epsilon: f64,
+    #[cfg(feature = "signals")]
+    signal_handlers: HashMap<Signal, Lambda>,
+    /// The number of times each signal with a handler had been received when
+    /// it was last handled, see `signal::take_pending`.
+    #[cfg(feature = "signals")]
+    signals_handled: HashMap<Signal, usize>,
+}
+
+impl Context {
+    /// Checks whether the policy allows the given operation.
+    ///
+    /// # Errors
+    ///
+    /// * `OperationNotPermitted` - If the policy denies the operation.
+    fn authorize(&self, operation: Operation, args: &[Expr]) -> Result<()> {
+        if self.policy.allows(operation, args) {
+            Ok(())
+        } else {
+            Err(Error::OperationNotPermitted(operation))
+        }
+    }
+
+    /// Returns a new context sharing the policy, quotas, logger, required
+    /// modules, macros, currying, epsilon and signal handlers of this context. The
+    /// audit log, the debugger, the observer, the established handlers and
+    /// restarts, and the state of loops, assignments and local scopes are not
+    /// shared.
+    fn detach(&self) -> Context {
+        Context {
+            policy: Rc::clone(&self.policy),
+            audit_log: None,
+            quotas: self.quotas,
+            debugging: None,
+            observing: None,
+            logger: Rc::clone(&self.logger),
+            modules: self.modules.clone(),
+            conditions: Conditions::default(),
+            recur: None,
+            assignments: vec![],
+            locals: vec![],
+            globals: None,
+            span: None,
+            steps: 0,
+            depth: 0,
+            macros: self.macros.clone(),
+            currying: self.currying,
+            epsilon: self.epsilon,
+            #[cfg(feature = "signals")]
+            signal_handlers: self.signal_handlers.clone(),
+            #[cfg(feature = "signals")]
+            signals_handled: self.signals_handled.clone(),
+        }
+    }
+
+    /// Notifies the debugger and the observer, if any, about an error, that
+    /// has reached the top level.
+    fn report(&mut self, error: &Error) {
+        if let Some(debugging) = &mut self.debugging {
+            debugging.report(error);
+        }
+        if let Some(observing) = &mut self.observing {
+            observing.report();
+        }
+    }
+
+    /// Adds a record to the audit log, if recording is enabled.
+    fn record(&mut self, kind: RecordKind, name: &str, args: &[Expr]) {
+        if let Some(log) = &mut self.audit_log {
+            log.push(Record::now(kind, name, args.to_vec(), self.span.clone()));
+        }
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Context {
+            policy: Rc::new(AllowAll),
+            audit_log: None,
+            quotas: Quotas::default(),
+            debugging: None,
+            observing: None,
+            logger: Rc::new(StderrLogger),
+            modules: Modules::default(),
+            conditions: Conditions::default(),
+            recur: None,
+            assignments: vec![],
+            locals: vec![],
+            globals: None,
+            span: None,
+            steps: 0,
+            depth: 0,
+            macros: HashMap::new(),
+            currying: false,
+            epsilon: f64::EPSILON,
+            #[cfg(feature = "signals")]
+            signal_handlers: HashMap::new(),
+            #[cfg(feature = "signals")]
+            signals_handled: HashMap::new(),
+        }
+    }
+}
+
+impl Debug for Context {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context").finish_non_exhaustive()
+    }
+}
+
+/// Tokenizes the given input and returns the tokens together with their
+/// location.
+///
+/// # Arguments
+/// * `input` - The source code to tokenize.
+///
+/// # Errors
+///
+/// This function can return all the errors returned by the `tokenize`
+/// function, e.g. `UnterminatedString`.
+pub fn tokenize<T>(input: T) -> Result<Vec<(Token, Span)>>
+where
+    T: AsRef<str>,
+{
+    tokenize::tokenize_spanned(input.as_ref())
+}
+
+/// Tokenizes and parses all expressions in the given input without
+/// interpreting them. This can be used to check the syntax of a program or
+/// whether an input is complete.
+///
+/// # Arguments
+/// * `input` - The source code to parse.
+///
+/// # Errors
+///
+/// This function can return all the errors returned by the `tokenize` and
+/// `parse` functions, e.g. `MissingClosingParenthesis` or
+/// `UnterminatedString` for incomplete inputs.
+pub fn parse<T>(input: T) -> Result<Vec<Expr>>
+where
+    T: AsRef<str>,
+{
+    parse::parse_all(tokenize::tokenize(input.as_ref())?)
+}
+
+/// Transpiles the given program into the source code of a Rust function of
+/// the given name. The function takes a `&mut Interpreter`, evaluates the
+/// program using `Interpreter::eval`, and returns the value of the last
+/// expression. This is experimental.
+///
+/// # Arguments
+/// * `input` - The source code of the program.
+/// * `name` - The name of the generated function.
+///
+/// # Errors
+///
+/// This function can return all the errors returned by the `parse` function.
+pub fn transpile<T>(input: T, name: &str) -> Result<String>
+where
+    T: AsRef<str>,
+{
+    transpile::transpile(input.as_ref(), name)
+}
+
+/// Formats the given source code in a canonical layout, e.g. to keep the
+/// scripts of a project consistent.
+///
+/// # Arguments
+/// * `input` - The source code to format.
+///
+/// # Errors
+///
+/// This function can return all the errors returned by the `tokenize` and
+/// `parse` functions, e.g. `MissingClosingParenthesis`.
+pub fn format<T>(input: T) -> Result<String>
+where
+    T: AsRef<str>,
+{
+    format::format(input.as_ref())
+}
+
+/// Returns all syntax errors in the given input together with their
+/// location, without interpreting it. An empty vector is returned, if the
+/// input is syntactically valid.
+///
+/// # Arguments
+/// * `input` - The source code to check.
+pub fn syntax_errors<T>(input: T) -> Vec<(Error, Span)>
+where
+    T: AsRef<str>,
+{
+    parse::syntax_errors(input.as_ref())
+}
+
+/// The time spent in the different phases of interpreting an input.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Timings {
+    pub tokenize: Duration,
+    pub parse: Duration,
+    pub eval: Duration,
+}
 
 /// The name of our used Hashmap passed in a struct.
 #[derive(Debug, Default)]
 pub struct Interpreter {
     env: Env,
+    context: Context,
+    loaded: HashMap<PathBuf, Shadowed>,
+    // Declared last, so the libraries are unloaded only after the builtins
+    // they registered in the environment have been dropped.
+    #[cfg(feature = "plugins")]
+    plugins: Vec<Rc<libloading::Library>>,
 }
 
 /// Implementing the Interpreter for our Hashmap by parsing the
@@ -68,14 +460,328 @@ impl Interpreter {
     pub fn new() -> Interpreter {
         Interpreter {
             env: prelude::create(),
+            context: Context::default(),
+            loaded: HashMap::new(),
+            #[cfg(feature = "plugins")]
+            plugins: vec![],
+        }
+    }
+
+    /// Defines or updates a variable in the global environment. This can be
+    /// used to provide native functions or values, such as external handles,
+    /// to scripts.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the variable.
+    /// * `value` - The value of the variable.
+    pub fn define<T>(&mut self, name: T, value: Expr)
+    where
+        T: Into<String>,
+    {
+        self.env.insert(name.into(), value);
+    }
+
+    /// Returns the value of the variable of the given name in the global
+    /// environment, e.g. to read settings defined by a script.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the variable.
+    #[must_use]
+    pub fn get<T>(&self, name: T) -> Option<&Expr>
+    where
+        T: AsRef<str>,
+    {
+        self.env.get(name.as_ref())
+    }
+
+    /// Returns the names of all special forms, macros, and defined variables
+    /// starting with the given prefix in alphabetical order, e.g. for tab completion.
+    ///
+    /// # Arguments
+    /// * `prefix` - The prefix of the names.
+    #[must_use]
+    pub fn completions(&self, prefix: &str) -> Vec<String> {
+        let mut names = interpret::SPECIAL_FORMS
+            .iter()
+            .copied()
+            .chain(self.context.macros.keys().map(String::as_str))
+            .chain(self.env.iter().map(|(name, _)| name.as_str()))
+            .filter(|name| name.starts_with(prefix))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Classifies the tokens of the given input for syntax highlighting.
+    /// Identifiers referring to special forms, macros, or defined functions
+    /// are classified as `TokenClass::Function`. Unbalanced parentheses and
+    /// invalid or unterminated literals are classified as
+    /// `TokenClass::Invalid`.
+    ///
+    /// # Arguments
+    /// * `input` - The input to classify.
+    #[must_use]
+    pub fn classify(&self, input: &str) -> Vec<(Span, TokenClass)> {
+        highlight::classify(input, |ident| {
+            interpret::SPECIAL_FORMS.contains(&ident)
+                || self.context.macros.contains_key(ident)
+                || matches!(
+                    self.env.get(ident),
+                    Some(Expr::Func(_) | Expr::Intrinsic(_) | Expr::Lambda(_))
+                )
+        })
+    }
+
+    /// Loads the plugin at the given path and adds the builtins it registers
+    /// to the global environment. See the `plugin` module for the expected
+    /// interface of plugins.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the shared library implementing the plugin.
+    ///
+    /// # Errors
+    ///
+    /// * `PluginNotLoaded` - If the library could not be loaded or does not
+    ///     export the register function.
+    #[cfg(feature = "plugins")]
+    pub fn load_plugin<P>(&mut self, path: P) -> Result<()>
+    where
+        P: AsRef<std::ffi::OsStr>,
+    {
+        let library = plugin::load(path.as_ref(), &mut self.env)?;
+        self.plugins.push(Rc::new(library));
+        Ok(())
+    }
+
+    /// Returns an isolated copy of this interpreter. The copy shares the
+    /// definitions made so far with this interpreter instead of copying them,
+    /// making forks cheap, e.g. to evaluate each request of a service in a
+    /// throwaway interpreter. Definitions made by either interpreter after the
+    /// fork are not visible to the other one. If the audit log is recorded,
+    /// the fork starts with an empty log of its own.
+    #[must_use]
+    pub fn fork(&mut self) -> Interpreter {
+        Interpreter {
+            env: self.env.fork(),
+            context: Context {
+                audit_log: self.context.audit_log.as_ref().map(|_| vec![]),
+                ..self.context.detach()
+            },
+            loaded: self.loaded.clone(),
+            #[cfg(feature = "plugins")]
+            plugins: self.plugins.clone(),
         }
     }
 
+    /// Returns the function of the given name as a Rust closure, so it can be
+    /// stored and called later on, e.g. as an event handler. The closure
+    /// captures a snapshot of the current environment and shares the policy
+    /// and quotas of the interpreter, but calls made through it are not
+    /// recorded in the audit log. Like when called by a script, a lambda
+    /// sees the local variables it captured when it was created and the
+    /// global variables of the snapshot.
+    ///
+    /// # Arguments
+    /// * `name` - The name of a lambda or prelude function.
+    ///
+    /// # Errors
+    ///
+    /// * `FunctionNotDefined` - If there is no variable of the given name.
+    /// * `UnexpectedType` - If the variable is not a function.
+    pub fn get_callable<T>(&self, name: T) -> Result<impl FnMut(Vec<Expr>) -> Result<Expr>>
+    where
+        T: AsRef<str>,
+    {
+        let name = name.as_ref().to_string();
+        let callable = match self.env.get(&name) {
+            Some(callable @ (Expr::Func(_) | Expr::Intrinsic(_) | Expr::Lambda(_))) => {
+                callable.clone()
+            }
+            Some(_) => return Err(Error::UnexpectedType),
+            None => return Err(Error::FunctionNotDefined(name)),
+        };
+        let env = self.env.clone();
+        let mut ctx = self.context.detach();
+
+        Ok(move |args: Vec<Expr>| match &callable {
+            Expr::Func(func) => interpret::call_builtin(&name, *func, args, &mut ctx),
+            callable => {
+                // Assignments to variables of the interpreter are not written
+                // back, so each call starts from the same environment.
+                let mut env = env.clone();
+                // Steps are counted per call, like per top-level expression.
+                ctx.steps = 0;
+                let out = match callable {
+                    Expr::Intrinsic(Intrinsic(func)) => func(args, &mut env, &mut ctx),
+                    Expr::Lambda(lambda) => interpret::interpret_lambda(
+                        &name,
+                        (**lambda).clone(),
+                        &args,
+                        &mut env,
+                        &mut ctx,
+                    ),
+                    _ => unreachable!("previously checked when getting the callable"),
+                };
+                ctx.assignments.clear();
+                out
+            }
+        })
+    }
+
+    /// Defines the variable `argv` as a list of the given arguments, e.g. the
+    /// command line arguments passed to a script.
+    ///
+    /// # Arguments
+    /// * `args` - The arguments, which are passed as strings.
+    pub fn set_args<I, T>(&mut self, args: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let args = args.into_iter().map(|arg| Expr::Str(arg.into())).collect();
+        self.define("argv", Expr::List(args));
+    }
+
+    /// Replaces the policy consulted before privileged builtins, such as
+    /// `read` or `exit`, are run.
+    ///
+    /// # Arguments
+    /// * `policy` - The new policy.
+    pub fn set_policy<P>(&mut self, policy: P)
+    where
+        P: Policy + 'static,
+    {
+        self.context.policy = Rc::new(policy);
+    }
+
+    /// Replaces the quotas limiting the size of values produced during
+    /// evaluation and the evaluation itself.
+    ///
+    /// # Arguments
+    /// * `quotas` - The new quotas.
+    pub fn set_quotas(&mut self, quotas: Quotas) {
+        self.context.quotas = quotas;
+    }
+
+    /// Replaces the directories searched, in order, for the modules required
+    /// using `(require "name")`. The search path is empty by default.
+    ///
+    /// # Arguments
+    /// * `dirs` - The directories to search.
+    pub fn set_search_path<I, P>(&mut self, dirs: I)
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.context.modules.search_path = dirs.into_iter().map(Into::into).collect();
+    }
+
+    /// Enables or disables currying. While enabled, calling a lambda with
+    /// fewer arguments than it expects returns a lambda awaiting the remaining
+    /// arguments instead of failing with `InvalidNumberOfArguments`. Currying
+    /// is disabled by default, as it changes the semantics of calls. Forks
+    /// and callables returned by `get_callable` share the setting.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether lambdas should be curried.
+    pub fn set_currying(&mut self, enabled: bool) {
+        self.context.currying = enabled;
+    }
+
+    /// Replaces the epsilon of `=`, i.e. the largest difference of numbers,
+    /// that are still considered equal, which is `f64::EPSILON` by default.
+    /// A larger epsilon tolerates accumulated rounding errors, e.g.
+    /// `(= (+ 0.1 0.2) 0.3)` is true with the default epsilon, but
+    /// `(= (* 3 0.1 10) 3)` requires a larger one. Forks and callables
+    /// returned by `get_callable` share the setting.
+    ///
+    /// # Arguments
+    /// * `epsilon` - The new epsilon.
+    pub fn set_epsilon(&mut self, epsilon: f64) {
+        self.context.epsilon = epsilon;
+    }
+
+    /// Attaches a debugger, which is notified about every call of a lambda
+    /// and every error. Forks and callables returned by `get_callable` are
+    /// not debugged.
+    ///
+    /// # Arguments
+    /// * `debugger` - The debugger.
+    pub fn set_debugger<D>(&mut self, debugger: D)
+    where
+        D: Debugger + 'static,
+    {
+        self.context.debugging = Some(Debugging::new(Rc::new(debugger)));
+    }
+
+    /// Replaces the logger receiving the records logged using `log-info`,
+    /// `log-warn` and `log-error`, which writes them to stderr by default.
+    /// Forks and callables returned by `get_callable` share the logger.
+    ///
+    /// # Arguments
+    /// * `logger` - The logger, e.g. a closure forwarding the records to the
+    ///     logging framework of the host.
+    pub fn set_logger<L>(&mut self, logger: L)
+    where
+        L: Logger + 'static,
+    {
+        self.context.logger = Rc::new(logger);
+    }
+
+    /// Attaches an observer, which is notified about every call, definition
+    /// and error. Forks and callables returned by `get_callable` are not
+    /// observed.
+    ///
+    /// # Arguments
+    /// * `observer` - The observer, e.g. a closure or the sending half of a
+    ///     channel.
+    pub fn set_observer<O>(&mut self, observer: O)
+    where
+        O: Observer + 'static,
+    {
+        self.context.observing = Some(Observing::new(Rc::new(observer)));
+    }
+
+    /// Enables or disables recording of the audit log. While enabled, every
+    /// definition and side-effecting builtin call is recorded. Disabling the
+    /// recording discards the log.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether the audit log should be recorded.
+    pub fn record_audit_log(&mut self, enabled: bool) {
+        self.context.audit_log = if enabled {
+            self.context.audit_log.take().or_else(|| Some(vec![]))
+        } else {
+            None
+        };
+    }
+
+    /// Returns the records of the audit log in the order they were made. The
+    /// returned slice is empty, if recording is disabled.
+    #[must_use]
+    pub fn audit_log(&self) -> &[Record] {
+        self.context.audit_log.as_deref().unwrap_or_default()
+    }
+
+    /// Removes and returns all records from the audit log, while keeping the
+    /// recording enabled.
+    pub fn take_audit_log(&mut self) -> Vec<Record> {
+        self.context
+            .audit_log
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
     /// This function is the heart so that our Lisp Interpreter will work.
     /// This function summarizes our three steps:
     /// the tokenstream which presents our input with the datatypes,
     /// the expression which presents our parser which handles the abstract syntax tree,
     /// and the out which presents our interpretation for the execution.
+    /// Every top-level expression of the input is interpreted in order and
+    /// the value of the last one is returned, like `interpret_all`.
     ///
     /// # Arguments
     /// * `input` - Our input from the terminal.
@@ -89,9 +795,1878 @@ impl Interpreter {
     where
         T: AsRef<str>,
     {
-        let token_stream = tokenize::tokenize(input.as_ref());
-        let expr = parse::parse(token_stream)?;
-        let out = interpret::interpret(expr, &mut self.env)?;
-        Ok(out)
+        self.interpret_timed(input).map(|(out, _)| out)
+    }
+
+    /// Like `interpret`, but additionally returns the time spent in each of
+    /// the three steps, summed up over all expressions of the input.
+    ///
+    /// # Arguments
+    /// * `input` - Our input from the terminal.
+    ///
+    /// # Errors
+    ///
+    /// This function can return all the errors returned by `interpret`.
+    pub fn interpret_timed<T>(&mut self, input: T) -> Result<(Expr, Timings)>
+    where
+        T: AsRef<str>,
+    {
+        let start = Instant::now();
+        let token_stream = tokenize::tokenize_spanned(input.as_ref())?;
+        let tokenized = Instant::now();
+        let exprs = parse::parse_all_spanned(token_stream)?;
+        let parsed = Instant::now();
+        let out = self.interpret_exprs(exprs)?;
+        let evaluated = Instant::now();
+
+        let timings = Timings {
+            tokenize: tokenized - start,
+            parse: parsed - tokenized,
+            eval: evaluated - parsed,
+        };
+        Ok((out, timings))
+    }
+
+    /// Interprets all expressions in the given input in order and returns the
+    /// value of the last one.
+    ///
+    /// # Arguments
+    /// * `input` - The source code containing the expressions.
+    ///
+    /// # Errors
+    ///
+    /// * `UnexpectedEndOfTokenStream` - If the input does not contain any
+    ///     expression.
+    ///
+    /// Additionally, all the errors returned by `interpret` can be returned.
+    pub fn interpret_all<T>(&mut self, input: T) -> Result<Expr>
+    where
+        T: AsRef<str>,
+    {
+        let tokens = tokenize::tokenize_spanned(input.as_ref())?;
+        self.interpret_exprs(parse::parse_all_spanned(tokens)?)
+    }
+
+    /// Interprets the given top-level expressions and their spans in order
+    /// and returns the value of the last one, stopping at the first error.
+    fn interpret_exprs(&mut self, exprs: Vec<(Expr, Span)>) -> Result<Expr> {
+        exprs
+            .into_iter()
+            .try_fold(None, |_, (expr, span)| {
+                self.interpret_expr(expr, Some(span)).map(Some)
+            })?
+            .ok_or(Error::UnexpectedEndOfTokenStream)
+    }
+
+    /// Interprets a single, already parsed expression, e.g. one of the
+    /// expressions returned by `parse`.
+    ///
+    /// # Arguments
+    /// * `expr` - The expression to interpret.
+    ///
+    /// # Errors
+    ///
+    /// This function can return all the errors returned by `interpret`,
+    /// except for those returned by the tokenizer and parser.
+    pub fn eval(&mut self, expr: Expr) -> Result<Expr> {
+        self.interpret_expr(expr, None)
+    }
+
+    /// Interprets a single, top-level expression, resolving an identifier
+    /// to its value. The debugger and the observer are notified,
+    /// if interpreting the expression fails.
+    ///
+    /// # Arguments
+    /// * `expr` - The expression to interpret.
+    /// * `span` - The span of the expression within the input, if any.
+    fn interpret_expr(&mut self, expr: Expr, span: Option<Span>) -> Result<Expr> {
+        self.context.span = span;
+        self.context.steps = 0;
+        let out = interpret::interpret_value(expr, &mut self.env, &mut self.context);
+        // All assignments have been applied to the global environment.
+        self.context.assignments.clear();
+        self.context.span = None;
+        if let Err(error) = &out {
+            self.context.report(error);
+        }
+        out
+    }
+
+    /// Returns source code redefining the variables of the global
+    /// environment in alphabetical order, e.g. to save an interactive session
+    /// and restore it later on using `interpret_all`. Unchanged definitions
+    /// of the prelude and values, that can not be written as source code,
+    /// such as functions, are skipped.
+    #[must_use]
+    pub fn to_source(&self) -> String {
+        self.to_source_filtered(|_| true).0
+    }
+
+    /// Like `to_source`, but only redefines the variables, whose names are
+    /// accepted by the given filter, e.g. to leave out variables defined by
+    /// the application. Additionally returns the names of the accepted
+    /// variables in alphabetical order, that were skipped, as their values
+    /// can not be written as source code.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Returns whether the variable of the given name is saved.
+    #[must_use]
+    pub fn to_source_filtered<F>(&self, filter: F) -> (String, Vec<String>)
+    where
+        F: Fn(&str) -> bool,
+    {
+        let prelude = prelude::create();
+        let mut defs = vec![];
+        let mut skipped = vec![];
+        for (name, value) in self.env.iter() {
+            if prelude.get(name) == Some(value) || !filter(name) {
+                continue;
+            }
+            match value.to_source() {
+                Some(value) => defs.push((name, value)),
+                None => skipped.push(name.clone()),
+            }
+        }
+        defs.sort();
+        skipped.sort();
+        let source = defs
+            .into_iter()
+            .fold(String::new(), |mut out, (name, value)| {
+                let _ = writeln!(out, "(def {} {})", name, value);
+                out
+            });
+        (source, skipped)
+    }
+
+    /// Interprets all expressions in the file at the given path and returns
+    /// the value of the last one. The definitions made by the file are
+    /// tracked, so the file can be reloaded later on.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file.
+    ///
+    /// # Errors
+    ///
+    /// * `FileNotReadable` - If the file could not be read.
+    /// * `UnexpectedEndOfTokenStream` - If the file does not contain any
+    ///     expression.
+    ///
+    /// Additionally, all the errors returned by `interpret` can be returned.
+    pub fn load<P>(&mut self, path: P) -> Result<Expr>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| Error::FileNotReadable(format!("`{}`: {}", path.display(), err)))?;
+
+        let before = self.env.clone();
+        let out = self.interpret_all(&source);
+
+        let shadowed = self.loaded.entry(path.to_path_buf()).or_default();
+        for (name, value) in self.env.iter() {
+            if before.get(name) != Some(value) {
+                shadowed
+                    .entry(name.clone())
+                    .or_insert_with(|| before.get(name).cloned());
+            }
+        }
+
+        out
+    }
+
+    /// Returns the paths of the files loaded using `load` and the modules
+    /// required using `require`, in alphabetical order.
+    #[must_use]
+    pub fn loaded_files(&self) -> Vec<PathBuf> {
+        let mut files = self
+            .loaded
+            .keys()
+            .chain(self.context.modules.paths())
+            .cloned()
+            .collect::<Vec<_>>();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Reloads a previously loaded file or required module. The definitions
+    /// made by the previous load are reverted before the file is interpreted
+    /// again, while all unrelated definitions are preserved. Files that have
+    /// not been loaded before are simply loaded.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file, as passed to `load` or returned by
+    ///     `loaded_files`.
+    ///
+    /// # Errors
+    ///
+    /// This function can return all the errors returned by `load`.
+    pub fn reload<P>(&mut self, path: P) -> Result<Expr>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if let Some(shadowed) = self.loaded.remove(path) {
+            for (name, value) in shadowed {
+                match value {
+                    Some(value) => self.env.insert(name, value),
+                    None => self.env.remove(&name),
+                }
+            }
+        }
+
+        let out = self.load(path);
+        // Later requires of a reloaded module return its new value.
+        if let (Ok(value), Some(_)) = (&out, self.context.modules.get(path)) {
+            self.context
+                .modules
+                .insert(path.to_path_buf(), value.clone());
+        }
+        out
+    }
+
+    /// Watches the file at the given path, along with the modules it requires,
+    /// and reloads it whenever one of them is modified. The result of each reload is passed to `on_reload`, which
+    /// returns whether watching should continue. This function blocks the
+    /// current thread until then.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file.
+    /// * `interval` - The interval in which the files are checked for changes.
+    /// * `on_reload` - A function called after every reload.
+    pub fn watch<P, F>(&mut self, path: P, interval: Duration, mut on_reload: F)
+    where
+        P: AsRef<Path>,
+        F: FnMut(Result<Expr>) -> bool,
+    {
+        let path = path.as_ref();
+        // The required modules are collected again after every reload, as
+        // the file may require different ones.
+        let watched = |interpreter: &Interpreter| {
+            std::iter::once(path.to_path_buf())
+                .chain(interpreter.loaded_files())
+                .map(|p| {
+                    let modified = std::fs::metadata(&p).and_then(|m| m.modified()).ok();
+                    (p, modified)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut last_modified = watched(self);
+        loop {
+            std::thread::sleep(interval);
+            let current = watched(self);
+            if current != last_modified {
+                // Required modules are evaluated only once, so modified
+                // ones are forgotten to be evaluated again by the reload.
+                for (p, modified) in &current {
+                    if !last_modified.contains(&(p.clone(), *modified)) {
+                        self.context.modules.remove(p);
+                    }
+                }
+                let out = self.reload(path);
+                last_modified = watched(self);
+                if !on_reload(out) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reload() {
+        let path = std::env::temp_dir().join(format!("rusht_reload_{}.lsp", std::process::id()));
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def unrelated 1)").expect("error");
+
+        std::fs::write(&path, "(def a 1) (def b 2)").expect("failed to write file");
+        assert_eq!(interpreter.load(&path), Ok(Expr::Int(2)));
+
+        std::fs::write(&path, "(def a 3)").expect("failed to write file");
+        assert_eq!(interpreter.reload(&path), Ok(Expr::Int(3)));
+        std::fs::remove_file(&path).expect("failed to remove file");
+
+        assert_eq!(interpreter.interpret("(+ a unrelated)"), Ok(Expr::Int(4)));
+        assert_eq!(
+            interpreter.interpret("(+ b 1)"),
+            Err(Error::VariableNotDefined("b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_watch_required() {
+        let dir = std::env::temp_dir().join(format!("rusht_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("failed to create directory");
+        let dependency = dir.join("dependency.rusht");
+        let script = dir.join("script.rusht");
+        std::fs::write(&dependency, "(def a 1)").expect("failed to write file");
+        std::fs::write(&script, "(require \"dependency\") (+ a 1)").expect("failed to write file");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_search_path(vec![&dir]);
+        assert_eq!(interpreter.load(&script), Ok(Expr::Int(2)));
+
+        let modified = dependency.clone();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            std::fs::write(modified, "(def a 2)").expect("failed to write file");
+        });
+        let mut results = vec![];
+        interpreter.watch(&script, Duration::from_millis(10), |result| {
+            results.push(result);
+            false
+        });
+        writer.join().expect("failed to join writer");
+        std::fs::remove_dir_all(&dir).expect("failed to remove directory");
+
+        assert_eq!(results, vec![Ok(Expr::Int(3))]);
+    }
+
+    #[test]
+    fn test_require() {
+        let dir = std::env::temp_dir().join(format!("rusht_require_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("failed to create directory");
+        std::fs::write(dir.join("utils.rusht"), "(def a 1) (def b 2)")
+            .expect("failed to write file");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_search_path(vec![&dir]);
+        let first = interpreter.interpret("(require \"utils\")");
+        interpreter.interpret("(def a 3)").expect("error");
+        let second = interpreter.interpret("(require \"utils\")");
+        std::fs::remove_dir_all(&dir).expect("failed to remove directory");
+
+        assert_eq!(first, Ok(Expr::Int(2)));
+        assert_eq!(second, Ok(Expr::Int(2)));
+        assert_eq!(interpreter.interpret("(+ a b)"), Ok(Expr::Int(5)));
+        assert_eq!(
+            interpreter.interpret("(require \"missing\")"),
+            Err(Error::ModuleNotFound("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_require_in_local_scope() {
+        let dir = std::env::temp_dir().join(format!("rusht_require_local_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("failed to create directory");
+        std::fs::write(
+            dir.join("m.rusht"),
+            "(def helper (func (x) (* x 2))) (def x 10)",
+        )
+        .expect("failed to write file");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_search_path(vec![&dir]);
+        let local = interpreter.interpret("(def f (func (x) (require \"m\") (helper x))) (f 2)");
+        let global = interpreter.interpret("(require \"m\") (helper x)");
+        std::fs::remove_dir_all(&dir).expect("failed to remove directory");
+
+        assert_eq!(local, Ok(Expr::Int(4)));
+        assert_eq!(global, Ok(Expr::Int(20)));
+        assert_eq!(
+            interpreter.interpret("(require \"../m\")"),
+            Err(Error::InvalidModuleName("../m".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_reload_required() {
+        let dir =
+            std::env::temp_dir().join(format!("rusht_reload_required_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("failed to create directory");
+        let path = dir.join("utils.rusht");
+        std::fs::write(&path, "(def a 1)").expect("failed to write file");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_search_path(vec![&dir]);
+        interpreter.interpret("(require \"utils\")").expect("error");
+        interpreter.interpret("(def unrelated 2)").expect("error");
+        let files = interpreter.loaded_files();
+
+        std::fs::write(&path, "(def a 3)").expect("failed to write file");
+        let reloaded = interpreter.reload(&path);
+        let required = interpreter.interpret("(require \"utils\")");
+        std::fs::remove_dir_all(&dir).expect("failed to remove directory");
+
+        assert_eq!(files, vec![path]);
+        assert_eq!(reloaded, Ok(Expr::Int(3)));
+        assert_eq!(interpreter.interpret("(+ a unrelated)"), Ok(Expr::Int(5)));
+        assert_eq!(required, Ok(Expr::Int(3)));
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut parent = Interpreter::new();
+        parent.interpret("(def a 1)").expect("error");
+
+        let mut child = parent.fork();
+        child.interpret("(def a 2)").expect("error");
+        child.interpret("(def b 3)").expect("error");
+
+        assert_eq!(parent.interpret("(+ a 0)"), Ok(Expr::Int(1)));
+        assert_eq!(child.interpret("(+ a b)"), Ok(Expr::Int(5)));
+        assert_eq!(
+            parent.interpret("(+ b 0)"),
+            Err(Error::VariableNotDefined("b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_to_source() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret_all("(def b (quote (1 \"x\"))) (def a (func (x) (+ x 1)))")
+            .unwrap();
+        let source = interpreter.to_source();
+        assert_eq!(
+            source,
+            "(def a (func (x) (+ x 1)))\n(def b (quote (1 \"x\")))\n"
+        );
+
+        let mut restored = Interpreter::new();
+        restored.interpret_all(&source).unwrap();
+        assert_eq!(restored.interpret("(a 1)"), Ok(Expr::Int(2)));
+        assert_eq!(restored.to_source(), source);
+
+        interpreter.set_args(vec!["x"]);
+        interpreter
+            .interpret_all("(def h (list +)) (def g +)")
+            .unwrap();
+        assert_eq!(
+            interpreter.to_source_filtered(|name| name != "argv" && name != "b"),
+            (
+                "(def a (func (x) (+ x 1)))\n".to_string(),
+                vec!["g".to_string(), "h".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_set_args() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_args(vec!["foo", "42"]);
+
+        assert_eq!(
+            interpreter.interpret("(+ (nth 1 argv) 1)"),
+            Ok(Expr::Num(43.0))
+        );
+    }
+
+    #[test]
+    fn test_eval() {
+        let mut interpreter = Interpreter::new();
+        let exprs = parse("(def a 1) (+ a 1)").unwrap();
+        let out = exprs.into_iter().map(|expr| interpreter.eval(expr)).last();
+        assert_eq!(out, Some(Ok(Expr::Int(2))));
+    }
+
+    #[test]
+    fn test_interpret_every_form() {
+        let mut interpreter = Interpreter::new();
+        let (out, _) = interpreter
+            .interpret_timed("(def a 1)\n(def b 2)\n(+ a b)")
+            .expect("error");
+        assert_eq!(out, Expr::Int(3));
+        assert_eq!(interpreter.get("b"), Some(&Expr::Int(2)));
+        assert_eq!(
+            interpreter.interpret("(def c 1) (exit 3) (def d 1)"),
+            Err(Error::Exit(3))
+        );
+        assert_eq!(interpreter.get("c"), Some(&Expr::Int(1)));
+        assert_eq!(interpreter.get("d"), None);
+        assert_eq!(
+            interpreter.interpret(""),
+            Err(Error::UnexpectedEndOfTokenStream)
+        );
+    }
+
+    #[test]
+    fn test_get() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def a 1)").unwrap();
+        assert_eq!(interpreter.get("a"), Some(&Expr::Int(1)));
+        assert_eq!(interpreter.get("b"), None);
+    }
+
+    #[test]
+    fn test_completions() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def answer 42)").expect("error");
+
+        assert_eq!(
+            interpreter.completions("a"),
+            [
+                "abs",
+                #[cfg(feature = "net")]
+                "accept",
+                "and",
+                "answer",
+                "any?",
+                "append",
+                "approx=",
+                "assert-eq",
+                "assoc",
+                "atan2",
+            ]
+        );
+        assert_eq!(
+            interpreter.completions("d"),
+            vec!["def", "defn", "defsyntax", "dissoc", "do", "dotimes"]
+        );
+    }
+
+    #[test]
+    fn test_get_callable() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(def add (func (a b) (+ a b)))")
+            .expect("error");
+
+        let mut add = interpreter.get_callable("add").expect("error");
+        let mut concat = interpreter.get_callable("concat").expect("error");
+        drop(interpreter);
+
+        assert_eq!(
+            add(vec![Expr::Num(1.0), Expr::Num(2.0)]),
+            Ok(Expr::Num(3.0))
+        );
+        assert_eq!(
+            concat(vec![Expr::Str("a".to_string()), Expr::Str("b".to_string())]),
+            Ok(Expr::Str("ab".to_string()))
+        );
+        assert_eq!(add(vec![]), Err(Error::InvalidNumberOfArguments));
+    }
+
+    #[test]
+    fn test_currying() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(def add (func (a b c) (+ a (+ b c))))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(add 1)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+
+        interpreter.set_currying(true);
+        assert_eq!(
+            interpreter.interpret_all("(def add1 (add 1)) (def add3 (add1 2)) (add3 3)"),
+            Ok(Expr::Int(6))
+        );
+        assert_eq!(interpreter.interpret("(add1 2 4)"), Ok(Expr::Int(7)));
+        assert_eq!(
+            interpreter.interpret("(add1 2 3 4)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(interpreter.get("add3").and_then(Expr::to_source), None);
+    }
+
+    #[test]
+    fn test_restarts() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret(
+                "(def parse (func (x) (with-restart (use-default (func () 0)) (+ (error x) 1))))",
+            )
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(parse \"bad\")"),
+            Err(Error::Raised("bad".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret(
+                "(with-handler (func (m) (invoke-restart use-default)) (+ (parse \"bad\") 10))"
+            ),
+            Ok(Expr::Int(10))
+        );
+        assert_eq!(
+            interpreter.interpret(
+                "(with-restart (use-value (func (v) (+ v 0))) (with-handler (func (m) (invoke-restart use-value 5)) (error 1)))"
+            ),
+            Ok(Expr::Int(5))
+        );
+    }
+
+    #[test]
+    fn test_handlers_decline() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter
+                .interpret("(with-handler (func (m) (assert-eq m \"bad\")) (error \"bad\"))"),
+            Err(Error::Raised("bad".to_string()))
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(with-handler (func (m) (invoke-restart retry)) (error \"bad\"))"),
+            Err(Error::RestartNotFound("retry".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(with-handler (func (m) (error \"again\")) (exit 2))"),
+            Err(Error::Exit(2))
+        );
+        assert_eq!(
+            interpreter.interpret("(with-handler (func (m) 1) 1 2)"),
+            Ok(Expr::Int(2))
+        );
+    }
+
+    #[test]
+    fn test_let() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def x 10)").expect("error");
+        assert_eq!(
+            interpreter.interpret("(let ((x 1) (y (+ x 2))) (def z 3) (+ x y z))"),
+            Ok(Expr::Int(16))
+        );
+        assert_eq!(interpreter.interpret("(let ((x 1)) x)"), Ok(Expr::Int(1)));
+        assert_eq!(interpreter.get("x"), Some(&Expr::Int(10)));
+        assert_eq!(interpreter.get("y"), None);
+        assert_eq!(interpreter.get("z"), None);
+        assert_eq!(interpreter.interpret("(let ())"), Ok(Expr::Nil));
+        assert_eq!(
+            interpreter.interpret("(let ((1 2)) x)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(let)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+    }
+
+    #[test]
+    fn test_let_sequential() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def x 10)").expect("error");
+        assert_eq!(
+            interpreter.interpret("(let* ((x 1) (y (+ x 2)) (x (* x y))) (+ x y))"),
+            Ok(Expr::Int(6))
+        );
+        assert_eq!(
+            interpreter.interpret("(let ((x 1) (y (+ x 2))) y)"),
+            Ok(Expr::Int(12))
+        );
+        assert_eq!(interpreter.get("x"), Some(&Expr::Int(10)));
+        assert_eq!(interpreter.get("y"), None);
+    }
+
+    #[test]
+    fn test_letrec() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret(
+                "(letrec ((even (func (n) (case n (0 1) (else (odd (- n 1))))))
+                          (odd (func (n) (case n (0 0) (else (even (- n 1))))))
+                          (x (even 10)))
+                   (+ x (odd 7)))"
+            ),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(
+            interpreter.interpret("(letrec ((x 1) (y (+ x 1))) y)"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(interpreter.get("even"), None);
+        assert_eq!(interpreter.get("x"), None);
+
+        interpreter.interpret("(def y 5)").expect("error");
+        assert_eq!(
+            interpreter.interpret("(letrec ((x (+ y 1)) (y 1)) x)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(let* ((x (+ y 1)) (y 1)) x)"),
+            Ok(Expr::Int(6))
+        );
+    }
+
+    #[test]
+    fn test_if() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(if (< 1 2) (+ 1 1) (exit 1))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(
+            interpreter.interpret("(if false (exit 7) 2)"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(interpreter.interpret("(if false 1)"), Ok(Expr::Nil));
+        interpreter.interpret("(def x 3)").expect("error");
+        assert_eq!(interpreter.interpret("(if true x)"), Ok(Expr::Int(3)));
+        assert_eq!(
+            interpreter.interpret("(if \"foo\" 1 2)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(if true)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret("(if true 1 2 3)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+    }
+
+    #[test]
+    fn test_when_unless() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(when (< 1 2) (def x 1) (+ x 1))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(interpreter.interpret("(when 0 (exit 1))"), Ok(Expr::Nil));
+        assert_eq!(
+            interpreter.interpret("(unless (< 1 2) (exit 1))"),
+            Ok(Expr::Nil)
+        );
+        assert_eq!(
+            interpreter.interpret("(unless false 1 2)"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(
+            interpreter.interpret("(when (quote ()) 1)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(when)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+    }
+
+    #[test]
+    fn test_case() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret(
+                "(def describe (func (x) (case x (1 \"one\") (\"two\" \"two\") (else \"many\"))))",
+            )
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(describe 1)"),
+            Ok(Expr::Str("one".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(describe \"two\")"),
+            Ok(Expr::Str("two".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(describe 2)"),
+            Ok(Expr::Str("many".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(case (+ 1 1) (1 (exit 1)) (2 (def y 2) (+ y 1)))"),
+            Ok(Expr::Int(3))
+        );
+        assert_eq!(interpreter.interpret("(case 3 (1 2))"), Ok(Expr::Nil));
+        assert_eq!(
+            interpreter.interpret("(case 3 ())"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(defn sum (xs) (match xs (() 0) ((x . rest) (+ x (sum rest)))))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(sum (quote (1 2 3)))"),
+            Ok(Expr::Int(6))
+        );
+        assert_eq!(
+            interpreter.interpret("(match (quote (1 2)) ((1 _ _) (exit 1)) ((_ y) (+ y 1)))"),
+            Ok(Expr::Int(3))
+        );
+        assert_eq!(
+            interpreter.interpret("(match (quote (1 2)) ((x _) x))"),
+            Ok(Expr::Int(1))
+        );
+        assert_eq!(
+            interpreter.interpret("(match \"a\" (1 (exit 1)) (\"a\" 2))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(interpreter.interpret("(match 3 (() 2))"), Ok(Expr::Nil));
+        assert_eq!(interpreter.get("x"), None);
+        assert_eq!(interpreter.get("y"), None);
+        assert_eq!(
+            interpreter.interpret("(match (quote (1)) ((. x) 1))"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_quote_sugar() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("'(1 \"a\" (2))"),
+            interpreter.interpret("(quote (1 \"a\" (2)))")
+        );
+        assert_eq!(
+            interpreter.interpret("(nth 1 '(1 '(2 3)))"),
+            Ok(Expr::List(vec![
+                Expr::Symbol("quote".to_string()),
+                Expr::List(vec![Expr::Int(2), Expr::Int(3)])
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("'a"),
+            Ok(Expr::Symbol("a".to_string()))
+        );
+        assert_eq!(interpreter.interpret("'1"), Ok(Expr::Int(1)));
+        assert_eq!(
+            interpreter.interpret("''a"),
+            interpreter.interpret("(quote (quote a))")
+        );
+        assert_eq!(
+            interpreter.interpret("(quote 1 2)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret("(quote)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret("(for (x '(1 2)) (+ x 1))"),
+            Ok(Expr::List(vec![Expr::Int(2), Expr::Int(3)]))
+        );
+        assert_eq!(
+            interpreter.interpret("'"),
+            Err(Error::UnexpectedEndOfTokenStream)
+        );
+    }
+
+    #[test]
+    fn test_quasiquote() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def x 2)").expect("error");
+        interpreter.interpret("(def xs '(3 4))").expect("error");
+        assert_eq!(
+            interpreter.interpret("`(1 ,x ,@xs (x ,(+ x 3)) ,@'())"),
+            Ok(Expr::List(vec![
+                Expr::Int(1),
+                Expr::Int(2),
+                Expr::Int(3),
+                Expr::Int(4),
+                Expr::List(vec![Expr::Symbol("x".to_string()), Expr::Int(5)])
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("`x"),
+            Ok(Expr::Symbol("x".to_string()))
+        );
+        assert_eq!(interpreter.interpret("`,x"), Ok(Expr::Int(2)));
+        assert_eq!(interpreter.interpret("`(,@x)"), Err(Error::UnexpectedType));
+        assert_eq!(interpreter.interpret("`,@xs"), Err(Error::UnexpectedType));
+        assert_eq!(
+            interpreter.interpret("(quasiquote 1 2)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret("(quasiquote (unquote x xs))"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret(",x"),
+            Err(Error::Unquote("unquote".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(+ ,@xs)"),
+            Err(Error::Unquote("unquote-splicing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_syntax_rules() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret_all(
+                "(defsyntax swap! (syntax-rules () ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))
+                 (def tmp 1)
+                 (def y 2)
+                 (swap! tmp y)",
+            )
+            .expect("error");
+        assert_eq!(interpreter.get("tmp"), Some(&Expr::Int(2)));
+        assert_eq!(interpreter.get("y"), Some(&Expr::Int(1)));
+        assert_eq!(interpreter.completions("swap"), vec!["swap!"]);
+
+        interpreter
+            .interpret(
+                "(defsyntax sum (syntax-rules () ((_) 0) ((_ x xs ...) (+ x (sum xs ...)))))",
+            )
+            .expect("error");
+        assert_eq!(interpreter.interpret("(sum 1 2 (+ 1 2))"), Ok(Expr::Int(6)));
+        assert_eq!(
+            interpreter.interpret("(defsyntax one (syntax-rules () ((_ x) x)))"),
+            Ok(Expr::Nil)
+        );
+        assert_eq!(
+            interpreter.interpret("(one)"),
+            Err(Error::NoMatchingRule("one".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(defsyntax two (rules () ((_ x) x)))"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_list_primitives() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret(
+                "(defn double (xs) (case (empty? xs) (true (list)) (else (cons (* 2 (car xs)) (double (cdr xs))))))",
+            )
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(double (list 1 2 (+ 1 2)))"),
+            Ok(Expr::List(vec![Expr::Int(2), Expr::Int(4), Expr::Int(6)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(first (rest '(1 2 3)))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(
+            interpreter.interpret("(car (list))"),
+            Err(Error::IndexOutOfBounds(0))
+        );
+    }
+
+    #[test]
+    fn test_higher_order() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(defn even (x) (= (% x 2) 0))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(map (func (x) (* x 2)) (list 1 2 3))"),
+            Ok(Expr::List(vec![Expr::Int(2), Expr::Int(4), Expr::Int(6)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(map concat (list 1 2))"),
+            Ok(Expr::List(vec![
+                Expr::Str("1".to_string()),
+                Expr::Str("2".to_string())
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(filter even (list 1 2 3 4))"),
+            Ok(Expr::List(vec![Expr::Int(2), Expr::Int(4)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(flat-map (func (x) (list x x)) (list 1 2))"),
+            Ok(Expr::List(vec![
+                Expr::Int(1),
+                Expr::Int(1),
+                Expr::Int(2),
+                Expr::Int(2)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(reduce + 10 (list 1 2 3))"),
+            Ok(Expr::Int(16))
+        );
+        assert_eq!(
+            interpreter.interpret("(reduce (func (a b) (- a b)) (list 10 2 3))"),
+            Ok(Expr::Int(5))
+        );
+        assert_eq!(
+            interpreter.interpret("(reduce + (list))"),
+            Err(Error::IndexOutOfBounds(0))
+        );
+        assert_eq!(
+            interpreter.interpret("(map 1 (list 1))"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_quantifiers() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(defn even (x) (= (% x 2) 0))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(any? even (list 1 2 3))"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.interpret("(any? even (list))"),
+            Ok(Expr::Bool(false))
+        );
+        assert_eq!(
+            interpreter.interpret("(every? even (list 2 4))"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.interpret("(every? even (list 2 3 \"a\"))"),
+            Ok(Expr::Bool(false))
+        );
+        assert_eq!(
+            interpreter.interpret("(count even (list 1 2 3 4))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(
+            interpreter.interpret("(count (func (x) (list x)) (list 1))"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_map_literal() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter
+                .interpret("(def m {:name \"marc\" :age (+ 1 2) :tags '(a b)})")
+                .map(|m| m.to_string()),
+            Ok("{:age 3 :name \"marc\" :tags (a b)}".to_string())
+        );
+        assert_eq!(interpreter.interpret("(get m :age)"), Ok(Expr::Int(3)));
+        assert_eq!(
+            interpreter.interpret("(get m :name)"),
+            Ok(Expr::Str("marc".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(equal? m (hash-map :age 3 :name \"marc\" :tags '(a b)))"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.to_source(),
+            "(def m {:age 3 :name \"marc\" :tags (quote (a b))})\n"
+        );
+        assert_eq!(interpreter.interpret("{:a}"), Err(Error::MissingMapValue));
+        assert_eq!(
+            interpreter.interpret("(get m undefined)"),
+            Err(Error::VariableNotDefined("undefined".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_nil() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.interpret("nil"), Ok(Expr::Nil));
+        assert_eq!(interpreter.interpret("(if (< 2 1) 1)"), Ok(Expr::Nil));
+        assert_eq!(
+            interpreter.interpret("(nil? (when false 1))"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(interpreter.interpret("(get {:a 1} :b)"), Ok(Expr::Nil));
+        assert_eq!(
+            interpreter.interpret("(match nil (nil 1) (_ 2))"),
+            Ok(Expr::Int(1))
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(def x (list nil))")
+                .map(|x| x.to_string()),
+            Ok("(nil)".to_string())
+        );
+        assert_eq!(interpreter.to_source(), "(def x (quote (nil)))\n");
+    }
+
+    #[test]
+    fn test_keywords() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret(":name"),
+            Ok(Expr::Keyword("name".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (eq? :a :a) (equal? :a :b) (keyword? :a))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(false),
+                Expr::Bool(true)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(case :desc (:asc 1) (:desc 2))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(def m (assoc {:b 1} :a 2 \"a\" 3))")
+                .map(|m| m.to_string()),
+            Ok("{\"a\" 3 :a 2 :b 1}".to_string())
+        );
+        assert_eq!(
+            interpreter.interpret("(def :a 1)"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_integers() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(+ 9007199254740992 1)"),
+            Ok(Expr::Int(9_007_199_254_740_993))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (/ 6 3) (/ 1 2) (% 7 2) (- 1 1.5))"),
+            Ok(Expr::List(vec![
+                Expr::Int(2),
+                Expr::Ratio(1, 2),
+                Expr::Int(1),
+                Expr::Num(-0.5)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(* 4611686018427387904 2)"),
+            Ok(Expr::Num(9_223_372_036_854_775_808.0))
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(list (= 1 1.0) (equal? 1 1.0) (< 9007199254740992 9007199254740993))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(false),
+                Expr::Bool(true)
+            ]))
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(sort (list 2 1.5 1 1.0))")
+                .map(|list| list.to_string()),
+            Ok("(1 1.0 1.5 2)".to_string())
+        );
+        let floats = Expr::Vector(vec![Expr::Num(2.0), Expr::Num(-3.0)]);
+        assert_eq!(
+            interpreter.interpret(&floats.to_source().expect("floats can be written")),
+            Ok(floats)
+        );
+        let min = Expr::Int(i64::MIN);
+        assert_eq!(
+            interpreter.interpret(&min.to_source().expect("integers can be written")),
+            Ok(min)
+        );
+    }
+
+    #[test]
+    fn test_epsilon() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(list (= (+ 0.1 0.2) 0.3) (= (* 3 0.1 10) 3) (= 1 \"1\" true))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(false),
+                Expr::Bool(true)
+            ]))
+        );
+        interpreter.set_epsilon(1e-9);
+        assert_eq!(
+            interpreter.interpret("(list (= (* 3 0.1 10) 3) (= 1 1.001))"),
+            Ok(Expr::List(vec![Expr::Bool(true), Expr::Bool(false)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (approx= 0.01 1 1.001 1.002) (approx= 0 1 1.001))"),
+            Ok(Expr::List(vec![Expr::Bool(true), Expr::Bool(false)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(= (list) 1)"),
+            Err(Error::UnexpectedType)
+        );
+        interpreter.interpret("(def eq =)").expect("error");
+        assert_eq!(
+            interpreter.interpret("(list (eq 1 1.0) (eq 1 (+ 1 1e-10)) (eq 1 2))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(true),
+                Expr::Bool(false)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (map = (list 1 2)) (reduce = (list 1 1.0)))"),
+            Ok(Expr::List(vec![
+                Expr::List(vec![Expr::Bool(true), Expr::Bool(true)]),
+                Expr::Bool(true)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(let ((= +)) (= 1 2))"),
+            Ok(Expr::Int(3))
+        );
+    }
+
+    #[test]
+    fn test_ratios() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter
+                .interpret("(list (/ 1 3) (/ 4 (- 0 6)) 2/4 (+ 1/3 1/6))")
+                .map(|list| list.to_string()),
+            Ok("(1/3 -2/3 1/2 1/2)".to_string())
+        );
+        assert_eq!(interpreter.interpret("(* 3 1/3)"), Ok(Expr::Int(1)));
+        assert_eq!(interpreter.interpret("(+ 1/2 0.25)"), Ok(Expr::Num(0.75)));
+        assert_eq!(
+            interpreter.interpret("(list (< 1/3 0.34) (= 1/2 0.5) (equal? 1/2 2/4))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(true),
+                Expr::Bool(true)
+            ]))
+        );
+        assert_eq!(interpreter.interpret("(/ 1 0)"), Err(Error::DivisionByZero));
+        let ratios = Expr::Vector(vec![Expr::Ratio(1, 3), Expr::Ratio(i64::MIN + 1, 2)]);
+        assert_eq!(
+            interpreter.interpret(&ratios.to_source().expect("ratios can be written")),
+            Ok(ratios)
+        );
+    }
+
+    #[test]
+    fn test_chars() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.interpret("#\\a"), Ok(Expr::Char('a')));
+        assert_eq!(
+            interpreter
+                .interpret("(list #\\a #\\space #\\newline #\\()")
+                .map(|list| list.to_string()),
+            Ok("(#\\a #\\space #\\newline #\\()".to_string())
+        );
+        assert_eq!(
+            interpreter.interpret("(list (eq? #\\a #\\a) (equal? #\\a \"a\"))"),
+            Ok(Expr::List(vec![Expr::Bool(true), Expr::Bool(false)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(int->char (+ (char->int #\\a) 1))"),
+            Ok(Expr::Char('b'))
+        );
+        assert_eq!(
+            interpreter.interpret("(case (car (string->chars \"xy\")) (#\\y 1) (#\\x 2))"),
+            Ok(Expr::Int(2))
+        );
+        let chars = Expr::List(vec![Expr::Char(' '), Expr::Char(')')]);
+        assert_eq!(
+            interpreter.interpret(&chars.to_source().expect("chars can be written")),
+            Ok(chars)
+        );
+        assert_eq!(
+            interpreter.interpret("#\\nope"),
+            Err(Error::InvalidCharacter("#\\nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(car '(a b))"),
+            Ok(Expr::Symbol("a".to_string()))
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(list (symbol? (car '(a))) (symbol? \"a\") (equal? (car '(a)) \"a\"))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(false),
+                Expr::Bool(false)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(eq? (string->symbol (symbol->string (car '(a)))) (car '(a)))"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.interpret(
+                "(list (symbol? 'a) (symbol->string 'a) (== 'a (string->symbol \"a\")))"
+            ),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Str("a".to_string()),
+                Expr::Bool(true)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(case 'b (a 1) (b 2))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(
+            interpreter.interpret("(case (car '(b)) (a 1) (b 2))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(
+            interpreter.interpret("{a 1 b '(c)}").map(|m| m.to_string()),
+            Ok("{a 1 b (c)}".to_string())
+        );
+        let symbol = Expr::Symbol("a b".to_string());
+        assert_eq!(
+            interpreter.interpret(&symbol.to_source().expect("symbol can be written")),
+            Ok(symbol)
+        );
+    }
+
+    #[test]
+    fn test_gensym() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(def s (gensym \"tmp\"))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(list (symbol? s) (symbol? (gensym)))"),
+            Ok(Expr::List(vec![Expr::Bool(true), Expr::Bool(true)]))
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(let ((gs (list (gensym) (gensym)))) (eq? (car gs) (nth 1 gs)))"),
+            Ok(Expr::Bool(false))
+        );
+        assert!(matches!(
+            interpreter.interpret("(symbol->string s)"),
+            Ok(Expr::Str(name)) if name.starts_with("tmp")
+        ));
+    }
+
+    #[test]
+    fn test_vector_literal() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter
+                .interpret("(def v [1 (+ 1 1) [\"a\"] '(b)])")
+                .map(|v| v.to_string()),
+            Ok("[1 2 [\"a\"] (b)]".to_string())
+        );
+        assert_eq!(interpreter.interpret("(vec-ref v 1)"), Ok(Expr::Int(2)));
+        assert_eq!(
+            interpreter.interpret("(equal? v (vector 1 2 (vector \"a\") '(b)))"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.to_source(),
+            "(def v [1 2 [\"a\"] (quote (b))])\n"
+        );
+        assert_eq!(
+            interpreter.interpret("[1 2)"),
+            Err(Error::MismatchedParenthesis(']', ')'))
+        );
+    }
+
+    #[test]
+    fn test_group_by() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret(
+                "(equal? (group-by (func (x) (% x 2)) (list 1 2 3)) (hash-map 0 (list 2) 1 (list 1 3)))"
+            ),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.interpret("(group-by length (list))"),
+            interpreter.interpret("(hash-map)")
+        );
+        assert_eq!(
+            interpreter.interpret("(group-by (func (x) (list +)) (list 1))"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(frequencies (list \"a\" 1 \"a\"))")
+                .map(|out| out.to_string()),
+            Ok("{1 1 \"a\" 2}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_first_class_functions() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def plus +)").expect("error");
+        assert_eq!(interpreter.interpret("(plus 1 2)"), Ok(Expr::Int(3)));
+        assert_eq!(
+            interpreter.interpret("(let ((f *)) (f 2 3))"),
+            Ok(Expr::Int(6))
+        );
+        interpreter
+            .interpret("(defn twice (f x) (f (f x x) (f x x)))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("(twice plus 1)"), Ok(Expr::Int(4)));
+        assert_eq!(
+            interpreter.interpret("(map plus (list 1 2))"),
+            Ok(Expr::List(vec![Expr::Int(1), Expr::Int(2)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(plus undefined)"),
+            Err(Error::VariableNotDefined("undefined".to_string()))
+        );
+        interpreter.interpret("(def apply-all map)").expect("error");
+        assert_eq!(
+            interpreter.interpret("(apply-all (func (x) (+ x 1)) (list 1 2))"),
+            Ok(Expr::List(vec![Expr::Int(2), Expr::Int(3)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(map filter (list))"),
+            Ok(Expr::List(vec![]))
+        );
+        assert_eq!(
+            interpreter.interpret("(reduce reduce (list plus (list 1 2)))"),
+            Ok(Expr::Int(3))
+        );
+        assert_eq!(
+            interpreter.interpret("(let ((count 1)) count)"),
+            Ok(Expr::Int(1))
+        );
+        interpreter
+            .interpret("(defn map (f xs) \"shadowed\")")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(map + (list 1))"),
+            Ok(Expr::Str("shadowed".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_call_expression() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("((func (x) (* x x)) 4)"),
+            Ok(Expr::Int(16))
+        );
+        assert_eq!(interpreter.interpret("((func (x) x) 5)"), Ok(Expr::Int(5)));
+        assert_eq!(
+            interpreter.interpret("((if false + -) 5 3)"),
+            Ok(Expr::Int(2))
+        );
+        interpreter
+            .interpret("(defn incrementer () (func (x) (+ x 1)))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("((incrementer) 2)"), Ok(Expr::Int(3)));
+        assert_eq!(
+            interpreter.interpret("((list 1) 2)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(1 2)"),
+            Err(Error::NotAnIdentifier("1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_closures() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(defn make-adder (n) (func (x) (+ x n)))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("((make-adder 1) 2)"),
+            Ok(Expr::Int(3))
+        );
+        interpreter
+            .interpret("(def add-2 (make-adder 2))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(let ((n 10)) (add-2 1))"),
+            Ok(Expr::Int(3))
+        );
+        let mut add_2 = interpreter.get_callable("add-2").expect("error");
+        assert_eq!(add_2(vec![Expr::Int(3)]), Ok(Expr::Int(5)));
+
+        interpreter.interpret("(def x 1)").expect("error");
+        interpreter.interpret("(def f (func () x))").expect("error");
+        assert_eq!(interpreter.interpret("(let ((x 2)) (f))"), Ok(Expr::Int(1)));
+        interpreter.interpret("(def x 3)").expect("error");
+        assert_eq!(interpreter.interpret("(f)"), Ok(Expr::Int(3)));
+
+        assert_eq!(
+            interpreter.interpret(
+                "(letrec ((even (func (n) (if (= n 0) true (odd (- n 1))))) (odd (func (n) (if (= n 0) false (even (- n 1)))))) (even 10))"
+            ),
+            Ok(Expr::Bool(true))
+        );
+        interpreter
+            .interpret(
+                "(defn count-down (n) (defn step (k) (if (= k 0) n (step (- k 1)))) (step n))",
+            )
+            .expect("error");
+        assert_eq!(interpreter.interpret("(count-down 3)"), Ok(Expr::Int(3)));
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(sort (list 3 1 2))"),
+            Ok(Expr::List(vec![Expr::Int(1), Expr::Int(2), Expr::Int(3)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(sort '(\"b\" \"a\"))"),
+            Ok(Expr::List(vec![
+                Expr::Str("a".to_string()),
+                Expr::Str("b".to_string())
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(sort (list 1 2 3) >)"),
+            Ok(Expr::List(vec![Expr::Int(3), Expr::Int(2), Expr::Int(1)]))
+        );
+        assert_eq!(
+            interpreter.interpret(
+                "(sort (list (list 2 \"a\") (list 1 \"b\") (list 2 \"c\")) (func (a b) (< (car a) (car b))))"
+            ),
+            interpreter.interpret("(list (list 1 \"b\") (list 2 \"a\") (list 2 \"c\"))")
+        );
+        assert_eq!(
+            interpreter.interpret("(sort (list 1 \"a\"))"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(sort (list))"),
+            Ok(Expr::List(vec![]))
+        );
+    }
+
+    #[test]
+    fn test_recursion() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(def fact (func (n) (if (= n 0) 1 (* n (fact (- n 1))))))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("(fact 5)"), Ok(Expr::Int(120)));
+        assert_eq!(
+            interpreter.interpret("(let ((m 3)) (fact (+ m 1)))"),
+            Ok(Expr::Int(24))
+        );
+
+        let mut fact = interpreter.get_callable("fact").expect("error");
+        drop(interpreter);
+        assert_eq!(fact(vec![Expr::Int(3)]), Ok(Expr::Int(6)));
+    }
+
+    #[test]
+    fn test_evaluation_quotas() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_quotas(Quotas {
+            max_steps: Some(1000),
+            max_recursion_depth: Some(100),
+            ..Quotas::default()
+        });
+        interpreter
+            .interpret("(def fact (func (n) (if (= n 0) 1 (* n (fact (- n 1))))))")
+            .expect("error");
+
+        assert_eq!(interpreter.interpret("(fact 5)"), Ok(Expr::Int(120)));
+        assert_eq!(
+            interpreter.interpret("(fact 50)"),
+            Err(Error::QuotaExceeded(Quota::RecursionDepth))
+        );
+        assert_eq!(
+            interpreter.interpret("(loop ((i 0)) (recur (+ i 1)))"),
+            Err(Error::QuotaExceeded(Quota::Steps))
+        );
+        // Steps are counted per top-level expression.
+        let steps = "(dotimes (i 400) (+ i 1))".repeat(3);
+        assert_eq!(interpreter.interpret_all(&steps), Ok(Expr::Nil));
+        assert_eq!(interpreter.interpret("(fact 5)"), Ok(Expr::Int(120)));
+    }
+
+    #[test]
+    fn test_default_recursion_depth() {
+        // Frames are much larger without optimizations, so the interpreter
+        // runs on a thread with a larger stack than the default one.
+        std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(|| {
+                let mut interpreter = Interpreter::new();
+                interpreter
+                    .interpret("(defn f (n) (if (= n 0) 0 (+ 1 (f (- n 1)))))")
+                    .expect("error");
+                assert_eq!(interpreter.interpret("(f 700)"), Ok(Expr::Int(700)));
+                assert_eq!(
+                    interpreter.interpret("(f 100000)"),
+                    Err(Error::QuotaExceeded(Quota::RecursionDepth))
+                );
+            })
+            .expect("error")
+            .join()
+            .expect("error");
+    }
+
+    #[test]
+    fn test_defn() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret_all("(defn add (a b) (+ a b)) (defn inc \"Increments x.\" (x) (add x 1))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("(inc 2)"), Ok(Expr::Int(3)));
+        assert_eq!(
+            interpreter.to_source(),
+            "(def add (func (a b) (+ a b)))\n(def inc (func (x) (add x 1)))\n"
+        );
+        assert_eq!(
+            interpreter.interpret("(defn f 1 (x) x)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(defn f (x))"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+    }
+
+    #[test]
+    fn test_rest_argument() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(defn collect (a & rest) (append rest (list a)))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(collect 1 2 3)"),
+            Ok(Expr::List(vec![Expr::Int(2), Expr::Int(3), Expr::Int(1)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(collect 1)"),
+            Ok(Expr::List(vec![Expr::Int(1)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(collect)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.to_source(),
+            "(def collect (func (a & rest) (append rest (list a))))\n"
+        );
+        assert_eq!(
+            interpreter.interpret("(func (a & rest more) a)"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_optional_arguments() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(defn add (a (b 10) (c (* b 2)) & rest) (+ a b c))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("(add 1)"), Ok(Expr::Int(31)));
+        assert_eq!(interpreter.interpret("(add 1 2)"), Ok(Expr::Int(7)));
+        assert_eq!(interpreter.interpret("(add 1 2 3 4)"), Ok(Expr::Int(6)));
+        assert_eq!(
+            interpreter.interpret("(add)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.to_source(),
+            "(def add (func (a (b 10) (c (* b 2)) & rest) (+ a b c)))\n"
+        );
+
+        interpreter
+            .interpret("(defn greet ((name \"world\")) (concat \"hello \" name))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(greet)"),
+            Ok(Expr::Str("hello world".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(greet 1 2)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret("(func ((b 1) a) a)"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_lambda_body() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(defn f (x) (def y (+ x 1)) (* y 2))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("(f 2)"), Ok(Expr::Int(6)));
+        assert_eq!(interpreter.get("y"), None);
+        assert_eq!(
+            interpreter.to_source(),
+            "(def f (func (x) (def y (+ x 1)) (* y 2)))\n"
+        );
+        assert_eq!(
+            interpreter.interpret("(func (x))"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+    }
+
+    #[test]
+    fn test_do() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(do (def x 1) (def y (+ x 1)) (* y 3))"),
+            Ok(Expr::Int(6))
+        );
+        assert_eq!(interpreter.get("y"), Some(&Expr::Int(2)));
+        assert_eq!(interpreter.interpret("(do (def y 2) y)"), Ok(Expr::Int(2)));
+        assert_eq!(interpreter.interpret("y"), Ok(Expr::Int(2)));
+        assert_eq!(
+            interpreter.interpret("undefined"),
+            Err(Error::VariableNotDefined("undefined".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(let ((x 1)) z)"),
+            Err(Error::VariableNotDefined("z".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(if true zzz)"),
+            Err(Error::VariableNotDefined("zzz".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(do 1 zzz)"),
+            Err(Error::VariableNotDefined("zzz".to_string()))
+        );
+        assert_eq!(interpreter.interpret("(do)"), Ok(Expr::Nil));
+    }
+
+    #[test]
+    fn test_loop() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret(
+                "(loop ((i 0) (acc 0)) (case i (10000 acc) (else (recur (+ i 1) (+ acc i)))))"
+            ),
+            Ok(Expr::Int(49_995_000))
+        );
+        assert_eq!(
+            interpreter.interpret("(loop ((i 0)) (if (< i 3) (recur (+ i 1)) i))"),
+            Ok(Expr::Int(3))
+        );
+        assert_eq!(
+            interpreter.interpret("(loop ((i 1) (j (+ i 1))) (if (< i 3) (recur j (+ j 1)) j))"),
+            Ok(Expr::Int(4))
+        );
+        assert_eq!(
+            interpreter.interpret(
+                "(loop ((i 0)) (if (< i 2) (recur (+ i 1)) (loop ((j 0)) (if (< j 2) (recur (+ j 1)) (+ i j)))))"
+            ),
+            Ok(Expr::Int(4))
+        );
+        assert_eq!(
+            interpreter.interpret("(loop ((i 0)) (when (< i 1) (recur 1 2)))"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(interpreter.interpret("(recur 1)"), Err(Error::Recur));
+    }
+
+    #[test]
+    fn test_recur_tail_position() {
+        let mut interpreter = Interpreter::new();
+        for source in [
+            "(loop ((i 0)) (if (< i 3) (+ 100 (recur (+ i 1))) i))",
+            "(loop ((i 0)) (when (< i 3) (recur (+ i 1))) i)",
+            "(loop ((i 0)) (let ((j (recur 1))) j))",
+            "(loop ((i 0)) (if (recur 1) i))",
+            "(loop ((i 0)) (car (map (func (x) (recur x)) (list i))))",
+            "(loop ((i 0)) (with-handler (func (m) nil) (recur 1)))",
+        ] {
+            assert_eq!(
+                interpreter.interpret(source),
+                Err(Error::RecurNotInTailPosition),
+                "{source}"
+            );
+        }
+        assert_eq!(
+            interpreter.interpret("(loop ((i 0)) (case i (0 (recur 1)) (else (quote (recur)))))"),
+            Ok(Expr::List(vec![Expr::Symbol("recur".to_string())]))
+        );
+    }
+
+    #[test]
+    fn test_recur_function_boundary() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(defn next (i) (recur (+ i 1)))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(loop ((i 0)) (if (< i 3) (next i) i))"),
+            Err(Error::RecurNotInTailPosition)
+        );
+        assert_eq!(
+            interpreter.interpret("(loop ((i 0)) (if (< i 3) ((func () (recur 3))) i))"),
+            Err(Error::RecurNotInTailPosition)
+        );
+        // A loop within the lambda is the target of its `recur`.
+        interpreter
+            .interpret("(defn count-up (n) (loop ((i 0)) (if (< i n) (recur (+ i 1)) i)))")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret(
+                "(loop ((i 0) (acc 0)) (if (< i 3) (recur (+ i 1) (+ acc (count-up i))) acc))"
+            ),
+            Ok(Expr::Int(3))
+        );
+    }
+
+    #[test]
+    fn test_for_dotimes() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(for (x (quote (1 2 3))) (def y (* x 2)) (+ y 1))"),
+            Ok(Expr::List(vec![Expr::Int(3), Expr::Int(5), Expr::Int(7)]))
+        );
+        assert_eq!(interpreter.get("x"), None);
+        assert_eq!(interpreter.get("y"), None);
+        assert_eq!(
+            interpreter.interpret("(for (x 1) x)"),
+            Err(Error::UnexpectedType)
+        );
+
+        assert_eq!(
+            interpreter.interpret("(dotimes (i 3) (when (= i 2) (error i)))"),
+            Err(Error::Raised("2".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(dotimes (i 3) (+ i 1))"),
+            Ok(Expr::Nil)
+        );
+        assert_eq!(
+            interpreter.interpret("(dotimes (i (- 0 1)) (exit 1))"),
+            Ok(Expr::Nil)
+        );
+    }
+
+    #[test]
+    fn test_set() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def n 0)").unwrap();
+        interpreter
+            .interpret("(dotimes (i 4) (set! n (+ n i)))")
+            .unwrap();
+        assert_eq!(interpreter.get("n"), Some(&Expr::Int(6)));
+
+        interpreter
+            .interpret("(defn inc! () (let ((n 10)) (set! n 20)) (set! n (+ n 1)))")
+            .unwrap();
+        assert_eq!(interpreter.interpret("(inc!)"), Ok(Expr::Int(7)));
+        assert_eq!(interpreter.get("n"), Some(&Expr::Int(7)));
+
+        interpreter
+            .interpret("(let ((m 1)) (def m 2) (set! m 3))")
+            .unwrap();
+        assert_eq!(interpreter.get("m"), None);
+        assert_eq!(
+            interpreter.interpret("(set! m 1)"),
+            Err(Error::VariableNotDefined("m".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(set! 1 1)"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_get_callable_not_a_function() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("(def a 1)").expect("error");
+
+        assert!(matches!(
+            interpreter.get_callable("a"),
+            Err(Error::UnexpectedType)
+        ));
+        assert!(matches!(
+            interpreter.get_callable("b"),
+            Err(Error::FunctionNotDefined(_))
+        ));
     }
 }
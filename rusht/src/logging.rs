@@ -0,0 +1,224 @@
+//! Structured logging from scripts. The `log-info`, `log-warn` and
+//! `log-error` special forms pass a message and key-value fields, given as a
+//! map or as alternating keys and values, to the logger of the interpreter, which writes them to stderr by default. Hosts
+//! can attach a logger of their own to forward the records to their logging
+//! framework, e.g. `log` or `tracing`.
+use std::fmt::{Display, Formatter};
+
+use crate::expr::Expr;
+use crate::tokenize::Span;
+use crate::{Error, Result};
+
+/// The severity of a log record.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Info => write!(f, "INFO"),
+            Level::Warn => write!(f, "WARN"),
+            Level::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+/// A message logged by a script.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LogRecord {
+    pub level: Level,
+    pub message: String,
+    /// The key-value pairs passed after the message, in the given order,
+    /// or ordered by key, if passed as a map.
+    pub fields: Vec<(String, Expr)>,
+    /// The span of the top-level expression, that logged the record, within
+    /// the input, like the span of observed events.
+    pub span: Option<Span>,
+}
+
+impl Display for LogRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.level, self.message)?;
+        for (key, value) in &self.fields {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A logger receives the records logged by scripts.
+pub trait Logger {
+    /// Called for every record logged.
+    fn log(&self, record: &LogRecord);
+}
+
+/// The default logger, writing every record to stderr.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StderrLogger;
+
+impl Logger for StderrLogger {
+    fn log(&self, record: &LogRecord) {
+        eprintln!("{}", record);
+    }
+}
+
+impl<F> Logger for F
+where
+    F: Fn(&LogRecord),
+{
+    fn log(&self, record: &LogRecord) {
+        self(record);
+    }
+}
+
+/// Returns the record described by the interpreted arguments of a logging
+/// special form.
+///
+/// # Arguments
+///
+/// * `level` - The level of the special form.
+/// * `args[0]` - The message.
+/// * `args[1..]` - Either a map, whose keys are keywords or strings, or
+///     alternating keys, which are strings, and values.
+/// * `span` - The span of the logging expression.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If no message or a key without a value is
+///     passed.
+/// * `UnexpectedType` - If the message or a key is not a string, or a key of
+///     the map is neither a keyword nor a string.
+pub(crate) fn record(level: Level, args: Vec<Expr>, span: Option<Span>) -> Result<LogRecord> {
+    let mut args = args.into_iter();
+    let message = match args.next() {
+        Some(Expr::Str(message)) => message,
+        Some(_) => return Err(Error::UnexpectedType),
+        None => return Err(Error::InvalidNumberOfArguments),
+    };
+
+    let fields = match args.as_slice() {
+        [Expr::Map(map)] => map
+            .iter()
+            .map(|(key, value)| match key.as_expr() {
+                Expr::Keyword(key) | Expr::Str(key) => Ok((key.clone(), value.clone())),
+                _ => Err(Error::UnexpectedType),
+            })
+            .collect::<Result<_>>()?,
+        _ => {
+            let mut fields = vec![];
+            while let Some(key) = args.next() {
+                let Expr::Str(key) = key else {
+                    return Err(Error::UnexpectedType);
+                };
+                let value = args.next().ok_or(Error::InvalidNumberOfArguments)?;
+                fields.push((key, value));
+            }
+            fields
+        }
+    };
+    Ok(LogRecord {
+        level,
+        message,
+        fields,
+        span,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::Interpreter;
+
+    #[test]
+    fn logs_records() {
+        let records = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = Interpreter::new();
+        let sink = Rc::clone(&records);
+        interpreter.set_logger(move |record: &LogRecord| sink.borrow_mut().push(record.clone()));
+
+        interpreter
+            .interpret_all(r#"(log-info "started") (log-error "failed" "user" "bob" "id" (+ 1 2))"#)
+            .expect("error");
+        assert_eq!(
+            *records.borrow(),
+            vec![
+                LogRecord {
+                    level: Level::Info,
+                    message: "started".to_string(),
+                    fields: vec![],
+                    span: Some(0..20)
+                },
+                LogRecord {
+                    level: Level::Error,
+                    message: "failed".to_string(),
+                    fields: vec![
+                        ("user".to_string(), Expr::Str("bob".to_string())),
+                        ("id".to_string(), Expr::Int(3))
+                    ],
+                    span: Some(21..67)
+                }
+            ]
+        );
+        assert_eq!(
+            records.borrow()[1].to_string(),
+            "ERROR failed user=\"bob\" id=3"
+        );
+    }
+
+    #[test]
+    fn logs_map_fields() {
+        let records = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = Interpreter::new();
+        let sink = Rc::clone(&records);
+        interpreter.set_logger(move |record: &LogRecord| sink.borrow_mut().push(record.clone()));
+
+        interpreter
+            .interpret(r#"(log-info "msg" {:key 1 "user" "bob"})"#)
+            .expect("error");
+        assert_eq!(
+            records.borrow()[0].fields,
+            vec![
+                ("user".to_string(), Expr::Str("bob".to_string())),
+                ("key".to_string(), Expr::Int(1))
+            ]
+        );
+        interpreter
+            .eval(Expr::List(vec![
+                Expr::Ident("log-info".to_string()),
+                Expr::Str("msg".to_string()),
+            ]))
+            .expect("error");
+        assert_eq!(records.borrow()[1].span, None);
+    }
+
+    #[test]
+    fn invalid_records() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_logger(|_: &LogRecord| {});
+        assert_eq!(
+            interpreter.interpret("(log-warn)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret(r#"(log-warn "message" "key")"#),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret(r#"(log-warn "message" 1 2)"#),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret(r#"(log-warn "message" {1 2})"#),
+            Err(Error::UnexpectedType)
+        );
+    }
+}
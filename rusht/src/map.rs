@@ -0,0 +1,444 @@
+//! Maps from keys to values. Keys are integers, floats, characters, strings, keywords,
+//! symbols, identifiers, booleans, or lists of keys and are compared by value, like
+//! `equal?`. Maps are ordered by their keys, so they are displayed and
+//! iterated in the same order regardless of how they were built. Like lists,
+//! maps are copied when passed around, so `assoc` and `dissoc` return
+//! modified copies.
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::expr::Expr;
+use crate::number::Number;
+use crate::{BuiltinDoc, Env, Error, Result};
+
+/// A key of a map, i.e. an expression, which can be compared by value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key(Expr);
+
+impl Key {
+    /// Returns the given expression as a key.
+    ///
+    /// # Errors
+    ///
+    /// * `UnexpectedType` - If the expression is neither an integer, a
+    ///     ratio, a float, a character, a string, a keyword, a symbol, an identifier, a boolean, nor a list of such
+    ///     expressions.
+    pub fn new(expr: Expr) -> Result<Key> {
+        match expr {
+            // Zero is stored without sign, so that `0` and `-0` are the same
+            // key, as they are equal.
+            Expr::Num(x) if x == 0.0 => Ok(Key(Expr::Num(0.0))),
+            Expr::Int(_)
+            | Expr::Ratio(..)
+            | Expr::Num(_)
+            | Expr::Char(_)
+            | Expr::Str(_)
+            | Expr::Keyword(_)
+            | Expr::Symbol(_)
+            | Expr::Ident(_)
+            | Expr::Bool(_) => Ok(Key(expr)),
+            Expr::List(list) => {
+                let list = list
+                    .into_iter()
+                    .map(|expr| Key::new(expr).map(Key::into_expr))
+                    .collect::<Result<_>>()?;
+                Ok(Key(Expr::List(list)))
+            }
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    /// Returns this key as quoted data, see `Expr::quoted`.
+    #[must_use]
+    pub fn quoted(self) -> Key {
+        Key(self.0.quoted())
+    }
+
+    /// Returns the expression of this key.
+    #[must_use]
+    pub fn as_expr(&self) -> &Expr {
+        &self.0
+    }
+
+    /// Returns the expression of this key.
+    #[must_use]
+    pub fn into_expr(self) -> Expr {
+        self.0
+    }
+}
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Keys of different types are ordered by type, booleans first and lists
+/// last, except for integers and floats, which are ordered by value like
+/// `number::Number::total_cmp`. Keys of the same type are ordered by value.
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(&self.0, &other.0)
+    }
+}
+
+/// Compares the given expressions, which are valid keys.
+fn compare(a: &Expr, b: &Expr) -> Ordering {
+    if let (Some(a), Some(b)) = (Number::of(a), Number::of(b)) {
+        return a.total_cmp(&b);
+    }
+    match (a, b) {
+        (Expr::Bool(a), Expr::Bool(b)) => a.cmp(b),
+        (Expr::Char(a), Expr::Char(b)) => a.cmp(b),
+        (Expr::Str(a), Expr::Str(b))
+        | (Expr::Keyword(a), Expr::Keyword(b))
+        | (Expr::Symbol(a), Expr::Symbol(b))
+        | (Expr::Ident(a), Expr::Ident(b)) => a.cmp(b),
+        (Expr::List(a), Expr::List(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Returns the position of the type of the given key in the order of keys.
+fn rank(key: &Expr) -> usize {
+    match key {
+        Expr::Bool(_) => 0,
+        Expr::Int(_) | Expr::Ratio(..) | Expr::Num(_) => 1,
+        Expr::Char(_) => 2,
+        Expr::Str(_) => 3,
+        Expr::Keyword(_) => 4,
+        Expr::Symbol(_) => 5,
+        Expr::Ident(_) => 6,
+        _ => 7,
+    }
+}
+
+/// Defines the map builtins in the given environment.
+pub fn register(env: &mut Env) {
+    env.insert("hash-map".to_string(), Expr::Func(rusht_hash_map));
+    env.insert("get".to_string(), Expr::Func(|args| rusht_get(&args)));
+    env.insert("assoc".to_string(), Expr::Func(rusht_assoc));
+    env.insert("dissoc".to_string(), Expr::Func(rusht_dissoc));
+    env.insert(
+        "contains-key?".to_string(),
+        Expr::Func(|args| rusht_contains_key(&args)),
+    );
+    env.insert("keys".to_string(), Expr::Func(|args| rusht_keys(&args)));
+    env.insert("vals".to_string(), Expr::Func(|args| rusht_vals(&args)));
+    env.insert("merge".to_string(), Expr::Func(rusht_merge));
+}
+
+/// The documentation of the map builtins.
+pub const DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(hash-map key value...)",
+        description: "Returns a map of the given keys to the values following them.",
+    },
+    BuiltinDoc {
+        signature: "(get map key [default])",
+        description: "Returns the value of the key in the map, or the default (`nil` by default), if the map does not contain the key.",
+    },
+    BuiltinDoc {
+        signature: "(assoc map key value...)",
+        description: "Returns a copy of the map with the keys set to the values following them.",
+    },
+    BuiltinDoc {
+        signature: "(dissoc map key...)",
+        description: "Returns a copy of the map without the keys.",
+    },
+    BuiltinDoc {
+        signature: "(contains-key? map key)",
+        description: "Returns whether the map contains the key.",
+    },
+    BuiltinDoc {
+        signature: "(keys map)",
+        description: "Returns a list of the keys of the map in order.",
+    },
+    BuiltinDoc {
+        signature: "(vals map)",
+        description: "Returns a list of the values of the map in the order of their keys.",
+    },
+    BuiltinDoc {
+        signature: "(merge map...)",
+        description: "Returns a map of the entries of the given maps. Values of later maps replace those of earlier ones.",
+    },
+];
+
+/// Sets the keys to the values following them in the given map.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If a key has no value.
+/// * `UnexpectedType` - If a key is not a valid key.
+fn insert_pairs(map: &mut BTreeMap<Key, Expr>, args: Vec<Expr>) -> Result<()> {
+    let mut args = args.into_iter();
+    while let Some(key) = args.next() {
+        let value = args.next().ok_or(Error::InvalidNumberOfArguments)?;
+        map.insert(Key::new(key)?, value);
+    }
+    Ok(())
+}
+
+/// Returns a map of the given keys to the values following them. Later
+/// values replace earlier ones of the same key.
+///
+/// # Arguments
+/// * `args` - The keys, each followed by its value.
+///
+/// # Errors
+/// * `UnexpectedType` - If a key is not a valid key.
+/// * `InvalidNumberOfArguments` - If the number of arguments is odd.
+fn rusht_hash_map(args: Vec<Expr>) -> Result<Expr> {
+    let mut map = BTreeMap::new();
+    insert_pairs(&mut map, args)?;
+    Ok(Expr::Map(map))
+}
+
+/// Returns the value of a key in a map.
+///
+/// # Arguments
+/// * `args[0]` - The map.
+/// * `args[1]` - The key.
+/// * `args[2]` - The value returned, if the map does not contain the key.
+///     Defaults to `nil`.
+///
+/// # Errors
+/// * `UnexpectedType` - If the first argument is not a map or the key is
+///     not a valid key.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two or
+///     three.
+fn rusht_get(args: &[Expr]) -> Result<Expr> {
+    let (map, key, default) = match args {
+        [Expr::Map(map), key] => (map, key, Expr::Nil),
+        [Expr::Map(map), key, default] => (map, key, default.clone()),
+        [_, _] | [_, _, _] => return Err(Error::UnexpectedType),
+        &_ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let key = Key::new(key.clone())?;
+    Ok(map.get(&key).cloned().unwrap_or(default))
+}
+
+/// Sets keys to the values following them in a map. The operation is made
+/// out of place, meaning a modified copy of the map is returned.
+///
+/// # Arguments
+/// * `args[0]` - The map on which to operate.
+/// * `args[1..]` - The keys, each followed by its value.
+///
+/// # Errors
+/// * `UnexpectedType` - If the first argument is not a map or a key is not
+///     a valid key.
+/// * `InvalidNumberOfArguments` - If no map is passed or a key has no value.
+fn rusht_assoc(mut args: Vec<Expr>) -> Result<Expr> {
+    if args.is_empty() {
+        return Err(Error::InvalidNumberOfArguments);
+    }
+    let Expr::Map(mut map) = args.remove(0) else {
+        return Err(Error::UnexpectedType);
+    };
+    insert_pairs(&mut map, args)?;
+    Ok(Expr::Map(map))
+}
+
+/// Removes keys from a map. Like `assoc`, the operation is made out of
+/// place.
+///
+/// # Arguments
+/// * `args[0]` - The map on which to operate.
+/// * `args[1..]` - The keys to remove.
+///
+/// # Errors
+/// * `UnexpectedType` - If the first argument is not a map or a key is not
+///     a valid key.
+/// * `InvalidNumberOfArguments` - If no map is passed.
+fn rusht_dissoc(mut args: Vec<Expr>) -> Result<Expr> {
+    if args.is_empty() {
+        return Err(Error::InvalidNumberOfArguments);
+    }
+    let Expr::Map(mut map) = args.remove(0) else {
+        return Err(Error::UnexpectedType);
+    };
+    for key in args {
+        map.remove(&Key::new(key)?);
+    }
+    Ok(Expr::Map(map))
+}
+
+/// Returns whether a map contains a key.
+///
+/// # Arguments
+/// * `args[0]` - The map.
+/// * `args[1]` - The key.
+///
+/// # Errors
+/// * `UnexpectedType` - If the first argument is not a map or the key is
+///     not a valid key.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two.
+fn rusht_contains_key(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Map(map), key] => Ok(Expr::Bool(map.contains_key(&Key::new(key.clone())?))),
+        [_, _] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the keys of a map as a list.
+///
+/// # Arguments
+/// * `args[0]` - The map.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a map.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_keys(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Map(map)] => Ok(Expr::List(
+            map.keys().map(|key| key.as_expr().clone()).collect(),
+        )),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the values of a map as a list, ordered by their keys.
+///
+/// # Arguments
+/// * `args[0]` - The map.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a map.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_vals(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Map(map)] => Ok(Expr::List(map.values().cloned().collect())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Merges the given maps. Values of later maps replace the values of the
+/// same keys in earlier maps.
+///
+/// # Arguments
+/// * `args` - The maps to merge.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument is not a map.
+fn rusht_merge(args: Vec<Expr>) -> Result<Expr> {
+    let mut out = BTreeMap::new();
+    for arg in args {
+        let Expr::Map(map) = arg else {
+            return Err(Error::UnexpectedType);
+        };
+        out.extend(map);
+    }
+    Ok(Expr::Map(out))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Interpreter;
+
+    fn key(expr: Expr) -> Key {
+        Key::new(expr).expect("valid key")
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(key(Expr::Num(-0.0)), key(Expr::Num(0.0)));
+        assert_eq!(
+            Key::new(Expr::List(vec![Expr::Func(|_| Ok(Expr::Num(0.0)))])),
+            Err(Error::UnexpectedType)
+        );
+        let mut keys = vec![
+            key(Expr::List(vec![Expr::Num(1.0)])),
+            key(Expr::Str("a".to_string())),
+            key(Expr::Ident("a".to_string())),
+            key(Expr::Keyword("b".to_string())),
+            key(Expr::Symbol("c".to_string())),
+            key(Expr::Char('z')),
+            key(Expr::Num(2.0)),
+            key(Expr::List(vec![])),
+            key(Expr::Bool(false)),
+            key(Expr::Num(-1.0)),
+        ];
+        keys.sort();
+        assert_eq!(
+            keys.into_iter().map(Key::into_expr).collect::<Vec<_>>(),
+            vec![
+                Expr::Bool(false),
+                Expr::Num(-1.0),
+                Expr::Num(2.0),
+                Expr::Char('z'),
+                Expr::Str("a".to_string()),
+                Expr::Keyword("b".to_string()),
+                Expr::Symbol("c".to_string()),
+                Expr::Ident("a".to_string()),
+                Expr::List(vec![]),
+                Expr::List(vec![Expr::Num(1.0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn builtins() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(def m (hash-map \"b\" 2 \"a\" 1))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("(get m \"a\")"), Ok(Expr::Int(1)));
+        assert_eq!(interpreter.interpret("(get m \"c\")"), Ok(Expr::Nil));
+        assert_eq!(interpreter.interpret("(get m \"c\" 0)"), Ok(Expr::Int(0)));
+        assert_eq!(
+            interpreter.interpret("(contains-key? (dissoc m \"a\") \"a\")"),
+            Ok(Expr::Bool(false))
+        );
+        assert_eq!(
+            interpreter.interpret("(contains-key? m \"a\")"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.interpret("(equal? (assoc m \"c\" 3) (hash-map \"a\" 1 \"b\" 2 \"c\" 3))"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.interpret("(keys m)"),
+            Ok(Expr::List(vec![
+                Expr::Str("a".to_string()),
+                Expr::Str("b".to_string())
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(vals m)"),
+            Ok(Expr::List(vec![Expr::Int(1), Expr::Int(2)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(vals (merge m (hash-map \"b\" 3 \"c\" 4) (hash-map)))"),
+            Ok(Expr::List(vec![Expr::Int(1), Expr::Int(3), Expr::Int(4)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(merge)"),
+            interpreter.interpret("{}")
+        );
+        assert_eq!(
+            interpreter.interpret("(merge m (list))"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(hash-map \"a\")"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret("(get (list) \"a\")"),
+            Err(Error::UnexpectedType)
+        );
+    }
+}
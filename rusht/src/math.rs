@@ -0,0 +1,711 @@
+//! Math builtins of the prelude, complementing the arithmetic operators.
+//! Like those, the numeric functions keep integers and ratios exact, where
+//! possible, e.g. `(pow 4 -1)` is `1/4` and `(sqrt 4)` is `2`, and return
+//! floats otherwise. The integer functions accept integers and integral
+//! floats, e.g. `4.0`, and return integers. Like the arithmetic operators,
+//! they fall back to floats if the result is too large for an integer, and
+//! fail on division by zero, as does raising an exact zero to a negative
+//! power. The transcendental functions, e.g. `sin` and `ln`, always return
+//! floats, and the constants `pi` and `e` are floats as well. Float results
+//! may be infinite or not a number, which are written `inf`, `-inf`, and
+//! `nan`.
+use std::cmp::Ordering;
+use std::convert::{TryFrom, TryInto};
+use std::f64::consts;
+
+use crate::expr::Expr;
+use crate::number::{self, Number};
+use crate::{BuiltinDoc, Env, Error, Result};
+
+/// Defines the math builtins in the given environment.
+pub fn register(env: &mut Env) {
+    env.insert("quot".to_string(), Expr::Func(|args| rusht_quot(&args)));
+    env.insert("rem".to_string(), Expr::Func(|args| rusht_rem(&args)));
+    env.insert("gcd".to_string(), Expr::Func(|args| rusht_gcd(&args)));
+    env.insert("lcm".to_string(), Expr::Func(|args| rusht_lcm(&args)));
+    env.insert("sqrt".to_string(), Expr::Func(|args| rusht_sqrt(&args)));
+    env.insert("pow".to_string(), Expr::Func(|args| rusht_pow(&args)));
+    env.insert("abs".to_string(), Expr::Func(|args| rusht_abs(&args)));
+    env.insert(
+        "min".to_string(),
+        Expr::Func(|args| extremum(args, Ordering::Less)),
+    );
+    env.insert(
+        "max".to_string(),
+        Expr::Func(|args| extremum(args, Ordering::Greater)),
+    );
+    env.insert(
+        "floor".to_string(),
+        Expr::Func(|args| rounding(&args, i128::div_euclid, f64::floor)),
+    );
+    env.insert(
+        "ceil".to_string(),
+        Expr::Func(|args| rounding(&args, |a, b| -(-a).div_euclid(b), f64::ceil)),
+    );
+    env.insert(
+        "round".to_string(),
+        Expr::Func(|args| {
+            rounding(
+                &args,
+                |a, b| a.signum() * (2 * a.abs() + b).div_euclid(2 * b),
+                f64::round,
+            )
+        }),
+    );
+    env.insert(
+        "trunc".to_string(),
+        Expr::Func(|args| rounding(&args, |a, b| a / b, f64::trunc)),
+    );
+    env.insert("sin".to_string(), Expr::Func(|args| unary(&args, f64::sin)));
+    env.insert("cos".to_string(), Expr::Func(|args| unary(&args, f64::cos)));
+    env.insert("tan".to_string(), Expr::Func(|args| unary(&args, f64::tan)));
+    env.insert("atan2".to_string(), Expr::Func(|args| rusht_atan2(&args)));
+    env.insert("ln".to_string(), Expr::Func(|args| unary(&args, f64::ln)));
+    env.insert(
+        "log10".to_string(),
+        Expr::Func(|args| unary(&args, f64::log10)),
+    );
+    env.insert("exp".to_string(), Expr::Func(|args| unary(&args, f64::exp)));
+    env.insert("pi".to_string(), Expr::Num(consts::PI));
+    env.insert("e".to_string(), Expr::Num(consts::E));
+    env.insert(
+        "nan?".to_string(),
+        Expr::Func(|args| predicate(&args, f64::is_nan)),
+    );
+    env.insert(
+        "infinite?".to_string(),
+        Expr::Func(|args| predicate(&args, f64::is_infinite)),
+    );
+    env.insert(
+        "finite?".to_string(),
+        Expr::Func(|args| predicate(&args, f64::is_finite)),
+    );
+}
+
+/// The documentation of the math builtins.
+pub const DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(quot x y)",
+        description: "Divides the integer x by the integer y, truncating the quotient towards zero.",
+    },
+    BuiltinDoc {
+        signature: "(rem x y)",
+        description: "Returns the remainder of `quot`, which has the sign of x. Unlike `%`, it only accepts two integers.",
+    },
+    BuiltinDoc {
+        signature: "(gcd x...)",
+        description: "Returns the greatest common divisor of the integers, or 0 if none are given.",
+    },
+    BuiltinDoc {
+        signature: "(lcm x...)",
+        description: "Returns the least common multiple of the integers, or 1 if none are given.",
+    },
+    BuiltinDoc {
+        signature: "(sqrt x)",
+        description: "Returns the square root of the number, which is `nan` for negative numbers.",
+    },
+    BuiltinDoc {
+        signature: "(pow x y)",
+        description: "Raises the number x to the power y.",
+    },
+    BuiltinDoc {
+        signature: "(abs x)",
+        description: "Returns the absolute value of the number.",
+    },
+    BuiltinDoc {
+        signature: "(min x y...)",
+        description: "Returns the smallest of the given numbers.",
+    },
+    BuiltinDoc {
+        signature: "(max x y...)",
+        description: "Returns the largest of the given numbers.",
+    },
+    BuiltinDoc {
+        signature: "(floor x)",
+        description: "Returns the largest integer not greater than the number.",
+    },
+    BuiltinDoc {
+        signature: "(ceil x)",
+        description: "Returns the smallest integer not less than the number.",
+    },
+    BuiltinDoc {
+        signature: "(round x)",
+        description: "Returns the integer closest to the number, rounding halfway cases away from zero.",
+    },
+    BuiltinDoc {
+        signature: "(trunc x)",
+        description: "Returns the integer part of the number, i.e. rounds towards zero.",
+    },
+    BuiltinDoc {
+        signature: "(sin x)",
+        description: "Returns the sine of the angle in radians.",
+    },
+    BuiltinDoc {
+        signature: "(cos x)",
+        description: "Returns the cosine of the angle in radians.",
+    },
+    BuiltinDoc {
+        signature: "(tan x)",
+        description: "Returns the tangent of the angle in radians.",
+    },
+    BuiltinDoc {
+        signature: "(atan2 y x)",
+        description: "Returns the angle in radians between the positive x-axis and the point (x, y).",
+    },
+    BuiltinDoc {
+        signature: "(ln x)",
+        description: "Returns the natural logarithm of the number.",
+    },
+    BuiltinDoc {
+        signature: "(log10 x)",
+        description: "Returns the base 10 logarithm of the number.",
+    },
+    BuiltinDoc {
+        signature: "(exp x)",
+        description: "Returns `e` raised to the power of the number.",
+    },
+    BuiltinDoc {
+        signature: "pi",
+        description: "The ratio of a circle's circumference to its diameter, i.e. 3.14159...",
+    },
+    BuiltinDoc {
+        signature: "e",
+        description: "Euler's number, the base of the natural logarithm, i.e. 2.71828...",
+    },
+    BuiltinDoc {
+        signature: "(nan? x)",
+        description: "Returns whether the number is `nan`, i.e. not a number.",
+    },
+    BuiltinDoc {
+        signature: "(infinite? x)",
+        description: "Returns whether the number is `inf` or `-inf`.",
+    },
+    BuiltinDoc {
+        signature: "(finite? x)",
+        description: "Returns whether the number is neither infinite nor `nan`.",
+    },
+];
+
+/// Coerces the given arguments to integers.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to an integer.
+fn integers(args: &[Expr]) -> Result<Vec<i64>> {
+    args.iter().cloned().map(i64::try_from).collect()
+}
+
+/// Returns the dividend and the divisor of the given arguments.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to an integer.
+/// * `DivisionByZero` - If the divisor is zero.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two.
+fn division(args: &[Expr]) -> Result<(i64, i64)> {
+    match integers(args)?[..] {
+        [_, 0] => Err(Error::DivisionByZero),
+        [x, y] => Ok((x, y)),
+        _ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Divides two integers, truncating the quotient towards zero. The quotient
+/// is a float, if it is too large, i.e. the smallest integer is divided by
+/// -1.
+///
+/// # Arguments
+/// * `args[0]` - The dividend.
+/// * `args[1]` - The divisor.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to an integer.
+/// * `DivisionByZero` - If the divisor is zero.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two.
+#[allow(clippy::cast_precision_loss)]
+fn rusht_quot(args: &[Expr]) -> Result<Expr> {
+    let (x, y) = division(args)?;
+    Ok(x.checked_div(y)
+        .map_or_else(|| Expr::Num(x as f64 / y as f64), Expr::Int))
+}
+
+/// Returns the remainder of dividing two integers, which has the sign of the
+/// dividend, so that `(+ (* (quot x y) y) (rem x y))` is `x`.
+///
+/// # Arguments
+/// * `args[0]` - The dividend.
+/// * `args[1]` - The divisor.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to an integer.
+/// * `DivisionByZero` - If the divisor is zero.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two.
+fn rusht_rem(args: &[Expr]) -> Result<Expr> {
+    let (x, y) = division(args)?;
+    // The remainder is zero, even if the quotient overflows.
+    Ok(Expr::Int(x.wrapping_rem(y)))
+}
+
+/// Returns the greatest common divisor of the given integers, which is not
+/// negative. The divisor is a float, if it is too large, i.e. the integers
+/// are only zeros and the smallest integer.
+///
+/// # Arguments
+/// * `args` - The integers.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to an integer.
+#[allow(clippy::cast_precision_loss)]
+fn rusht_gcd(args: &[Expr]) -> Result<Expr> {
+    let gcd = integers(args)?
+        .into_iter()
+        .fold(0, |gcd, x| number::gcd(gcd, i128::from(x)));
+    Ok(i64::try_from(gcd).map_or(Expr::Num(gcd as f64), Expr::Int))
+}
+
+/// Returns the least common multiple of the given integers, which is not
+/// negative. Once the multiple is too large for an integer, it is computed
+/// on floats.
+///
+/// # Arguments
+/// * `args` - The integers.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to an integer.
+#[allow(clippy::cast_precision_loss)]
+fn rusht_lcm(args: &[Expr]) -> Result<Expr> {
+    let lcm = integers(args)?
+        .into_iter()
+        .fold(Number::Int(1), |lcm, x| match lcm {
+            Number::Int(lcm) => {
+                let (lcm, x) = (i128::from(lcm), i128::from(x));
+                let gcd = number::gcd(lcm, x);
+                let multiple = if gcd == 0 { 0 } else { (lcm / gcd * x).abs() };
+                i64::try_from(multiple).map_or(Number::Float(multiple as f64), Number::Int)
+            }
+            lcm => {
+                let (lcm, x) = (lcm.to_f64(), (x as f64).abs());
+                // The remainder of integral floats is exact.
+                let (mut a, mut b) = (lcm, x);
+                while b != 0.0 {
+                    (a, b) = (b, a % b);
+                }
+                Number::Float(if a == 0.0 { 0.0 } else { lcm / a * x })
+            }
+        });
+    Ok(Expr::from(lcm))
+}
+
+/// Returns the square root of a number.
+///
+/// # Arguments
+/// * `args[0]` - The number.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_sqrt(args: &[Expr]) -> Result<Expr> {
+    let [x] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let x = Number::try_from(x.clone())?;
+    let root = x.exact().and_then(number::sqrt);
+    Ok(Expr::from(
+        root.unwrap_or_else(|| Number::Float(x.to_f64().sqrt())),
+    ))
+}
+
+/// Raises a number to a power. The power is exact, if the base is exact and
+/// the exponent is an integer.
+///
+/// # Arguments
+/// * `args[0]` - The base.
+/// * `args[1]` - The exponent.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two.
+fn rusht_pow(args: &[Expr]) -> Result<Expr> {
+    let [base, exponent] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let base = Number::try_from(base.clone())?;
+    let exponent = Number::try_from(exponent.clone())?;
+    let power = match (base.exact(), exponent) {
+        (Some((0, _)), Number::Int(exponent)) if exponent < 0 => return Err(Error::DivisionByZero),
+        (Some(base), Number::Int(exponent)) => number::pow(base, exponent),
+        _ => None,
+    };
+    Ok(Expr::from(power.unwrap_or_else(|| {
+        Number::Float(base.to_f64().powf(exponent.to_f64()))
+    })))
+}
+
+/// Returns the absolute value of a number.
+///
+/// # Arguments
+/// * `args[0]` - The number.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_abs(args: &[Expr]) -> Result<Expr> {
+    let [x] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let x = Number::try_from(x.clone())?;
+    let abs = x.exact().and_then(|(a, b)| number::ratio(a.abs(), b));
+    Ok(Expr::from(
+        abs.unwrap_or_else(|| Number::Float(x.to_f64().abs())),
+    ))
+}
+
+/// Returns the smallest or largest of the given numbers, i.e. the first one,
+/// to which every other one is not in the given ordering.
+///
+/// # Arguments
+/// * `args` - The numbers.
+/// * `ordering` - `Less` for the smallest and `Greater` for the largest
+///     number.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If no arguments are passed.
+fn extremum(args: Vec<Expr>, ordering: Ordering) -> Result<Expr> {
+    args.into_iter()
+        .map(Number::try_from)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .reduce(|a, b| {
+            if b.partial_cmp(&a) == Some(ordering) {
+                b
+            } else {
+                a
+            }
+        })
+        .map(Expr::from)
+        .ok_or(Error::InvalidNumberOfArguments)
+}
+
+/// Rounds a number to an integer. Integers and ratios are rounded to
+/// integers, while floats are rounded to integral floats, e.g. `(floor 2.5)`
+/// is `2.0`, as they may be too large for integers.
+///
+/// # Arguments
+/// * `args[0]` - The number.
+/// * `exact` - Rounds the quotient of a numerator and a positive
+///     denominator.
+/// * `float` - Rounds a float.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rounding(args: &[Expr], exact: fn(i128, i128) -> i128, float: fn(f64) -> f64) -> Result<Expr> {
+    let [x] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let x = Number::try_from(x.clone())?;
+    let rounded = x.exact().and_then(|(a, b)| number::ratio(exact(a, b), 1));
+    Ok(Expr::from(
+        rounded.unwrap_or_else(|| Number::Float(float(x.to_f64()))),
+    ))
+}
+
+/// Applies a float function to a number.
+///
+/// # Arguments
+/// * `args[0]` - The number.
+/// * `func` - The function.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn unary(args: &[Expr], func: fn(f64) -> f64) -> Result<Expr> {
+    let [x] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let x: f64 = x.clone().try_into()?;
+    Ok(Expr::Num(func(x)))
+}
+
+/// Returns whether a number satisfies a float predicate. Integers and ratios
+/// are always finite.
+///
+/// # Arguments
+/// * `args[0]` - The number.
+/// * `predicate` - The predicate.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn predicate(args: &[Expr], predicate: fn(f64) -> bool) -> Result<Expr> {
+    let [x] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let x = Number::try_from(x.clone())?;
+    Ok(Expr::Bool(predicate(x.to_f64())))
+}
+
+/// Returns the angle between the positive x-axis and a point, i.e. the arc
+/// tangent of `y / x` in the quadrant of the point.
+///
+/// # Arguments
+/// * `args[0]` - The y-coordinate of the point.
+/// * `args[1]` - The x-coordinate of the point.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two.
+fn rusht_atan2(args: &[Expr]) -> Result<Expr> {
+    let [y, x] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let (y, x): (f64, f64) = (y.clone().try_into()?, x.clone().try_into()?);
+    Ok(Expr::Num(y.atan2(x)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Interpreter;
+
+    #[test]
+    fn division() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(list (quot 7 2) (quot (- 0 7) 2) (quot 7.0 (- 0 2)))"),
+            Ok(Expr::List(vec![Expr::Int(3), Expr::Int(-3), Expr::Int(-3)]))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (rem 7 2) (rem (- 0 7) 2) (rem 7 (- 0 2)) (% (- 0 7) 2))"),
+            Ok(Expr::List(vec![
+                Expr::Int(1),
+                Expr::Int(-1),
+                Expr::Int(1),
+                Expr::Int(-1)
+            ]))
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(list (% 7.5 2) (% 7.0 0))")
+                .map(|list| list.to_string()),
+            Ok("(1.5 nan)".to_string())
+        );
+        for program in ["(rem 7 0)", "(quot 7 0.0)", "(% 7 0)", "(% 1/2 0)"] {
+            assert_eq!(interpreter.interpret(program), Err(Error::DivisionByZero));
+        }
+        assert_eq!(
+            interpreter.interpret("(quot 7.5 2)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(quot 1/2 2)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(quot 7 2 1)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret("(quot -9223372036854775808 -1)"),
+            Ok(Expr::Num(9_223_372_036_854_775_808.0))
+        );
+        assert_eq!(
+            interpreter.interpret("(rem (- 0 9223372036854775807 1) (- 0 1))"),
+            Ok(Expr::Int(0))
+        );
+    }
+
+    #[test]
+    fn divisors() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(list (gcd 12 (- 0 18)) (gcd 7) (gcd 0 5) (gcd))"),
+            Ok(Expr::List(vec![
+                Expr::Int(6),
+                Expr::Int(7),
+                Expr::Int(5),
+                Expr::Int(0)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (lcm 4 (- 0 6)) (lcm 4 6 10) (lcm 3 0) (lcm))"),
+            Ok(Expr::List(vec![
+                Expr::Int(12),
+                Expr::Int(60),
+                Expr::Int(0),
+                Expr::Int(1)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(gcd -9223372036854775808)"),
+            Ok(Expr::Num(9_223_372_036_854_775_808.0))
+        );
+        assert_eq!(
+            interpreter.interpret("(lcm 9223372036854775807 2 4 0)"),
+            Ok(Expr::Num(0.0))
+        );
+        assert_eq!(
+            interpreter.interpret("(lcm 4611686018427387904 3 6)"),
+            Ok(Expr::Num(13_835_058_055_282_163_712.0))
+        );
+        assert_eq!(
+            interpreter.interpret("(gcd 1.5)"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter
+                .interpret("(list (sqrt 16) (sqrt 1/4) (sqrt 2.25) (sqrt 2) (sqrt (- 0 1)))")
+                .map(|list| list.to_string()),
+            Ok("(4 1/2 1.5 1.4142135623730951 nan)".to_string())
+        );
+        assert_eq!(
+            interpreter
+                .interpret("(list (pow 2 10) (pow 2 (- 0 2)) (pow 2/3 2) (pow 4 0.5) (pow 2 64))")
+                .map(|list| list.to_string()),
+            Ok("(1024 1/4 4/9 2.0 1.8446744073709552e19)".to_string())
+        );
+        assert_eq!(
+            interpreter.interpret("(list (abs (- 0 3)) (abs (- 0 1/2)) (abs (- 0 1.5)))"),
+            Ok(Expr::List(vec![
+                Expr::Int(3),
+                Expr::Ratio(1, 2),
+                Expr::Num(1.5)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (min 3 1/2 2.5) (max 3 1/2 2.5) (min 1 1.0) (max 7))"),
+            Ok(Expr::List(vec![
+                Expr::Ratio(1, 2),
+                Expr::Int(3),
+                Expr::Int(1),
+                Expr::Int(7)
+            ]))
+        );
+        for program in ["(sqrt)", "(sqrt 1 2)", "(pow 2)", "(abs)", "(min)", "(max)"] {
+            assert_eq!(
+                interpreter.interpret(program),
+                Err(Error::InvalidNumberOfArguments)
+            );
+        }
+        assert_eq!(
+            interpreter.interpret("(abs (list 1))"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn rounding() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(def xs (list 7/2 (- 0 7/2) 5/3 (- 0 5/3) 2.5 (- 0 2.5) 4))")
+            .expect("error");
+        for (func, expected) in [
+            ("floor", "(3 -4 1 -2 2.0 -3.0 4)"),
+            ("ceil", "(4 -3 2 -1 3.0 -2.0 4)"),
+            ("round", "(4 -4 2 -2 3.0 -3.0 4)"),
+            ("trunc", "(3 -3 1 -1 2.0 -2.0 4)"),
+        ] {
+            assert_eq!(
+                interpreter
+                    .interpret(&format!("(map {} xs)", func))
+                    .map(|list| list.to_string()),
+                Ok(expected.to_string())
+            );
+        }
+        assert_eq!(interpreter.interpret("(floor 7/2)"), Ok(Expr::Int(3)));
+        assert_eq!(interpreter.interpret("(floor 3.5)"), Ok(Expr::Num(3.0)));
+        assert_eq!(
+            interpreter.interpret("(round 1 2)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+    }
+
+    #[test]
+    fn transcendental() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(list (sin 0) (cos 0) (tan 0) (ln 1) (log10 1000) (exp 0))"),
+            Ok(Expr::List(vec![
+                Expr::Num(0.0),
+                Expr::Num(1.0),
+                Expr::Num(0.0),
+                Expr::Num(0.0),
+                Expr::Num(3.0),
+                Expr::Num(1.0)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(list pi e (ln e) (atan2 1 1) (* 4 (atan2 1 1)))"),
+            Ok(Expr::List(vec![
+                Expr::Num(consts::PI),
+                Expr::Num(consts::E),
+                Expr::Num(1.0),
+                Expr::Num(consts::FRAC_PI_4),
+                Expr::Num(consts::PI)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(atan2 1)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+        assert_eq!(
+            interpreter.interpret("(sin (list))"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn special_floats() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter
+                .interpret("(list (/ 1.0 0) (/ -1 0.0) (% 1 0.0) inf -inf nan)")
+                .map(|list| list.to_string()),
+            Ok("(inf -inf nan inf -inf nan)".to_string())
+        );
+        for program in ["(/ 1 0)", "(/ 1/2 0)", "(/ 1 2 0 1.0)", "(pow 0 -1)"] {
+            assert_eq!(interpreter.interpret(program), Err(Error::DivisionByZero));
+        }
+        assert_eq!(
+            interpreter.interpret("(pow 0.0 -1)"),
+            Ok(Expr::Num(f64::INFINITY))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (nan? nan) (nan? inf) (nan? 1/2))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(false),
+                Expr::Bool(false)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (infinite? -inf) (infinite? nan) (infinite? 1))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(false),
+                Expr::Bool(false)
+            ]))
+        );
+        assert_eq!(
+            interpreter.interpret("(list (finite? 1.5) (finite? inf) (finite? nan))"),
+            Ok(Expr::List(vec![
+                Expr::Bool(true),
+                Expr::Bool(false),
+                Expr::Bool(false)
+            ]))
+        );
+        let floats = Expr::Vector(vec![Expr::Num(f64::INFINITY), Expr::Num(f64::NEG_INFINITY)]);
+        assert_eq!(
+            interpreter.interpret(&floats.to_source().expect("floats can be written")),
+            Ok(floats)
+        );
+        assert_eq!(
+            interpreter.interpret("(nan? (list))"),
+            Err(Error::UnexpectedType)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Modules are script files, that are interpreted using
+//! `(require "name")`. A module is searched for as `name.rusht` in the
+//! directories of the search path, in order, and evaluated only once. Later
+//! requires of the same file return the cached value of its first
+//! evaluation. Names are plain file names, so modules can't be loaded from
+//! outside of the search path.
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::expr::Expr;
+use crate::{Error, Result};
+
+/// The extension of module files, which is appended to required names.
+pub const MODULE_FILE_SUFFIX: &str = ".rusht";
+
+/// The search path and the values of the already required modules.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Modules {
+    pub search_path: Vec<PathBuf>,
+    cache: HashMap<PathBuf, Expr>,
+}
+
+impl Modules {
+    /// Returns the path of the first file in the search path, that matches
+    /// the given module name.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidModuleName` - If the name is absolute, refers to a parent
+    ///     or the current directory, or contains a path separator.
+    /// * `ModuleNotFound` - If no directory of the search path contains the
+    ///     module.
+    pub fn resolve(&self, name: &str) -> Result<PathBuf> {
+        let mut components = Path::new(name).components();
+        if name.contains(std::path::is_separator)
+            || !matches!(components.next(), Some(Component::Normal(_)))
+            || components.next().is_some()
+        {
+            return Err(Error::InvalidModuleName(name.to_string()));
+        }
+        let file_name = if name.ends_with(MODULE_FILE_SUFFIX) {
+            name.to_string()
+        } else {
+            format!("{}{}", name, MODULE_FILE_SUFFIX)
+        };
+        self.search_path
+            .iter()
+            .map(|dir| dir.join(&file_name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| Error::ModuleNotFound(name.to_string()))
+    }
+
+    /// Returns the value of the module at the given path, if it has already
+    /// been required.
+    pub fn get(&self, path: &Path) -> Option<&Expr> {
+        self.cache.get(path)
+    }
+
+    /// Stores the value of the module at the given path.
+    pub fn insert(&mut self, path: PathBuf, value: Expr) {
+        self.cache.insert(path, value);
+    }
+
+    /// Returns the paths of the required modules.
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.cache.keys()
+    }
+
+    /// Forgets the module at the given path, so it is evaluated again when it
+    /// is required the next time.
+    pub fn remove(&mut self, path: &Path) {
+        self.cache.remove(path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve_in_order() {
+        let base = std::env::temp_dir().join(format!("rusht_module_{}", std::process::id()));
+        let (first, second) = (base.join("first"), base.join("second"));
+        std::fs::create_dir_all(&first).expect("failed to create directory");
+        std::fs::create_dir_all(&second).expect("failed to create directory");
+        std::fs::write(first.join("a.rusht"), "1").expect("failed to write file");
+        std::fs::write(second.join("a.rusht"), "2").expect("failed to write file");
+        std::fs::write(second.join("b.rusht"), "3").expect("failed to write file");
+
+        let modules = Modules {
+            search_path: vec![first.clone(), second.clone()],
+            ..Modules::default()
+        };
+        let resolved = (
+            modules.resolve("a"),
+            modules.resolve("b.rusht"),
+            modules.resolve("c"),
+        );
+        std::fs::remove_dir_all(&base).expect("failed to remove directory");
+
+        assert_eq!(resolved.0, Ok(first.join("a.rusht")));
+        assert_eq!(resolved.1, Ok(second.join("b.rusht")));
+        assert_eq!(resolved.2, Err(Error::ModuleNotFound("c".to_string())));
+    }
+
+    #[test]
+    fn resolve_rejects_paths() {
+        let modules = Modules {
+            search_path: vec![std::env::temp_dir()],
+            ..Modules::default()
+        };
+        for name in &["", ".", "..", "../a", "a/b", "/etc/passwd", "a/../b"] {
+            assert_eq!(
+                modules.resolve(name),
+                Err(Error::InvalidModuleName((*name).to_string()))
+            );
+        }
+    }
+}
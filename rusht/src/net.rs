@@ -0,0 +1,450 @@
+//! TCP and UDP socket builtins, available with the `net` feature. Listeners,
+//! connections and UDP sockets are passed to scripts as external handles,
+//! which are closed once the last reference to them is dropped. Connecting
+//! and listening, as well as sending and binding UDP sockets, are privileged
+//! operations, which have to be allowed by the policy.
+use std::convert::{TryFrom, TryInto};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+
+use crate::expr::{Expr, External};
+use crate::{BuiltinDoc, Env, Error, Result};
+
+/// Defines the socket builtins in the given environment.
+pub fn register(env: &mut Env) {
+    env.insert(
+        "tcp-connect".to_string(),
+        Expr::Func(|args| rusht_tcp_connect(&args)),
+    );
+    env.insert(
+        "tcp-listen".to_string(),
+        Expr::Func(|args| rusht_tcp_listen(&args)),
+    );
+    env.insert("accept".to_string(), Expr::Func(|args| rusht_accept(&args)));
+    env.insert(
+        "tcp-send".to_string(),
+        Expr::Func(|args| rusht_tcp_send(&args)),
+    );
+    env.insert(
+        "tcp-recv".to_string(),
+        Expr::Func(|args| rusht_tcp_recv(&args)),
+    );
+    env.insert(
+        "tcp-close".to_string(),
+        Expr::Func(|args| rusht_tcp_close(&args)),
+    );
+    env.insert(
+        "tcp-port".to_string(),
+        Expr::Func(|args| rusht_tcp_port(&args)),
+    );
+    env.insert(
+        "udp-bind".to_string(),
+        Expr::Func(|args| rusht_udp_bind(&args)),
+    );
+    env.insert(
+        "udp-send".to_string(),
+        Expr::Func(|args| rusht_udp_send(&args)),
+    );
+    env.insert(
+        "udp-recv".to_string(),
+        Expr::Func(|args| rusht_udp_recv(&args)),
+    );
+}
+
+/// The largest number of bytes received at once, so scripts can't allocate
+/// arbitrarily large buffers. It exceeds the largest UDP payload.
+const MAX_RECV_SIZE: usize = 64 * 1024;
+
+/// The documentation of the socket builtins.
+pub const DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(tcp-connect host port)",
+        description: "Opens a connection to the given host and port.",
+    },
+    BuiltinDoc {
+        signature: "(tcp-listen host port)",
+        description: "Returns a listener bound to the given address. Port 0 picks a free port.",
+    },
+    BuiltinDoc {
+        signature: "(accept listener)",
+        description: "Waits for and returns the next connection to the listener.",
+    },
+    BuiltinDoc {
+        signature: "(tcp-send connection data)",
+        description: "Sends the string and returns the number of bytes sent.",
+    },
+    BuiltinDoc {
+        signature: "(tcp-recv connection n)",
+        description: "Receives up to `n` (at most 65536) bytes as a string, which is empty once the peer closed the connection.",
+    },
+    BuiltinDoc {
+        signature: "(tcp-close connection)",
+        description: "Shuts down both directions of the connection.",
+    },
+    BuiltinDoc {
+        signature: "(tcp-port socket)",
+        description: "Returns the local port of a listener, connection, or UDP socket.",
+    },
+    BuiltinDoc {
+        signature: "(udp-bind host port)",
+        description: "Returns a UDP socket bound to the given address. Port 0 picks a free port.",
+    },
+    BuiltinDoc {
+        signature: "(udp-send socket host port data)",
+        description: "Sends the string as a datagram to the given address and returns the number of bytes sent.",
+    },
+    BuiltinDoc {
+        signature: "(udp-recv socket n)",
+        description: "Waits for a datagram and returns a list of up to `n` (at most 65536) bytes of it as a string, and the host and port of the sender.",
+    },
+];
+
+/// Returns the address described by the given host and port arguments.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the host can't be coerced to a string or the port
+///     to a number.
+/// * `NetworkFailed` - If the port is not a valid port number.
+fn address(host: &Expr, port: &Expr) -> Result<(String, u16)> {
+    let host: String = host.clone().try_into()?;
+    let port: f64 = port.clone().try_into()?;
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    let valid = port.fract() == 0.0 && (0.0..=f64::from(u16::MAX)).contains(&port);
+    if !valid {
+        return Err(Error::NetworkFailed(format!("invalid port {}", port)));
+    }
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    Ok((host, port as u16))
+}
+
+/// Returns the number of bytes to receive at once, which is capped at
+/// `MAX_RECV_SIZE`.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the number can't be coerced to a number.
+/// * `NetworkFailed` - If the number is not a positive integer.
+fn recv_size(n: &Expr) -> Result<usize> {
+    let n: f64 = n.clone().try_into()?;
+    if !(n.is_finite() && n.fract() == 0.0 && n >= 1.0) {
+        return Err(Error::NetworkFailed(format!(
+            "invalid number of bytes {}",
+            Expr::Num(n)
+        )));
+    }
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    Ok((n as usize).min(MAX_RECV_SIZE))
+}
+
+/// Returns the given number of bytes as an integer.
+fn byte_count(n: usize) -> Expr {
+    Expr::Int(i64::try_from(n).unwrap_or(i64::MAX))
+}
+
+/// Returns the value of the given type wrapped by the handle.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the expression is no handle to a `T`.
+fn handle<T: 'static>(expr: &Expr) -> Result<&T> {
+    match expr {
+        Expr::External(external) => external.downcast_ref().ok_or(Error::UnexpectedType),
+        _ => Err(Error::UnexpectedType),
+    }
+}
+
+/// Converts the given IO error into an error, that can be handled by scripts.
+#[allow(clippy::needless_pass_by_value)]
+fn network_failed(err: std::io::Error) -> Error {
+    Error::NetworkFailed(err.to_string())
+}
+
+/// Opens a connection to the given host and port.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two.
+/// * `UnexpectedType` - If the host or port have the wrong type.
+/// * `NetworkFailed` - If the connection could not be established.
+fn rusht_tcp_connect(args: &[Expr]) -> Result<Expr> {
+    let [host, port] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let stream = TcpStream::connect(address(host, port)?).map_err(network_failed)?;
+    Ok(Expr::External(External::new(stream)))
+}
+
+/// Returns a listener bound to the given host and port.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two.
+/// * `UnexpectedType` - If the host or port have the wrong type.
+/// * `NetworkFailed` - If the address could not be bound.
+fn rusht_tcp_listen(args: &[Expr]) -> Result<Expr> {
+    let [host, port] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let listener = TcpListener::bind(address(host, port)?).map_err(network_failed)?;
+    Ok(Expr::External(External::new(listener)))
+}
+
+/// Waits for the next connection to the given listener.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one.
+/// * `UnexpectedType` - If the argument is not a listener.
+/// * `NetworkFailed` - If accepting the connection failed.
+fn rusht_accept(args: &[Expr]) -> Result<Expr> {
+    let [listener] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let (stream, _) = handle::<TcpListener>(listener)?
+        .accept()
+        .map_err(network_failed)?;
+    Ok(Expr::External(External::new(stream)))
+}
+
+/// Sends the given string over the connection.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two.
+/// * `UnexpectedType` - If the first argument is not a connection.
+/// * `NetworkFailed` - If sending failed.
+fn rusht_tcp_send(args: &[Expr]) -> Result<Expr> {
+    let [stream, data] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let data: String = data.clone().try_into()?;
+    let mut stream = handle::<TcpStream>(stream)?;
+    stream.write_all(data.as_bytes()).map_err(network_failed)?;
+    Ok(byte_count(data.len()))
+}
+
+/// Receives up to the given number of bytes from the connection. Invalid
+/// UTF-8 sequences are replaced with the replacement character.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two.
+/// * `UnexpectedType` - If the first argument is not a connection or the
+///     second one can't be coerced to a number.
+/// * `NetworkFailed` - If the number of bytes is not a positive integer or
+///     receiving failed.
+fn rusht_tcp_recv(args: &[Expr]) -> Result<Expr> {
+    let [stream, n] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let mut buf = vec![0; recv_size(n)?];
+    let mut stream = handle::<TcpStream>(stream)?;
+    let read = stream.read(&mut buf).map_err(network_failed)?;
+    Ok(Expr::Str(
+        String::from_utf8_lossy(&buf[..read]).into_owned(),
+    ))
+}
+
+/// Shuts down both directions of the connection.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one.
+/// * `UnexpectedType` - If the argument is not a connection.
+/// * `NetworkFailed` - If shutting down failed.
+fn rusht_tcp_close(args: &[Expr]) -> Result<Expr> {
+    let [stream] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    handle::<TcpStream>(stream)?
+        .shutdown(std::net::Shutdown::Both)
+        .map_err(network_failed)?;
+    Ok(Expr::Bool(true))
+}
+
+/// Returns the local port of a listener, connection, or UDP socket.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one.
+/// * `UnexpectedType` - If the argument is neither a listener, a
+///     connection, nor a UDP socket.
+/// * `NetworkFailed` - If the local address could not be determined.
+fn rusht_tcp_port(args: &[Expr]) -> Result<Expr> {
+    let [socket] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let address = if let Ok(listener) = handle::<TcpListener>(socket) {
+        listener.local_addr()
+    } else if let Ok(stream) = handle::<TcpStream>(socket) {
+        stream.local_addr()
+    } else {
+        handle::<UdpSocket>(socket)?.local_addr()
+    };
+    Ok(Expr::Int(i64::from(
+        address.map_err(network_failed)?.port(),
+    )))
+}
+
+/// Returns a UDP socket bound to the given host and port.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two.
+/// * `UnexpectedType` - If the host or port have the wrong type.
+/// * `NetworkFailed` - If the address could not be bound.
+fn rusht_udp_bind(args: &[Expr]) -> Result<Expr> {
+    let [host, port] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let socket = UdpSocket::bind(address(host, port)?).map_err(network_failed)?;
+    Ok(Expr::External(External::new(socket)))
+}
+
+/// Sends the given string as a single datagram to the given host and port.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not four.
+/// * `UnexpectedType` - If the first argument is not a UDP socket or the
+///     host or port have the wrong type.
+/// * `NetworkFailed` - If sending failed, e.g. as the string is too long.
+fn rusht_udp_send(args: &[Expr]) -> Result<Expr> {
+    let [socket, host, port, data] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let data: String = data.clone().try_into()?;
+    let sent = handle::<UdpSocket>(socket)?
+        .send_to(data.as_bytes(), address(host, port)?)
+        .map_err(network_failed)?;
+    Ok(byte_count(sent))
+}
+
+/// Waits for the next datagram and returns a list of up to the given number
+/// of bytes of it as a string, and the host and port of the sender. The
+/// remainder of longer datagrams is discarded. Invalid UTF-8 sequences are
+/// replaced with the replacement character.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two.
+/// * `UnexpectedType` - If the first argument is not a UDP socket or the
+///     second one can't be coerced to a number.
+/// * `NetworkFailed` - If the number of bytes is not a positive integer or
+///     receiving failed.
+fn rusht_udp_recv(args: &[Expr]) -> Result<Expr> {
+    let [socket, n] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let mut buf = vec![0; recv_size(n)?];
+    let (read, sender) = handle::<UdpSocket>(socket)?
+        .recv_from(&mut buf)
+        .map_err(network_failed)?;
+    Ok(Expr::List(vec![
+        Expr::Str(String::from_utf8_lossy(&buf[..read]).into_owned()),
+        Expr::Str(sender.ip().to_string()),
+        Expr::Int(i64::from(sender.port())),
+    ]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Interpreter, Operation};
+
+    #[test]
+    fn send_and_receive() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret_all(
+                r#"(def server (tcp-listen "127.0.0.1" 0))
+                (def client (tcp-connect "127.0.0.1" (tcp-port server)))
+                (def conn (accept server))"#,
+            )
+            .expect("error");
+
+        assert_eq!(
+            interpreter.interpret(r#"(tcp-send client "ping")"#),
+            Ok(Expr::Int(4))
+        );
+        assert_eq!(
+            interpreter.interpret("(tcp-recv conn 16)"),
+            Ok(Expr::Str("ping".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(tcp-close client)"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.interpret("(tcp-recv conn 16)"),
+            Ok(Expr::Str(String::new()))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret(r#"(tcp-listen "127.0.0.1" 70000)"#),
+            Err(Error::NetworkFailed("invalid port 70000".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret(r#"(tcp-recv "socket" 1)"#),
+            Err(Error::UnexpectedType)
+        );
+        for n in &["0", "1.5", "(- 0 1)", "nan", "inf"] {
+            assert_eq!(
+                interpreter.interpret(format!(r#"(tcp-recv "socket" {})"#, n)),
+                Err(Error::NetworkFailed(format!(
+                    "invalid number of bytes {}",
+                    interpreter
+                        .interpret(format!("(+ 0.0 {})", n))
+                        .expect("error")
+                )))
+            );
+        }
+
+        interpreter.set_policy(|op, _: &[Expr]| op != Operation::Connect);
+        assert_eq!(
+            interpreter.interpret(r#"(tcp-connect "127.0.0.1" 1)"#),
+            Err(Error::OperationNotPermitted(Operation::Connect))
+        );
+        assert_eq!(
+            interpreter.interpret(r#"(udp-send (udp-bind "127.0.0.1" 0) "127.0.0.1" 1 "x")"#),
+            Err(Error::OperationNotPermitted(Operation::Connect))
+        );
+    }
+
+    #[test]
+    fn datagrams() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret_all(
+                r#"(def server (udp-bind "127.0.0.1" 0))
+                (def client (udp-bind "127.0.0.1" 0))"#,
+            )
+            .expect("error");
+
+        assert_eq!(
+            interpreter.interpret(r#"(udp-send client "127.0.0.1" (tcp-port server) "ping")"#),
+            Ok(Expr::Int(4))
+        );
+        assert_eq!(
+            interpreter.interpret("(udp-recv server 2)"),
+            Ok(Expr::List(vec![
+                Expr::Str("pi".to_string()),
+                Expr::Str("127.0.0.1".to_string()),
+                interpreter.interpret("(tcp-port client)").expect("error"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn port_conversion() {
+        let port = |n: f64| address(&Expr::Str("localhost".to_string()), &Expr::Num(n));
+        assert_eq!(port(80.0), Ok(("localhost".to_string(), 80)));
+        assert!(port(-1.0).is_err());
+        assert!(port(1.5).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+//! Numbers, i.e. integers, ratios, and floats. Number literals without a
+//! period are integers, unless they are too large, literals of two integers
+//! separated by a slash are ratios, e.g. `1/3`, and all others are floats.
+//! Integers and ratios are exact: arithmetic on them returns integers or
+//! ratios in lowest terms, e.g. `(/ 1 3)` is `1/3` and `(* 3 1/3)` is `1`,
+//! unless the result overflows, in which case it is computed on floats
+//! instead. A float among the operands promotes the other operands to
+//! floats, and values of other types are coerced to floats, e.g.
+//! `(+ true "5")` is `6.0`. Dividing an integer or ratio by an exact zero
+//! fails, while float arithmetic follows IEEE 754, e.g. `(/ 1 0)` is an
+//! error, but `(/ 1 0.0)` is `inf` and `(% 1.0 0)` is `nan`.
+use std::cmp::Ordering;
+use std::convert::{TryFrom, TryInto};
+
+use crate::expr::Expr;
+use crate::{Error, Result};
+
+/// Floats with a magnitude of at least this are displayed in scientific
+/// notation. Integral floats below are displayed with all their digits,
+/// which are exact, as they are less than 2^53.
+const SCIENTIFIC_ABOVE: f64 = 1e15;
+
+/// Non-zero floats with a magnitude below this are displayed in scientific
+/// notation.
+const SCIENTIFIC_BELOW: f64 = 1e-5;
+
+/// A number coerced from an expression for arithmetic or comparisons.
+#[derive(Debug, Clone, Copy)]
+pub enum Number {
+    Int(i64),
+    /// A ratio in lowest terms, whose denominator is greater than one.
+    Ratio(i64, i64),
+    Float(f64),
+}
+
+/// The numerator and denominator of an exact number, which are large enough
+/// to add or multiply two numerators or denominators without overflowing.
+pub type Exact = (i128, i128);
+
+impl Number {
+    /// Returns the number of the given expression without coercion, i.e.
+    /// `None`, if it is neither an integer, a ratio, nor a float.
+    #[must_use]
+    pub fn of(expr: &Expr) -> Option<Number> {
+        match expr {
+            Expr::Int(x) => Some(Number::Int(*x)),
+            Expr::Ratio(n, d) => Some(Number::Ratio(*n, *d)),
+            Expr::Num(x) => Some(Number::Float(*x)),
+            _ => None,
+        }
+    }
+
+    /// Returns this number as a float, rounding if necessary.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Number::Int(x) => x as f64,
+            Number::Ratio(n, d) => n as f64 / d as f64,
+            Number::Float(x) => x,
+        }
+    }
+
+    /// Returns the numerator and denominator of this number, if it is exact.
+    #[must_use]
+    pub fn exact(self) -> Option<Exact> {
+        match self {
+            Number::Int(x) => Some((i128::from(x), 1)),
+            Number::Ratio(n, d) => Some((i128::from(n), i128::from(d))),
+            Number::Float(_) => None,
+        }
+    }
+
+    /// Returns the ordering of this number and the given one, ordering NaN
+    /// like `f64::total_cmp` does. Exact numbers are compared exactly, exact
+    /// numbers and floats by value and exact numbers first, if the values
+    /// are equal.
+    #[must_use]
+    pub fn total_cmp(&self, other: &Number) -> Ordering {
+        match (self.exact(), other.exact()) {
+            (Some(a), Some(b)) => compare_exact(a, b),
+            (Some(_), None) => self
+                .to_f64()
+                .total_cmp(&other.to_f64())
+                .then(Ordering::Less),
+            (None, Some(_)) => other.total_cmp(self).reverse(),
+            (None, None) => self.to_f64().total_cmp(&other.to_f64()),
+        }
+    }
+}
+
+/// Returns the ordering of the given exact numbers.
+fn compare_exact((a, b): Exact, (c, d): Exact) -> Ordering {
+    // The denominators are positive, so multiplying by them keeps the order.
+    (a * d).cmp(&(c * b))
+}
+
+/// Returns the given float as it is displayed. Very large and small floats
+/// are written in scientific notation, e.g. `1e300` and `1.5e-7`, other ones
+/// in decimal notation, with a fractional part of zero if they are integral,
+/// e.g. `42.0` and `-0.5`. The shortest digits, that read back as the same
+/// float, are used, so tokenizing the output returns the same float.
+#[must_use]
+pub fn format_float(x: f64) -> String {
+    if x.is_nan() {
+        "nan".to_string()
+    } else if x.is_infinite() {
+        if x > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else if x != 0.0 && !(SCIENTIFIC_BELOW..SCIENTIFIC_ABOVE).contains(&x.abs()) {
+        format!("{:e}", x)
+    } else if x.fract() == 0.0 {
+        format!("{:.1}", x)
+    } else {
+        x.to_string()
+    }
+}
+
+/// Returns the number with the given numerator and denominator in lowest
+/// terms, or `None`, if the denominator is zero or the number is too large.
+#[must_use]
+pub fn ratio(numerator: i128, denominator: i128) -> Option<Number> {
+    if denominator == 0 {
+        return None;
+    }
+    let gcd = gcd(numerator, denominator);
+    let sign = denominator.signum();
+    let numerator = i64::try_from(numerator / gcd * sign).ok()?;
+    let denominator = i64::try_from(denominator / gcd * sign).ok()?;
+    Some(match denominator {
+        1 => Number::Int(numerator),
+        _ => Number::Ratio(numerator, denominator),
+    })
+}
+
+/// Returns the greatest common divisor of the given numbers, which is
+/// positive unless both are zero.
+#[must_use]
+pub fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Numbers are equal, if they have the same value, regardless of whether
+/// they are exact or floats.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.exact(), other.exact()) {
+            (Some(a), Some(b)) => Some(compare_exact(a, b)),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl TryFrom<Expr> for Number {
+    type Error = Error;
+
+    fn try_from(expr: Expr) -> Result<Self> {
+        match Number::of(&expr) {
+            Some(number) => Ok(number),
+            None => expr.try_into().map(Number::Float),
+        }
+    }
+}
+
+impl From<Number> for Expr {
+    fn from(number: Number) -> Self {
+        match number {
+            Number::Int(x) => Expr::Int(x),
+            Number::Ratio(n, d) => Expr::Ratio(n, d),
+            Number::Float(x) => Expr::Num(x),
+        }
+    }
+}
+
+/// Reduces the given arguments from left to right using the exact
+/// operation, as long as both operands are exact and the operation returns a
+/// result, or using the float operation otherwise.
+///
+/// # Arguments
+///
+/// * `args` - The operands.
+/// * `exact` - The exact operation on numerators and denominators, returning
+///     `None` if the result is not exact, e.g. on overflow or division by
+///     zero.
+/// * `float` - The float operation.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If an argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If no arguments are passed.
+pub fn arithmetic(
+    args: Vec<Expr>,
+    exact: fn(Exact, Exact) -> Option<Number>,
+    float: fn(f64, f64) -> f64,
+) -> Result<Expr> {
+    reduce(args, |a, b| Ok(apply(a, b, exact, float)))
+}
+
+/// Like `arithmetic`, but fails if an exact number is divided by an exact
+/// zero, instead of returning an infinite float or `nan`.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If an argument can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If no arguments are passed.
+/// * `DivisionByZero` - If an exact number is divided by an exact zero.
+pub fn division(
+    args: Vec<Expr>,
+    exact: fn(Exact, Exact) -> Option<Number>,
+    float: fn(f64, f64) -> f64,
+) -> Result<Expr> {
+    reduce(args, |a, b| match (a.exact(), b.exact()) {
+        (Some(_), Some((0, _))) => Err(Error::DivisionByZero),
+        _ => Ok(apply(a, b, exact, float)),
+    })
+}
+
+/// Coerces the given arguments to numbers and reduces them from left to
+/// right using the given operation.
+fn reduce(args: Vec<Expr>, op: impl Fn(Number, Number) -> Result<Number>) -> Result<Expr> {
+    let mut numbers = args
+        .into_iter()
+        .map(Number::try_from)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter();
+    let first = numbers.next().ok_or(Error::InvalidNumberOfArguments)?;
+    numbers.try_fold(first, op).map(Expr::from)
+}
+
+/// Applies the exact operation, if both operands are exact and the operation
+/// returns a result, or the float operation otherwise.
+fn apply(
+    a: Number,
+    b: Number,
+    exact: fn(Exact, Exact) -> Option<Number>,
+    float: fn(f64, f64) -> f64,
+) -> Number {
+    a.exact()
+        .zip(b.exact())
+        .and_then(|(x, y)| exact(x, y))
+        .unwrap_or_else(|| Number::Float(float(a.to_f64(), b.to_f64())))
+}
+
+/// Adds the given exact numbers.
+pub fn add((a, b): Exact, (c, d): Exact) -> Option<Number> {
+    ratio(a * d + c * b, b * d)
+}
+
+/// Subtracts the second exact number from the first one.
+pub fn sub((a, b): Exact, (c, d): Exact) -> Option<Number> {
+    ratio(a * d - c * b, b * d)
+}
+
+/// Multiplies the given exact numbers.
+pub fn mul((a, b): Exact, (c, d): Exact) -> Option<Number> {
+    ratio(a * c, b * d)
+}
+
+/// Divides the first exact number by the second one.
+pub fn div((a, b): Exact, (c, d): Exact) -> Option<Number> {
+    ratio(a * d, b * c)
+}
+
+/// Returns the remainder of dividing the first exact number by the second
+/// one, which has the sign of the first one, like `%` on floats.
+pub fn rem((a, b): Exact, (c, d): Exact) -> Option<Number> {
+    if c == 0 {
+        return None;
+    }
+    // The quotient truncated towards zero.
+    let quotient = (a * d) / (b * c);
+    ratio(a * d - quotient * c * b, b * d)
+}
+
+/// Raises the given exact number to the given power, returning `None` on
+/// overflow or if zero is raised to a negative power.
+pub fn pow((a, b): Exact, exponent: i64) -> Option<Number> {
+    let power = u32::try_from(exponent.unsigned_abs()).ok()?;
+    let (a, b) = (a.checked_pow(power)?, b.checked_pow(power)?);
+    if exponent < 0 {
+        ratio(b, a)
+    } else {
+        ratio(a, b)
+    }
+}
+
+/// Returns the square root of the given exact number, if it is exact, i.e.
+/// the numerator and denominator are perfect squares.
+pub fn sqrt((a, b): Exact) -> Option<Number> {
+    if a < 0 {
+        return None;
+    }
+    let (root_a, root_b) = (a.isqrt(), b.isqrt());
+    if root_a * root_a == a && root_b * root_b == b {
+        ratio(root_a, root_b)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn promotion() {
+        let add = |args| arithmetic(args, add, |a, b| a + b);
+        assert_eq!(add(vec![Expr::Int(1), Expr::Int(2)]), Ok(Expr::Int(3)));
+        assert_eq!(
+            add(vec![Expr::Int(1), Expr::Num(0.5), Expr::Int(2)]),
+            Ok(Expr::Num(3.5))
+        );
+        assert_eq!(
+            add(vec![Expr::Int(i64::MAX), Expr::Int(1)]),
+            Ok(Expr::Num(9_223_372_036_854_775_808.0))
+        );
+        assert_eq!(
+            add(vec![Expr::Bool(true), Expr::Int(1)]),
+            Ok(Expr::Num(2.0))
+        );
+        assert_eq!(
+            add(vec![Expr::Ratio(1, 2), Expr::Ratio(1, 3)]),
+            Ok(Expr::Ratio(5, 6))
+        );
+        assert_eq!(
+            add(vec![Expr::Ratio(1, 2), Expr::Num(0.25)]),
+            Ok(Expr::Num(0.75))
+        );
+        assert_eq!(add(vec![]), Err(Error::InvalidNumberOfArguments));
+    }
+
+    #[test]
+    fn exact() {
+        let expr = |number: Option<Number>| number.map(Expr::from);
+        assert_eq!(expr(div((6, 1), (3, 1))), Some(Expr::Int(2)));
+        assert_eq!(expr(div((2, 1), (-4, 1))), Some(Expr::Ratio(-1, 2)));
+        assert_eq!(expr(div((1, 1), (0, 1))), None);
+        assert_eq!(expr(mul((3, 1), (1, 3))), Some(Expr::Int(1)));
+        assert_eq!(expr(sub((1, 2), (1, 2))), Some(Expr::Int(0)));
+        assert_eq!(expr(rem((7, 2), (1, 1))), Some(Expr::Ratio(1, 2)));
+        assert_eq!(expr(rem((-7, 1), (2, 1))), Some(Expr::Int(-1)));
+        assert_eq!(expr(rem((1, 1), (0, 1))), None);
+        assert_eq!(expr(div((i128::from(i64::MIN), 1), (-1, 1))), None);
+        assert_eq!(expr(pow((2, 3), -2)), Some(Expr::Ratio(9, 4)));
+        assert_eq!(expr(pow((0, 1), -1)), None);
+        assert_eq!(expr(pow((2, 1), 63)), None);
+        assert_eq!(expr(sqrt((9, 4))), Some(Expr::Ratio(3, 2)));
+        assert_eq!(expr(sqrt((2, 1))), None);
+    }
+
+    #[test]
+    fn formatting() {
+        for (x, formatted) in [
+            (42.0, "42.0"),
+            (-0.5, "-0.5"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (123_456_789_012_345.0, "123456789012345.0"),
+            (1e15, "1e15"),
+            (-1.5e300, "-1.5e300"),
+            (0.00001, "0.00001"),
+            (1.5e-7, "1.5e-7"),
+            (0.0, "0.0"),
+            (-0.0, "-0.0"),
+            (f64::NEG_INFINITY, "-inf"),
+            (f64::NAN, "nan"),
+        ] {
+            assert_eq!(format_float(x), formatted);
+        }
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(Number::Int(1), Number::Float(1.0));
+        assert!(Number::Int(i64::MAX) > Number::Int(i64::MAX - 1));
+        assert!(Number::Int(1) < Number::Float(1.5));
+        assert!(Number::Ratio(1, 3) < Number::Ratio(1, 2));
+        assert!(Number::Ratio(-1, 2) < Number::Int(0));
+        assert_eq!(Number::Ratio(1, 2), Number::Float(0.5));
+        assert_eq!(
+            Number::Int(1).total_cmp(&Number::Float(1.0)),
+            Ordering::Less
+        );
+        assert_eq!(
+            Number::Float(2.0).total_cmp(&Number::Int(1)),
+            Ordering::Greater
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Observers receive a stream of events while a program is interpreted, e.g.
+//! to animate the evaluation of a program step by step. Besides implementing
+//! `Observer`, closures and the sending half of a channel can be used as
+//! observers, the latter to consume the events as an iterator:
+//!
+//! ```
+//! use std::sync::mpsc;
+//!
+//! let (sender, receiver) = mpsc::channel();
+//! let mut interpreter = rusht::Interpreter::new();
+//! interpreter.set_observer(sender);
+//! interpreter.interpret("(+ 1 2)").unwrap();
+//! assert_eq!(receiver.try_iter().count(), 2);
+//! ```
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+
+use crate::condition;
+use crate::expr::Expr;
+use crate::tokenize::Span;
+use crate::Error;
+
+/// An event during the interpretation of a program. Each event carries the
+/// span of the top-level expression, during whose interpretation it
+/// occurred, within the input passed to the interpreter, e.g. the contents
+/// of a loaded file. Expressions passed to `Interpreter::eval` have no span.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A builtin or lambda is about to be called with the given, already
+    /// interpreted arguments.
+    EnterCall {
+        name: String,
+        args: Vec<Expr>,
+        /// The number of calls, that have not returned yet.
+        depth: usize,
+        span: Option<Span>,
+    },
+    /// A call returned a value. Calls failing with an error do not return.
+    Return {
+        name: String,
+        value: Expr,
+        /// The number of calls, that have not returned yet, excluding this
+        /// one.
+        depth: usize,
+        span: Option<Span>,
+    },
+    /// A variable was defined using `def` or assigned using `set!`.
+    Define {
+        name: String,
+        value: Expr,
+        span: Option<Span>,
+    },
+    /// Interpreting an expression failed and the error reached the top
+    /// level. Only the innermost expression is reported, not the ones the
+    /// error propagates through. Errors handled before, e.g. using a
+    /// restart, and the unwinding done by `exit`, `recur`, and restarts are
+    /// not reported.
+    Error {
+        expr: Expr,
+        error: Error,
+        span: Option<Span>,
+    },
+}
+
+/// An observer is notified about every event during interpretation.
+pub trait Observer {
+    /// Called for every event in the order they occur.
+    fn on_event(&self, event: &Event);
+}
+
+impl<F> Observer for F
+where
+    F: Fn(&Event),
+{
+    fn on_event(&self, event: &Event) {
+        self(event);
+    }
+}
+
+/// Events are sent to the receiving half of the channel. Events occurring
+/// after the receiver has been dropped are discarded.
+impl Observer for Sender<Event> {
+    fn on_event(&self, event: &Event) {
+        let _ = self.send(event.clone());
+    }
+}
+
+/// The state of the attached observer.
+pub(crate) struct Observing {
+    observer: Rc<dyn Observer>,
+    /// The number of calls, that have not returned yet.
+    depth: usize,
+    /// The error event of the innermost expression, that failed, which is
+    /// reported once the error reaches the top level.
+    failure: Option<Event>,
+}
+
+impl Observing {
+    pub(crate) fn new(observer: Rc<dyn Observer>) -> Observing {
+        Observing {
+            observer,
+            depth: 0,
+            failure: None,
+        }
+    }
+
+    /// Reports a call about to be made.
+    pub(crate) fn enter_call(&mut self, name: &str, args: &[Expr], span: Option<Span>) {
+        self.observer.on_event(&Event::EnterCall {
+            name: name.to_string(),
+            args: args.to_vec(),
+            depth: self.depth,
+            span,
+        });
+        self.depth += 1;
+    }
+
+    /// Reports the value returned by a call, unless the call failed.
+    pub(crate) fn leave_call(&mut self, name: &str, out: &Result<Expr, Error>, span: Option<Span>) {
+        self.depth -= 1;
+        if let Ok(value) = out {
+            self.observer.on_event(&Event::Return {
+                name: name.to_string(),
+                value: value.clone(),
+                depth: self.depth,
+                span,
+            });
+        }
+    }
+
+    /// Reports a definition.
+    pub(crate) fn define(&self, name: &str, value: &Expr, span: Option<Span>) {
+        self.observer.on_event(&Event::Define {
+            name: name.to_string(),
+            value: value.clone(),
+            span,
+        });
+    }
+
+    /// Remembers the given error, if it is the first one since the last
+    /// error was handled or reached the top level. The unwinding done by
+    /// `exit`, `recur`, and restarts is ignored, see `condition::is_signaled`.
+    pub(crate) fn fail(&mut self, expr: Expr, error: &Error, span: Option<Span>) {
+        if self.failure.is_none() && condition::is_signaled(error) {
+            self.failure = Some(Event::Error {
+                expr,
+                error: error.clone(),
+                span,
+            });
+        }
+    }
+
+    /// Forgets the remembered error, as an expression has been interpreted
+    /// successfully since, so the error has been handled.
+    pub(crate) fn recover(&mut self) {
+        self.failure = None;
+    }
+
+    /// Reports the remembered error, which has reached the top level, and
+    /// resets the state.
+    pub(crate) fn report(&mut self) {
+        if let Some(event) = self.failure.take() {
+            self.observer.on_event(&event);
+        }
+        self.depth = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::Interpreter;
+
+    fn call(name: &str, args: Vec<Expr>, depth: usize) -> Event {
+        Event::EnterCall {
+            name: name.to_string(),
+            args,
+            depth,
+            span: Some(0..14),
+        }
+    }
+
+    fn ret(name: &str, value: Expr, depth: usize) -> Event {
+        Event::Return {
+            name: name.to_string(),
+            value,
+            depth,
+            span: Some(0..14),
+        }
+    }
+
+    #[test]
+    fn observes_calls() {
+        let (sender, receiver) = mpsc::channel();
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(def sq (func (x) (* x x)))")
+            .expect("error");
+        interpreter.set_observer(sender);
+        interpreter.interpret("(def a (sq 3))").expect("error");
+
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                call("sq", vec![Expr::Int(3)], 0),
+                call("*", vec![Expr::Int(3), Expr::Int(3)], 1),
+                ret("*", Expr::Int(9), 1),
+                ret("sq", Expr::Int(9), 0),
+                Event::Define {
+                    name: "a".to_string(),
+                    value: Expr::Int(9),
+                    span: Some(0..14)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn observes_innermost_error() {
+        let (sender, receiver) = mpsc::channel();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_observer(sender);
+
+        let error = Error::FunctionNotDefined("foo".to_string());
+        assert_eq!(
+            interpreter.interpret("(+ 1 (- 2 (foo)))"),
+            Err(error.clone())
+        );
+        assert_eq!(
+            interpreter.interpret("(def x 1)\n(foo)"),
+            Err(error.clone())
+        );
+        let events = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(
+            events[0],
+            Event::Error {
+                expr: Expr::List(vec![Expr::Ident("foo".to_string())]),
+                error: error.clone(),
+                span: Some(0..17)
+            }
+        );
+        assert_eq!(
+            events[2],
+            Event::Error {
+                expr: Expr::List(vec![Expr::Ident("foo".to_string())]),
+                error,
+                span: Some(10..15)
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_handled_errors() {
+        let (sender, receiver) = mpsc::channel();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_observer(sender);
+
+        interpreter
+            .interpret(
+                "(with-restart (skip (func () 0))
+                   (with-handler (func (m) (invoke-restart skip)) (error \"bad\")))",
+            )
+            .expect("error");
+        interpreter
+            .interpret("(loop ((i 0)) (if (< i 2) (recur (+ i 1)) i))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("(foo)").ok(), None);
+        assert_eq!(interpreter.interpret("(exit 1)"), Err(Error::Exit(1)));
+
+        let errors = receiver
+            .try_iter()
+            .filter_map(|event| match event {
+                Event::Error { error, .. } => Some(error),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(errors, vec![Error::FunctionNotDefined("foo".to_string())]);
+    }
+}
@@ -3,16 +3,22 @@
 //! The input gets splitted by going through the tokenstream and
 //! split each stream's list correctly by parsing it to one knot
 //! with the inside order to manage the right final execution.
+//! Braces enclose map literals of keys, which are not interpreted, each
+//! followed by its value, e.g. `{:name "marc" :age 3}`, so identifiers used
+//! as keys are read as symbols, and brackets enclose
+//! vector literals, e.g. `[1 2 3]`.
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::iter::Peekable;
 
 use crate::expr::Expr;
-use crate::tokenize::Token;
+use crate::map::Key;
+use crate::tokenize::{closing, Lexer, Span, Token};
 use crate::Error;
 use crate::Result;
 
-/// Creates an abstract syntax tree from the given (non-empty) token stream.
-/// Here we iterate throught the tokenstream and call
+/// Creates an abstract syntax tree for each of the top-level expressions in
+/// the given token stream, e.g. the contents of a file.
 ///
 /// # Arguments
 ///
@@ -20,16 +26,101 @@ use crate::Result;
 ///
 /// # Errors
 ///
-/// * `UnexpectedEndOfTokenStream` - If the given token stream is empty.
 /// * `MissingClosingParenthesis` - If the number of opening braces exceeds the
 ///     number of closing braces.
 /// * `UnexpectedClosingParenthesis` - If the number of closing braces exceeds
 ///     the number of opening braces.
-pub fn parse<T>(token_stream: T) -> Result<Expr>
+/// * `MismatchedParenthesis` - If an expression is closed by a different
+///     kind of brace than it was opened with.
+pub fn parse_all<T>(token_stream: T) -> Result<Vec<Expr>>
 where
     T: IntoIterator<Item = Token>,
 {
-    parse_it(&mut token_stream.into_iter().peekable())
+    let mut token_stream = token_stream.into_iter().peekable();
+    let mut exprs = vec![];
+    while token_stream.peek().is_some() {
+        exprs.push(parse_it(&mut token_stream)?);
+    }
+    Ok(exprs)
+}
+
+/// Like `parse_all`, but additionally returns the span of each top-level
+/// expression, from the start of its first to the end of its last token.
+///
+/// # Arguments
+///
+/// * `token_stream` - The tokens to be parsed together with their spans.
+///
+/// # Errors
+///
+/// This function can return all the errors returned by `parse_all`.
+pub fn parse_all_spanned<T>(token_stream: T) -> Result<Vec<(Expr, Span)>>
+where
+    T: IntoIterator<Item = (Token, Span)>,
+{
+    let mut token_stream = token_stream.into_iter().peekable();
+    let mut exprs = vec![];
+    while let Some((_, span)) = token_stream.peek() {
+        let start = span.start;
+        let mut end = start;
+        let mut tokens = token_stream
+            .by_ref()
+            .map(|(token, span)| {
+                end = span.end;
+                token
+            })
+            .peekable();
+        let expr = parse_it(&mut tokens)?;
+        exprs.push((expr, start..end));
+    }
+    Ok(exprs)
+}
+
+/// Returns all syntax errors in the given input together with their
+/// location. Unlike `tokenize` and `parse`, this function does not stop at
+/// the first error. An empty vector is returned for valid inputs.
+///
+/// # Arguments
+///
+/// * `input` - The source code to check.
+pub fn syntax_errors(input: &str) -> Vec<(Error, Span)> {
+    let mut errors = vec![];
+    // The closing parentheses, braces, and brackets matching the opening
+    // ones, which have not been closed yet.
+    let mut open_parens: Vec<(char, Span)> = vec![];
+    // The span of the last token, if it is a quote.
+    let mut quote = None;
+
+    for (token, span) in Lexer::new(input) {
+        let quoted = quote.take().is_some();
+        match token {
+            Ok(Token::Paren(paren)) => match closing(paren) {
+                Some(close) => open_parens.push((close, span)),
+                // A quote must be followed by an expression.
+                None => match open_parens.pop() {
+                    Some((close, _)) if close == paren && !quoted => {}
+                    Some((close, _)) if !quoted => {
+                        errors.push((Error::MismatchedParenthesis(close, paren), span));
+                    }
+                    _ => errors.push((Error::UnexpectedClosingParenthesis, span)),
+                },
+            },
+            Ok(Token::Quote(_)) => quote = Some(span),
+            Err(err) => errors.push((err, span)),
+            Ok(_) => {}
+        }
+    }
+    if let Some(span) = quote {
+        errors.push((Error::UnexpectedEndOfTokenStream, span));
+    }
+    errors.extend(
+        open_parens
+            .into_iter()
+            .map(|(_, span)| (Error::MissingClosingParenthesis, span)),
+    );
+
+    errors.sort_by_key(|(_, span)| span.start);
+    errors
 }
 
 /// Creates an abstract syntax tree from the given iterator of tokens.
@@ -46,6 +137,11 @@ where
 ///     number of closing braces.
 /// * `UnexpectedClosingParenthesis` - If the number of closing braces exceeds
 ///     the number of opening braces.
+/// * `MismatchedParenthesis` - If an expression is closed by a different
+///     kind of brace than it was opened with.
+/// * `MissingMapValue` - If a map literal contains an odd number of
+///     expressions.
+/// * `UnexpectedType` - If a key of a map literal is a map.
 fn parse_it<T>(token_stream: &mut Peekable<T>) -> Result<Expr>
 where
     T: Iterator<Item = Token>,
@@ -54,13 +150,30 @@ where
         .next()
         .ok_or(Error::UnexpectedEndOfTokenStream)?
     {
-        Token::Paren('(') => parse_nested_expression(token_stream),
-        Token::Paren(')') => Err(Error::UnexpectedClosingParenthesis),
+        Token::Paren('(') => parse_nested_expression(token_stream, ')').map(Expr::List),
+        Token::Paren('{') => parse_map(token_stream),
+        Token::Paren('[') => parse_nested_expression(token_stream, ']').map(Expr::Vector),
+        Token::Paren(_) => Err(Error::UnexpectedClosingParenthesis),
+        Token::Quote(prefix) => parse_it(token_stream).map(|expr| quote(prefix, expr)),
         atom => atom.try_into(),
     }
 }
 
-/// Parses a nested expression from the given token stream.
+/// Expands the given expression quoted by the given prefix to the
+/// corresponding form, which takes the expression as its only argument, e.g.
+/// `'x` is read as `(quote x)`, `'(1 2)` as `(quote (1 2))` and `,(f x)` as
+/// `(unquote (f x))`.
+fn quote(prefix: &str, expr: Expr) -> Expr {
+    let form = match prefix {
+        "'" => "quote",
+        "`" => "quasiquote",
+        ",@" => "unquote-splicing",
+        _ => "unquote",
+    };
+    Expr::List(vec![Expr::Ident(form.to_string()), expr])
+}
+
+/// Parses the elements of a nested expression from the given token stream.
 ///
 /// An expression begins at each opening brace and ends at the matching closing
 /// brace.
@@ -68,26 +181,62 @@ where
 /// # Arguments
 ///
 /// * `token_stream` - A peekable iterator, containing the tokens to be parsed.
+/// * `close` - The closing parenthesis or brace ending the expression.
 ///
 /// # Errors
 ///
 /// * `MissingClosingParenthesis` - If the number of opening braces exceeds the
 ///     number of closing braces.
+/// * `MismatchedParenthesis` - If the expression is closed by a different
+///     kind of brace.
 #[inline]
-fn parse_nested_expression<T>(token_stream: &mut Peekable<T>) -> Result<Expr>
+fn parse_nested_expression<T>(token_stream: &mut Peekable<T>, close: char) -> Result<Vec<Expr>>
 where
     T: Iterator<Item = Token>,
 {
     let mut list = vec![];
-    while *token_stream
-        .peek()
-        .ok_or(Error::MissingClosingParenthesis)?
-        != Token::Paren(')')
-    {
-        list.push(parse_it(token_stream)?);
+    loop {
+        match token_stream
+            .peek()
+            .ok_or(Error::MissingClosingParenthesis)?
+        {
+            Token::Paren(c) if *c == close => break,
+            Token::Paren(c) if closing(*c).is_none() => {
+                return Err(Error::MismatchedParenthesis(close, *c));
+            }
+            _ => list.push(parse_it(token_stream)?),
+        }
     }
     token_stream.next();
-    Ok(Expr::List(list))
+    Ok(list)
+}
+
+/// Parses a map literal from the given token stream, following its opening
+/// brace. Keys are quoted and later values replace earlier ones of the same
+/// key.
+///
+/// # Arguments
+///
+/// * `token_stream` - A peekable iterator, containing the tokens to be parsed.
+///
+/// # Errors
+///
+/// * `MissingClosingParenthesis` - If the closing brace is missing.
+/// * `MismatchedParenthesis` - If the map is closed by a different kind of
+///     brace.
+/// * `MissingMapValue` - If the last key has no value.
+/// * `UnexpectedType` - If a key is a map.
+fn parse_map<T>(token_stream: &mut Peekable<T>) -> Result<Expr>
+where
+    T: Iterator<Item = Token>,
+{
+    let mut map = BTreeMap::new();
+    let mut exprs = parse_nested_expression(token_stream, '}')?.into_iter();
+    while let Some(key) = exprs.next() {
+        let value = exprs.next().ok_or(Error::MissingMapValue)?;
+        map.insert(Key::new(key.quoted())?, value);
+    }
+    Ok(Expr::Map(map))
 }
 
 #[cfg(test)]
@@ -95,6 +244,10 @@ mod test {
     use super::Token::*;
     use super::*;
 
+    fn parse(token_stream: Vec<Token>) -> Result<Expr> {
+        parse_it(&mut token_stream.into_iter().peekable())
+    }
+
     macro_rules! test_parse {
         ($($name:ident: $input:expr => $expected:expr),*) => {
             $(
@@ -106,6 +259,31 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_parse_all_spanned() {
+        let input = "(+ 1 2)\n  'a";
+        assert_eq!(
+            parse_all_spanned(crate::tokenize::tokenize_spanned(input).unwrap()),
+            Ok(vec![
+                (
+                    Expr::List(vec![
+                        Expr::Ident("+".to_string()),
+                        Expr::Int(1),
+                        Expr::Int(2)
+                    ]),
+                    0..7
+                ),
+                (
+                    Expr::List(vec![
+                        Expr::Ident("quote".to_string()),
+                        Expr::Ident("a".to_string())
+                    ]),
+                    10..12
+                ),
+            ])
+        );
+    }
+
     test_parse!(
         test_empty: vec![Paren('('), Paren(')')] => Ok(Expr::List(vec![])),
         test_single: vec![Paren('('), Num(4.0), Paren(')')] => Ok(Expr::List(vec![Expr::Num(4.0)])),
@@ -158,8 +336,114 @@ mod test {
             Expr::Num(2.0),
             Expr::Num(4.0)
         ])),
+        test_quote: vec![Quote("'"), Paren('('), Num(1.0), Quote("'"), Paren('('), Paren(')'), Paren(')')] => Ok(Expr::List(vec![
+            Expr::Ident("quote".to_string()),
+            Expr::List(vec![
+                Expr::Num(1.0),
+                Expr::List(vec![Expr::Ident("quote".to_string()), Expr::List(vec![])])
+            ])
+        ])),
+        test_quote_atom: vec![Quote("'"), Ident("a".to_string())] => Ok(Expr::List(vec![
+            Expr::Ident("quote".to_string()),
+            Expr::Ident("a".to_string())
+        ])),
+        test_quasiquote: vec![
+            Quote("`"),
+            Paren('('),
+            Quote(","),
+            Ident("a".to_string()),
+            Quote(",@"),
+            Paren('('),
+            Ident("b".to_string()),
+            Paren(')'),
+            Paren(')')
+        ] => Ok(Expr::List(vec![
+            Expr::Ident("quasiquote".to_string()),
+            Expr::List(vec![
+                Expr::List(vec![
+                    Expr::Ident("unquote".to_string()),
+                    Expr::Ident("a".to_string())
+                ]),
+                Expr::List(vec![
+                    Expr::Ident("unquote-splicing".to_string()),
+                    Expr::List(vec![Expr::Ident("b".to_string())])
+                ])
+            ])
+        ])),
+        test_map: vec![
+            Paren('{'),
+            Keyword("a".to_string()),
+            Paren('('),
+            Ident("f".to_string()),
+            Paren(')'),
+            Paren('}')
+        ] => Ok(Expr::Map(BTreeMap::from([(
+            Key::new(Expr::Keyword("a".to_string())).expect("valid key"),
+            Expr::List(vec![Expr::Ident("f".to_string())])
+        )]))),
+        test_map_missing_value: vec![Paren('{'), Num(1.0), Paren('}')] => Err(Error::MissingMapValue),
+        test_vector: vec![
+            Paren('['),
+            Num(1.0),
+            Paren('['),
+            Paren(']'),
+            Paren(']')
+        ] => Ok(Expr::Vector(vec![Expr::Num(1.0), Expr::Vector(vec![])])),
+        test_mismatched_paren: vec![Paren('{'), Paren(')')] => Err(Error::MismatchedParenthesis('}', ')')),
+        test_mismatched_bracket: vec![Paren('('), Paren(']')] => Err(Error::MismatchedParenthesis(')', ']')),
+        test_unterminated_quote: vec![Quote("'")] => Err(Error::UnexpectedEndOfTokenStream),
         test_unexpected_closing_paren: vec![Paren(')')] => Err(Error::UnexpectedClosingParenthesis),
         test_unclosed_expression: vec![Paren('(')] => Err(Error::MissingClosingParenthesis),
         test_unexpected_end_of_tokenstream: vec![] => Err(Error::UnexpectedEndOfTokenStream)
     );
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(syntax_errors("(+ 1 2) (a)"), vec![]);
+        assert_eq!(
+            syntax_errors("(+ 1.2.3 (a) \")\"\n) ) (\"b"),
+            vec![
+                (Error::InvalidNumber("1.2.3".to_string()), 3..8),
+                (Error::UnexpectedClosingParenthesis, 19..20),
+                (Error::MissingClosingParenthesis, 21..22),
+                (Error::UnterminatedString, 22..24)
+            ]
+        );
+        assert_eq!(
+            syntax_errors("'(a ') '"),
+            vec![
+                (Error::UnexpectedClosingParenthesis, 5..6),
+                (Error::UnexpectedEndOfTokenStream, 7..8)
+            ]
+        );
+        assert_eq!(syntax_errors("{:a (1 2)}"), vec![]);
+        assert_eq!(
+            syntax_errors("{:a (1 2}"),
+            vec![
+                (Error::MissingClosingParenthesis, 0..1),
+                (Error::MismatchedParenthesis(')', '}'), 8..9)
+            ]
+        );
+        assert_eq!(syntax_errors("[1 {:a [2]}]"), vec![]);
+        assert_eq!(
+            syntax_errors("[1 2) ]"),
+            vec![
+                (Error::MismatchedParenthesis(']', ')'), 4..5),
+                (Error::UnexpectedClosingParenthesis, 6..7)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_all() {
+        assert_eq!(
+            parse_all(vec![Num(1.0), Paren('('), Num(2.0), Paren(')')]),
+            Ok(vec![Expr::Num(1.0), Expr::List(vec![Expr::Num(2.0)])])
+        );
+        assert_eq!(parse_all(vec![]), Ok(vec![]));
+        assert_eq!(
+            parse_all(vec![Num(1.0), Paren(')')]),
+            Err(Error::UnexpectedClosingParenthesis)
+        );
+    }
 }
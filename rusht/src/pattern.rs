@@ -0,0 +1,211 @@
+//! Patterns matched by `match`. Patterns are not interpreted: `nil`,
+//! integers, ratios, floats, strings, characters, keywords, and booleans match equal values, `_` matches anything, and any
+//! other identifier matches anything and binds the value to its name. A list
+//! of patterns matches a list of the same length, whose elements match the
+//! patterns. A list pattern ending in `. rest` matches lists with at least
+//! as many elements as the patterns before the period and binds the
+//! remaining elements to `rest`, e.g. `(x . rest)` destructures a non-empty
+//! list into its first element and the rest.
+use crate::expr::Expr;
+use crate::{Error, Result};
+
+/// Matches the given value against the pattern and returns the variables
+/// bound by it in order, or `None` if the value does not match.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the pattern is neither a literal, an identifier,
+///     nor a list of patterns, optionally followed by a period and a single
+///     identifier.
+pub fn match_pattern(pattern: &Expr, value: &Expr) -> Result<Option<Vec<(String, Expr)>>> {
+    check(pattern)?;
+    let mut bindings = vec![];
+    if bind(pattern, value, &mut bindings) {
+        Ok(Some(bindings))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns the names of the variables bound by the given pattern.
+pub fn pattern_names(pattern: &Expr) -> Vec<String> {
+    match pattern {
+        Expr::Ident(name) if name != "_" && !is_period(pattern) => vec![name.clone()],
+        Expr::List(patterns) => patterns.iter().flat_map(pattern_names).collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns whether the given expression is the period separating the rest
+/// pattern of a list pattern.
+fn is_period(expr: &Expr) -> bool {
+    matches!(expr, Expr::Ident(name) if name == ".")
+}
+
+/// Splits the given list pattern into the patterns of the elements and the
+/// rest pattern, if any.
+fn split_rest(patterns: &[Expr]) -> (&[Expr], Option<&Expr>) {
+    match patterns {
+        [elements @ .., period, rest] if !elements.is_empty() && is_period(period) => {
+            (elements, Some(rest))
+        }
+        _ => (patterns, None),
+    }
+}
+
+/// Checks whether the given pattern is valid.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the pattern is invalid.
+fn check(pattern: &Expr) -> Result<()> {
+    match pattern {
+        Expr::Nil
+        | Expr::Int(_)
+        | Expr::Ratio(..)
+        | Expr::Num(_)
+        | Expr::Str(_)
+        | Expr::Char(_)
+        | Expr::Keyword(_)
+        | Expr::Bool(_) => Ok(()),
+        Expr::Ident(_) if !is_period(pattern) => Ok(()),
+        Expr::List(patterns) => match split_rest(patterns) {
+            (elements, Some(Expr::Ident(_)) | None) => elements.iter().try_for_each(check),
+            _ => Err(Error::UnexpectedType),
+        },
+        _ => Err(Error::UnexpectedType),
+    }
+}
+
+/// Matches the given value against the valid pattern and adds the bound
+/// variables to `bindings`. The bindings are incomplete, if the value does
+/// not match.
+fn bind(pattern: &Expr, value: &Expr, bindings: &mut Vec<(String, Expr)>) -> bool {
+    match pattern {
+        Expr::Ident(name) if name == "_" => true,
+        Expr::Ident(name) => {
+            bindings.push((name.clone(), value.clone()));
+            true
+        }
+        Expr::List(patterns) => {
+            let Expr::List(values) = value else {
+                return false;
+            };
+            let (elements, rest) = split_rest(patterns);
+            let matches_len = match rest {
+                Some(_) => values.len() >= elements.len(),
+                None => values.len() == elements.len(),
+            };
+            matches_len
+                && elements
+                    .iter()
+                    .zip(values)
+                    .all(|(pattern, value)| bind(pattern, value, bindings))
+                && match rest {
+                    Some(rest) => bind(
+                        rest,
+                        &Expr::List(values[elements.len()..].to_vec()),
+                        bindings,
+                    ),
+                    None => true,
+                }
+        }
+        _ => pattern.is_equal(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ident(name: &str) -> Expr {
+        Expr::Ident(name.to_string())
+    }
+
+    fn list(values: &[f64]) -> Expr {
+        Expr::List(values.iter().map(|&x| Expr::Num(x)).collect())
+    }
+
+    #[test]
+    fn test_match_literal() {
+        assert_eq!(
+            match_pattern(&Expr::Num(1.0), &Expr::Num(1.0)),
+            Ok(Some(vec![]))
+        );
+        assert_eq!(match_pattern(&Expr::Num(1.0), &Expr::Num(2.0)), Ok(None));
+        assert_eq!(
+            match_pattern(&Expr::Str("1".to_string()), &Expr::Num(1.0)),
+            Ok(None)
+        );
+        assert_eq!(match_pattern(&ident("_"), &list(&[1.0])), Ok(Some(vec![])));
+    }
+
+    #[test]
+    fn test_match_list() {
+        let pattern = Expr::List(vec![ident("x"), Expr::Num(2.0), ident("y")]);
+        assert_eq!(
+            match_pattern(&pattern, &list(&[1.0, 2.0, 3.0])),
+            Ok(Some(vec![
+                ("x".to_string(), Expr::Num(1.0)),
+                ("y".to_string(), Expr::Num(3.0)),
+            ]))
+        );
+        assert_eq!(match_pattern(&pattern, &list(&[1.0, 3.0, 3.0])), Ok(None));
+        assert_eq!(match_pattern(&pattern, &list(&[1.0, 2.0])), Ok(None));
+        assert_eq!(match_pattern(&pattern, &Expr::Num(1.0)), Ok(None));
+        assert_eq!(
+            match_pattern(&Expr::List(vec![]), &list(&[])),
+            Ok(Some(vec![]))
+        );
+    }
+
+    #[test]
+    fn test_match_rest() {
+        let pattern = Expr::List(vec![ident("x"), ident("."), ident("rest")]);
+        assert_eq!(
+            match_pattern(&pattern, &list(&[1.0, 2.0, 3.0])),
+            Ok(Some(vec![
+                ("x".to_string(), Expr::Num(1.0)),
+                ("rest".to_string(), list(&[2.0, 3.0])),
+            ]))
+        );
+        assert_eq!(
+            match_pattern(&pattern, &list(&[1.0])),
+            Ok(Some(vec![
+                ("x".to_string(), Expr::Num(1.0)),
+                ("rest".to_string(), list(&[])),
+            ]))
+        );
+        assert_eq!(match_pattern(&pattern, &list(&[])), Ok(None));
+        assert_eq!(
+            match_pattern(
+                &Expr::List(vec![ident("x"), ident("."), Expr::Num(1.0)]),
+                &list(&[1.0])
+            ),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            match_pattern(
+                &Expr::List(vec![ident("x"), ident("."), ident("y"), ident("z")]),
+                &list(&[])
+            ),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            match_pattern(&Expr::List(vec![ident("."), ident("x")]), &list(&[1.0])),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_pattern_names() {
+        let pattern = Expr::List(vec![
+            ident("x"),
+            ident("_"),
+            Expr::List(vec![ident("y"), Expr::Num(1.0)]),
+            ident("."),
+            ident("rest"),
+        ]);
+        assert_eq!(pattern_names(&pattern), vec!["x", "y", "rest"]);
+    }
+}
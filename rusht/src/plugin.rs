@@ -0,0 +1,89 @@
+//! Plugins extend the prelude with builtins implemented in shared libraries.
+//!
+//! A plugin is a shared library (`cdylib`) exporting a function with the
+//! following signature, which adds its builtins to the given registry:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub fn rusht_register(registry: &mut rusht::Registry) {
+//!     registry.register("answer", |_| Ok(rusht::Expr::Num(42.0)));
+//! }
+//! ```
+//!
+//! As the function uses the Rust ABI, the plugin must be compiled using the
+//! same compiler and the same version of this crate as the host. Loading
+//! plugins requires the `plugins` feature.
+use crate::expr::Expr;
+use crate::{Env, Result};
+
+/// The name of the function exported by plugins.
+pub const REGISTER_SYMBOL: &str = "rusht_register";
+
+/// A registry, to which plugins add their builtins.
+#[derive(Debug)]
+pub struct Registry<'a> {
+    env: &'a mut Env,
+}
+
+impl Registry<'_> {
+    /// Registers a new builtin, replacing existing definitions of the same
+    /// name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name under which the builtin can be called.
+    /// * `func` - The implementation of the builtin.
+    pub fn register<T>(&mut self, name: T, func: fn(Vec<Expr>) -> Result<Expr>)
+    where
+        T: Into<String>,
+    {
+        self.env.insert(name.into(), Expr::Func(func));
+    }
+}
+
+/// Loads the plugin at the given path and registers its builtins in the given
+/// environment. The returned library must be kept alive as long as the
+/// builtins may be called.
+///
+/// # Arguments
+///
+/// * `path` - The path of the shared library.
+/// * `env` - The environment, in which the builtins are registered.
+///
+/// # Errors
+///
+/// * `PluginNotLoaded` - If the library could not be loaded or does not
+///     export the register function.
+#[cfg(feature = "plugins")]
+#[allow(unsafe_code)]
+pub(crate) fn load(path: &std::ffi::OsStr, env: &mut Env) -> Result<libloading::Library> {
+    let not_loaded = |err: libloading::Error| crate::Error::PluginNotLoaded(err.to_string());
+
+    // Loading a library runs its initialization routines and calling the
+    // register function trusts it to have the expected signature. Both is
+    // inherent to plugins and documented in the module documentation.
+    unsafe {
+        let library = libloading::Library::new(path).map_err(not_loaded)?;
+        let register = *library
+            .get::<fn(&mut Registry)>(REGISTER_SYMBOL.as_bytes())
+            .map_err(not_loaded)?;
+        register(&mut Registry { env });
+        Ok(library)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn register() {
+        let mut env = Env::default();
+        Registry { env: &mut env }.register("answer", |_| Ok(Expr::Num(42.0)));
+
+        match env.get("answer") {
+            Some(Expr::Func(func)) => assert_eq!(func(vec![]), Ok(Expr::Num(42.0))),
+            _ => panic!("builtin not registered"),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Security policies restrict what scripts are allowed to do. Before a
+//! privileged builtin (one interacting with the world outside of the
+//! interpreter) is run, the interpreter asks its policy whether the operation
+//! may be performed. By default, every operation is allowed.
+use std::fmt::{Display, Formatter};
+
+use crate::expr::Expr;
+
+/// A privileged operation, which has to be allowed by the policy of an
+/// interpreter before it is performed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum Operation {
+    /// Reading a line from the standard input (`read`).
+    ReadInput,
+    /// Terminating the current process (`exit`).
+    Exit,
+    /// Reading and interpreting a module file (`require`).
+    Require,
+    /// Starting a subprocess (`spawn-process`).
+    Spawn,
+    /// Opening a network connection or sending a datagram (`tcp-connect`,
+    /// `udp-send`).
+    #[cfg(feature = "net")]
+    Connect,
+    /// Listening for network connections or datagrams (`tcp-listen`,
+    /// `udp-bind`).
+    #[cfg(feature = "net")]
+    Listen,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::ReadInput => write!(f, "read"),
+            Operation::Exit => write!(f, "exit"),
+            Operation::Require => write!(f, "require"),
+            Operation::Spawn => write!(f, "spawn"),
+            #[cfg(feature = "net")]
+            Operation::Connect => write!(f, "connect"),
+            #[cfg(feature = "net")]
+            Operation::Listen => write!(f, "listen"),
+        }
+    }
+}
+
+/// A policy decides whether a privileged operation may be performed.
+pub trait Policy {
+    /// Returns `true` if the given operation may be performed with the given
+    /// arguments and `false` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The operation about to be performed.
+    /// * `args` - The (already interpreted) arguments passed to the builtin.
+    fn allows(&self, operation: Operation, args: &[Expr]) -> bool;
+}
+
+/// The default policy, allowing every operation.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AllowAll;
+
+impl Policy for AllowAll {
+    fn allows(&self, _: Operation, _: &[Expr]) -> bool {
+        true
+    }
+}
+
+/// Closures can be used as policies directly, e.g. `|op, _| op != Operation::Exit`.
+impl<F> Policy for F
+where
+    F: Fn(Operation, &[Expr]) -> bool,
+{
+    fn allows(&self, operation: Operation, args: &[Expr]) -> bool {
+        self(operation, args)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allow_all() {
+        assert!(AllowAll.allows(Operation::Exit, &[]));
+    }
+
+    #[test]
+    fn closure_policy() {
+        let policy = |op, _: &[Expr]| op != Operation::Exit;
+        assert!(policy.allows(Operation::ReadInput, &[]));
+        assert!(!policy.allows(Operation::Exit, &[Expr::Num(1.0)]));
+    }
+}
@@ -3,12 +3,19 @@
 //! Depending on the called operator we defined each a function.
 #![allow(clippy::unnecessary_wraps)]
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
 use std::io::stdin;
+use std::sync::OnceLock;
 
 use crate::expr::Expr;
-use crate::{Env, Error, Result};
+use crate::map::Key;
+use crate::number::{self, arithmetic, division, Number};
+use crate::syntax;
+use crate::{BuiltinDoc, Env, Error, Operation, Result, VERSION};
+
+/// The name of the variable containing the version of the interpreter.
+const VERSION_VARIABLE: &str = "*rusht-version*";
 
 /// Using macros to initialize the hash map in an easier and compact way.
 /// Each entry of the map has a key and the belongig value.
@@ -16,38 +23,279 @@ use crate::{Env, Error, Result};
 macro_rules! prelude {
     ($($key:expr => $val:expr),*) => {
         {
-            let mut hash_map: Env = HashMap::new();
+            let mut env = Env::default();
             $(
-                hash_map.insert($key.to_string(), Expr::Func($val));
+                env.insert($key.to_string(), Expr::Func($val));
             )*
-            hash_map
+            env
         }
     };
 }
 
 /// Returns a prelude (standard library) of often used functions.
 pub fn create() -> Env {
-    prelude!(
-        "+" => |args| reduce(args, |a, b| -> f64 { a + b }),
-        "-" => |args| reduce(args, |a, b| -> f64 { a - b }),
-        "*" => |args| reduce(args, |a, b| -> f64 { a * b }),
-        "/" => |args| reduce(args, |a, b| -> f64 { a / b }),
-        "%" => |args| reduce(args, |a, b| -> f64 { a % b }),
+    let mut env = prelude!(
+        "+" => |args| arithmetic(args, number::add, |a, b| a + b),
+        "-" => |args| arithmetic(args, number::sub, |a, b| a - b),
+        "*" => |args| arithmetic(args, number::mul, |a, b| a * b),
+        "/" => |args| division(args, number::div, |a, b| a / b),
+        "%" => |args| division(args, number::rem, |a, b| a % b),
         "concat" => |args| reduce(args, |a, b| -> String { format!("{}{}", a, b) }),
         "and" => |args| reduce(args, |a, b| -> bool { a && b }),
         "or" => |args| reduce(args, |a, b| -> bool { a || b }),
         "exit" => |args| rusht_exit(&args),
-        "if" => |args| rusht_if(&args),
         "read" => |args| rusht_read(&args),
-        "==" => |args |rusht_strict_eq(&args),
-        "=" => |args| rusht_cmp(args, |a, b| (a - b).abs() < f64::EPSILON),
+        "==" => |args| rusht_relate(&args, Expr::is_equal),
+        "eq?" => |args| rusht_relate(&args, Expr::is_eq),
+        "equal?" => |args| rusht_relate(&args, Expr::is_equal),
+        "approx=" => |args| rusht_approx_equal(&args),
         "<" => |args| rusht_cmp(args, |a, b| a < b),
         "<=" => |args| rusht_cmp(args, |a, b| a <= b),
         ">" => |args| rusht_cmp(args, |a, b| a > b),
         ">=" => |args| rusht_cmp(args, |a, b| a >= b),
+        "length" => |args| rusht_length(&args),
         "nth" => |args| rusht_nth(&args),
-        "append" => |args| rusht_append(&args)
-    )
+        "reverse" => |args| rusht_reverse(&args),
+        "append" => |args| rusht_append(&args),
+        "zip" => |args| rusht_zip(&args),
+        "enumerate" => |args| rusht_enumerate(&args),
+        "flatten" => |args| rusht_flatten(&args),
+        "frequencies" => |args| rusht_frequencies(&args),
+        "list" => |args| Ok(Expr::List(args)),
+        "cons" => |args| rusht_cons(&args),
+        "car" => |args| rusht_first(&args),
+        "first" => |args| rusht_first(&args),
+        "cdr" => |args| rusht_rest(&args),
+        "rest" => |args| rusht_rest(&args),
+        "empty?" => |args| rusht_is_empty(&args),
+        "nil?" => |args| rusht_is_nil(&args),
+        "keyword?" => |args| rusht_is_keyword(&args),
+        "symbol?" => |args| rusht_is_symbol(&args),
+        "symbol->string" => |args| rusht_symbol_to_string(&args),
+        "string->symbol" => |args| rusht_string_to_symbol(&args),
+        "char?" => |args| rusht_is_char(&args),
+        "char->int" => |args| rusht_char_to_int(&args),
+        "int->char" => |args| rusht_int_to_char(&args),
+        "string->chars" => |args| rusht_string_to_chars(&args),
+        "version" => |args| rusht_version(&args),
+        "assert-eq" => |args| rusht_assert_eq(&args),
+        "error" => |args| rusht_error(&args),
+        "gensym" => |args| rusht_gensym(&args)
+    );
+    env.insert(VERSION_VARIABLE.to_string(), Expr::Str(VERSION.to_string()));
+    crate::interpret::register(&mut env);
+    crate::map::register(&mut env);
+    crate::math::register(&mut env);
+    crate::vector::register(&mut env);
+    crate::pretty::register(&mut env);
+    crate::process::register(&mut env);
+    #[cfg(feature = "unicode")]
+    crate::grapheme::register(&mut env);
+    #[cfg(feature = "net")]
+    crate::net::register(&mut env);
+    env
+}
+
+/// The documentation of the prelude functions.
+pub const DOCS: &[BuiltinDoc] = &[
+    doc("(+ x y...)", "Adds the given numbers."),
+    doc(
+        "(- x y...)",
+        "Subtracts the following numbers from the first one.",
+    ),
+    doc("(* x y...)", "Multiplies the given numbers."),
+    doc(
+        "(/ x y...)",
+        "Divides the first number by the following ones. Integers and ratios are divided exactly, e.g. `(/ 1 3)` is `1/3`, and fail on division by zero.",
+    ),
+    doc(
+        "(% x y...)",
+        "Returns the remainder of dividing the first number by the following ones. Integers and ratios fail on division by zero.",
+    ),
+    doc("(concat x y...)", "Concatenates the given strings."),
+    doc(
+        "(and x y...)",
+        "Returns whether all given booleans are true.",
+    ),
+    doc(
+        "(or x y...)",
+        "Returns whether any of the given booleans is true.",
+    ),
+    doc(
+        "(exit code)",
+        "Stops the program with the given exit code (0 by default).",
+    ),
+    doc("(read)", "Reads a line from the standard input."),
+    doc(
+        "(== x y...)",
+        "Returns whether the given values are structurally equal, same as `equal?`.",
+    ),
+    doc(
+        "(eq? x y...)",
+        "Returns whether the given values are identical: handles and functions refer to the same value, atoms have the same type and value, lists are empty.",
+    ),
+    doc(
+        "(equal? x y...)",
+        "Returns whether the given values are structurally equal without coercion, comparing lists element-wise.",
+    ),
+    doc(
+        "(approx= tolerance x y...)",
+        "Returns whether the given values are numerically equal, i.e. differ by at most the tolerance, coercing them to numbers.",
+    ),
+    doc(
+        "(< x y...)",
+        "Returns whether the given numbers are strictly increasing.",
+    ),
+    doc(
+        "(<= x y...)",
+        "Returns whether the given numbers are increasing.",
+    ),
+    doc(
+        "(> x y...)",
+        "Returns whether the given numbers are strictly decreasing.",
+    ),
+    doc(
+        "(>= x y...)",
+        "Returns whether the given numbers are decreasing.",
+    ),
+    doc("(length list)", "Returns the number of elements of the list."),
+    doc(
+        "(nth index list)",
+        "Returns the element at the given index of the list.",
+    ),
+    doc(
+        "(reverse list)",
+        "Returns a copy of the list with the elements in reverse order.",
+    ),
+    doc(
+        "(append list...)",
+        "Returns a list of the elements of the given lists in order.",
+    ),
+    doc(
+        "(flatten list [deep])",
+        "Returns a list, in which the lists nested in the list are replaced by their elements. If deep is true, nested lists are flattened recursively.",
+    ),
+    doc(
+        "(frequencies list)",
+        "Returns a map of the distinct elements of the list to the numbers of their occurrences.",
+    ),
+    doc(
+        "(zip list list)",
+        "Returns a list of two-element lists pairing the elements of the lists at the same index, up to the length of the shorter list.",
+    ),
+    doc(
+        "(enumerate list)",
+        "Returns a list of two-element lists pairing the index of each element with the element.",
+    ),
+    doc("(list x...)", "Returns a list of the given values."),
+    doc(
+        "(cons elem list)",
+        "Returns a copy of the list with the element prepended.",
+    ),
+    doc(
+        "(car list)",
+        "Returns the first element of the non-empty list, same as `first`.",
+    ),
+    doc(
+        "(first list)",
+        "Returns the first element of the non-empty list.",
+    ),
+    doc(
+        "(cdr list)",
+        "Returns the non-empty list without its first element, same as `rest`.",
+    ),
+    doc(
+        "(rest list)",
+        "Returns the non-empty list without its first element.",
+    ),
+    doc(
+        "(empty? coll)",
+        "Returns whether the list, vector, map, or string is empty. `nil` is empty.",
+    ),
+    doc("(nil? x)", "Returns whether the value is `nil`."),
+    doc("(keyword? x)", "Returns whether the value is a keyword, e.g. `:name`."),
+    doc("(symbol? x)", "Returns whether the value is a symbol, e.g. `'name`."),
+    doc("(symbol->string symbol)", "Returns the name of the symbol."),
+    doc(
+        "(string->symbol string)",
+        "Returns the symbol with the string as its name.",
+    ),
+    doc("(char? x)", "Returns whether the value is a character, e.g. `#\\a`."),
+    doc("(char->int char)", "Returns the Unicode code point of the character."),
+    doc(
+        "(int->char code)",
+        "Returns the character with the Unicode code point.",
+    ),
+    doc(
+        "(string->chars string)",
+        "Returns a list of the characters of the string.",
+    ),
+    doc("(version)", "Returns the version of the interpreter."),
+    doc(
+        "(assert-eq actual expected)",
+        "Fails if the values are not of the same type and value.",
+    ),
+    doc(
+        "(error message)",
+        "Fails with the given message, which is passed to the handlers.",
+    ),
+    doc(
+        "(gensym [prefix])",
+        "Returns a fresh symbol starting with the prefix (`g` by default), e.g. for temporaries introduced by macros.",
+    ),
+];
+
+/// Creates the documentation of a builtin.
+const fn doc(signature: &'static str, description: &'static str) -> BuiltinDoc {
+    BuiltinDoc {
+        signature,
+        description,
+    }
+}
+
+/// Returns the privileged operation performed by the prelude function with
+/// the given name or `None`, if the function does not need to be authorized.
+///
+/// # Arguments
+/// * `name` - The name of the prelude function.
+pub fn operation(name: &str) -> Option<Operation> {
+    match name {
+        "read" => Some(Operation::ReadInput),
+        "exit" => Some(Operation::Exit),
+        "spawn-process" => Some(Operation::Spawn),
+        #[cfg(feature = "net")]
+        "tcp-connect" | "udp-send" => Some(Operation::Connect),
+        #[cfg(feature = "net")]
+        "tcp-listen" | "udp-bind" => Some(Operation::Listen),
+        _ => None,
+    }
+}
+
+/// Returns the name and the privileged operation of the given prelude
+/// function or `None`, if the function does not need to be authorized. Unlike
+/// `operation`, the function is identified independently of the name it is
+/// called under, e.g. after being passed as an argument or defined as
+/// another variable.
+///
+/// # Arguments
+/// * `func` - The prelude function.
+pub fn operation_of(func: fn(Vec<Expr>) -> Result<Expr>) -> Option<(&'static str, Operation)> {
+    type Privileged = Vec<(fn(Vec<Expr>) -> Result<Expr>, String, Operation)>;
+    static PRIVILEGED: OnceLock<Privileged> = OnceLock::new();
+
+    PRIVILEGED
+        .get_or_init(|| {
+            create()
+                .iter()
+                .filter_map(|(name, value)| match value {
+                    Expr::Func(func) => Some((*func, name.clone(), operation(name)?)),
+                    _ => None,
+                })
+                .collect()
+        })
+        .iter()
+        .find(|(privileged, _, _)| std::ptr::fn_addr_eq(*privileged, func))
+        .map(|(_, name, operation)| (name.as_str(), *operation))
 }
 
 /// Returns the nth element of a given list.
@@ -76,47 +324,402 @@ fn rusht_nth(args: &[Expr]) -> Result<Expr> {
     }
 }
 
-/// Appends an element to the end of a given list. The operation is made out
-/// of place, meaning a modified copy of the list is returned.
+/// Returns the number of elements of a given list.
 ///
 /// # Arguments
-/// * `args[0]` - The element to append to the list.
+/// * `args[0]` - The list on which to operate.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a list.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_length(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::List(list)] => Ok(Expr::from(list.len())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Reverses a given list. The operation is made out of place, meaning a
+/// modified copy of the list is returned.
+///
+/// # Arguments
+/// * `args[0]` - The list on which to operate.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a list.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_reverse(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::List(list)] => Ok(Expr::List(list.iter().rev().cloned().collect())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Concatenates the given lists. Like `reverse`, the operation is made out of
+/// place.
+///
+/// # Arguments
+/// * `args` - The lists to concatenate.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument is not a list.
+fn rusht_append(args: &[Expr]) -> Result<Expr> {
+    let mut out = vec![];
+    for arg in args {
+        let Expr::List(list) = arg else {
+            return Err(Error::UnexpectedType);
+        };
+        out.extend(list.iter().cloned());
+    }
+    Ok(Expr::List(out))
+}
+
+/// Replaces the lists nested in a given list by their elements.
+///
+/// # Arguments
+/// * `args[0]` - The list on which to operate.
+/// * `args[1]` - Whether nested lists are flattened recursively. Defaults to
+///     `false`, which flattens a single level.
+///
+/// # Errors
+/// * `UnexpectedType` - If the first argument is not a list or the second
+///     argument can't be coerced to a boolean.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one or
+///     two.
+fn rusht_flatten(args: &[Expr]) -> Result<Expr> {
+    let (list, deep) = match args {
+        [Expr::List(list)] => (list, false),
+        [Expr::List(list), deep] => (list, deep.clone().try_into()?),
+        [_] | [_, _] => return Err(Error::UnexpectedType),
+        &_ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let mut out = vec![];
+    flatten_into(list.clone(), deep, &mut out);
+    Ok(Expr::List(out))
+}
+
+/// Appends the given values to `out`, replacing lists by their elements.
+///
+/// # Arguments
+/// * `values` - The values to append.
+/// * `deep` - Whether nested lists are flattened recursively.
+/// * `out` - The list to append to.
+pub fn flatten_into(values: Vec<Expr>, deep: bool, out: &mut Vec<Expr>) {
+    for value in values {
+        match value {
+            Expr::List(list) if deep => {
+                for item in list {
+                    flatten_into(vec![item], true, out);
+                }
+            }
+            Expr::List(list) => out.extend(list),
+            value => out.push(value),
+        }
+    }
+}
+
+/// Counts the occurrences of the distinct elements of a given list. Returns a
+/// map of the elements to their counts.
+///
+/// # Arguments
+/// * `args[0]` - The list on which to operate.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a list or an element is not a
+///     valid key.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_frequencies(args: &[Expr]) -> Result<Expr> {
+    let list = match args {
+        [Expr::List(list)] => list,
+        [_] => return Err(Error::UnexpectedType),
+        &_ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let mut counts = BTreeMap::new();
+    for item in list {
+        *counts.entry(Key::new(item.clone())?).or_insert(0) += 1;
+    }
+    Ok(Expr::Map(
+        counts
+            .into_iter()
+            .map(|(item, count)| (item, Expr::Int(count)))
+            .collect(),
+    ))
+}
+
+/// Pairs the elements of two given lists at the same index. The result is as
+/// long as the shorter list.
+///
+/// # Arguments
+/// * `args[0]` - The list of the first elements of the pairs.
+/// * `args[1]` - The list of the second elements of the pairs.
+///
+/// # Errors
+/// * `UnexpectedType` - If an argument is not a list.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two.
+fn rusht_zip(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::List(xs), Expr::List(ys)] => Ok(Expr::List(
+            xs.iter()
+                .zip(ys)
+                .map(|(x, y)| Expr::List(vec![x.clone(), y.clone()]))
+                .collect(),
+        )),
+        [_, _] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Pairs each element of a given list with its index, starting at zero.
+///
+/// # Arguments
+/// * `args[0]` - The list on which to operate.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a list.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_enumerate(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::List(list)] => Ok(Expr::List(
+            list.iter()
+                .enumerate()
+                .map(|(index, x)| Expr::List(vec![Expr::from(index), x.clone()]))
+                .collect(),
+        )),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Prepends an element to a given list. Like `reverse`, the operation is made
+/// out of place.
+///
+/// # Arguments
+/// * `args[0]` - The element to prepend.
 /// * `args[1]` - The list on which to operate.
 ///
 /// # Errors
-/// * `UnexpectedType` - If the index can't be coerced to a number or the
-///     second argument is not a list.
+/// * `UnexpectedType` - If the second argument is not a list.
 /// * `InvalidNumberOfArguments` - If the number of arguments in not two.
-fn rusht_append(args: &[Expr]) -> Result<Expr> {
+fn rusht_cons(args: &[Expr]) -> Result<Expr> {
     match args {
-        [elem, Expr::List(list)] => {
-            let mut list = list.clone();
-            list.push(elem.clone());
-            Ok(Expr::List(list))
-        }
+        [elem, Expr::List(list)] => Ok(Expr::List(
+            std::iter::once(elem.clone())
+                .chain(list.iter().cloned())
+                .collect(),
+        )),
         [_, _] => Err(Error::UnexpectedType),
         &_ => Err(Error::InvalidNumberOfArguments),
     }
 }
 
-/// Checks a given condition and returns one of two possible values.
+/// Returns the first element of a given list.
+///
+/// # Arguments
+/// * `args[0]` - The list on which to operate.
+///
+/// # Errors
+/// * `IndexOutOfBounds` - If the list is empty.
+/// * `UnexpectedType` - If the argument is not a list.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_first(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::List(list)] => list.first().cloned().ok_or(Error::IndexOutOfBounds(0)),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns a copy of a given list without its first element.
+///
+/// # Arguments
+/// * `args[0]` - The list on which to operate.
+///
+/// # Errors
+/// * `IndexOutOfBounds` - If the list is empty.
+/// * `UnexpectedType` - If the argument is not a list.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_rest(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::List(list)] if list.is_empty() => Err(Error::IndexOutOfBounds(0)),
+        [Expr::List(list)] => Ok(Expr::List(list[1..].to_vec())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns whether a given list, vector, map, or string is empty. `nil` is
+/// considered empty.
+///
+/// # Arguments
+/// * `args[0]` - The collection to check.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is neither a collection nor `nil`.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_is_empty(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Nil] => Ok(Expr::Bool(true)),
+        [Expr::List(list) | Expr::Vector(list)] => Ok(Expr::Bool(list.is_empty())),
+        [Expr::Map(map)] => Ok(Expr::Bool(map.is_empty())),
+        [Expr::Str(s)] => Ok(Expr::Bool(s.is_empty())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns whether a given value is `nil`.
+///
+/// # Arguments
+/// * `args[0]` - The value to check.
+///
+/// # Errors
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_is_nil(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [value] => Ok(Expr::Bool(*value == Expr::Nil)),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns whether a given value is a keyword.
 ///
 /// # Arguments
+/// * `args[0]` - The value to check.
 ///
-/// * `args[0]` - A condition to be checked.
-/// * `args[1]` - The value to be returned if the condition is truthy.
-/// * `args[2]` - The value to be returned if the condition is not truthy.
+/// # Errors
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_is_keyword(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [value] => Ok(Expr::Bool(matches!(value, Expr::Keyword(_)))),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns whether a given value is a symbol.
+///
+/// # Arguments
+/// * `args[0]` - The value to check.
+///
+/// # Errors
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_is_symbol(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [value] => Ok(Expr::Bool(matches!(value, Expr::Symbol(_)))),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the name of a given symbol.
+///
+/// # Arguments
+/// * `args[0]` - The symbol.
 ///
 /// # Errors
+/// * `UnexpectedType` - If the argument is not a symbol.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_symbol_to_string(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Symbol(name)] => Ok(Expr::Str(name.clone())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the symbol with a given name.
+///
+/// # Arguments
+/// * `args[0]` - The name of the symbol.
 ///
-/// * `InvalidNumberOfArguments` - If there are too less or too many passed arguments.
-fn rusht_if(args: &[Expr]) -> Result<Expr> {
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a string.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_string_to_symbol(args: &[Expr]) -> Result<Expr> {
     match args {
-        [cond, on_true, on_false] => match cond.clone().try_into() {
-            Ok(true) => Ok(on_true.clone()),
-            Ok(false) => Ok(on_false.clone()),
-            Err(err) => Err(err),
-        },
+        [Expr::Str(name)] => Ok(Expr::Symbol(name.clone())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns whether a given value is a character.
+///
+/// # Arguments
+/// * `args[0]` - The value to check.
+///
+/// # Errors
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_is_char(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [value] => Ok(Expr::Bool(matches!(value, Expr::Char(_)))),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the Unicode code point of a given character.
+///
+/// # Arguments
+/// * `args[0]` - The character.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a character.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_char_to_int(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Char(c)] => Ok(Expr::Int(i64::from(u32::from(*c)))),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the character with a given Unicode code point.
+///
+/// # Arguments
+/// * `args[0]` - The code point.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument can't be coerced to a number.
+/// * `InvalidCharacter` - If the number is not a Unicode scalar value, e.g.
+///     negative, fractional, or a surrogate.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_int_to_char(args: &[Expr]) -> Result<Expr> {
+    let [code] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let code: f64 = code.clone().try_into()?;
+    let valid = code.fract() == 0.0 && (0.0..=f64::from(u32::MAX)).contains(&code);
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    valid
+        .then(|| char::from_u32(code as u32))
+        .flatten()
+        .map(Expr::Char)
+        .ok_or_else(|| Error::InvalidCharacter(code.to_string()))
+}
+
+/// Returns the characters of a given string as a list.
+///
+/// # Arguments
+/// * `args[0]` - The string.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a string.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_string_to_chars(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Str(s)] => Ok(Expr::List(s.chars().map(Expr::Char).collect())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the version of the interpreter, e.g. `"0.1.0"`.
+///
+/// # Errors
+/// * `InvalidNumberOfArguments` - If any arguments are passed.
+fn rusht_version(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [] => Ok(Expr::Str(VERSION.to_string())),
         &_ => Err(Error::InvalidNumberOfArguments),
     }
 }
@@ -134,14 +737,76 @@ fn rusht_read(_: &[Expr]) -> Result<Expr> {
     Ok(Expr::Str(buf))
 }
 
-/// Compares the given `args` strictly, meaning they must be of the same type
-/// and value.
+/// Returns whether all subsequent `args` are related by the given relation,
+/// e.g. `Expr::is_equal`. No type coercion is performed.
 ///
 /// # Arguments
 ///
 /// * `args` - The arguments passed to the function.
-fn rusht_strict_eq(args: &[Expr]) -> Result<Expr> {
-    Ok(Expr::Bool(args.windows(2).all(|w| w[0] == w[1])))
+/// * `relation` - The relation of two subsequent values.
+fn rusht_relate(args: &[Expr], relation: fn(&Expr, &Expr) -> bool) -> Result<Expr> {
+    Ok(Expr::Bool(args.windows(2).all(|w| relation(&w[0], &w[1]))))
+}
+
+/// Asserts, that the given values are strictly equal, e.g. in tests.
+///
+/// # Arguments
+///
+/// * `args[0]` - The actual value.
+/// * `args[1]` - The expected value.
+///
+/// # Errors
+///
+/// * `AssertionFailed` - If the values are not equal.
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two.
+fn rusht_assert_eq(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [actual, expected] if actual == expected => Ok(Expr::Bool(true)),
+        [actual, expected] => Err(Error::AssertionFailed(format!(
+            "`{}` is not equal to `{}`",
+            actual, expected
+        ))),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Fails with the given message, e.g. to signal a condition to the handlers
+/// established using `with-handler`.
+///
+/// # Arguments
+///
+/// * `args[0]` - The message, which is converted to a string.
+///
+/// # Errors
+///
+/// * `Raised` - If a message is passed.
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one.
+fn rusht_error(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Str(message)] => Err(Error::Raised(message.clone())),
+        [message] => Err(Error::Raised(message.to_string())),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns a fresh symbol, whose name is different from all identifiers
+/// generated before, including those generated for hygienic macros.
+///
+/// # Arguments
+///
+/// * `args[0]` - The optional prefix of the identifier, `g` by default.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the prefix is not a string.
+/// * `InvalidNumberOfArguments` - If more than one argument is passed.
+fn rusht_gensym(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [] => Ok(Expr::Symbol(syntax::gensym("g"))),
+        [Expr::Str(prefix)] => Ok(Expr::Symbol(syntax::gensym(prefix))),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
 }
 
 /// Compares the numeric values of its arguments using a given comparator
@@ -159,18 +824,59 @@ fn rusht_strict_eq(args: &[Expr]) -> Result<Expr> {
 ///     number.
 fn rusht_cmp<F>(args: Vec<Expr>, cmp: F) -> Result<Expr>
 where
-    F: Fn(f64, f64) -> bool,
+    F: Fn(Number, Number) -> bool,
 {
     Ok(args
         .into_iter()
-        .map(Expr::try_into)
-        .collect::<Result<Vec<f64>>>()?
+        .map(Number::try_from)
+        .collect::<Result<Vec<_>>>()?
         .windows(2)
         .all(|w| cmp(w[0], w[1]))
         .into())
 }
 
-/// Exits the current process with a given exit code or `0`.
+/// Returns whether the given values are numerically equal, i.e. subsequent
+/// values are equal or differ by at most the given tolerance. Like the
+/// comparisons, the values are coerced to numbers.
+///
+/// # Arguments
+///
+/// * `args` - The values to compare.
+/// * `tolerance` - The largest difference of equal values.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If one or more of the arguments can't be coerced to
+///     a number.
+pub fn approx_equal(args: Vec<Expr>, tolerance: f64) -> Result<Expr> {
+    rusht_cmp(args, |a, b| {
+        a == b || (a.to_f64() - b.to_f64()).abs() <= tolerance
+    })
+}
+
+/// Returns whether the given values are numerically equal within a given
+/// tolerance, see `approx_equal`.
+///
+/// # Arguments
+///
+/// * `args[0]` - The tolerance.
+/// * `args[1..]` - The values to compare.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If one or more of the arguments can't be coerced to
+///     a number.
+/// * `InvalidNumberOfArguments` - If no tolerance is passed.
+fn rusht_approx_equal(args: &[Expr]) -> Result<Expr> {
+    let Some((tolerance, values)) = args.split_first() else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    approx_equal(values.to_vec(), tolerance.clone().try_into()?)
+}
+
+/// Stops the program with a given exit code or `0`. Instead of terminating
+/// the process, the `Exit` error is returned, which propagates to the caller
+/// of the interpreter like any other error.
 ///
 /// # Arguments
 ///
@@ -178,6 +884,7 @@ where
 ///
 /// # Errors
 ///
+/// * `Exit` - If the arguments are valid.
 /// * `InvalidNumberOfArguments` - If the vector of args has a size greater
 ///     than 1.
 /// * `TypeError` - If the given status code can't be coerced to a number.
@@ -189,7 +896,7 @@ fn rusht_exit(args: &[Expr]) -> Result<Expr> {
     }?;
 
     #[allow(clippy::cast_possible_truncation)]
-    std::process::exit(status_code as i32);
+    Err(Error::Exit(status_code as i32))
 }
 
 /// Reduces the given vector of `Token`s  using the given `reducer` function.
@@ -222,7 +929,7 @@ where
 
 #[cfg(test)]
 mod test {
-    use super::Expr::{Bool, List, Num, Str};
+    use super::Expr::{Bool, Int, List, Nil, Num, Str};
     use super::*;
 
     macro_rules! test_prelude {
@@ -239,6 +946,18 @@ mod test {
         };
     }
 
+    #[test]
+    fn every_function_is_documented() {
+        for (name, _) in create().iter() {
+            assert!(
+                name == VERSION_VARIABLE
+                    || crate::builtin_docs().iter().any(|doc| doc.name() == name),
+                "`{}` is not documented",
+                name
+            );
+        }
+    }
+
     test_prelude!(
         add_two => "+"; vec![Num(1.0), Num(2.0)] => Ok(Num(3.0)),
         add_three => "+"; vec![Num(1.0), Num(2.0), Num(2.0)] => Ok(Num(5.0)),
@@ -254,11 +973,6 @@ mod test {
         or_two => "or"; vec![Bool(false), Bool(false)] => Ok(Bool(false)),
         or_three => "or"; vec![Bool(true), Bool(false), Bool(true)] => Ok(Bool(true)),
         type_error => "-"; vec![Bool(true), Str("foo".to_string())] => Err(Error::UnexpectedType),
-        if_true => "if"; vec![Bool(true), Num(1.0), Num(2.0)] => Ok(Num(1.0)),
-        if_false => "if"; vec![Bool(false), Num(1.0), Num(2.0)] => Ok(Num(2.0)),
-        if_no_conditional => "if"; vec![Str("foo".to_string()), Num(1.0), Num(2.0)] => Err(Error::UnexpectedType),
-        if_too_few_args => "if"; vec![Bool(true), Num(1.0)] => Err(Error::InvalidNumberOfArguments),
-        if_too_many_args => "if"; vec![Bool(true), Num(1.0), Num(2.0), Num(3.0)] => Err(Error::InvalidNumberOfArguments),
         bigger => ">"; vec![Num(10.0), Num(8.0)] => Ok(Bool(true)),
         equal_bigger => ">="; vec![Num(1.0), Num(1.0)] => Ok(Bool(true)),
         equal_bigger_bool => ">="; vec![Bool(false), Num(1.0)] => Ok(Bool(false)),
@@ -266,8 +980,98 @@ mod test {
         equal_smaller => "<="; vec![Num(3.0), Num(3.1)] => Ok(Bool(true)),
         compare_true => "=="; vec![Num(4.0), Num(4.0)]=> Ok(Bool(true)),
         compare_false => "=="; vec![Num(4.0), Num(3.0)] => Ok(Bool(false)),
+        eq_atoms => "eq?"; vec![Str("a".to_string()), Str("a".to_string())] => Ok(Bool(true)),
+        eq_no_coercion => "eq?"; vec![Num(1.0), Str("1".to_string())] => Ok(Bool(false)),
+        eq_lists => "eq?"; vec![List(vec![Num(1.0)]), List(vec![Num(1.0)])] => Ok(Bool(false)),
+        eq_empty_lists => "eq?"; vec![List(vec![]), List(vec![])] => Ok(Bool(true)),
+        equal_lists => "equal?"; vec![List(vec![Num(1.0), List(vec![])]), List(vec![Num(1.0), List(vec![])])] => Ok(Bool(true)),
+        equal_lists_differ => "equal?"; vec![List(vec![Num(1.0)]), List(vec![Num(1.0), Num(2.0)])] => Ok(Bool(false)),
+        equal_three => "equal?"; vec![Num(1.0), Num(1.0), Num(2.0)] => Ok(Bool(false)),
+        equal_no_coercion => "equal?"; vec![List(vec![Num(1.0)]), List(vec![Str("1".to_string())])] => Ok(Bool(false)),
+        approx_equal => "approx="; vec![Num(0.1), Num(1.0), Str("1.05".to_string()), Bool(true)] => Ok(Bool(true)),
+        approx_equal_differ => "approx="; vec![Num(0.1), Int(1), Num(1.2)] => Ok(Bool(false)),
+        approx_equal_list => "approx="; vec![Num(0.1), List(vec![]), List(vec![])] => Err(Error::UnexpectedType),
+        approx_equal_no_tolerance => "approx="; vec![] => Err(Error::InvalidNumberOfArguments),
         nth => "nth"; vec![Num(1.0), List(vec![Num(1.0), Num(2.0)])] => Ok(Num(2.0)),
         nth_out_of_bounds => "nth"; vec![Num(5.0), List(vec![Num(1.0), Num(2.0)])] => Err(Error::IndexOutOfBounds(5)),
-        append => "append"; vec![Num(3.0), List(vec![Num(1.0), Num(2.0)])] => Ok(List(vec![Num(1.0), Num(2.0), Num(3.0)]))
+        length => "length"; vec![List(vec![Num(1.0), Num(2.0)])] => Ok(Int(2)),
+        length_empty => "length"; vec![List(vec![])] => Ok(Int(0)),
+        length_str => "length"; vec![Str("ab".to_string())] => Err(Error::UnexpectedType),
+        reverse => "reverse"; vec![List(vec![Num(1.0), Num(2.0)])] => Ok(List(vec![Num(2.0), Num(1.0)])),
+        reverse_too_many_args => "reverse"; vec![List(vec![]), List(vec![])] => Err(Error::InvalidNumberOfArguments),
+        append => "append"; vec![List(vec![Num(1.0)]), List(vec![]), List(vec![Num(2.0), Num(3.0)])] => Ok(List(vec![Num(1.0), Num(2.0), Num(3.0)])),
+        append_none => "append"; vec![] => Ok(List(vec![])),
+        append_elem => "append"; vec![Num(3.0), List(vec![Num(1.0), Num(2.0)])] => Err(Error::UnexpectedType),
+        flatten => "flatten"; vec![List(vec![Num(1.0), List(vec![Num(2.0), List(vec![Num(3.0)])]), List(vec![])])] => Ok(List(vec![Num(1.0), Num(2.0), List(vec![Num(3.0)])])),
+        flatten_deep => "flatten"; vec![List(vec![Num(1.0), List(vec![Num(2.0), List(vec![Num(3.0)])])]), Bool(true)] => Ok(List(vec![Num(1.0), Num(2.0), Num(3.0)])),
+        flatten_not_list => "flatten"; vec![Num(1.0)] => Err(Error::UnexpectedType),
+        flatten_too_many_args => "flatten"; vec![List(vec![]), Bool(true), Bool(true)] => Err(Error::InvalidNumberOfArguments),
+        frequencies_not_list => "frequencies"; vec![Str("a".to_string())] => Err(Error::UnexpectedType),
+        zip => "zip"; vec![List(vec![Num(1.0), Num(2.0), Num(3.0)]), List(vec![Str("a".to_string()), Str("b".to_string())])] => Ok(List(vec![List(vec![Num(1.0), Str("a".to_string())]), List(vec![Num(2.0), Str("b".to_string())])])),
+        zip_empty => "zip"; vec![List(vec![]), List(vec![Num(1.0)])] => Ok(List(vec![])),
+        zip_not_list => "zip"; vec![List(vec![]), Num(1.0)] => Err(Error::UnexpectedType),
+        zip_too_few_args => "zip"; vec![List(vec![])] => Err(Error::InvalidNumberOfArguments),
+        enumerate => "enumerate"; vec![List(vec![Str("a".to_string()), Str("b".to_string())])] => Ok(List(vec![List(vec![Int(0), Str("a".to_string())]), List(vec![Int(1), Str("b".to_string())])])),
+        enumerate_not_list => "enumerate"; vec![Num(1.0)] => Err(Error::UnexpectedType),
+        list => "list"; vec![Num(1.0), Str("a".to_string())] => Ok(List(vec![Num(1.0), Str("a".to_string())])),
+        list_empty => "list"; vec![] => Ok(List(vec![])),
+        cons => "cons"; vec![Num(1.0), List(vec![Num(2.0)])] => Ok(List(vec![Num(1.0), Num(2.0)])),
+        cons_no_list => "cons"; vec![Num(1.0), Num(2.0)] => Err(Error::UnexpectedType),
+        car => "car"; vec![List(vec![Num(1.0), Num(2.0)])] => Ok(Num(1.0)),
+        first_empty => "first"; vec![List(vec![])] => Err(Error::IndexOutOfBounds(0)),
+        cdr => "cdr"; vec![List(vec![Num(1.0), Num(2.0)])] => Ok(List(vec![Num(2.0)])),
+        rest_empty => "rest"; vec![List(vec![])] => Err(Error::IndexOutOfBounds(0)),
+        rest_no_list => "rest"; vec![Num(1.0)] => Err(Error::UnexpectedType),
+        is_empty => "empty?"; vec![List(vec![])] => Ok(Bool(true)),
+        is_empty_not => "empty?"; vec![List(vec![Num(1.0)])] => Ok(Bool(false)),
+        is_empty_nil => "empty?"; vec![Nil] => Ok(Bool(true)),
+        is_empty_str => "empty?"; vec![Str(String::new())] => Ok(Bool(true)),
+        is_empty_num => "empty?"; vec![Num(0.0)] => Err(Error::UnexpectedType),
+        is_nil => "nil?"; vec![Nil] => Ok(Bool(true)),
+        is_nil_not => "nil?"; vec![List(vec![])] => Ok(Bool(false)),
+        is_keyword => "keyword?"; vec![Expr::Keyword("a".to_string())] => Ok(Bool(true)),
+        is_keyword_not => "keyword?"; vec![Str(":a".to_string())] => Ok(Bool(false)),
+        is_symbol => "symbol?"; vec![Expr::Symbol("a".to_string())] => Ok(Bool(true)),
+        is_symbol_not => "symbol?"; vec![Str("a".to_string())] => Ok(Bool(false)),
+        symbol_to_string => "symbol->string"; vec![Expr::Symbol("a".to_string())] => Ok(Str("a".to_string())),
+        symbol_to_string_type => "symbol->string"; vec![Str("a".to_string())] => Err(Error::UnexpectedType),
+        string_to_symbol => "string->symbol"; vec![Str("a b".to_string())] => Ok(Expr::Symbol("a b".to_string())),
+        is_char => "char?"; vec![Expr::Char('a')] => Ok(Bool(true)),
+        is_char_not => "char?"; vec![Str("a".to_string())] => Ok(Bool(false)),
+        char_to_int => "char->int"; vec![Expr::Char('\u{e9}')] => Ok(Int(233)),
+        char_to_int_type => "char->int"; vec![Num(97.0)] => Err(Error::UnexpectedType),
+        int_to_char => "int->char"; vec![Num(97.0)] => Ok(Expr::Char('a')),
+        int_to_char_surrogate => "int->char"; vec![Num(55296.0)] => Err(Error::InvalidCharacter("55296".to_string())),
+        int_to_char_fraction => "int->char"; vec![Num(97.5)] => Err(Error::InvalidCharacter("97.5".to_string())),
+        int_to_char_negative => "int->char"; vec![Num(-1.0)] => Err(Error::InvalidCharacter("-1".to_string())),
+        string_to_chars => "string->chars"; vec![Str("a\u{e9}".to_string())] => Ok(List(vec![Expr::Char('a'), Expr::Char('\u{e9}')])),
+        string_to_chars_empty => "string->chars"; vec![Str(String::new())] => Ok(List(vec![])),
+        is_empty_too_many_args => "empty?"; vec![List(vec![]), List(vec![])] => Err(Error::InvalidNumberOfArguments),
+        exit => "exit"; vec![] => Err(Error::Exit(0)),
+        exit_with_code => "exit"; vec![Num(3.0)] => Err(Error::Exit(3)),
+        version => "version"; vec![] => Ok(Str(VERSION.to_string())),
+        version_with_args => "version"; vec![Num(1.0)] => Err(Error::InvalidNumberOfArguments),
+        assert_eq => "assert-eq"; vec![Num(1.0), Num(1.0)] => Ok(Bool(true)),
+        assert_eq_failed => "assert-eq"; vec![Num(1.0), Str("1".to_string())] => Err(Error::AssertionFailed("`1.0` is not equal to `\"1\"`".to_string())),
+        error => "error"; vec![Str("failed".to_string())] => Err(Error::Raised("failed".to_string())),
+        error_without_message => "error"; vec![] => Err(Error::InvalidNumberOfArguments),
+        gensym_non_string_prefix => "gensym"; vec![Num(1.0)] => Err(Error::UnexpectedType),
+        gensym_too_many_args => "gensym"; vec![Str("a".to_string()), Str("b".to_string())] => Err(Error::InvalidNumberOfArguments)
     );
+
+    #[test]
+    fn gensym_fresh() {
+        let gensym = match create().get("gensym") {
+            Some(Expr::Func(gensym)) => *gensym,
+            _ => panic!("gensym is not a builtin"),
+        };
+        let (Ok(Expr::Symbol(a)), Ok(Expr::Symbol(b))) =
+            (gensym(vec![]), gensym(vec![Str("tmp".to_string())]))
+        else {
+            panic!("gensym does not return symbols");
+        };
+        assert!(a.starts_with('g'));
+        assert!(b.starts_with("tmp"));
+        assert_ne!(gensym(vec![]), Ok(Expr::Symbol(a)));
+    }
 }
@@ -0,0 +1,146 @@
+//! Pretty printing of values. Unlike their single-line display
+//! representation, nested lists, that don't fit into the given width, are
+//! written with one element per line, aligned after the opening parenthesis.
+use std::convert::TryInto;
+
+use crate::expr::Expr;
+use crate::{BuiltinDoc, Env, Error, Result};
+
+/// The width used, if none is passed to the builtins.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Defines the pretty printing builtins in the given environment.
+pub fn register(env: &mut Env) {
+    env.insert("pprint".to_string(), Expr::Func(|args| rusht_pprint(&args)));
+    env.insert(
+        "pprint-str".to_string(),
+        Expr::Func(|args| rusht_pprint_str(&args)),
+    );
+}
+
+/// The documentation of the pretty printing builtins.
+pub const DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(pprint value [width])",
+        description: "Prints the value wrapped at the width (80 by default) and returns it.",
+    },
+    BuiltinDoc {
+        signature: "(pprint-str value [width])",
+        description: "Returns the value wrapped at the width (80 by default) as a string.",
+    },
+];
+
+/// Returns the given value as a string, wrapping lists exceeding the given
+/// width.
+///
+/// # Arguments
+///
+/// * `value` - The value to render.
+/// * `width` - The maximum width of a line. Values, that can't be wrapped,
+///     like long strings, may exceed it.
+#[must_use]
+pub fn pretty(value: &Expr, width: usize) -> String {
+    let mut out = String::new();
+    render(value, 0, width, &mut out);
+    out
+}
+
+/// Writes the given value starting at the given column.
+fn render(value: &Expr, column: usize, width: usize, out: &mut String) {
+    let flat = value.to_string();
+    match value {
+        Expr::List(list) if column + flat.chars().count() > width && list.len() > 1 => {
+            out.push('(');
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(column + 1));
+                }
+                render(item, column + 1, width, out);
+            }
+            out.push(')');
+        }
+        _ => out.push_str(&flat),
+    }
+}
+
+/// Returns the value and the width passed to a pretty printing builtin.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one or
+///     two.
+/// * `UnexpectedType` - If the width can't be coerced to a number.
+fn arguments(args: &[Expr]) -> Result<(&Expr, usize)> {
+    match args {
+        [value] => Ok((value, DEFAULT_WIDTH)),
+        [value, width] => {
+            let width: f64 = width.clone().try_into()?;
+            #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+            Ok((value, width as usize))
+        }
+        _ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Prints the given value to stdout, wrapped at the optional width, and
+/// returns it.
+///
+/// # Errors
+///
+/// See `arguments`.
+fn rusht_pprint(args: &[Expr]) -> Result<Expr> {
+    let (value, width) = arguments(args)?;
+    println!("{}", pretty(value, width));
+    Ok(value.clone())
+}
+
+/// Returns the given value wrapped at the optional width as a string.
+///
+/// # Errors
+///
+/// See `arguments`.
+fn rusht_pprint_str(args: &[Expr]) -> Result<Expr> {
+    let (value, width) = arguments(args)?;
+    Ok(Expr::Str(pretty(value, width)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Interpreter;
+
+    fn list(items: Vec<Expr>) -> Expr {
+        Expr::List(items)
+    }
+
+    #[test]
+    fn fits_on_line() {
+        let value = list(vec![Expr::Int(1), Expr::Str("a".to_string())]);
+        assert_eq!(pretty(&value, 80), "(1 \"a\")");
+        assert_eq!(pretty(&Expr::Num(1.5), 0), "1.5");
+    }
+
+    #[test]
+    fn wraps_nested_lists() {
+        let inner = list(vec![Expr::Int(10), Expr::Int(20), Expr::Int(30)]);
+        let value = list(vec![Expr::Int(1), inner, Expr::Bool(true)]);
+        assert_eq!(pretty(&value, 20), "(1 (10 20 30) true)");
+        assert_eq!(pretty(&value, 15), "(1\n (10 20 30)\n true)");
+        assert_eq!(pretty(&value, 8), "(1\n (10\n  20\n  30)\n true)");
+    }
+
+    #[test]
+    fn builtins() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(pprint-str (quote (1 (2 3))) 6)"),
+            Ok(Expr::Str("(1\n (2 3))".to_string()))
+        );
+        assert_eq!(interpreter.interpret("(pprint 1)"), Ok(Expr::Int(1)));
+        assert_eq!(
+            interpreter.interpret("(pprint-str 1 2 3)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+    }
+}
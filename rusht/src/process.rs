@@ -0,0 +1,251 @@
+//! Builtins spawning subprocesses, whose standard input and output are piped
+//! to the script, so interactive programs can be driven line by line. The
+//! processes are passed to scripts as external handles. Spawning a process is
+//! a privileged operation, which has to be allowed by the policy.
+use std::cell::RefCell;
+use std::convert::TryInto;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::expr::{Expr, External};
+use crate::{BuiltinDoc, Env, Error, Result};
+
+/// A running or exited subprocess.
+struct Process {
+    child: Child,
+    /// The standard input of the process, which is closed on waiting.
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+/// Defines the process builtins in the given environment.
+pub fn register(env: &mut Env) {
+    env.insert(
+        "spawn-process".to_string(),
+        Expr::Func(|args| rusht_spawn_process(&args)),
+    );
+    env.insert(
+        "proc-write".to_string(),
+        Expr::Func(|args| rusht_proc_write(&args)),
+    );
+    env.insert(
+        "proc-read-line".to_string(),
+        Expr::Func(|args| rusht_proc_read_line(&args)),
+    );
+    env.insert(
+        "proc-wait".to_string(),
+        Expr::Func(|args| rusht_proc_wait(&args)),
+    );
+}
+
+/// The documentation of the process builtins.
+pub const DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(spawn-process cmd [args])",
+        description: "Starts the command with the given list of arguments and returns its handle.",
+    },
+    BuiltinDoc {
+        signature: "(proc-write process str)",
+        description: "Writes the string to the standard input of the process.",
+    },
+    BuiltinDoc {
+        signature: "(proc-read-line process)",
+        description: "Reads a line from the standard output of the process, which is empty at its end.",
+    },
+    BuiltinDoc {
+        signature: "(proc-wait process)",
+        description: "Closes the standard input of the process, waits for it to exit, and returns its exit code, or -1 if it was terminated by a signal.",
+    },
+];
+
+/// Returns the process wrapped by the given handle.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the expression is no process handle.
+fn process(expr: &Expr) -> Result<&RefCell<Process>> {
+    match expr {
+        Expr::External(external) => external.downcast_ref().ok_or(Error::UnexpectedType),
+        _ => Err(Error::UnexpectedType),
+    }
+}
+
+/// Converts the given IO error into an error, that can be handled by scripts.
+#[allow(clippy::needless_pass_by_value)]
+fn process_failed(err: std::io::Error) -> Error {
+    Error::ProcessFailed(err.to_string())
+}
+
+/// Starts the given command with piped standard input and output. The
+/// standard error is inherited from the interpreter.
+///
+/// # Arguments
+///
+/// * `args[0]` - The command to run.
+/// * `args[1]` - The optional list of arguments passed to the command.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one or
+///     two.
+/// * `UnexpectedType` - If the command or one of its arguments can't be
+///     coerced to a string or the arguments are not a list.
+/// * `ProcessFailed` - If the process could not be started.
+fn rusht_spawn_process(args: &[Expr]) -> Result<Expr> {
+    let (cmd, cmd_args) = match args {
+        [cmd] => (cmd, vec![]),
+        [cmd, Expr::List(cmd_args)] => (cmd, cmd_args.clone()),
+        [_, _] => return Err(Error::UnexpectedType),
+        _ => return Err(Error::InvalidNumberOfArguments),
+    };
+    let cmd: String = cmd.clone().try_into()?;
+    let cmd_args = cmd_args
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect::<Result<Vec<String>>>()?;
+
+    let mut child = Command::new(cmd)
+        .args(cmd_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(process_failed)?;
+    let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Err(Error::ProcessFailed("failed to open pipes".to_string()));
+    };
+    Ok(Expr::External(External::new(RefCell::new(Process {
+        child,
+        stdin: Some(stdin),
+        stdout: BufReader::new(stdout),
+    }))))
+}
+
+/// Writes the given string to the standard input of the process and returns
+/// the number of bytes written.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not two.
+/// * `UnexpectedType` - If the first argument is not a process handle.
+/// * `ProcessFailed` - If writing failed, e.g. because the process exited.
+fn rusht_proc_write(args: &[Expr]) -> Result<Expr> {
+    let [handle, data] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let data: String = data.clone().try_into()?;
+    let mut process = process(handle)?.borrow_mut();
+    let stdin = process
+        .stdin
+        .as_mut()
+        .ok_or_else(|| Error::ProcessFailed("standard input is closed".to_string()))?;
+    stdin
+        .write_all(data.as_bytes())
+        .and_then(|()| stdin.flush())
+        .map_err(process_failed)?;
+    #[allow(clippy::cast_precision_loss)]
+    Ok(Expr::Num(data.len() as f64))
+}
+
+/// Reads a line, including the line break, from the standard output of the
+/// process. Once the process closed its output, an empty string is returned.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one.
+/// * `UnexpectedType` - If the argument is not a process handle.
+/// * `ProcessFailed` - If reading failed, e.g. because the output is not
+///     valid UTF-8.
+fn rusht_proc_read_line(args: &[Expr]) -> Result<Expr> {
+    let [handle] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let mut line = String::new();
+    process(handle)?
+        .borrow_mut()
+        .stdout
+        .read_line(&mut line)
+        .map_err(process_failed)?;
+    Ok(Expr::Str(line))
+}
+
+/// Closes the standard input of the process, so it sees the end of its
+/// input, and waits for it to exit.
+///
+/// # Errors
+///
+/// * `InvalidNumberOfArguments` - If the number of arguments is not one.
+/// * `UnexpectedType` - If the argument is not a process handle.
+/// * `ProcessFailed` - If waiting failed.
+fn rusht_proc_wait(args: &[Expr]) -> Result<Expr> {
+    let [handle] = args else {
+        return Err(Error::InvalidNumberOfArguments);
+    };
+    let mut process = process(handle)?.borrow_mut();
+    process.stdin = None;
+    let status = process.child.wait().map_err(process_failed)?;
+    Ok(Expr::Num(f64::from(status.code().unwrap_or(-1))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Interpreter, Operation};
+
+    #[test]
+    fn drive_process() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret(r#"(def cat (spawn-process "cat"))"#)
+            .expect("error");
+
+        assert_eq!(
+            interpreter.interpret(r#"(proc-write cat "hello world")"#),
+            Ok(Expr::Num(11.0))
+        );
+        assert_eq!(
+            interpreter.interpret(r#"(proc-write cat " ")"#),
+            Ok(Expr::Num(1.0))
+        );
+        interpreter.define("line", Expr::Str("again\n".to_string()));
+        interpreter
+            .interpret("(proc-write cat line)")
+            .expect("error");
+        assert_eq!(
+            interpreter.interpret("(proc-read-line cat)"),
+            Ok(Expr::Str("hello world again\n".to_string()))
+        );
+        assert_eq!(interpreter.interpret("(proc-wait cat)"), Ok(Expr::Num(0.0)));
+        assert_eq!(
+            interpreter.interpret("(proc-read-line cat)"),
+            Ok(Expr::Str(String::new()))
+        );
+    }
+
+    #[test]
+    fn exit_code() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret(r#"(proc-wait (spawn-process "sh" (quote ("-c" "exit 3"))))"#),
+            Ok(Expr::Num(3.0))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let mut interpreter = Interpreter::new();
+        assert!(matches!(
+            interpreter.interpret(r#"(spawn-process "does-not-exist")"#),
+            Err(Error::ProcessFailed(_))
+        ));
+        assert_eq!(
+            interpreter.interpret(r#"(spawn-process "sh" "-c")"#),
+            Err(Error::UnexpectedType)
+        );
+
+        interpreter.set_policy(|op, _: &[Expr]| op != Operation::Spawn);
+        assert_eq!(
+            interpreter.interpret(r#"(spawn-process "cat")"#),
+            Err(Error::OperationNotPermitted(Operation::Spawn))
+        );
+    }
+}
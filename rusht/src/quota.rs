@@ -0,0 +1,206 @@
+//! Quotas limit the size of the values produced during evaluation and the
+//! evaluation itself. This prevents scripts from allocating arbitrary amounts
+//! of memory, e.g. by repeatedly calling `concat`, from running forever, or
+//! from overflowing the stack by recursing endlessly, when embedding the
+//! interpreter.
+use std::fmt::{Display, Formatter};
+
+use crate::expr::Expr;
+use crate::{Error, Result};
+
+/// The different kinds of quotas.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Quota {
+    /// The maximum number of characters in a string.
+    StringLength,
+    /// The maximum number of elements in a list or entries in a map.
+    ListLength,
+    /// The maximum nesting depth of lists and maps.
+    Depth,
+    /// The maximum number of evaluation steps.
+    Steps,
+    /// The maximum depth of nested evaluations.
+    RecursionDepth,
+}
+
+impl Display for Quota {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Quota::StringLength => write!(f, "string length"),
+            Quota::ListLength => write!(f, "list length"),
+            Quota::Depth => write!(f, "nesting depth"),
+            Quota::Steps => write!(f, "evaluation steps"),
+            Quota::RecursionDepth => write!(f, "recursion depth"),
+        }
+    }
+}
+
+/// The default maximum number of nested evaluations. Each evaluation takes
+/// up to about 2 KiB of stack in release builds, so this keeps deep
+/// recursion well within the 8 MiB stack of the main thread on most
+/// platforms. Interpreters running on threads with smaller stacks, or built
+/// without optimizations, need a lower limit.
+pub const DEFAULT_MAX_RECURSION_DEPTH: usize = 3_000;
+
+/// Limits on the size of values produced during evaluation and on the
+/// evaluation itself. A limit of `None` means that there is no limit, which
+/// is the default for all quotas but the recursion depth, which is limited
+/// to `DEFAULT_MAX_RECURSION_DEPTH`, so deep recursion fails with an error
+/// instead of overflowing the stack.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Quotas {
+    /// The maximum number of characters in a string.
+    pub max_string_length: Option<usize>,
+
+    /// The maximum number of elements in a list.
+    pub max_list_length: Option<usize>,
+
+    /// The maximum nesting depth of lists. A value that is not a list has a
+    /// depth of zero, a flat list a depth of one.
+    pub max_depth: Option<usize>,
+
+    /// The maximum number of lists, i.e. calls and special forms, evaluated
+    /// for a single top-level expression.
+    pub max_steps: Option<usize>,
+
+    /// The maximum number of nested evaluations, e.g. of arguments within
+    /// calls. Each call of a function defined in a script nests a few
+    /// evaluations.
+    pub max_recursion_depth: Option<usize>,
+}
+
+impl Default for Quotas {
+    fn default() -> Quotas {
+        Quotas {
+            max_string_length: None,
+            max_list_length: None,
+            max_depth: None,
+            max_steps: None,
+            max_recursion_depth: Some(DEFAULT_MAX_RECURSION_DEPTH),
+        }
+    }
+}
+
+impl Quotas {
+    /// Checks the given value against the quotas.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - The value to check.
+    ///
+    /// # Errors
+    ///
+    /// * `QuotaExceeded` - If the value exceeds one of the quotas.
+    pub(crate) fn check(&self, expr: &Expr) -> Result<()> {
+        if self.max_string_length.is_none()
+            && self.max_list_length.is_none()
+            && self.max_depth.is_none()
+        {
+            return Ok(());
+        }
+        self.check_nested(expr, 0)
+    }
+
+    /// Checks the number of evaluation steps taken against the quotas.
+    ///
+    /// # Errors
+    ///
+    /// * `QuotaExceeded` - If the number exceeds the quota.
+    pub(crate) fn check_steps(&self, steps: usize) -> Result<()> {
+        ensure(Quota::Steps, self.max_steps, steps)
+    }
+
+    /// Checks the depth of nested evaluations against the quotas.
+    ///
+    /// # Errors
+    ///
+    /// * `QuotaExceeded` - If the depth exceeds the quota.
+    pub(crate) fn check_recursion_depth(&self, depth: usize) -> Result<()> {
+        ensure(Quota::RecursionDepth, self.max_recursion_depth, depth)
+    }
+
+    /// Checks the given value, which is nested at the given depth, against
+    /// the quotas.
+    fn check_nested(&self, expr: &Expr, depth: usize) -> Result<()> {
+        match expr {
+            Expr::Str(s) => ensure(
+                Quota::StringLength,
+                self.max_string_length,
+                s.chars().count(),
+            ),
+            Expr::List(list) | Expr::Vector(list) => {
+                ensure(Quota::Depth, self.max_depth, depth + 1)?;
+                ensure(Quota::ListLength, self.max_list_length, list.len())?;
+                list.iter()
+                    .try_for_each(|expr| self.check_nested(expr, depth + 1))
+            }
+            Expr::Map(map) => {
+                ensure(Quota::Depth, self.max_depth, depth + 1)?;
+                ensure(Quota::ListLength, self.max_list_length, map.len())?;
+                map.iter().try_for_each(|(key, value)| {
+                    self.check_nested(key.as_expr(), depth + 1)?;
+                    self.check_nested(value, depth + 1)
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Ensures that the given value does not exceed the given limit.
+fn ensure(quota: Quota, limit: Option<usize>, value: usize) -> Result<()> {
+    match limit {
+        Some(limit) if value > limit => Err(Error::QuotaExceeded(quota)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unlimited() {
+        let expr = Expr::List(vec![Expr::Str("foo".repeat(100))]);
+        assert_eq!(Quotas::default().check(&expr), Ok(()));
+    }
+
+    #[test]
+    fn string_length() {
+        let quotas = Quotas {
+            max_string_length: Some(3),
+            ..Quotas::default()
+        };
+        assert_eq!(quotas.check(&Expr::Str("foo".to_string())), Ok(()));
+        assert_eq!(
+            quotas.check(&Expr::List(vec![Expr::Str("foob".to_string())])),
+            Err(Error::QuotaExceeded(Quota::StringLength))
+        );
+    }
+
+    #[test]
+    fn list_length() {
+        let quotas = Quotas {
+            max_list_length: Some(1),
+            ..Quotas::default()
+        };
+        assert_eq!(
+            quotas.check(&Expr::List(vec![Expr::Num(1.0), Expr::Num(2.0)])),
+            Err(Error::QuotaExceeded(Quota::ListLength))
+        );
+    }
+
+    #[test]
+    fn depth() {
+        let quotas = Quotas {
+            max_depth: Some(1),
+            ..Quotas::default()
+        };
+        assert_eq!(quotas.check(&Expr::List(vec![Expr::Num(1.0)])), Ok(()));
+        assert_eq!(
+            quotas.check(&Expr::List(vec![Expr::List(vec![])])),
+            Err(Error::QuotaExceeded(Quota::Depth))
+        );
+    }
+}
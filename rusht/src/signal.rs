@@ -0,0 +1,158 @@
+//! Handling of `SIGINT` and `SIGTERM`, available with the `signals` feature.
+//! Once a script registers a handler using `on-sigint` or `on-sigterm`, the
+//! signal no longer terminates the process. Instead, the signal handler only
+//! counts the signal, and each interpreter checks for signals counted since it
+//! last handled them before each call. If there is one, for which it has a
+//! handler, the handler of the script is called, e.g. to clean up, and the
+//! evaluation is aborted with an `Interrupted` error. Interpreters without a
+//! handler for the signal are not interrupted.
+//! Builtins blocking on IO, like `read`, are not interrupted, so the signal
+//! is handled once they return.
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of times each signal has been received, indexed by
+/// `Signal::index`.
+static RECEIVED: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+/// A signal, for which scripts can register handlers.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Signal {
+    /// `SIGINT`, usually sent by pressing Ctrl-C.
+    Interrupt,
+    /// `SIGTERM`, usually sent to request termination.
+    Terminate,
+}
+
+impl Signal {
+    /// All signals, for which handlers can be registered.
+    const ALL: [Signal; 2] = [Signal::Interrupt, Signal::Terminate];
+
+    /// Returns the number of the signal, e.g. 2 for `SIGINT`.
+    #[must_use]
+    pub fn number(self) -> i32 {
+        match self {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+        }
+    }
+
+    /// Returns the index of the signal in `RECEIVED`.
+    fn index(self) -> usize {
+        match self {
+            Signal::Interrupt => 0,
+            Signal::Terminate => 1,
+        }
+    }
+
+    /// Returns the number of times the signal has been received.
+    pub(crate) fn received(self) -> usize {
+        RECEIVED[self.index()].load(Ordering::SeqCst)
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Signal::Interrupt => write!(f, "SIGINT"),
+            Signal::Terminate => write!(f, "SIGTERM"),
+        }
+    }
+}
+
+/// Records the signal of the given number. Only async-signal-safe operations
+/// may be performed here, so the signal is handled later on.
+extern "C" fn record(number: libc::c_int) {
+    for signal in Signal::ALL {
+        if signal.number() == number {
+            RECEIVED[signal.index()].fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Replaces the default action of the given signal, terminating the process,
+/// with recording it.
+#[allow(unsafe_code)]
+pub(crate) fn install(signal: Signal) {
+    let handler = record as extern "C" fn(libc::c_int);
+    // The handler only performs an atomic operation, which is
+    // async-signal-safe.
+    unsafe {
+        libc::signal(signal.number(), handler as libc::sighandler_t);
+    }
+}
+
+/// Returns a signal received since it was last handled, if any, and marks it
+/// as handled.
+///
+/// # Arguments
+///
+/// * `handled` - The number of times each signal, for which the interpreter
+///     has a handler, was received when it was last handled.
+pub(crate) fn take_pending(handled: &mut HashMap<Signal, usize>) -> Option<Signal> {
+    Signal::ALL.iter().copied().find(|signal| {
+        handled.get_mut(signal).is_some_and(|handled| {
+            let received = signal.received();
+            let pending = *handled != received;
+            *handled = received;
+            pending
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{Error, Expr, Interpreter, LogRecord};
+
+    /// The only test recording signals, which are received by all
+    /// interpreters, including those of tests running concurrently.
+    #[test]
+    fn handle_signal() {
+        let messages = Rc::new(RefCell::new(vec![]));
+        let mut interpreters = vec![];
+        for name in ["first", "second"] {
+            let sink = Rc::clone(&messages);
+            let mut interpreter = Interpreter::new();
+            interpreter.set_logger(move |record: &LogRecord| {
+                sink.borrow_mut().push(record.message.clone());
+            });
+            interpreter
+                .interpret(format!(r#"(on-sigterm (func () (log-info "{name}")))"#))
+                .expect("error");
+            interpreters.push(interpreter);
+        }
+        let mut other = Interpreter::new();
+        other.interpret("(on-sigint (func () nil))").expect("error");
+
+        assert_eq!(interpreters[0].interpret("(+ 1 2)"), Ok(Expr::Int(3)));
+        record(libc::SIGTERM);
+        for interpreter in &mut interpreters {
+            assert_eq!(
+                interpreter.interpret("(+ 1 2)"),
+                Err(Error::Interrupted(Signal::Terminate))
+            );
+            assert_eq!(interpreter.interpret("(+ 1 2)"), Ok(Expr::Int(3)));
+        }
+        assert_eq!(*messages.borrow(), vec!["first", "second"]);
+        assert_eq!(other.interpret("(+ 1 2)"), Ok(Expr::Int(3)));
+    }
+
+    #[test]
+    fn invalid_handler() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.interpret("(on-sigint 1)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(on-sigint)"),
+            Err(Error::InvalidNumberOfArguments)
+        );
+    }
+}
@@ -0,0 +1,433 @@
+//! Macros defined using `syntax-rules`. A macro consists of rules, each a
+//! pattern and a template. The first rule, whose pattern matches the use of
+//! the macro, is expanded by substituting the pattern variables in its
+//! template, and the expansion is interpreted in place of the use.
+//!
+//! Patterns are lists, whose first element stands for the name of the macro
+//! and is ignored. Identifiers in patterns are pattern variables matching
+//! any expression, except for `_`, which matches anything without binding
+//! it, and the literals of the macro, which match only themselves. A pattern
+//! followed by `...` matches any number of expressions, and the template
+//! followed by `...` is repeated for each of them.
+//!
+//! Macros are hygienic in that the variables bound by the templates, e.g.
+//! using `let` or `func`, are renamed to fresh identifiers on each
+//! expansion, so they do not capture the variables of the code passed to
+//! the macro.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::expr::Expr;
+use crate::{Error, Result};
+
+/// The identifier marking a repeated pattern or template.
+const ELLIPSIS: &str = "...";
+
+/// The number of identifiers generated so far.
+static GENERATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a fresh identifier starting with the given prefix, that is
+/// different from all identifiers generated before.
+pub fn gensym(prefix: &str) -> String {
+    format!("{}#{}", prefix, GENERATED.fetch_add(1, Ordering::Relaxed))
+}
+
+/// A macro defined using `syntax-rules`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    /// The identifiers, that only match themselves in patterns.
+    literals: Vec<String>,
+
+    /// The patterns and templates of the rules.
+    rules: Vec<(Vec<Expr>, Expr)>,
+}
+
+/// The expressions matched by a pattern variable: either a single one, or
+/// one per expression matched by a repeated pattern.
+#[derive(Debug, Clone, PartialEq)]
+enum Binding {
+    One(Expr),
+    Many(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+impl Macro {
+    /// Creates a macro from the arguments of `syntax-rules`.
+    ///
+    /// # Arguments
+    ///
+    /// * `args[0]` - The list of literals.
+    /// * `args[1..]` - The rules, each a list of a pattern and a template.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidNumberOfArguments` - If no literals are passed.
+    /// * `UnexpectedType` - If the literals are not a list of identifiers, a
+    ///     rule is not a list of a non-empty list and a template, or a list
+    ///     in a pattern contains more than one or a leading `...`.
+    pub fn new(args: &[Expr]) -> Result<Macro> {
+        let Some((Expr::List(literals), rules)) = args.split_first() else {
+            return match args {
+                [] => Err(Error::InvalidNumberOfArguments),
+                _ => Err(Error::UnexpectedType),
+            };
+        };
+        let literals = literals
+            .iter()
+            .map(|literal| match literal {
+                Expr::Ident(literal) => Ok(literal.clone()),
+                _ => Err(Error::UnexpectedType),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let rules = rules
+            .iter()
+            .map(|rule| match rule {
+                Expr::List(rule) => match rule.as_slice() {
+                    [Expr::List(pattern), template] if !pattern.is_empty() => {
+                        check(&pattern[1..])?;
+                        Ok((pattern[1..].to_vec(), template.clone()))
+                    }
+                    _ => Err(Error::UnexpectedType),
+                },
+                _ => Err(Error::UnexpectedType),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Macro { literals, rules })
+    }
+
+    /// Expands the use of the macro with the given arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name under which the macro is used.
+    /// * `args` - The expressions passed to the macro, which are not
+    ///     interpreted.
+    ///
+    /// # Errors
+    ///
+    /// * `NoMatchingRule` - If no pattern matches the arguments.
+    /// * `UnexpectedType` - If a template uses a pattern variable with a
+    ///     different number of `...` than the pattern.
+    pub fn expand(&self, name: &str, args: &[Expr]) -> Result<Expr> {
+        for (pattern, template) in &self.rules {
+            let mut bindings = Bindings::new();
+            if self.bind_list(pattern, args, &mut bindings) {
+                let mut binders = vec![];
+                collect_binders(template, &mut binders);
+                let renames = binders
+                    .into_iter()
+                    .filter(|binder| !bindings.contains_key(binder))
+                    .map(|binder| {
+                        let fresh = Binding::One(Expr::Ident(gensym(&binder)));
+                        (binder, fresh)
+                    })
+                    .collect::<Bindings>();
+                bindings.extend(renames);
+                return substitute(template, &bindings);
+            }
+        }
+        Err(Error::NoMatchingRule(name.to_string()))
+    }
+
+    /// Matches the given expression against the pattern and adds the bound
+    /// pattern variables to `bindings`.
+    fn bind(&self, pattern: &Expr, expr: &Expr, bindings: &mut Bindings) -> bool {
+        match pattern {
+            Expr::Ident(name) if name == "_" => true,
+            Expr::Ident(name) if self.literals.contains(name) => {
+                matches!(expr, Expr::Ident(ident) if ident == name)
+            }
+            Expr::Ident(name) => {
+                bindings.insert(name.clone(), Binding::One(expr.clone()));
+                true
+            }
+            Expr::List(patterns) => match expr {
+                Expr::List(exprs) => self.bind_list(patterns, exprs, bindings),
+                _ => false,
+            },
+            _ => pattern.is_equal(expr),
+        }
+    }
+
+    /// Matches the given expressions against the list of patterns, which
+    /// may contain a repeated pattern, and adds the bound pattern variables
+    /// to `bindings`.
+    fn bind_list(&self, patterns: &[Expr], exprs: &[Expr], bindings: &mut Bindings) -> bool {
+        let Some(ellipsis) = patterns.iter().position(is_ellipsis) else {
+            return patterns.len() == exprs.len()
+                && patterns
+                    .iter()
+                    .zip(exprs)
+                    .all(|(pattern, expr)| self.bind(pattern, expr, bindings));
+        };
+        let (before, after) = (&patterns[..ellipsis - 1], &patterns[ellipsis + 1..]);
+        if exprs.len() < before.len() + after.len() {
+            return false;
+        }
+        let (head, rest) = exprs.split_at(before.len());
+        let (repeated, tail) = rest.split_at(rest.len() - after.len());
+        if !self.bind_list(before, head, bindings) || !self.bind_list(after, tail, bindings) {
+            return false;
+        }
+
+        let pattern = &patterns[ellipsis - 1];
+        let mut matches = Vec::with_capacity(repeated.len());
+        for expr in repeated {
+            let mut repetition = Bindings::new();
+            if !self.bind(pattern, expr, &mut repetition) {
+                return false;
+            }
+            matches.push(repetition);
+        }
+        let mut names = vec![];
+        collect_idents(pattern, &mut names);
+        for name in names {
+            if name == "_" || self.literals.contains(&name) {
+                continue;
+            }
+            let repetitions = matches
+                .iter_mut()
+                .filter_map(|repetition| repetition.remove(&name))
+                .collect();
+            bindings.insert(name, Binding::Many(repetitions));
+        }
+        true
+    }
+}
+
+/// Checks whether each list in the given patterns contains at most one
+/// `...`, which follows a pattern.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If a list contains more than one or a leading `...`.
+fn check(patterns: &[Expr]) -> Result<()> {
+    match patterns.iter().position(is_ellipsis) {
+        Some(0) => return Err(Error::UnexpectedType),
+        Some(i) if patterns[i + 1..].iter().any(is_ellipsis) => return Err(Error::UnexpectedType),
+        _ => {}
+    }
+    patterns.iter().try_for_each(|pattern| match pattern {
+        Expr::List(patterns) => check(patterns),
+        _ => Ok(()),
+    })
+}
+
+/// Returns whether the given expression is `...`.
+fn is_ellipsis(expr: &Expr) -> bool {
+    matches!(expr, Expr::Ident(ident) if ident == ELLIPSIS)
+}
+
+/// Adds the identifiers in the given expression to `names`.
+fn collect_idents(expr: &Expr, names: &mut Vec<String>) {
+    match expr {
+        Expr::Ident(ident) if ident != ELLIPSIS => names.push(ident.clone()),
+        Expr::List(exprs) => exprs.iter().for_each(|expr| collect_idents(expr, names)),
+        _ => {}
+    }
+}
+
+/// Adds the variables bound by `let`, `let*`, `letrec`, `loop`, `for`,
+/// `dotimes`, and `func` forms in the given template to `names`.
+fn collect_binders(template: &Expr, names: &mut Vec<String>) {
+    let Expr::List(exprs) = template else {
+        return;
+    };
+    match exprs.as_slice() {
+        [Expr::Ident(form), Expr::List(bindings), ..]
+            if matches!(form.as_str(), "let" | "let*" | "letrec" | "loop") =>
+        {
+            for binding in bindings {
+                if let Expr::List(binding) = binding {
+                    if let Some(Expr::Ident(name)) = binding.first() {
+                        names.push(name.clone());
+                    }
+                }
+            }
+        }
+        [Expr::Ident(form), Expr::List(binding), ..] if form == "for" || form == "dotimes" => {
+            if let Some(Expr::Ident(name)) = binding.first() {
+                names.push(name.clone());
+            }
+        }
+        [Expr::Ident(form), Expr::List(params), ..] if form == "func" => {
+            for param in params {
+                match param {
+                    Expr::Ident(name) if name != "&" && name != ELLIPSIS => {
+                        names.push(name.clone());
+                    }
+                    Expr::List(param) => {
+                        if let Some(Expr::Ident(name)) = param.first() {
+                            names.push(name.clone());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    for expr in exprs {
+        collect_binders(expr, names);
+    }
+}
+
+/// Substitutes the bound pattern variables in the given template.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If a pattern variable is used with a different
+///     number of `...` than in the pattern.
+fn substitute(template: &Expr, bindings: &Bindings) -> Result<Expr> {
+    match template {
+        Expr::Ident(name) => match bindings.get(name) {
+            Some(Binding::One(expr)) => Ok(expr.clone()),
+            Some(Binding::Many(_)) => Err(Error::UnexpectedType),
+            None => Ok(template.clone()),
+        },
+        Expr::List(templates) => {
+            let mut out = Vec::with_capacity(templates.len());
+            let mut templates = templates.iter().peekable();
+            while let Some(template) = templates.next() {
+                if templates
+                    .next_if(|template| is_ellipsis(template))
+                    .is_some()
+                {
+                    out.extend(substitute_repeated(template, bindings)?);
+                } else {
+                    out.push(substitute(template, bindings)?);
+                }
+            }
+            Ok(Expr::List(out))
+        }
+        _ => Ok(template.clone()),
+    }
+}
+
+/// Substitutes the given template followed by `...` once for each of the
+/// expressions matched by the repeated pattern variables it uses.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the template uses no repeated pattern variables
+///     or ones of different lengths.
+fn substitute_repeated(template: &Expr, bindings: &Bindings) -> Result<Vec<Expr>> {
+    let mut names = vec![];
+    collect_idents(template, &mut names);
+    let repeated = names
+        .into_iter()
+        .filter_map(|name| match bindings.get(&name) {
+            Some(Binding::Many(repetitions)) => Some((name, repetitions)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let Some((_, first)) = repeated.first() else {
+        return Err(Error::UnexpectedType);
+    };
+    if repeated
+        .iter()
+        .any(|(_, repetitions)| repetitions.len() != first.len())
+    {
+        return Err(Error::UnexpectedType);
+    }
+
+    (0..first.len())
+        .map(|i| {
+            let mut bindings = bindings.clone();
+            for (name, repetitions) in &repeated {
+                bindings.insert(name.clone(), repetitions[i].clone());
+            }
+            substitute(template, &bindings)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(source: &str) -> Expr {
+        crate::parse(source).expect("invalid source").remove(0)
+    }
+
+    fn expand(rules: &str, expr: &str) -> Result<Expr> {
+        let (Expr::List(args), Expr::List(exprs)) = (read(rules), read(expr)) else {
+            unreachable!("rules and uses are lists");
+        };
+        Macro::new(&args)?.expand("m", &exprs[1..])
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            expand("(() ((_ a b) (+ b a)))", "(m 1 (f 2))"),
+            Ok(read("(+ (f 2) 1)"))
+        );
+        assert_eq!(
+            expand("((else) ((_ else x) x) ((_ a x) a))", "(m else 1)"),
+            Ok(Expr::Int(1))
+        );
+        assert_eq!(
+            expand("((else) ((_ else x) x) ((_ a x) a))", "(m y 1)"),
+            Ok(Expr::Ident("y".to_string()))
+        );
+        assert_eq!(
+            expand("(() ((_ a) a))", "(m 1 2)"),
+            Err(Error::NoMatchingRule("m".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_expand_repeated() {
+        assert_eq!(
+            expand(
+                "(() ((_ (k v) ... last) (f last (k ...) (g v) ...)))",
+                "(m (a 1) (b 2) 3)"
+            ),
+            Ok(read("(f 3 (a b) (g 1) (g 2))"))
+        );
+        assert_eq!(expand("(() ((_ x ...) (f x ...)))", "(m)"), Ok(read("(f)")));
+        assert_eq!(
+            expand("(() ((_ x ...) (f x)))", "(m 1)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            expand("(() ((_ ... x) x))", "(m 1)"),
+            Err(Error::UnexpectedType)
+        );
+    }
+
+    #[test]
+    fn test_expand_renames_binders() {
+        let Ok(Expr::List(expansion)) = expand(
+            "(() ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp))))",
+            "(m tmp y)",
+        ) else {
+            panic!("expansion is a list");
+        };
+        let Expr::List(bindings) = &expansion[1] else {
+            panic!("bindings are a list");
+        };
+        let Expr::List(binding) = &bindings[0] else {
+            panic!("binding is a list");
+        };
+        assert_ne!(binding[0], Expr::Ident("tmp".to_string()));
+        assert_eq!(binding[1], Expr::Ident("tmp".to_string()));
+        assert_eq!(
+            expansion[3],
+            Expr::List(vec![
+                Expr::Ident("set!".to_string()),
+                Expr::Ident("y".to_string()),
+                binding[0].clone()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_gensym() {
+        let a = gensym("x");
+        assert!(a.starts_with("x#"));
+        assert_ne!(a, gensym("x"));
+    }
+}
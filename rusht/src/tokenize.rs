@@ -2,17 +2,58 @@
 //! Here we pass our terminal input into a token stream.
 //! This gives us the opportunity to first of all identify our input's data types.
 //! And secondly put everything together in a tokenstream for passing it to the next step.
+//!
+//! Apart from parentheses, braces, brackets, and string literals, tokens are
+//! words ending at whitespace or any of those. Words starting with an ASCII
+//! digit, or a minus sign followed by one, are numbers and may only contain
+//! ASCII digits and periods, followed by an optional exponent, e.g. `1e300`
+//! or `-1.5`. Numbers without a period or exponent are integers, unless they
+//! are too large for 64 bits, and two integers separated by a slash are
+//! ratios, e.g. `-1/3`. All other words
+//! are identifiers (or booleans, `nil`, the floats `inf`, `-inf`, and `nan`,
+//! and keywords, which start with a colon, e.g. `:name`): they start with a
+//! character of the Unicode class `XID_Start`, an underscore, or an ASCII
+//! symbol, such as `+` or `*`, and continue with characters of the class `XID_Continue` or ASCII
+//! symbols. Thus, letters of any script and combining characters can be used
+//! in identifiers, while typographic quotes (like `“`) or emoji can not.
+//! An apostrophe, backquote, comma, or comma followed by an at sign at the
+//! start of a token is a quote token of its own, so `'(1 2)` is read as a
+//! quote followed by a list. Braces enclose map literals and brackets vector
+//! literals, both are tokenized like parentheses. A number sign followed by a
+//! backslash starts a character literal, which is either a single character,
+//! e.g. `#\a` or `#\(`, or the name of one, i.e. `#\space` or `#\newline`.
 use std::iter::Peekable;
-use std::str::Chars;
+use std::ops::Range;
+use std::str::CharIndices;
+
+use unicode_xid::UnicodeXID;
+
+use crate::number::{self, Number};
+use crate::{Error, Result};
+
+/// The byte range of a token within the input.
+pub type Span = Range<usize>;
 
 /// Represent the datatypes that are defines as a Token.
 #[derive(Debug, PartialEq)]
 pub enum Token {
+    /// An opening or closing parenthesis, brace, or bracket.
     Paren(char),
+    /// The prefix quoting the following expression: an apostrophe (quote),
+    /// backquote (quasiquote), comma (unquote), or comma followed by an at
+    /// sign (unquote-splicing).
+    Quote(&'static str),
+    Int(i64),
+    /// A ratio in lowest terms, e.g. `1/3`.
+    Ratio(i64, i64),
     Num(f64),
     Str(String),
+    Char(char),
     Ident(String),
+    /// A keyword, e.g. `:name`, without its leading colon.
+    Keyword(String),
     Bool(bool),
+    Nil,
 }
 
 /// Takes the input from our terminal and checks each char with allocating it to the right function.
@@ -21,44 +62,182 @@ pub enum Token {
 /// # Arguments
 ///
 /// * `input` - The passed input.
-pub fn tokenize(input: &str) -> Vec<Token> {
-    let mut tokens = vec![];
-
-    let mut it = input.chars().peekable();
-    while let Some(c) = it.peek() {
-        match c {
-            '(' | ')' => tokens.push(Token::Paren(it.next().unwrap())),
-            '0'..='9' => tokens.push(take_number(&mut it)),
-            '"' => tokens.push(take_str(&mut it)),
-            _ if c.is_whitespace() => {
-                it.next();
+///
+/// # Errors
+///
+/// * `InvalidNumber` - If a number literal can't be parsed, e.g. `1.2.3`.
+/// * `UnterminatedString` - If a string literal is missing its closing
+///     quotation mark.
+/// * `InvalidCharacter` - If a character literal names no character.
+/// * `UnexpectedCharacter` - If an identifier contains a character, that is
+///     not allowed in identifiers, e.g. `“`.
+pub fn tokenize(input: &str) -> Result<Vec<Token>> {
+    tokenize_spanned(input).map(|tokens| tokens.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Like `tokenize`, but returns the span of each token in the input, too.
+///
+/// # Arguments
+///
+/// * `input` - The passed input.
+///
+/// # Errors
+///
+/// * `InvalidNumber` - If a number literal can't be parsed, e.g. `1.2.3`.
+/// * `UnterminatedString` - If a string literal is missing its closing
+///     quotation mark.
+/// * `InvalidCharacter` - If a character literal names no character.
+/// * `UnexpectedCharacter` - If an identifier contains a character, that is
+///     not allowed in identifiers, e.g. `“`.
+pub fn tokenize_spanned(input: &str) -> Result<Vec<(Token, Span)>> {
+    Lexer::new(input)
+        .map(|(token, span)| token.map(|token| (token, span)))
+        .collect()
+}
+
+/// An iterator over the tokens of an input and their spans. Unlike
+/// `tokenize`, the lexer continues after invalid tokens.
+pub struct Lexer<'a> {
+    input: &'a str,
+    it: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a new lexer for the given input.
+    pub fn new(input: &'a str) -> Lexer<'a> {
+        Lexer {
+            input,
+            it: input.char_indices().peekable(),
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = (Result<Token>, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.it.peek()?.1.is_whitespace() {
+            self.it.next();
+        }
+
+        let &(start, c) = self.it.peek()?;
+        let token = match c {
+            '(' | ')' | '{' | '}' | '[' | ']' => Ok(Token::Paren(self.it.next()?.1)),
+            '\'' | '`' | ',' => Ok(Token::Quote(take_quote(&mut self.it))),
+            '"' => take_str(&mut self.it),
+            '#' if self.input[start..].starts_with("#\\") => take_char(&mut self.it),
+            '0'..='9' => parse_number(take_word(&mut self.it)),
+            '-' if self.input[start + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+                parse_number(take_word(&mut self.it))
             }
-            _ => tokens.push(take_ident_or_bool(&mut it)),
+            _ => parse_ident_or_bool(take_word(&mut self.it)),
         };
-    }
+        let end = self.it.peek().map_or(self.input.len(), |&(i, _)| i);
 
-    tokens
+        Some((token, start..end))
+    }
 }
 
-/// Takes a single number from the characters. Numbers are made up of the
-/// numerals from 0 to 9 as well as the period (.) character.
+/// Takes a single word from the characters. Words end at whitespace, a
+/// parenthesis, a brace, or a bracket.
 ///
 /// # Arguments
 ///
-/// * `it` - The passed number of our input.
-fn take_number(it: &mut Peekable<Chars>) -> Token {
+/// * `it` - The passed word of our input.
+fn take_word(it: &mut Peekable<CharIndices>) -> String {
     let mut val = String::new();
 
     // We can not use take_while here, as it always consumes the next token
     // instead of just peeking it.
-    while let Some(c) = it.peek() {
-        if !c.is_numeric() && *c != '.' {
+    while let Some(&(_, c)) = it.peek() {
+        if c.is_whitespace() || matches!(c, '(' | ')' | '{' | '}' | '[' | ']') {
             break;
         }
-        val.push(it.next().unwrap())
+        val.push(c);
+        it.next();
     }
+    val
+}
 
-    Token::Num(val.parse().unwrap())
+/// Takes a quote prefix from the characters, i.e. an apostrophe, backquote,
+/// comma, or a comma followed by an at sign.
+/// This function assumes the passed iterator to have one of the first three
+/// at the beginning and skips it without further checks.
+///
+/// # Arguments
+///
+/// * `it` - The passed quote of our input.
+fn take_quote(it: &mut Peekable<CharIndices>) -> &'static str {
+    match it.next() {
+        Some((_, '\'')) => "'",
+        Some((_, '`')) => "`",
+        _ if it.next_if(|&(_, c)| c == '@').is_some() => ",@",
+        _ => ",",
+    }
+}
+
+/// Parses a number from the given word. Numbers are made up of an optional
+/// minus sign, the ASCII numerals from 0 to 9 as well as the period (.)
+/// character, optionally followed by an exponent, i.e. an `e` and an integer
+/// with an optional sign, e.g. `-1.5e-7`. Numbers without a period and
+/// exponent are parsed as integers, if they fit into 64 bits, and all others
+/// as floats. Two integers separated by a slash are parsed as a ratio, which
+/// is reduced to lowest terms, e.g. `2/4` is `1/2` and `4/2` is `2`.
+///
+/// # Arguments
+///
+/// * `val` - The word to parse.
+///
+/// # Errors
+///
+/// * `InvalidNumber` - If the word is not a valid number, e.g. a ratio whose
+///     denominator is zero.
+fn parse_number(val: String) -> Result<Token> {
+    let (negative, unsigned) = match val.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, val.as_str()),
+    };
+    if let Some((numerator, denominator)) = unsigned.split_once('/') {
+        return match parse_ratio(numerator, denominator) {
+            Some(Token::Int(x)) if negative => Ok(Token::Int(-x)),
+            Some(Token::Ratio(n, d)) if negative => Ok(Token::Ratio(-n, d)),
+            Some(token) => Ok(token),
+            None => Err(Error::InvalidNumber(val)),
+        };
+    }
+    let (mantissa, exponent) = match unsigned.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let valid_exponent = exponent.is_none_or(|exponent| {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    });
+    if !mantissa.chars().all(|c| c.is_ascii_digit() || c == '.') || !valid_exponent {
+        return Err(Error::InvalidNumber(val));
+    }
+    if let Ok(x) = val.parse() {
+        return Ok(Token::Int(x));
+    }
+    val.parse()
+        .map(Token::Num)
+        .map_err(|_| Error::InvalidNumber(val))
+}
+
+/// Parses a ratio from the given numerator and denominator, or returns
+/// `None`, if either is not an integer or the denominator is zero.
+fn parse_ratio(numerator: &str, denominator: &str) -> Option<Token> {
+    let parse = |val: &str| {
+        val.chars()
+            .all(|c| c.is_ascii_digit())
+            .then(|| val.parse::<i64>().ok())
+            .flatten()
+    };
+    match number::ratio(parse(numerator)?.into(), parse(denominator)?.into())? {
+        Number::Int(x) => Some(Token::Int(x)),
+        Number::Ratio(n, d) => Some(Token::Ratio(n, d)),
+        Number::Float(_) => None,
+    }
 }
 
 /// Takes a string from the characters. Strings start and stop with a
@@ -69,31 +248,113 @@ fn take_number(it: &mut Peekable<Chars>) -> Token {
 /// # Arguments
 ///
 /// * `it` - The passed string of our input.
-fn take_str(it: &mut Peekable<Chars>) -> Token {
+///
+/// # Errors
+///
+/// * `UnterminatedString` - If the closing quotation mark is missing.
+fn take_str(it: &mut Peekable<CharIndices>) -> Result<Token> {
     // Skip the leading quotation mark without any further checks. This is
     // fine here, as we control all the invocations of this function.
-    Token::Str(it.skip(1).take_while(|&c| c != '"').collect())
+    it.next();
+
+    let mut val = String::new();
+    for (_, c) in it {
+        if c == '"' {
+            return Ok(Token::Str(val));
+        }
+        val.push(c);
+    }
+    Err(Error::UnterminatedString)
 }
 
-/// Takes an identifier or boolean from the characters. The token is assumed to
-/// end at the first occurrence of whitespace.
+/// Takes a character literal from the characters. The character following
+/// the backslash is part of the literal, even if it is whitespace or a
+/// parenthesis, while any further characters are the name of a character.
+/// This function assumes the passed iterator to have the number sign and the
+/// backslash at the beginning and skips them without further checks.
 ///
 /// # Arguments
 ///
-/// * `it` - The passed identifier of our input.
-fn take_ident_or_bool(it: &mut Peekable<Chars>) -> Token {
-    let mut val = String::new();
+/// * `it` - The passed character literal of our input.
+///
+/// # Errors
+///
+/// * `InvalidCharacter` - If the literal is empty or names no character.
+fn take_char(it: &mut Peekable<CharIndices>) -> Result<Token> {
+    it.next();
+    it.next();
 
-    while let Some(c) = it.peek() {
-        if c.is_whitespace() || *c == '(' || *c == ')' {
-            break;
-        }
-        val.push(it.next().unwrap())
+    let Some((_, first)) = it.next() else {
+        return Err(Error::InvalidCharacter("#\\".to_string()));
+    };
+    let rest = take_word(it);
+    match (first, rest.as_str()) {
+        (c, "") => Ok(Token::Char(c)),
+        ('s', "pace") => Ok(Token::Char(' ')),
+        ('n', "ewline") => Ok(Token::Char('\n')),
+        _ => Err(Error::InvalidCharacter(format!("#\\{}{}", first, rest))),
+    }
+}
+
+/// Parses an identifier, keyword, boolean, or `nil` from the given word, or
+/// one of the floats `inf`, `-inf`, and `nan`, which can't be written as
+/// number literals.
+///
+/// # Arguments
+///
+/// * `val` - The word to parse.
+///
+/// # Errors
+///
+/// * `UnexpectedCharacter` - If the word contains a character, that is not
+///     allowed in identifiers, or does not start with a character, that
+///     identifiers may start with.
+fn parse_ident_or_bool(val: String) -> Result<Token> {
+    let mut chars = val.chars();
+    if let Some(c) = chars.next().filter(|&c| !is_ident_start(c)) {
+        return Err(Error::UnexpectedCharacter(c));
+    }
+    if let Some(c) = chars.find(|&c| !is_ident_continue(c)) {
+        return Err(Error::UnexpectedCharacter(c));
     }
 
-    match val.as_str() {
+    Ok(match val.as_str() {
         "true" | "false" => Token::Bool(val.parse().unwrap()),
+        "nil" => Token::Nil,
+        "inf" => Token::Num(f64::INFINITY),
+        "-inf" => Token::Num(f64::NEG_INFINITY),
+        "nan" => Token::Num(f64::NAN),
+        _ if val.len() > 1 && val.starts_with(':') => Token::Keyword(val[1..].to_string()),
         _ => Token::Ident(val),
+    })
+}
+
+/// Returns whether identifiers may start with the given character.
+fn is_ident_start(c: char) -> bool {
+    c.is_xid_start() || c == '_' || is_symbol(c)
+}
+
+/// Returns whether identifiers may contain the given character after the
+/// first one.
+fn is_ident_continue(c: char) -> bool {
+    c.is_xid_continue() || is_symbol(c)
+}
+
+/// Returns whether the given character is an ASCII symbol allowed in
+/// identifiers, such as `+`, `*`, or `$`.
+fn is_symbol(c: char) -> bool {
+    c.is_ascii_punctuation() && !matches!(c, '(' | ')' | '{' | '}' | '[' | ']' | '"')
+}
+
+/// Returns the closing parenthesis, brace, or bracket matching the given
+/// opening one, or `None`, if the given character opens nothing.
+#[must_use]
+pub fn closing(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '{' => Some('}'),
+        '[' => Some(']'),
+        _ => None,
     }
 }
 
@@ -103,12 +364,21 @@ mod test {
     use super::*;
 
     macro_rules! test_tokenize {
+        (errors: $($name:ident: $input:expr => $error:expr),*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let out = tokenize($input);
+                    assert_eq!(out, Err($error));
+                }
+            )*
+        };
         ($($name:ident: $input:expr => $expected:expr),*) => {
             $(
                 #[test]
                 fn $name() {
                     let out = tokenize($input);
-                    assert_eq!(out, $expected);
+                    assert_eq!(out, Ok($expected));
                 }
             )*
         };
@@ -116,19 +386,56 @@ mod test {
 
     test_tokenize!(
         tokenize_empty: "()" => vec![Paren('('), Paren(')')],
-        tokenize_integer: "1" => vec![Num(1.0)],
-        tokenize_long_integer: "1234" => vec![Num(1234.0)],
-        tokenize_float: "1.234" => vec![Num(1.234)],
+        tokenize_integer: "1" => vec![Int(1)],
+        tokenize_long_integer: "1234" => vec![Int(1234)],
+        tokenize_huge_integer: "9223372036854775808" => vec![Num(9_223_372_036_854_775_808.0)],
+        tokenize_float: "1.234 1." => vec![Num(1.234), Num(1.0)],
+        tokenize_exponent: "1e300 1.5e-7 2e+3" => vec![Num(1e300), Num(1.5e-7), Num(2e3)],
+        tokenize_negative: "-1 -1.5 -0.0 -2/4 -1e-3 -9223372036854775808" => vec![
+            Int(-1),
+            Num(-1.5),
+            Num(-0.0),
+            Ratio(-1, 2),
+            Num(-1e-3),
+            Int(i64::MIN)
+        ],
+        tokenize_minus: "- -x -inf" => vec![
+            Ident("-".to_string()),
+            Ident("-x".to_string()),
+            Num(f64::NEG_INFINITY)
+        ],
+        tokenize_ratio: "1/3 2/4 4/2 0/5" => vec![Ratio(1, 3), Ratio(1, 2), Int(2), Int(0)],
         tokenize_str: "\"foo\"" => vec![Str("foo".to_string())],
+        tokenize_char: "#\\a #\\space #\\newline #\\( #\\  #\\\\" => vec![
+            Char('a'),
+            Char(' '),
+            Char('\n'),
+            Char('('),
+            Char(' '),
+            Char('\\')
+        ],
+        tokenize_char_in_list: "(#\\a #\\))" => vec![Paren('('), Char('a'), Char(')'), Paren(')')],
         tokenize_bool_true: "true" => vec![Bool(true)],
         tokenize_bool_false: "false" => vec![Bool(false)],
+        tokenize_nil: "nil nils" => vec![Nil, Ident("nils".to_string())],
+        tokenize_infinity: "inf -inf infinity" => vec![
+            Num(f64::INFINITY),
+            Num(f64::NEG_INFINITY),
+            Ident("infinity".to_string())
+        ],
+        tokenize_keyword: ":a :+ : a:" => vec![
+            Keyword("a".to_string()),
+            Keyword("+".to_string()),
+            Ident(":".to_string()),
+            Ident("a:".to_string())
+        ],
         tokenize_expr: "(foo 1 \"bar\" false 2)" => vec![
             Paren('('),
             Ident("foo".to_string()),
-            Num(1.0),
+            Int(1),
             Str("bar".to_string()),
             Bool(false),
-            Num(2.0),
+            Int(2),
             Paren(')')
         ],
         tokenize_bool_expr: "(= true false)" => vec![
@@ -146,9 +453,145 @@ mod test {
             Bool(true),
             Bool(false),
             Paren(')'),
-            Num(1.0),
-            Num(2.0),
+            Int(1),
+            Int(2),
+            Paren(')')
+        ],
+        tokenize_map: "{:a 1}{}" => vec![
+            Paren('{'),
+            Keyword("a".to_string()),
+            Int(1),
+            Paren('}'),
+            Paren('{'),
+            Paren('}')
+        ],
+        tokenize_vector: "[1 a][]" => vec![
+            Paren('['),
+            Int(1),
+            Ident("a".to_string()),
+            Paren(']'),
+            Paren('['),
+            Paren(']')
+        ],
+        tokenize_quote: "'(a 'b c')" => vec![
+            Quote("'"),
+            Paren('('),
+            Ident("a".to_string()),
+            Quote("'"),
+            Ident("b".to_string()),
+            Ident("c'".to_string()),
+            Paren(')')
+        ],
+        tokenize_quasiquote: "`(a ,b ,@(c) d,)" => vec![
+            Quote("`"),
+            Paren('('),
+            Ident("a".to_string()),
+            Quote(","),
+            Ident("b".to_string()),
+            Quote(",@"),
+            Paren('('),
+            Ident("c".to_string()),
+            Paren(')'),
+            Ident("d,".to_string()),
             Paren(')')
         ]
     );
+
+    #[test]
+    fn tokenize_unterminated_str() {
+        assert_eq!(tokenize("(concat \"foo"), Err(Error::UnterminatedString));
+    }
+
+    #[test]
+    fn tokenize_invalid_char() {
+        assert_eq!(
+            tokenize("#\\tab"),
+            Err(Error::InvalidCharacter("#\\tab".to_string()))
+        );
+        assert_eq!(
+            tokenize("#\\"),
+            Err(Error::InvalidCharacter("#\\".to_string()))
+        );
+        assert_eq!(tokenize("#a"), Ok(vec![Ident("#a".to_string())]));
+    }
+
+    #[test]
+    fn tokenize_nan() {
+        let tokens = tokenize("nan").expect("error");
+        assert!(matches!(tokens[..], [Num(x)] if x.is_nan()));
+    }
+
+    #[test]
+    fn tokenize_invalid_number() {
+        assert_eq!(
+            tokenize("1.2.3"),
+            Err(Error::InvalidNumber("1.2.3".to_string()))
+        );
+    }
+
+    test_tokenize!(
+        tokenize_symbols: "+ <= *prompt* $1 assert-eq _" => vec![
+            Ident("+".to_string()),
+            Ident("<=".to_string()),
+            Ident("*prompt*".to_string()),
+            Ident("$1".to_string()),
+            Ident("assert-eq".to_string()),
+            Ident("_".to_string())
+        ],
+        tokenize_non_ascii_ident: "(\u{3bb} caf\u{e9})" => vec![
+            Paren('('),
+            Ident("\u{3bb}".to_string()),
+            Ident("caf\u{e9}".to_string()),
+            Paren(')')
+        ],
+        tokenize_combining_character: "cafe\u{301}" => vec![Ident("cafe\u{301}".to_string())],
+        tokenize_smart_quotes_in_str: "\"\u{2018}foo\u{2019}\"" => vec![Str("\u{2018}foo\u{2019}".to_string())],
+        tokenize_ident_with_digits: "x1 x\u{663}" => vec![
+            Ident("x1".to_string()),
+            Ident("x\u{663}".to_string())
+        ]
+    );
+
+    test_tokenize!(
+        errors:
+        tokenize_leading_combining_character: "\u{301}a" => Error::UnexpectedCharacter('\u{301}'),
+        tokenize_smart_quotes: "(print \u{201c}foo\u{201d})" => Error::UnexpectedCharacter('\u{201c}'),
+        tokenize_quote_in_ident: "foo\"bar" => Error::UnexpectedCharacter('"'),
+        tokenize_emoji: "\u{1f600}" => Error::UnexpectedCharacter('\u{1f600}'),
+        tokenize_non_ascii_digit: "\u{663}" => Error::UnexpectedCharacter('\u{663}'),
+        tokenize_number_with_non_ascii_digit: "1\u{663}" => Error::InvalidNumber("1\u{663}".to_string()),
+        tokenize_number_with_letters: "12abc" => Error::InvalidNumber("12abc".to_string()),
+        tokenize_negative_number_with_letters: "-1x" => Error::InvalidNumber("-1x".to_string()),
+        tokenize_number_with_two_signs: "-1/-2" => Error::InvalidNumber("-1/-2".to_string()),
+        tokenize_number_with_empty_exponent: "1e" => Error::InvalidNumber("1e".to_string()),
+        tokenize_number_with_invalid_exponent: "1e5.5" => Error::InvalidNumber("1e5.5".to_string()),
+        tokenize_ratio_with_zero_denominator: "1/0" => Error::InvalidNumber("1/0".to_string()),
+        tokenize_ratio_with_float: "1.5/2" => Error::InvalidNumber("1.5/2".to_string())
+    );
+
+    #[test]
+    fn tokenize_spans() {
+        assert_eq!(
+            tokenize_spanned(" (+ 12 \"\u{e4}\")"),
+            Ok(vec![
+                (Paren('('), 1..2),
+                (Ident("+".to_string()), 2..3),
+                (Int(12), 4..6),
+                (Str("\u{e4}".to_string()), 7..11),
+                (Paren(')'), 11..12)
+            ])
+        );
+    }
+
+    #[test]
+    fn lexer_continues_after_error() {
+        let tokens = Lexer::new("1.2.3 4").collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                (Err(Error::InvalidNumber("1.2.3".to_string())), 0..5),
+                (Ok(Int(4)), 6..7)
+            ]
+        );
+    }
 }
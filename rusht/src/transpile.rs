@@ -0,0 +1,176 @@
+//! Transpilation of programs into Rust source code. The generated function
+//! constructs the parsed expressions directly and evaluates them one after
+//! another using `Interpreter::eval`, so programs embedded into an
+//! application at build time are neither tokenized nor parsed at runtime.
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::expr::Expr;
+use crate::{parse, Error, Result};
+
+/// Returns the source code of a public Rust function of the given name,
+/// which evaluates the given program using the interpreter passed to it and
+/// returns the value of the last expression, or `nil` if there is none.
+///
+/// # Arguments
+///
+/// * `input` - The source code of the program.
+/// * `name` - The name of the generated function.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the program contains a value, that can not be
+///     written as Rust code. Parsed programs only contain literals, lists,
+///     and vector and map literals, which can all be written.
+///
+/// Additionally, all the errors returned by `parse` can be returned.
+pub fn transpile(input: &str, name: &str) -> Result<String> {
+    let mut used = BTreeSet::new();
+    let exprs = parse(input)?
+        .iter()
+        .map(|expr| to_rust(expr, &mut used))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "pub fn {}(interpreter: &mut rusht::Interpreter) -> rusht::Result<rusht::Expr> {{",
+        name
+    );
+    match exprs.split_last() {
+        Some((last, init)) => {
+            let used = used.into_iter().collect::<Vec<_>>().join(", ");
+            let _ = writeln!(out, "    use rusht::Expr::{{{}}};\n", used);
+            for expr in init {
+                let _ = writeln!(out, "    interpreter.eval({})?;", expr);
+            }
+            let _ = writeln!(out, "    interpreter.eval({})", last);
+        }
+        None => out.push_str("    let _ = interpreter;\n    Ok(rusht::Expr::Nil)\n"),
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// Returns Rust code constructing the given expression. The names of the
+/// used variants of `Expr` are added to `used`.
+fn to_rust(expr: &Expr, used: &mut BTreeSet<&'static str>) -> Result<String> {
+    let (variant, value) = match expr {
+        Expr::Nil => {
+            used.insert("Nil");
+            return Ok("Nil".to_string());
+        }
+        Expr::Bool(b) => ("Bool", b.to_string()),
+        Expr::Int(n) => ("Int", n.to_string()),
+        Expr::Ratio(n, d) => ("Ratio", format!("{}, {}", n, d)),
+        Expr::Num(n) if n.is_nan() => ("Num", "f64::NAN".to_string()),
+        Expr::Num(n) if n.is_infinite() && *n > 0.0 => ("Num", "f64::INFINITY".to_string()),
+        Expr::Num(n) if n.is_infinite() => ("Num", "f64::NEG_INFINITY".to_string()),
+        Expr::Num(n) => ("Num", format!("{:?}", n)),
+        Expr::Str(s) => ("Str", format!("{:?}.to_string()", s)),
+        Expr::Char(c) => ("Char", format!("{:?}", c)),
+        Expr::Ident(s) => ("Ident", format!("{:?}.to_string()", s)),
+        Expr::Keyword(s) => ("Keyword", format!("{:?}.to_string()", s)),
+        Expr::Symbol(s) => ("Symbol", format!("{:?}.to_string()", s)),
+        Expr::List(list) => {
+            let elements = list
+                .iter()
+                .map(|expr| to_rust(expr, used))
+                .collect::<Result<Vec<_>>>()?;
+            ("List", format!("vec![{}]", elements.join(", ")))
+        }
+        Expr::Vector(vector) => {
+            let elements = vector
+                .iter()
+                .map(|expr| to_rust(expr, used))
+                .collect::<Result<Vec<_>>>()?;
+            ("Vector", format!("vec![{}]", elements.join(", ")))
+        }
+        // Keys are checked again when the map is constructed, which can't
+        // fail for the keys of a parsed map.
+        Expr::Map(map) => {
+            let entries = map
+                .iter()
+                .map(|(key, value)| {
+                    Ok(format!(
+                        "(rusht::Key::new({})?, {})",
+                        to_rust(key.as_expr(), used)?,
+                        to_rust(value, used)?
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            (
+                "Map",
+                format!("vec![{}].into_iter().collect()", entries.join(", ")),
+            )
+        }
+        _ => return Err(Error::UnexpectedType),
+    };
+    used.insert(variant);
+    Ok(format!("{}({})", variant, value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transpile_program() {
+        let out = transpile("(def a 1.5) (print \"a\" a)", "run");
+        assert_eq!(
+            out,
+            Ok(concat!(
+                "pub fn run(interpreter: &mut rusht::Interpreter) -> rusht::Result<rusht::Expr> {\n",
+                "    use rusht::Expr::{Ident, List, Num, Str};\n\n",
+                "    interpreter.eval(List(vec![Ident(\"def\".to_string()), ",
+                "Ident(\"a\".to_string()), Num(1.5)]))?;\n",
+                "    interpreter.eval(List(vec![Ident(\"print\".to_string()), ",
+                "Str(\"a\".to_string()), Ident(\"a\".to_string())]))\n",
+                "}\n",
+            )
+            .to_string())
+        );
+    }
+
+    #[test]
+    fn transpile_vector() {
+        let out = transpile("[1 [] (x)]", "run");
+        assert_eq!(
+            out,
+            Ok(concat!(
+                "pub fn run(interpreter: &mut rusht::Interpreter) -> rusht::Result<rusht::Expr> {\n",
+                "    use rusht::Expr::{Ident, Int, List, Vector};\n\n",
+                "    interpreter.eval(Vector(vec![Int(1), Vector(vec![]), ",
+                "List(vec![Ident(\"x\".to_string())])]))\n",
+                "}\n",
+            )
+            .to_string())
+        );
+    }
+
+    #[test]
+    fn transpile_map() {
+        let out = transpile("{:a 1 \"b\" (x)}", "run");
+        assert_eq!(
+            out,
+            Ok(concat!(
+                "pub fn run(interpreter: &mut rusht::Interpreter) -> rusht::Result<rusht::Expr> {\n",
+                "    use rusht::Expr::{Ident, Int, Keyword, List, Map, Str};\n\n",
+                "    interpreter.eval(Map(vec![",
+                "(rusht::Key::new(Str(\"b\".to_string()))?, List(vec![Ident(\"x\".to_string())])), ",
+                "(rusht::Key::new(Keyword(\"a\".to_string()))?, Int(1))",
+                "].into_iter().collect()))\n",
+                "}\n",
+            )
+            .to_string())
+        );
+    }
+
+    #[test]
+    fn transpile_invalid() {
+        assert_eq!(
+            transpile("(def a", "run"),
+            Err(Error::MissingClosingParenthesis)
+        );
+    }
+}
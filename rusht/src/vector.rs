@@ -0,0 +1,164 @@
+//! Vectors, i.e. sequences of values with constant-time access to their
+//! elements by index. Unlike lists, which are usually taken apart using
+//! `car` and `cdr`, vectors are meant for random access. Like lists, vectors
+//! are copied when passed around, so the only way to change an element in
+//! place is `vec-set!`, which updates the vector stored in a variable.
+use std::convert::TryInto;
+
+use crate::expr::Expr;
+use crate::{BuiltinDoc, Env, Error, Result};
+
+/// Defines the vector builtins in the given environment.
+pub fn register(env: &mut Env) {
+    env.insert("vector".to_string(), Expr::Func(rusht_vector));
+    env.insert(
+        "vec-ref".to_string(),
+        Expr::Func(|args| rusht_vec_ref(&args)),
+    );
+    env.insert(
+        "vec-len".to_string(),
+        Expr::Func(|args| rusht_vec_len(&args)),
+    );
+}
+
+/// The documentation of the vector builtins.
+pub const DOCS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        signature: "(vector value...)",
+        description: "Returns a vector of the given values.",
+    },
+    BuiltinDoc {
+        signature: "(vec-ref vector index)",
+        description: "Returns the element of the vector at the index.",
+    },
+    BuiltinDoc {
+        signature: "(vec-len vector)",
+        description: "Returns the number of elements of the vector.",
+    },
+];
+
+/// Returns the position of the element at the given index of a vector of the
+/// given length.
+///
+/// # Errors
+///
+/// * `UnexpectedType` - If the index can't be coerced to a number.
+/// * `IndexOutOfBounds` - If the index is negative, not an integer, or not
+///     less than the length.
+pub fn index(index: &Expr, len: usize) -> Result<usize> {
+    let index: f64 = index.clone().try_into()?;
+
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    let position = index as usize;
+    if index.fract() != 0.0 || index < 0.0 || position >= len {
+        return Err(Error::IndexOutOfBounds(position));
+    }
+    Ok(position)
+}
+
+/// Returns a vector of the given values.
+///
+/// # Arguments
+/// * `args` - The elements of the vector.
+#[allow(clippy::unnecessary_wraps)]
+fn rusht_vector(args: Vec<Expr>) -> Result<Expr> {
+    Ok(Expr::Vector(args))
+}
+
+/// Returns the element of a vector at the given index.
+///
+/// # Arguments
+/// * `args[0]` - The vector.
+/// * `args[1]` - The index of the element.
+///
+/// # Errors
+/// * `IndexOutOfBounds` - If the index is out of bounds of the vector.
+/// * `UnexpectedType` - If the first argument is not a vector or the index
+///     can't be coerced to a number.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not two.
+fn rusht_vec_ref(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Vector(vector), i] => Ok(vector[index(i, vector.len())?].clone()),
+        [_, _] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+/// Returns the number of elements of a vector.
+///
+/// # Arguments
+/// * `args[0]` - The vector.
+///
+/// # Errors
+/// * `UnexpectedType` - If the argument is not a vector.
+/// * `InvalidNumberOfArguments` - If the number of arguments in not one.
+fn rusht_vec_len(args: &[Expr]) -> Result<Expr> {
+    match args {
+        [Expr::Vector(vector)] => Ok(Expr::from(vector.len())),
+        [_] => Err(Error::UnexpectedType),
+        &_ => Err(Error::InvalidNumberOfArguments),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Interpreter;
+
+    #[test]
+    fn indices() {
+        assert_eq!(index(&Expr::Num(2.0), 3), Ok(2));
+        assert_eq!(index(&Expr::Num(3.0), 3), Err(Error::IndexOutOfBounds(3)));
+        assert_eq!(index(&Expr::Num(-1.0), 3), Err(Error::IndexOutOfBounds(0)));
+        assert_eq!(index(&Expr::Num(0.5), 3), Err(Error::IndexOutOfBounds(0)));
+        assert_eq!(index(&Expr::List(vec![]), 3), Err(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn builtins() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("(def v (vector 1 \"a\" (list 2)))")
+            .expect("error");
+        assert_eq!(interpreter.interpret("(vec-len v)"), Ok(Expr::Int(3)));
+        assert_eq!(
+            interpreter.interpret("(vec-ref v 1)"),
+            Ok(Expr::Str("a".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(vec-set! v 0 (+ 1 1))"),
+            Ok(Expr::Int(2))
+        );
+        assert_eq!(interpreter.interpret("(vec-ref v 0)"), Ok(Expr::Int(2)));
+        assert_eq!(
+            interpreter
+                .interpret("(vector 1 \"a\" (list 2))")
+                .map(|v| v.to_string()),
+            Ok("[1 \"a\" (2)]".to_string())
+        );
+        assert_eq!(
+            interpreter.interpret("(vec-ref v 3)"),
+            Err(Error::IndexOutOfBounds(3))
+        );
+        assert_eq!(
+            interpreter.interpret("(vec-set! v 3 0)"),
+            Err(Error::IndexOutOfBounds(3))
+        );
+        assert_eq!(
+            interpreter.interpret("(vec-ref (list 1) 0)"),
+            Err(Error::UnexpectedType)
+        );
+        assert_eq!(
+            interpreter.interpret("(vec-set! w 0 0)"),
+            Err(Error::VariableNotDefined("w".to_string()))
+        );
+        assert_eq!(
+            interpreter.interpret("(equal? (vector 1 2) (vector 1 2))"),
+            Ok(Expr::Bool(true))
+        );
+        assert_eq!(
+            interpreter.interpret("(equal? (vector 1 2) (list 1 2))"),
+            Ok(Expr::Bool(false))
+        );
+    }
+}